@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN index_start_delay_seconds INTEGER NOT NULL DEFAULT 0;
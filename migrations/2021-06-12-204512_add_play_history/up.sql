@@ -0,0 +1,7 @@
+CREATE TABLE play_history (
+	id INTEGER PRIMARY KEY NOT NULL,
+	owner INTEGER NOT NULL,
+	path TEXT NOT NULL,
+	timestamp INTEGER NOT NULL,
+	FOREIGN KEY(owner) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,6 @@
+CREATE TABLE artist_images (
+	id INTEGER PRIMARY KEY NOT NULL,
+	name TEXT NOT NULL,
+	path TEXT NOT NULL,
+	UNIQUE(name) ON CONFLICT REPLACE
+);
@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN unknown_artist_label TEXT NOT NULL DEFAULT 'Unknown Artist';
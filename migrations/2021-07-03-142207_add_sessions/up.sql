@@ -0,0 +1,8 @@
+CREATE TABLE sessions (
+	id INTEGER PRIMARY KEY NOT NULL,
+	owner INTEGER NOT NULL,
+	label TEXT,
+	created INTEGER NOT NULL,
+	last_seen INTEGER NOT NULL,
+	FOREIGN KEY(owner) REFERENCES users(id) ON DELETE CASCADE
+);
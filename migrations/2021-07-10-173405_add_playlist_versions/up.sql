@@ -0,0 +1 @@
+ALTER TABLE playlists ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN normalize_path_separators INTEGER NOT NULL DEFAULT 1;
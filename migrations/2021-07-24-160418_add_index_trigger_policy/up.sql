@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN index_trigger_policy TEXT NOT NULL DEFAULT 'queue';
@@ -0,0 +1 @@
+ALTER TABLE mount_points ADD COLUMN scan_interval_seconds INTEGER;
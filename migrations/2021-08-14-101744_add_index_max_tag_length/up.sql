@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN index_max_tag_length INTEGER NOT NULL DEFAULT 1024;
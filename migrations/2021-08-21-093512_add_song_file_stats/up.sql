@@ -0,0 +1,2 @@
+ALTER TABLE songs ADD COLUMN file_size BIGINT;
+ALTER TABLE songs ADD COLUMN file_modified BIGINT;
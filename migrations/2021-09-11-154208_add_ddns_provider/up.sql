@@ -0,0 +1 @@
+ALTER TABLE ddns_config ADD COLUMN provider TEXT NOT NULL DEFAULT 'ydns';
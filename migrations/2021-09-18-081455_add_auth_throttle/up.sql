@@ -0,0 +1,2 @@
+ALTER TABLE misc_settings ADD COLUMN auth_max_failed_attempts INTEGER NOT NULL DEFAULT 5;
+ALTER TABLE misc_settings ADD COLUMN auth_failure_window_seconds INTEGER NOT NULL DEFAULT 300;
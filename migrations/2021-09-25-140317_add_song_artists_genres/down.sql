@@ -0,0 +1,2 @@
+DROP TABLE song_genres;
+DROP TABLE song_artists;
@@ -0,0 +1,16 @@
+CREATE TABLE song_artists (
+	id INTEGER PRIMARY KEY NOT NULL,
+	path TEXT NOT NULL,
+	artist TEXT NOT NULL,
+	UNIQUE(path, artist) ON CONFLICT IGNORE
+);
+CREATE INDEX song_artists_artist ON song_artists(artist);
+CREATE TABLE song_genres (
+	id INTEGER PRIMARY KEY NOT NULL,
+	path TEXT NOT NULL,
+	genre TEXT NOT NULL,
+	UNIQUE(path, genre) ON CONFLICT IGNORE
+);
+CREATE INDEX song_genres_genre ON song_genres(genre);
+-- Forces the next incremental scan to read tags again and fill the tables above
+UPDATE songs SET file_modified = NULL;
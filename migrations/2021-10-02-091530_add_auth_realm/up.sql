@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN auth_realm TEXT NOT NULL DEFAULT 'Polaris';
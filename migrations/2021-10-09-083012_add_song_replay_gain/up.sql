@@ -0,0 +1,4 @@
+ALTER TABLE songs ADD COLUMN replay_gain_track REAL;
+ALTER TABLE songs ADD COLUMN replay_gain_album REAL;
+-- Forces the next incremental scan to read tags again
+UPDATE songs SET file_modified = NULL;
@@ -0,0 +1,5 @@
+ALTER TABLE directories ADD COLUMN search_text TEXT;
+ALTER TABLE songs ADD COLUMN search_text TEXT;
+-- Placeholder until the next index update stores normalized values
+UPDATE directories SET search_text = lower(path);
+UPDATE songs SET search_text = lower(path || char(10) || IFNULL(title, '') || char(10) || IFNULL(album, '') || char(10) || IFNULL(artist, '') || char(10) || IFNULL(album_artist, ''));
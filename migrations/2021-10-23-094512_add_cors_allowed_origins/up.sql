@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN cors_allowed_origins TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,21 @@
+CREATE TEMPORARY TABLE misc_settings_backup(id, auth_secret, index_sleep_duration_seconds, index_album_art_pattern, index_start_delay_seconds, unknown_artist_label, normalize_path_separators, index_trigger_policy, index_max_tag_length, index_last_completed, auth_max_failed_attempts, auth_failure_window_seconds, auth_realm, cors_allowed_origins);
+INSERT INTO misc_settings_backup SELECT id, auth_secret, index_sleep_duration_seconds, index_album_art_pattern, index_start_delay_seconds, unknown_artist_label, normalize_path_separators, index_trigger_policy, index_max_tag_length, index_last_completed, auth_max_failed_attempts, auth_failure_window_seconds, auth_realm, cors_allowed_origins FROM misc_settings;
+DROP TABLE misc_settings;
+CREATE TABLE misc_settings (
+	id INTEGER PRIMARY KEY NOT NULL CHECK(id = 0),
+	auth_secret BLOB NOT NULL DEFAULT (randomblob(32)),
+	index_sleep_duration_seconds INTEGER NOT NULL,
+	index_album_art_pattern TEXT NOT NULL,
+	index_start_delay_seconds INTEGER NOT NULL DEFAULT 0,
+	unknown_artist_label TEXT NOT NULL DEFAULT 'Unknown Artist',
+	normalize_path_separators INTEGER NOT NULL DEFAULT 1,
+	index_trigger_policy TEXT NOT NULL DEFAULT 'queue',
+	index_max_tag_length INTEGER NOT NULL DEFAULT 1024,
+	index_last_completed BIGINT,
+	auth_max_failed_attempts INTEGER NOT NULL DEFAULT 5,
+	auth_failure_window_seconds INTEGER NOT NULL DEFAULT 300,
+	auth_realm TEXT NOT NULL DEFAULT 'Polaris',
+	cors_allowed_origins TEXT NOT NULL DEFAULT ''
+);
+INSERT INTO misc_settings SELECT * FROM misc_settings_backup;
+DROP TABLE misc_settings_backup;
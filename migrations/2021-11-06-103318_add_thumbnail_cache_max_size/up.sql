@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN thumbnail_cache_max_size_bytes BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE songs ADD COLUMN bitrate INTEGER;
+UPDATE songs SET bitrate = file_size * 8 / (duration * 1000) WHERE file_size IS NOT NULL AND duration > 0;
@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN index_audio_extensions TEXT NOT NULL DEFAULT 'aif,aiff,ape,flac,m4a,mp3,mpc,ogg,opus,wav';
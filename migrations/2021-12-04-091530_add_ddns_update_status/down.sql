@@ -0,0 +1,12 @@
+CREATE TEMPORARY TABLE ddns_config_backup(id, host, username, password, provider);
+INSERT INTO ddns_config_backup SELECT id, host, username, password, provider FROM ddns_config;
+DROP TABLE ddns_config;
+CREATE TABLE ddns_config (
+	id INTEGER PRIMARY KEY NOT NULL CHECK(id = 0),
+	host TEXT NOT NULL,
+	username TEXT NOT NULL,
+	password TEXT NOT NULL,
+	provider TEXT NOT NULL DEFAULT 'ydns'
+);
+INSERT INTO ddns_config SELECT * FROM ddns_config_backup;
+DROP TABLE ddns_config_backup;
@@ -0,0 +1,3 @@
+ALTER TABLE ddns_config ADD COLUMN update_interval_seconds INTEGER NOT NULL DEFAULT 1800;
+ALTER TABLE ddns_config ADD COLUMN last_update_time INTEGER;
+ALTER TABLE ddns_config ADD COLUMN last_update_error TEXT;
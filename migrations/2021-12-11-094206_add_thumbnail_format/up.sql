@@ -0,0 +1,2 @@
+ALTER TABLE misc_settings ADD COLUMN thumbnail_format TEXT NOT NULL DEFAULT 'jpeg';
+ALTER TABLE misc_settings ADD COLUMN thumbnail_quality INTEGER NOT NULL DEFAULT 80;
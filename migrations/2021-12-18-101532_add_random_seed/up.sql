@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN random_seed BIGINT;
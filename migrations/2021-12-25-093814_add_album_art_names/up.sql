@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN index_album_art_names TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,13 @@
+CREATE TEMPORARY TABLE playlists_backup(id, owner, name, version);
+INSERT INTO playlists_backup SELECT id, owner, name, version FROM playlists;
+DROP TABLE playlists;
+CREATE TABLE playlists (
+	id INTEGER PRIMARY KEY NOT NULL,
+	owner INTEGER NOT NULL,
+	name TEXT NOT NULL,
+	version INTEGER NOT NULL DEFAULT 0,
+	FOREIGN KEY(owner) REFERENCES users(id) ON DELETE CASCADE,
+	UNIQUE(owner, name) ON CONFLICT REPLACE
+);
+INSERT INTO playlists SELECT * FROM playlists_backup;
+DROP TABLE playlists_backup;
@@ -0,0 +1,4 @@
+ALTER TABLE playlists ADD COLUMN created_at BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE playlists ADD COLUMN updated_at BIGINT NOT NULL DEFAULT 0;
+-- Versions of existing playlists are the time they were last saved, in milliseconds
+UPDATE playlists SET created_at = version / 1000, updated_at = version / 1000;
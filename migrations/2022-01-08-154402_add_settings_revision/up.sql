@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN settings_revision BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,122 @@
+use diesel;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use super::*;
+use crate::db::{artist_images, directories, DB};
+
+#[derive(Clone)]
+pub struct Manager {
+	db: DB,
+	images_dir_path: PathBuf,
+}
+
+impl Manager {
+	pub fn new(db: DB, images_dir_path: PathBuf) -> Self {
+		Self {
+			db,
+			images_dir_path,
+		}
+	}
+
+	pub fn set_image(&self, artist: &str, content: &[u8]) -> Result<(), Error> {
+		let format = image::guess_format(content).map_err(|_| Error::InvalidImage)?;
+		image::load_from_memory_with_format(content, format).map_err(|_| Error::InvalidImage)?;
+		let extension = format.extensions_str().first().ok_or(Error::InvalidImage)?;
+
+		let name = normalize(artist);
+		let mut path = self.images_dir_path.clone();
+		path.push(format!("{}.{}", hash(&name), extension));
+		fs::create_dir_all(&self.images_dir_path).map_err(anyhow::Error::new)?;
+		fs::write(&path, content).map_err(anyhow::Error::new)?;
+
+		let connection = self.db.connect()?;
+		let previous_path = self.get_assigned_image(&connection, &name)?;
+		diesel::insert_into(artist_images::table)
+			.values(&NewArtistImage {
+				name,
+				path: path.to_string_lossy().into_owned(),
+			})
+			.execute(&connection)
+			.map_err(anyhow::Error::new)?;
+
+		// Uploading a different image format leaves a stale file behind
+		if let Some(previous_path) = previous_path {
+			if previous_path != path {
+				fs::remove_file(previous_path).ok();
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Returns the image assigned to this artist, or the artwork of one of their albums.
+	pub fn get_image(&self, artist: &str) -> Result<PathBuf, Error> {
+		let name = normalize(artist);
+		let connection = self.db.connect()?;
+
+		if let Some(path) = self.get_assigned_image(&connection, &name)? {
+			if path.exists() {
+				return Ok(path);
+			}
+		}
+
+		let album_artwork: Option<Option<String>> = {
+			use self::directories::dsl::*;
+			directories
+				.select(artwork)
+				.filter(artwork.is_not_null())
+				.filter(
+					sql::<sql_types::Bool>("TRIM(artist) = ")
+						.bind::<sql_types::Text, _>(&name)
+						.sql(" COLLATE NOCASE"),
+				)
+				.order((year.desc(), path))
+				.first(&connection)
+				.optional()
+				.map_err(anyhow::Error::new)?
+		};
+
+		album_artwork
+			.flatten()
+			.map(PathBuf::from)
+			.ok_or(Error::ArtistImageNotFound)
+	}
+
+	fn get_assigned_image(
+		&self,
+		connection: &SqliteConnection,
+		artist: &str,
+	) -> Result<Option<PathBuf>, Error> {
+		use self::artist_images::dsl::*;
+		let image_path: Option<String> = artist_images
+			.select(path)
+			.filter(name.eq(artist))
+			.first(connection)
+			.optional()
+			.map_err(anyhow::Error::new)?;
+		Ok(image_path.map(PathBuf::from))
+	}
+}
+
+fn normalize(artist: &str) -> String {
+	artist.trim().to_lowercase()
+}
+
+fn hash(artist: &str) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	artist.hash(&mut hasher);
+	hasher.finish()
+}
+
+#[derive(Insertable)]
+#[table_name = "artist_images"]
+struct NewArtistImage {
+	name: String,
+	path: String,
+}
@@ -0,0 +1,69 @@
+use std::fs;
+use std::path::PathBuf;
+
+use super::*;
+use crate::app::test;
+use crate::test_name;
+
+const TEST_MOUNT_NAME: &str = "root";
+
+#[test]
+fn set_image_golden_path() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+
+	let image_path: PathBuf = [
+		"test-data",
+		"small-collection",
+		"Tobokegao",
+		"Picnic",
+		"Folder.png",
+	]
+	.iter()
+	.collect();
+	let content = fs::read(&image_path).unwrap();
+	ctx.artist_manager
+		.set_image("Stratovarius", &content)
+		.unwrap();
+
+	let stored_path = ctx.artist_manager.get_image("stratovarius ").unwrap();
+	assert!(stored_path.starts_with(&ctx.test_directory));
+	assert_eq!(fs::read(&stored_path).unwrap(), content);
+}
+
+#[test]
+fn set_image_rejects_invalid_content() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+
+	let result = ctx
+		.artist_manager
+		.set_image("Stratovarius", b"not an image");
+	assert!(matches!(result, Err(Error::InvalidImage)));
+}
+
+#[test]
+fn get_image_falls_back_to_album_artwork() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let artwork_path: PathBuf = [
+		"test-data",
+		"small-collection",
+		"Khemmis",
+		"Hunted",
+		"Folder.jpg",
+	]
+	.iter()
+	.collect();
+	let image_path = ctx.artist_manager.get_image("Khemmis").unwrap();
+	assert!(image_path.ends_with(&artwork_path));
+}
+
+#[test]
+fn get_image_without_artwork_fails() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+
+	let result = ctx.artist_manager.get_image("Stratovarius");
+	assert!(matches!(result, Err(Error::ArtistImageNotFound)));
+}
@@ -67,11 +67,17 @@ impl Manager {
 					.map_err(|_| Error::Unspecified)?;
 			}
 
-			// Update users
+			// Update users, without revoking sessions of users whose password did not change
 			for user in users {
-				self.user_manager
-					.set_password(&user.name, &user.password)
-					.map_err(|_| Error::Unspecified)?;
+				if self
+					.user_manager
+					.check_credentials(&user.name, &user.password)
+					.is_err()
+				{
+					self.user_manager
+						.set_password(&user.name, &user.password)
+						.map_err(|_| Error::Unspecified)?;
+				}
 				self.user_manager
 					.set_is_admin(&user.name, user.admin)
 					.map_err(|_| Error::Unspecified)?;
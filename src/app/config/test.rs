@@ -9,6 +9,22 @@ fn apply_saves_misc_settings() {
 		settings: Some(settings::NewSettings {
 			album_art_pattern: Some("🖼️\\.jpg".into()),
 			reindex_every_n_seconds: Some(100),
+			index_start_delay_seconds: Some(30),
+			unknown_artist_label: Some("Anonymous".into()),
+			normalize_path_separators: Some(false),
+			index_trigger_policy: Some(settings::IndexTriggerPolicy::Reject),
+			index_max_tag_length: Some(256),
+			auth_max_failed_attempts: Some(10),
+			auth_failure_window_seconds: Some(60),
+			auth_realm: Some("My Music".to_owned()),
+			cors_allowed_origins: Some(vec!["https://music.example.com/".to_owned()]),
+			session_lifetime_seconds: Some(7 * 24 * 60 * 60),
+			thumbnail_cache_max_size_bytes: Some(512 * 1024 * 1024),
+			index_audio_extensions: Some(vec!["MP3".to_owned(), ".wv".to_owned()]),
+			follow_symlinks: Some(false),
+			thumbnail_format: Some(settings::ThumbnailFormat::Png),
+			thumbnail_quality: Some(50),
+			album_art_names: Some(vec!["Folder.jpg".to_owned(), " cover.png".to_owned()]),
 			..Default::default()
 		}),
 		..Default::default()
@@ -25,6 +41,96 @@ fn apply_saves_misc_settings() {
 		settings.reindex_every_n_seconds,
 		new_settings.reindex_every_n_seconds.unwrap()
 	);
+	assert_eq!(
+		settings.index_start_delay_seconds,
+		new_settings.index_start_delay_seconds.unwrap()
+	);
+	assert_eq!(
+		settings.unknown_artist_label,
+		new_settings.unknown_artist_label.unwrap()
+	);
+	assert_eq!(
+		settings.normalize_path_separators,
+		new_settings.normalize_path_separators.unwrap()
+	);
+	assert_eq!(
+		settings.index_trigger_policy,
+		new_settings.index_trigger_policy.unwrap()
+	);
+	assert_eq!(
+		settings.index_max_tag_length,
+		new_settings.index_max_tag_length.unwrap()
+	);
+	assert_eq!(
+		settings.auth_max_failed_attempts,
+		new_settings.auth_max_failed_attempts.unwrap()
+	);
+	assert_eq!(
+		settings.auth_failure_window_seconds,
+		new_settings.auth_failure_window_seconds.unwrap()
+	);
+	assert_eq!(settings.auth_realm, new_settings.auth_realm.unwrap());
+	assert_eq!(
+		settings.cors_allowed_origins,
+		vec!["https://music.example.com".to_owned()]
+	);
+	assert_eq!(
+		settings.session_lifetime_seconds,
+		new_settings.session_lifetime_seconds.unwrap()
+	);
+	assert_eq!(
+		settings.thumbnail_cache_max_size_bytes,
+		new_settings.thumbnail_cache_max_size_bytes.unwrap()
+	);
+	assert_eq!(
+		settings.index_audio_extensions,
+		vec!["mp3".to_owned(), "wv".to_owned()]
+	);
+	assert_eq!(
+		settings.follow_symlinks,
+		new_settings.follow_symlinks.unwrap()
+	);
+	assert_eq!(
+		settings.thumbnail_format,
+		new_settings.thumbnail_format.unwrap()
+	);
+	assert_eq!(
+		settings.thumbnail_quality,
+		new_settings.thumbnail_quality.unwrap()
+	);
+	assert_eq!(
+		settings.album_art_names,
+		vec!["folder.jpg".to_owned(), "cover.png".to_owned()]
+	);
+}
+
+#[test]
+fn amending_settings_bumps_revision() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	let revision = ctx.settings_manager.read().unwrap().revision;
+
+	let config = Config {
+		settings: Some(settings::NewSettings::default()),
+		..Default::default()
+	};
+	ctx.config_manager.apply(&config).unwrap();
+	assert_eq!(ctx.settings_manager.read().unwrap().revision, revision + 1);
+
+	let new_settings = settings::NewSettings {
+		auth_realm: Some("Stale".to_owned()),
+		..Default::default()
+	};
+	assert!(matches!(
+		ctx.settings_manager
+			.amend_at_revision(&new_settings, revision),
+		Err(settings::Error::RevisionMismatch)
+	));
+	ctx.settings_manager
+		.amend_at_revision(&new_settings, revision + 1)
+		.unwrap();
+	let settings = ctx.settings_manager.read().unwrap();
+	assert_eq!(settings.revision, revision + 2);
+	assert_eq!(settings.auth_realm, "Stale");
 }
 
 #[test]
@@ -35,6 +141,7 @@ fn apply_saves_mount_points() {
 		mount_dirs: Some(vec![vfs::MountDir {
 			source: "/home/music".into(),
 			name: "🎵📁".into(),
+			scan_interval_seconds: Some(600),
 		}]),
 		..Default::default()
 	};
@@ -53,6 +160,8 @@ fn apply_saves_ddns_settings() {
 			host: "🐸🐸🐸.ydns.eu".into(),
 			username: "kfr🐸g".into(),
 			password: "tasty🐞".into(),
+			provider: ddns::Provider::Cloudflare,
+			update_interval_seconds: 0,
 		}),
 		..Default::default()
 	};
@@ -62,6 +171,37 @@ fn apply_saves_ddns_settings() {
 	assert_eq!(actual_ddns, new_config.ydns.unwrap());
 }
 
+#[test]
+fn ddns_provider_defaults_to_ydns() {
+	let config: Config = toml::de::from_str(
+		r#"
+		[ydns]
+		host = "polaris.ydns.eu"
+		username = "kfrog"
+		password = "tasty"
+		"#,
+	)
+	.unwrap();
+	assert_eq!(config.ydns.unwrap().provider, ddns::Provider::Ydns);
+}
+
+#[test]
+fn ddns_update_interval_has_default() {
+	let config: Config = toml::de::from_str(
+		r#"
+		[ydns]
+		host = "polaris.ydns.eu"
+		username = "kfrog"
+		password = "tasty"
+		"#,
+	)
+	.unwrap();
+	assert_eq!(
+		config.ydns.unwrap().update_interval_seconds,
+		ddns::DEFAULT_UPDATE_INTERVAL_SECONDS
+	);
+}
+
 #[test]
 fn apply_can_toggle_admin() {
 	let ctx = test::ContextBuilder::new(test_name!())
@@ -81,3 +221,23 @@ fn apply_can_toggle_admin() {
 	ctx.config_manager.apply(&new_config).unwrap();
 	assert!(!ctx.user_manager.list().unwrap()[0].is_admin());
 }
+
+#[test]
+fn apply_keeps_sessions_of_users_with_same_password() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user("Walter", "Tasty🍖", true)
+		.build();
+
+	ctx.user_manager.login("Walter", "Tasty🍖", None).unwrap();
+
+	let new_config = Config {
+		users: Some(vec![user::NewUser {
+			name: "Walter".into(),
+			password: "Tasty🍖".into(),
+			admin: true,
+		}]),
+		..Default::default()
+	};
+	ctx.config_manager.apply(&new_config).unwrap();
+	assert_eq!(ctx.user_manager.list_sessions("Walter").unwrap().len(), 1);
+}
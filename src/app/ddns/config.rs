@@ -1,11 +1,77 @@
+use anyhow::*;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
 
-use crate::db::ddns_config;
+/// Time between two automatic updates when none is specified.
+pub const DEFAULT_UPDATE_INTERVAL_SECONDS: i32 = 1800;
 
-#[derive(Clone, Debug, Deserialize, Insertable, PartialEq, Queryable, Serialize)]
-#[table_name = "ddns_config"]
+pub fn default_update_interval_seconds() -> i32 {
+	DEFAULT_UPDATE_INTERVAL_SECONDS
+}
+
+/// Dynamic DNS service to keep up to date. Every provider updates the hostname in `Config::host`,
+/// but each of them interprets the credential fields differently.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+	/// YDNS-style update URL, authenticated with `username` and `password`.
+	#[default]
+	Ydns,
+	/// Cloudflare DNS record, `username` is the zone ID and `password` an API token.
+	Cloudflare,
+	/// DuckDNS subdomain, `password` is the account token and `username` is unused.
+	DuckDns,
+}
+
+impl Provider {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Provider::Ydns => "ydns",
+			Provider::Cloudflare => "cloudflare",
+			Provider::DuckDns => "duckdns",
+		}
+	}
+}
+
+impl FromStr for Provider {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		match s {
+			"ydns" => Ok(Provider::Ydns),
+			"cloudflare" => Ok(Provider::Cloudflare),
+			"duckdns" => Ok(Provider::DuckDns),
+			_ => bail!("Unknown DDNS provider: {}", s),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct Config {
 	pub host: String,
 	pub username: String,
 	pub password: String,
+	#[serde(default)]
+	pub provider: Provider,
+	/// Time between automatic updates, 0 or less disables them.
+	#[serde(default = "default_update_interval_seconds")]
+	pub update_interval_seconds: i32,
+}
+
+impl Config {
+	pub fn update_interval(&self) -> Option<Duration> {
+		if self.update_interval_seconds <= 0 {
+			None
+		} else {
+			Some(Duration::from_secs(self.update_interval_seconds as u64))
+		}
+	}
+}
+
+/// Outcome of the most recent update, as seconds since the epoch and the error it ran into if any.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Status {
+	pub last_update_time: Option<i32>,
+	pub last_update_error: Option<String>,
 }
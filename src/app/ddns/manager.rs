@@ -1,14 +1,54 @@
 use anyhow::*;
 use diesel::prelude::*;
 use log::{error, info};
+use serde::Deserialize;
 use std::thread;
-use std::time;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use ureq;
 
 use super::*;
 use crate::db::DB;
 
 const DDNS_UPDATE_URL: &str = "https://ydns.io/api/v1/update/";
+const DUCKDNS_UPDATE_URL: &str = "https://www.duckdns.org/update";
+const CLOUDFLARE_API_URL: &str = "https://api.cloudflare.com/client/v4";
+const PUBLIC_IP_URL: &str = "https://api.ipify.org";
+
+// How often the config is checked for changes while automatic updates are disabled
+const CONFIG_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize)]
+struct CloudflareRecord {
+	id: String,
+	content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CloudflareRecords {
+	success: bool,
+	result: Vec<CloudflareRecord>,
+}
+
+/// HTTP status and body of a response from a DDNS provider.
+#[derive(Clone, Debug)]
+pub struct ProviderResponse {
+	pub status: u16,
+	pub body: String,
+}
+
+impl ProviderResponse {
+	fn is_success(&self) -> bool {
+		(200..300).contains(&self.status)
+	}
+}
+
+/// Outcome of a DDNS update. `response` is the last response received from the provider, which
+/// may be missing when the update failed before reaching it.
+#[derive(Clone, Debug)]
+pub struct UpdateReport {
+	pub response: Option<ProviderResponse>,
+	pub error: Option<String>,
+}
 
 #[derive(Clone)]
 pub struct Manager {
@@ -20,22 +60,170 @@ impl Manager {
 		Self { db }
 	}
 
-	fn update_my_ip(&self) -> Result<()> {
-		let config = self.config()?;
-		if config.host.is_empty() || config.username.is_empty() {
+	fn update_my_ip(&self, config: &Config) -> Result<()> {
+		if !has_credentials(config) {
 			info!("Skipping DDNS update because credentials are missing");
 			return Ok(());
 		}
+		match self.update(config).error {
+			Some(e) => Err(anyhow!(e)),
+			None => Ok(()),
+		}
+	}
+
+	/// Sends an update with the saved config right away, regardless of the periodic schedule.
+	pub fn test_update(&self) -> Result<UpdateReport> {
+		let config = self.config()?;
+		if !has_credentials(&config) {
+			return Ok(UpdateReport {
+				response: None,
+				error: Some("DDNS credentials are missing".to_owned()),
+			});
+		}
+		Ok(self.update(&config))
+	}
+
+	fn update(&self, config: &Config) -> UpdateReport {
+		let mut last_response = None;
+		let result = match config.provider {
+			Provider::Ydns => self.update_ydns(config, &mut last_response),
+			Provider::Cloudflare => self.update_cloudflare(config, &mut last_response),
+			Provider::DuckDns => self.update_duckdns(config, &mut last_response),
+		};
+		let report = UpdateReport {
+			response: last_response,
+			error: result.err().map(|e| e.to_string()),
+		};
+		if let Err(e) = self.record_status(&report) {
+			error!("Could not record DDNS update status: {:?}", e);
+		}
+		report
+	}
+
+	fn record_status(&self, report: &UpdateReport) -> Result<()> {
+		use crate::db::ddns_config::dsl::*;
+		let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i32;
+		let connection = self.db.connect()?;
+		diesel::update(ddns_config)
+			.set((
+				last_update_time.eq(now),
+				last_update_error.eq(&report.error),
+			))
+			.execute(&connection)?;
+		Ok(())
+	}
 
+	pub fn status(&self) -> Result<Status> {
+		use crate::db::ddns_config::dsl::*;
+		let connection = self.db.connect()?;
+		let (read_time, read_error) = ddns_config
+			.select((last_update_time, last_update_error))
+			.get_result(&connection)?;
+		Ok(Status {
+			last_update_time: read_time,
+			last_update_error: read_error,
+		})
+	}
+
+	fn update_ydns(
+		&self,
+		config: &Config,
+		last_response: &mut Option<ProviderResponse>,
+	) -> Result<()> {
 		let full_url = format!("{}?host={}", DDNS_UPDATE_URL, &config.host);
 		let response = ureq::get(full_url.as_str())
 			.auth(&config.username, &config.password)
 			.call();
+		let response = record_response(response, last_response)?;
 
-		if !response.ok() {
+		if !response.is_success() {
 			bail!(
 				"DDNS update query failed with status code: {}",
-				response.status()
+				response.status
+			);
+		}
+
+		Ok(())
+	}
+
+	fn update_duckdns(
+		&self,
+		config: &Config,
+		last_response: &mut Option<ProviderResponse>,
+	) -> Result<()> {
+		let domain = config.host.trim_end_matches(".duckdns.org");
+		let response = ureq::get(DUCKDNS_UPDATE_URL)
+			.query("domains", domain)
+			.query("token", &config.password)
+			.call();
+		let response = record_response(response, last_response)?;
+
+		if !response.is_success() {
+			bail!(
+				"DuckDNS update query failed with status code: {}",
+				response.status
+			);
+		}
+
+		// DuckDNS reports failures with a 200 status code and a KO body
+		if response.body.trim() != "OK" {
+			bail!("DuckDNS rejected the update");
+		}
+
+		Ok(())
+	}
+
+	fn update_cloudflare(
+		&self,
+		config: &Config,
+		last_response: &mut Option<ProviderResponse>,
+	) -> Result<()> {
+		let ip_response = ureq::get(PUBLIC_IP_URL).call();
+		if !ip_response.ok() {
+			bail!(
+				"Public IP query failed with status code: {}",
+				ip_response.status()
+			);
+		}
+		let ip = ip_response.into_string()?.trim().to_owned();
+
+		let authorization = format!("Bearer {}", config.password);
+		let records_url = format!(
+			"{}/zones/{}/dns_records",
+			CLOUDFLARE_API_URL, config.username
+		);
+		let response = ureq::get(&records_url)
+			.set("Authorization", &authorization)
+			.query("type", "A")
+			.query("name", &config.host)
+			.call();
+		let response = record_response(response, last_response)?;
+		if !response.is_success() {
+			bail!(
+				"Cloudflare record query failed with status code: {}",
+				response.status
+			);
+		}
+		let records: CloudflareRecords = serde_json::from_str(&response.body)?;
+		let record = match records.result.first() {
+			Some(record) if records.success => record,
+			_ => bail!("Could not find a Cloudflare A record for {}", config.host),
+		};
+		if record.content == ip {
+			return Ok(());
+		}
+
+		let record_url = format!("{}/{}", records_url, record.id);
+		let body = serde_json::json!({ "content": ip });
+		let response = ureq::request("PATCH", &record_url)
+			.set("Authorization", &authorization)
+			.set("Content-Type", "application/json")
+			.send_string(&body.to_string());
+		let response = record_response(response, last_response)?;
+		if !response.is_success() {
+			bail!(
+				"Cloudflare record update failed with status code: {}",
+				response.status
 			);
 		}
 
@@ -45,9 +233,22 @@ impl Manager {
 	pub fn config(&self) -> Result<Config> {
 		use crate::db::ddns_config::dsl::*;
 		let connection = self.db.connect()?;
-		Ok(ddns_config
-			.select((host, username, password))
-			.get_result(&connection)?)
+		let (read_host, read_username, read_password, read_provider, read_interval): (
+			String,
+			String,
+			String,
+			String,
+			i32,
+		) = ddns_config
+			.select((host, username, password, provider, update_interval_seconds))
+			.get_result(&connection)?;
+		Ok(Config {
+			host: read_host,
+			username: read_username,
+			password: read_password,
+			provider: read_provider.parse()?,
+			update_interval_seconds: read_interval,
+		})
 	}
 
 	pub fn set_config(&self, new_config: &Config) -> Result<()> {
@@ -58,6 +259,8 @@ impl Manager {
 				host.eq(&new_config.host),
 				username.eq(&new_config.username),
 				password.eq(&new_config.password),
+				provider.eq(new_config.provider.as_str()),
+				update_interval_seconds.eq(new_config.update_interval_seconds),
 			))
 			.execute(&connection)?;
 		Ok(())
@@ -72,10 +275,48 @@ impl Manager {
 
 	fn run(&self) {
 		loop {
-			if let Err(e) = self.update_my_ip() {
-				error!("Dynamic DNS update error: {:?}", e);
-			}
-			thread::sleep(time::Duration::from_secs(60 * 30));
+			let interval = match self.config() {
+				Ok(config) => {
+					let interval = config.update_interval();
+					if interval.is_some() {
+						if let Err(e) = self.update_my_ip(&config) {
+							error!("Dynamic DNS update error: {:?}", e);
+						}
+					}
+					interval
+				}
+				Err(e) => {
+					error!("Could not read DDNS config: {:?}", e);
+					None
+				}
+			};
+			// The config is read again after sleeping, so that changes apply without a restart
+			thread::sleep(interval.unwrap_or(CONFIG_REFRESH_INTERVAL));
+		}
+	}
+}
+
+fn has_credentials(config: &Config) -> bool {
+	match config.provider {
+		Provider::Ydns | Provider::Cloudflare => {
+			!config.host.is_empty() && !config.username.is_empty()
 		}
+		Provider::DuckDns => !config.host.is_empty() && !config.password.is_empty(),
+	}
+}
+
+// Keeps a copy of the response so it can be reported even if the update fails afterwards
+fn record_response(
+	response: ureq::Response,
+	last_response: &mut Option<ProviderResponse>,
+) -> Result<ProviderResponse> {
+	if let Some(e) = response.synthetic_error() {
+		bail!("Could not reach DDNS provider: {}", e);
 	}
+	let response = ProviderResponse {
+		status: response.status(),
+		body: response.into_string()?,
+	};
+	*last_response = Some(response.clone());
+	Ok(response)
 }
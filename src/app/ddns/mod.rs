@@ -1,5 +1,5 @@
 mod config;
 mod manager;
 
-pub use config::Config;
-pub use manager::Manager;
+pub use config::*;
+pub use manager::*;
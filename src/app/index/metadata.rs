@@ -9,18 +9,20 @@ use mp4ameta;
 use opus_headers;
 use regex::Regex;
 use std::fs;
+use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
 
 use crate::utils;
 use crate::utils::AudioFormat;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct SongTags {
 	pub disc_number: Option<u32>,
 	pub track_number: Option<u32>,
 	pub title: Option<String>,
 	pub duration: Option<u32>,
 	pub artist: Option<String>,
+	pub artists: Vec<String>,
 	pub album_artist: Option<String>,
 	pub album: Option<String>,
 	pub year: Option<i32>,
@@ -28,12 +30,108 @@ pub struct SongTags {
 	pub lyricist: Option<String>,
 	pub composer: Option<String>,
 	pub genre: Option<String>,
+	pub genres: Vec<String>,
 	pub label: Option<String>,
+	pub disc_subtitle: Option<String>,
+	pub replay_gain_track: Option<f32>,
+	pub replay_gain_album: Option<f32>,
+}
+
+impl SongTags {
+	// Shortens text fields longer than `max_length` characters, and returns the names of the
+	// fields which were truncated
+	pub fn truncate(&mut self, max_length: usize) -> Vec<&'static str> {
+		let fields = vec![
+			("title", &mut self.title),
+			("artist", &mut self.artist),
+			("album artist", &mut self.album_artist),
+			("album", &mut self.album),
+			("lyricist", &mut self.lyricist),
+			("composer", &mut self.composer),
+			("genre", &mut self.genre),
+			("label", &mut self.label),
+			("disc subtitle", &mut self.disc_subtitle),
+		];
+		let mut truncated: Vec<&'static str> = fields
+			.into_iter()
+			.filter_map(|(name, field)| {
+				let text = field.as_mut()?;
+				if truncate_text(text, max_length) {
+					Some(name)
+				} else {
+					None
+				}
+			})
+			.collect();
+		let lists = vec![("artist", &mut self.artists), ("genre", &mut self.genres)];
+		for (name, values) in lists {
+			let mut any_truncated = false;
+			for value in values.iter_mut() {
+				any_truncated |= truncate_text(value, max_length);
+			}
+			if any_truncated && !truncated.contains(&name) {
+				truncated.push(name);
+			}
+		}
+		truncated
+	}
+}
+
+// Splits tag values which hold several entries (ID3 and APE separate them with null
+// characters), and drops blank or duplicate entries
+fn split_tag_values<I, S>(values: I) -> Vec<String>
+where
+	I: IntoIterator<Item = S>,
+	S: AsRef<str>,
+{
+	let mut entries: Vec<String> = Vec::new();
+	for value in values {
+		for entry in value.as_ref().split('\0').map(str::trim) {
+			if !entry.is_empty() && !entries.iter().any(|e| e == entry) {
+				entries.push(entry.to_owned());
+			}
+		}
+	}
+	entries
+}
+
+fn join_tag_values(entries: &[String]) -> Option<String> {
+	if entries.is_empty() {
+		None
+	} else {
+		Some(entries.join(", "))
+	}
+}
+
+// Reads gain values such as "-6.48 dB"
+fn parse_replay_gain(value: &str) -> Option<f32> {
+	let value = value.trim();
+	let value = value
+		.strip_suffix("dB")
+		.or_else(|| value.strip_suffix("db"))
+		.or_else(|| value.strip_suffix("DB"))
+		.unwrap_or(value);
+	value.trim().parse::<f32>().ok().filter(|g| g.is_finite())
+}
+
+fn truncate_text(text: &mut String, max_length: usize) -> bool {
+	if text.chars().nth(max_length).is_none() {
+		return false;
+	}
+	let end = text
+		.char_indices()
+		.nth(max_length.saturating_sub(1))
+		.map(|(i, _)| i)
+		.unwrap_or(0);
+	text.truncate(end);
+	text.push('…');
+	true
 }
 
 impl From<id3::Tag> for SongTags {
 	fn from(tag: id3::Tag) -> Self {
-		let artist = tag.artist().map(|s| s.to_string());
+		let artists = split_tag_values(tag.artist());
+		let artist = join_tag_values(&artists);
 		let album_artist = tag.album_artist().map(|s| s.to_string());
 		let album = tag.album().map(|s| s.to_string());
 		let title = tag.title().map(|s| s.to_string());
@@ -48,11 +146,25 @@ impl From<id3::Tag> for SongTags {
 		let has_artwork = tag.pictures().count() > 0;
 		let lyricist = tag.get_text("TEXT");
 		let composer = tag.get_text("TCOM");
-		let genre = tag.genre().map(|s| s.to_string());
+		let genres = split_tag_values(tag.genre());
+		let genre = join_tag_values(&genres);
 		let label = tag.get_text("TPUB");
+		let extended_text = |description: &str| {
+			tag.extended_texts()
+				.find(|t| t.description.eq_ignore_ascii_case(description))
+				.map(|t| t.value.clone())
+		};
+		let disc_subtitle = tag
+			.get_text("TSST")
+			.or_else(|| extended_text("DISCSUBTITLE"));
+		let replay_gain_track =
+			extended_text("REPLAYGAIN_TRACK_GAIN").and_then(|g| parse_replay_gain(&g));
+		let replay_gain_album =
+			extended_text("REPLAYGAIN_ALBUM_GAIN").and_then(|g| parse_replay_gain(&g));
 
 		SongTags {
 			artist,
+			artists,
 			album_artist,
 			album,
 			title,
@@ -64,7 +176,11 @@ impl From<id3::Tag> for SongTags {
 			lyricist,
 			composer,
 			genre,
+			genres,
 			label,
+			disc_subtitle,
+			replay_gain_track,
+			replay_gain_album,
 		}
 	}
 }
@@ -180,7 +296,8 @@ fn read_ape_x_of_y(item: &ape::Item) -> Option<u32> {
 
 fn read_ape(path: &Path) -> Result<SongTags> {
 	let tag = ape::read(path)?;
-	let artist = tag.item("Artist").and_then(read_ape_string);
+	let artists = split_tag_values(tag.item("Artist").and_then(read_ape_string));
+	let artist = join_tag_values(&artists);
 	let album = tag.item("Album").and_then(read_ape_string);
 	let album_artist = tag.item("Album artist").and_then(read_ape_string);
 	let title = tag.item("Title").and_then(read_ape_string);
@@ -189,11 +306,22 @@ fn read_ape(path: &Path) -> Result<SongTags> {
 	let track_number = tag.item("Track").and_then(read_ape_x_of_y);
 	let lyricist = tag.item("LYRICIST").and_then(read_ape_string);
 	let composer = tag.item("COMPOSER").and_then(read_ape_string);
-	let genre = tag.item("GENRE").and_then(read_ape_string);
+	let genres = split_tag_values(tag.item("GENRE").and_then(read_ape_string));
+	let genre = join_tag_values(&genres);
 	let label = tag.item("PUBLISHER").and_then(read_ape_string);
+	let disc_subtitle = tag.item("DISCSUBTITLE").and_then(read_ape_string);
+	let replay_gain_track = tag
+		.item("REPLAYGAIN_TRACK_GAIN")
+		.and_then(read_ape_string)
+		.and_then(|g| parse_replay_gain(&g));
+	let replay_gain_album = tag
+		.item("REPLAYGAIN_ALBUM_GAIN")
+		.and_then(read_ape_string)
+		.and_then(|g| parse_replay_gain(&g));
 	Ok(SongTags {
 		//
 		artist,       //
+		artists,      //
 		album_artist, //
 		album,
 		title,
@@ -205,20 +333,72 @@ fn read_ape(path: &Path) -> Result<SongTags> {
 		lyricist,
 		composer,
 		genre,
+		genres,
 		label,
+		disc_subtitle,
+		replay_gain_track,
+		replay_gain_album,
 	})
 }
 
+// Number of samples in an Ogg stream, which is the granule position of its last page. Pages of
+// other logical streams multiplexed in the same file are ignored.
+fn read_ogg_sample_count<R: Read + Seek>(reader: &mut R) -> Result<u64> {
+	const HEADER_SIZE: usize = 27;
+	const MAX_PAGE_SIZE: u64 = 65_307;
+
+	let is_page_start = |bytes: &[u8]| bytes.len() >= HEADER_SIZE && &bytes[0..5] == b"OggS\0";
+	let granule_position = |bytes: &[u8]| {
+		let mut granule = [0; 8];
+		granule.copy_from_slice(&bytes[6..14]);
+		u64::from_le_bytes(granule)
+	};
+	let serial = |bytes: &[u8]| {
+		let mut serial = [0; 4];
+		serial.copy_from_slice(&bytes[14..18]);
+		u32::from_le_bytes(serial)
+	};
+
+	let mut first_page = [0; HEADER_SIZE];
+	reader.seek(SeekFrom::Start(0))?;
+	reader.read_exact(&mut first_page)?;
+	if !is_page_start(&first_page) {
+		bail!("Not an Ogg stream");
+	}
+	let stream_serial = serial(&first_page);
+
+	let length = reader.seek(SeekFrom::End(0))?;
+	reader.seek(SeekFrom::Start(length.saturating_sub(MAX_PAGE_SIZE)))?;
+	let mut tail = Vec::new();
+	reader.read_to_end(&mut tail)?;
+
+	(0..tail.len())
+		.rev()
+		.map(|offset| &tail[offset..])
+		.filter(|page| is_page_start(page) && serial(page) == stream_serial)
+		.map(granule_position)
+		.find(|granule| *granule != u64::MAX) // Pages where no packet ends have no granule position
+		.ok_or_else(|| anyhow!("Could not find last Ogg page"))
+}
+
 fn read_vorbis(path: &Path) -> Result<SongTags> {
-	let file = fs::File::open(path)?;
+	let mut file = fs::File::open(path)?;
+	let sample_count = read_ogg_sample_count(&mut file);
+	file.seek(SeekFrom::Start(0))?;
 	let source = OggStreamReader::new(file)?;
+	let sample_rate = u64::from(source.ident_hdr.audio_sample_rate);
+	let duration = sample_count
+		.ok()
+		.and_then(|s| s.checked_div(sample_rate))
+		.map(|d| d as u32);
 
 	let mut tags = SongTags {
 		artist: None,
+		artists: Vec::new(),
 		album_artist: None,
 		album: None,
 		title: None,
-		duration: None,
+		duration,
 		disc_number: None,
 		track_number: None,
 		year: None,
@@ -226,7 +406,11 @@ fn read_vorbis(path: &Path) -> Result<SongTags> {
 		lyricist: None,
 		composer: None,
 		genre: None,
+		genres: Vec::new(),
 		label: None,
+		disc_subtitle: None,
+		replay_gain_track: None,
+		replay_gain_album: None,
 	};
 
 	for (key, value) in source.comment_hdr.comment_list {
@@ -234,32 +418,48 @@ fn read_vorbis(path: &Path) -> Result<SongTags> {
 			match key {
 				"TITLE" => tags.title = Some(value),
 				"ALBUM" => tags.album = Some(value),
-				"ARTIST" => tags.artist = Some(value),
+				"ARTIST" => tags.artists.push(value),
 				"ALBUMARTIST" => tags.album_artist = Some(value),
 				"TRACKNUMBER" => tags.track_number = value.parse::<u32>().ok(),
 				"DISCNUMBER" => tags.disc_number = value.parse::<u32>().ok(),
 				"DATE" => tags.year = value.parse::<i32>().ok(),
 				"LYRICIST" => tags.lyricist = Some(value),
 				"COMPOSER" => tags.composer = Some(value),
-				"GENRE" => tags.genre = Some(value),
+				"GENRE" => tags.genres.push(value),
 				"PUBLISHER" => tags.label = Some(value),
+				"DISCSUBTITLE" => tags.disc_subtitle = Some(value),
+				"REPLAYGAIN_TRACK_GAIN" => tags.replay_gain_track = parse_replay_gain(&value),
+				"REPLAYGAIN_ALBUM_GAIN" => tags.replay_gain_album = parse_replay_gain(&value),
 				_ => (),
 			}
 		}
 	}
 
+	tags.artists = split_tag_values(&tags.artists);
+	tags.artist = join_tag_values(&tags.artists);
+	tags.genres = split_tag_values(&tags.genres);
+	tags.genre = join_tag_values(&tags.genres);
+
 	Ok(tags)
 }
 
 fn read_opus(path: &Path) -> Result<SongTags> {
 	let headers = opus_headers::parse_from_path(path)?;
 
+	// Opus granule positions always count samples at 48kHz, including the pre-skip samples
+	let duration = fs::File::open(path)
+		.map_err(Error::new)
+		.and_then(|mut file| read_ogg_sample_count(&mut file))
+		.ok()
+		.map(|s| (s.saturating_sub(u64::from(headers.id.pre_skip)) / 48_000) as u32);
+
 	let mut tags = SongTags {
 		artist: None,
+		artists: Vec::new(),
 		album_artist: None,
 		album: None,
 		title: None,
-		duration: None,
+		duration,
 		disc_number: None,
 		track_number: None,
 		year: None,
@@ -267,7 +467,11 @@ fn read_opus(path: &Path) -> Result<SongTags> {
 		lyricist: None,
 		composer: None,
 		genre: None,
+		genres: Vec::new(),
 		label: None,
+		disc_subtitle: None,
+		replay_gain_track: None,
+		replay_gain_album: None,
 	};
 
 	for (key, value) in headers.comments.user_comments {
@@ -275,20 +479,28 @@ fn read_opus(path: &Path) -> Result<SongTags> {
 			match key {
 				"TITLE" => tags.title = Some(value),
 				"ALBUM" => tags.album = Some(value),
-				"ARTIST" => tags.artist = Some(value),
+				"ARTIST" => tags.artists.push(value),
 				"ALBUMARTIST" => tags.album_artist = Some(value),
 				"TRACKNUMBER" => tags.track_number = value.parse::<u32>().ok(),
 				"DISCNUMBER" => tags.disc_number = value.parse::<u32>().ok(),
 				"DATE" => tags.year = value.parse::<i32>().ok(),
 				"LYRICIST" => tags.lyricist = Some(value),
 				"COMPOSER" => tags.composer = Some(value),
-				"GENRE" => tags.genre = Some(value),
+				"GENRE" => tags.genres.push(value),
 				"PUBLISHER" => tags.label = Some(value),
+				"DISCSUBTITLE" => tags.disc_subtitle = Some(value),
+				"REPLAYGAIN_TRACK_GAIN" => tags.replay_gain_track = parse_replay_gain(&value),
+				"REPLAYGAIN_ALBUM_GAIN" => tags.replay_gain_album = parse_replay_gain(&value),
 				_ => (),
 			}
 		}
 	}
 
+	tags.artists = split_tag_values(&tags.artists);
+	tags.artist = join_tag_values(&tags.artists);
+	tags.genres = split_tag_values(&tags.genres);
+	tags.genre = join_tag_values(&tags.genres);
+
 	Ok(tags)
 }
 
@@ -303,15 +515,20 @@ fn read_flac(path: &Path) -> Result<SongTags> {
 	let year = vorbis.get("DATE").and_then(|d| d[0].parse::<i32>().ok());
 	let mut streaminfo = tag.get_blocks(metaflac::BlockType::StreamInfo);
 	let duration = match streaminfo.next() {
-		Some(&metaflac::Block::StreamInfo(ref s)) => {
-			Some((s.total_samples as u32 / s.sample_rate) as u32)
-		}
+		// A sample count of zero means the length of the stream is unknown
+		Some(metaflac::Block::StreamInfo(s)) if s.total_samples > 0 => s
+			.total_samples
+			.checked_div(u64::from(s.sample_rate))
+			.map(|d| d as u32),
 		_ => None,
 	};
 	let has_artwork = tag.pictures().count() > 0;
+	let artists = split_tag_values(vorbis.artist().into_iter().flatten());
+	let genres = split_tag_values(vorbis.get("GENRE").into_iter().flatten());
 
 	Ok(SongTags {
-		artist: vorbis.artist().map(|v| v[0].clone()),
+		artist: join_tag_values(&artists),
+		artists,
 		album_artist: vorbis.album_artist().map(|v| v[0].clone()),
 		album: vorbis.album().map(|v| v[0].clone()),
 		title: vorbis.title().map(|v| v[0].clone()),
@@ -322,17 +539,33 @@ fn read_flac(path: &Path) -> Result<SongTags> {
 		has_artwork,
 		lyricist: vorbis.get("LYRICIST").map(|v| v[0].clone()),
 		composer: vorbis.get("COMPOSER").map(|v| v[0].clone()),
-		genre: vorbis.get("GENRE").map(|v| v[0].clone()),
+		genre: join_tag_values(&genres),
+		genres,
 		label: vorbis.get("PUBLISHER").map(|v| v[0].clone()),
+		disc_subtitle: vorbis.get("DISCSUBTITLE").map(|v| v[0].clone()),
+		replay_gain_track: vorbis
+			.get("REPLAYGAIN_TRACK_GAIN")
+			.and_then(|v| parse_replay_gain(&v[0])),
+		replay_gain_album: vorbis
+			.get("REPLAYGAIN_ALBUM_GAIN")
+			.and_then(|v| parse_replay_gain(&v[0])),
 	})
 }
 
 fn read_mp4(path: &Path) -> Result<SongTags> {
 	let mut tag = mp4ameta::Tag::read_from_path(path)?;
 	let label_ident = mp4ameta::FreeformIdent::new("com.apple.iTunes", "Label");
+	let disc_subtitle_ident = mp4ameta::FreeformIdent::new("com.apple.iTunes", "DISCSUBTITLE");
+	let replay_gain_track_ident =
+		mp4ameta::FreeformIdent::new("com.apple.iTunes", "replaygain_track_gain");
+	let replay_gain_album_ident =
+		mp4ameta::FreeformIdent::new("com.apple.iTunes", "replaygain_album_gain");
+	let artists = split_tag_values(tag.take_artists());
+	let genres = split_tag_values(tag.take_genres());
 
 	Ok(SongTags {
-		artist: tag.take_artist(),
+		artist: join_tag_values(&artists),
+		artists,
 		album_artist: tag.take_album_artist(),
 		album: tag.take_album(),
 		title: tag.take_title(),
@@ -343,8 +576,18 @@ fn read_mp4(path: &Path) -> Result<SongTags> {
 		has_artwork: tag.artwork().is_some(),
 		lyricist: tag.take_lyricist(),
 		composer: tag.take_composer(),
-		genre: tag.take_genre(),
+		genre: join_tag_values(&genres),
+		genres,
 		label: tag.take_string(&label_ident).next(),
+		disc_subtitle: tag.take_string(&disc_subtitle_ident).next(),
+		replay_gain_track: tag
+			.take_string(&replay_gain_track_ident)
+			.next()
+			.and_then(|g| parse_replay_gain(&g)),
+		replay_gain_album: tag
+			.take_string(&replay_gain_album_ident)
+			.next()
+			.and_then(|g| parse_replay_gain(&g)),
 	})
 }
 
@@ -355,6 +598,7 @@ fn reads_file_metadata() {
 		track_number: Some(1),
 		title: Some("TEST TITLE".into()),
 		artist: Some("TEST ARTIST".into()),
+		artists: vec!["TEST ARTIST".into()],
 		album_artist: Some("TEST ALBUM ARTIST".into()),
 		album: Some("TEST ALBUM".into()),
 		duration: None,
@@ -363,7 +607,11 @@ fn reads_file_metadata() {
 		lyricist: Some("TEST LYRICIST".into()),
 		composer: Some("TEST COMPOSER".into()),
 		genre: Some("TEST GENRE".into()),
+		genres: vec!["TEST GENRE".into()],
 		label: Some("TEST LABEL".into()),
+		disc_subtitle: None,
+		replay_gain_track: None,
+		replay_gain_album: None,
 	};
 	let flac_sample_tag = SongTags {
 		duration: Some(0),
@@ -377,6 +625,14 @@ fn reads_file_metadata() {
 		duration: Some(0),
 		..sample_tags.clone()
 	};
+	let ogg_sample_tag = SongTags {
+		duration: Some(0),
+		..sample_tags.clone()
+	};
+	let opus_sample_tag = SongTags {
+		duration: Some(30),
+		..sample_tags.clone()
+	};
 	assert_eq!(
 		read(Path::new("test-data/formats/sample.aif")).unwrap(),
 		sample_tags
@@ -387,7 +643,7 @@ fn reads_file_metadata() {
 	);
 	assert_eq!(
 		read(Path::new("test-data/formats/sample.ogg")).unwrap(),
-		sample_tags
+		ogg_sample_tag
 	);
 	assert_eq!(
 		read(Path::new("test-data/formats/sample.flac")).unwrap(),
@@ -399,7 +655,7 @@ fn reads_file_metadata() {
 	);
 	assert_eq!(
 		read(Path::new("test-data/formats/sample.opus")).unwrap(),
-		sample_tags
+		opus_sample_tag
 	);
 	assert_eq!(
 		read(Path::new("test-data/formats/sample.ape")).unwrap(),
@@ -411,6 +667,141 @@ fn reads_file_metadata() {
 	);
 }
 
+#[test]
+fn reads_disc_subtitle() {
+	let disc_subtitle = |path: &str| read(Path::new(path)).unwrap().disc_subtitle;
+	assert_eq!(
+		disc_subtitle("test-data/multi-disc/Box Set/1-01 - Above The Water.mp3"),
+		Some("The Studio Album".to_owned())
+	);
+	assert_eq!(
+		disc_subtitle("test-data/multi-disc/Box Set/2-01 - Three Gates (Live).flac"),
+		Some("Live at Roadburn".to_owned())
+	);
+	assert_eq!(
+		disc_subtitle("test-data/multi-disc/Box Set/3-01 - Beyond The Door (Demo).mp3"),
+		None
+	);
+}
+
+#[test]
+fn reads_replay_gain() {
+	let replay_gain = |path: &str| {
+		let tags = read(Path::new(path)).unwrap();
+		(tags.replay_gain_track, tags.replay_gain_album)
+	};
+	assert_eq!(
+		replay_gain("test-data/multi-disc/Box Set/1-01 - Above The Water.mp3"),
+		(Some(-6.48), Some(-7.12))
+	);
+	assert_eq!(
+		replay_gain("test-data/multi-disc/Box Set/2-01 - Three Gates (Live).flac"),
+		(Some(1.25), Some(-7.12))
+	);
+	assert_eq!(
+		replay_gain("test-data/multi-disc/Box Set/3-01 - Beyond The Door (Demo).mp3"),
+		(None, None)
+	);
+}
+
+#[test]
+fn parses_replay_gain_values() {
+	assert_eq!(parse_replay_gain("-6.48 dB"), Some(-6.48));
+	assert_eq!(parse_replay_gain("+1.25 dB"), Some(1.25));
+	assert_eq!(parse_replay_gain(" -3.1db "), Some(-3.1));
+	assert_eq!(parse_replay_gain("0.5"), Some(0.5));
+	assert_eq!(parse_replay_gain("loud"), None);
+	assert_eq!(parse_replay_gain("NaN dB"), None);
+}
+
+#[test]
+fn reads_ogg_sample_count() {
+	let page = |serial: u32, granule: u64| {
+		let mut page = b"OggS\0\0".to_vec();
+		page.extend_from_slice(&granule.to_le_bytes());
+		page.extend_from_slice(&serial.to_le_bytes());
+		page.extend_from_slice(&[0; 8]); // Sequence number and checksum
+		page.extend_from_slice(&[1, 4]); // Segment table
+		page.extend_from_slice(b"data");
+		page
+	};
+	let stream = [
+		page(7, 0),
+		page(7, 220_500),
+		page(9, 1_000_000),
+		page(7, 441_000),
+		page(7, u64::MAX),
+		page(9, 2_000_000),
+	]
+	.concat();
+
+	let mut reader = std::io::Cursor::new(stream);
+	assert_eq!(read_ogg_sample_count(&mut reader).unwrap(), 441_000);
+
+	let mut reader = std::io::Cursor::new(b"RIFF".repeat(20));
+	assert!(read_ogg_sample_count(&mut reader).is_err());
+}
+
+#[test]
+fn corrupt_stream_info_yields_no_duration() {
+	let output_dir = crate::test::prepare_test_directory(crate::test_name!());
+	let path = output_dir.join("sample.flac");
+	let mut content = fs::read("test-data/formats/sample.flac").unwrap();
+	// Zero out the sample rate in the STREAMINFO block
+	content[18] = 0;
+	content[19] = 0;
+	content[20] &= 0x0F;
+	fs::write(&path, content).unwrap();
+
+	let tags = read(&path).unwrap();
+	assert_eq!(tags.duration, None);
+	assert_eq!(tags.title, Some("TEST TITLE".into()));
+}
+
+#[test]
+fn truncates_long_text_fields() {
+	let mut tags = read(Path::new("test-data/formats/sample.mp3")).unwrap();
+	tags.title = Some("🎵".repeat(50_000));
+	tags.album = Some("12345678".into());
+
+	assert_eq!(
+		tags.truncate(8),
+		vec![
+			"title",
+			"artist",
+			"album artist",
+			"lyricist",
+			"composer",
+			"genre",
+			"label"
+		]
+	);
+	assert_eq!(tags.title, Some(format!("{}…", "🎵".repeat(7))));
+	assert_eq!(tags.artist, Some("TEST AR…".into()));
+	assert_eq!(tags.album, Some("12345678".into()));
+	assert_eq!(tags.genre, Some("TEST GE…".into()));
+	assert_eq!(tags.genres, vec!["TEST GE…".to_owned()]);
+}
+
+#[test]
+fn splits_multi_valued_tags() {
+	assert_eq!(
+		split_tag_values(vec!["Metal\0Doom Metal", " Metal ", ""]),
+		vec!["Metal".to_owned(), "Doom Metal".to_owned()]
+	);
+
+	let tags = read(Path::new(
+		"test-data/small-collection/Khemmis/Hunted/01 - Above The Water.mp3",
+	))
+	.unwrap();
+	assert_eq!(
+		tags.genres,
+		vec!["Metal".to_owned(), "Doom Metal".to_owned()]
+	);
+	assert_eq!(tags.genre, Some("Metal, Doom Metal".to_owned()));
+	assert_eq!(tags.artists, vec!["Khemmis".to_owned()]);
+}
+
 #[test]
 fn reads_embedded_artwork() {
 	assert!(
@@ -1,37 +1,201 @@
 use diesel;
 use log::error;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
 
-use crate::app::{settings, vfs};
+use crate::app::{metrics, settings, vfs};
 use crate::db::DB;
 
 mod metadata;
 mod query;
+mod schedule;
+mod search;
 #[cfg(test)]
 mod test;
 mod types;
 mod update;
 
 pub use self::query::*;
+use self::schedule::*;
+use self::search::*;
 pub use self::types::*;
 pub use self::update::*;
 
+// Events not yet received by a slow subscriber are dropped past this many
+const EVENTS_CAPACITY: usize = 16;
+
+// How often the automatic updater checks for setting changes while periodic scans are disabled
+const SCHEDULE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(thiserror::Error, Debug)]
+pub enum TriggerError {
+	#[error("VFS path not found")]
+	VFSPathNotFound,
+	#[error("Index is already being updated")]
+	AlreadyRunning,
+	#[error("Index is shutting down")]
+	ShuttingDown,
+	#[error("Unspecified")]
+	Unspecified,
+}
+
+impl From<anyhow::Error> for TriggerError {
+	fn from(_: anyhow::Error) -> Self {
+		TriggerError::Unspecified
+	}
+}
+
+/// How much work an index update does for files which were already indexed.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanMode {
+	/// Read metadata from every file.
+	Full,
+	/// Only read metadata from files whose size or modification time changed since they were indexed.
+	Incremental,
+}
+
+/// Lifecycle notifications published while the index is being updated.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IndexEvent {
+	IndexStarted,
+	IndexFinished {
+		song_count: i64,
+		directory_count: i64,
+	},
+}
+
+impl IndexEvent {
+	pub fn name(&self) -> &'static str {
+		match self {
+			IndexEvent::IndexStarted => "index_started",
+			IndexEvent::IndexFinished { .. } => "index_finished",
+		}
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct PendingReindex {
+	full: bool,
+	paths: Vec<PathBuf>,
+	incremental: bool,
+}
+
+impl PendingReindex {
+	fn is_empty(&self) -> bool {
+		!self.full && self.paths.is_empty()
+	}
+
+	fn mode(&self) -> ScanMode {
+		if self.incremental {
+			ScanMode::Incremental
+		} else {
+			ScanMode::Full
+		}
+	}
+
+	// A `None` path stands for the whole collection
+	fn covers(&self, virtual_path: Option<&Path>, mode: ScanMode) -> bool {
+		if self.incremental && mode == ScanMode::Full {
+			return false;
+		}
+		match virtual_path {
+			None => self.full,
+			Some(p) => self.full || self.paths.iter().any(|q| p.starts_with(q)),
+		}
+	}
+
+	// Requests are merged into a single update, which is only incremental if all of them are
+	fn add(&mut self, virtual_path: Option<&Path>, mode: ScanMode) {
+		let incremental = mode == ScanMode::Incremental;
+		self.incremental = if self.is_empty() {
+			incremental
+		} else {
+			self.incremental && incremental
+		};
+		match virtual_path {
+			None => self.full = true,
+			Some(p) => {
+				if !self.paths.iter().any(|q| q == p) {
+					self.paths.push(p.to_owned());
+				}
+			}
+		}
+	}
+}
+
+#[derive(Debug, Default)]
+struct ReindexState {
+	pending: PendingReindex,
+	running: Option<PendingReindex>,
+	shutting_down: bool,
+}
+
+impl ReindexState {
+	fn request(
+		&mut self,
+		policy: settings::IndexTriggerPolicy,
+		virtual_path: Option<&Path>,
+		mode: ScanMode,
+	) -> Result<(), TriggerError> {
+		if self.shutting_down {
+			return Err(TriggerError::ShuttingDown);
+		}
+		let busy = self.running.is_some() || !self.pending.is_empty();
+		match policy {
+			settings::IndexTriggerPolicy::Reject if busy => {
+				return Err(TriggerError::AlreadyRunning);
+			}
+			settings::IndexTriggerPolicy::Coalesce => {
+				if let Some(running) = &self.running {
+					if running.covers(virtual_path, mode) {
+						return Ok(());
+					}
+				}
+			}
+			_ => (),
+		}
+		self.pending.add(virtual_path, mode);
+		Ok(())
+	}
+}
+
 #[derive(Clone)]
 pub struct Index {
 	db: DB,
 	vfs_manager: vfs::Manager,
 	settings_manager: settings::Manager,
-	pending_reindex: Arc<(Mutex<bool>, Condvar)>,
+	metrics_manager: metrics::Manager,
+	reindex_state: Arc<(Mutex<ReindexState>, Condvar)>,
+	stop_scan: Arc<AtomicBool>,
+	scan_progress: Arc<ScanProgress>,
+	events: broadcast::Sender<IndexEvent>,
+	insert_buffer_size: usize,
 }
 
 impl Index {
-	pub fn new(db: DB, vfs_manager: vfs::Manager, settings_manager: settings::Manager) -> Self {
+	pub fn new(
+		db: DB,
+		vfs_manager: vfs::Manager,
+		settings_manager: settings::Manager,
+		metrics_manager: metrics::Manager,
+		insert_buffer_size: usize,
+	) -> Self {
 		let index = Self {
 			db,
 			vfs_manager,
 			settings_manager,
-			pending_reindex: Arc::new((Mutex::new(false), Condvar::new())),
+			metrics_manager,
+			reindex_state: Arc::new((Mutex::new(ReindexState::default()), Condvar::new())),
+			stop_scan: Arc::new(AtomicBool::new(false)),
+			scan_progress: Arc::new(ScanProgress::default()),
+			events: broadcast::channel(EVENTS_CAPACITY).0,
+			insert_buffer_size: insert_buffer_size.clamp(1, MAX_INSERT_BUFFER_SIZE),
 		};
 
 		let commands_index = index.clone();
@@ -42,11 +206,110 @@ impl Index {
 		index
 	}
 
-	pub fn trigger_reindex(&self) {
-		let (lock, cvar) = &*self.pending_reindex;
-		let mut pending_reindex = lock.lock().unwrap();
-		*pending_reindex = true;
+	pub fn trigger_reindex(&self) -> Result<(), TriggerError> {
+		self.request_reindex(None, ScanMode::Full)
+	}
+
+	pub fn trigger_incremental_reindex(&self) -> Result<(), TriggerError> {
+		self.request_reindex(None, ScanMode::Incremental)
+	}
+
+	pub fn trigger_reindex_path(&self, virtual_path: &Path) -> Result<(), TriggerError> {
+		self.trigger_reindex_path_with_mode(virtual_path, ScanMode::Full)
+	}
+
+	pub fn trigger_incremental_reindex_path(
+		&self,
+		virtual_path: &Path,
+	) -> Result<(), TriggerError> {
+		self.trigger_reindex_path_with_mode(virtual_path, ScanMode::Incremental)
+	}
+
+	fn trigger_reindex_path_with_mode(
+		&self,
+		virtual_path: &Path,
+		mode: ScanMode,
+	) -> Result<(), TriggerError> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		vfs.virtual_to_real(virtual_path)
+			.map_err(|_| TriggerError::VFSPathNotFound)?;
+		self.request_reindex(Some(virtual_path), mode)
+	}
+
+	pub fn is_updating(&self) -> bool {
+		let (lock, _) = &*self.reindex_state;
+		let state = lock.lock().unwrap();
+		state.running.is_some() || !state.pending.is_empty()
+	}
+
+	pub fn get_progress(&self) -> anyhow::Result<Progress> {
+		let is_running = {
+			let (lock, _) = &*self.reindex_state;
+			lock.lock().unwrap().running.is_some()
+		};
+		if !is_running {
+			return Ok(Progress {
+				state: ScanState::Idle,
+				processed: 0,
+				total: 0,
+				current_path: None,
+			});
+		}
+		let vfs = self.vfs_manager.get_vfs()?;
+		let current_path = self
+			.scan_progress
+			.current_path()
+			.and_then(|p| vfs.real_to_virtual(p).ok())
+			.map(|p| p.to_string_lossy().into_owned());
+		Ok(Progress {
+			state: ScanState::Scanning,
+			processed: self.scan_progress.processed(),
+			total: self.scan_progress.total(),
+			current_path,
+		})
+	}
+
+	/// Receives the events published by index updates starting after this call.
+	pub fn subscribe(&self) -> broadcast::Receiver<IndexEvent> {
+		self.events.subscribe()
+	}
+
+	fn publish(&self, event: IndexEvent) {
+		// Sending only fails when nobody is listening
+		let _ = self.events.send(event);
+	}
+
+	fn request_reindex(
+		&self,
+		virtual_path: Option<&Path>,
+		mode: ScanMode,
+	) -> Result<(), TriggerError> {
+		let policy = self
+			.settings_manager
+			.get_index_trigger_policy()
+			.unwrap_or_else(|e| {
+				error!("Could not retrieve index trigger policy: {}", e);
+				settings::IndexTriggerPolicy::default()
+			});
+		let (lock, cvar) = &*self.reindex_state;
+		let mut state = lock.lock().unwrap();
+		state.request(policy, virtual_path, mode)?;
 		cvar.notify_one();
+		Ok(())
+	}
+
+	/// Stops accepting index updates and interrupts the one in progress, if any.
+	/// Returns once content found by the interrupted update has been written to the database.
+	pub fn shutdown(&self) {
+		self.stop_scan.store(true, Ordering::SeqCst);
+		let (lock, cvar) = &*self.reindex_state;
+		let mut state = lock.lock().unwrap();
+		state.shutting_down = true;
+		state.pending = PendingReindex::default();
+		cvar.notify_all();
+		while state.running.is_some() {
+			state = cvar.wait(state).unwrap();
+		}
 	}
 
 	pub fn begin_periodic_updates(&self) {
@@ -58,31 +321,111 @@ impl Index {
 
 	fn process_commands(&self) {
 		loop {
-			{
-				let (lock, cvar) = &*self.pending_reindex;
-				let mut pending = lock.lock().unwrap();
-				while !*pending {
-					pending = cvar.wait(pending).unwrap();
+			let pending = {
+				let (lock, cvar) = &*self.reindex_state;
+				let mut state = lock.lock().unwrap();
+				while state.pending.is_empty() && !state.shutting_down {
+					state = cvar.wait(state).unwrap();
+				}
+				if state.shutting_down {
+					return;
+				}
+				let pending = std::mem::take(&mut state.pending);
+				state.running = Some(pending.clone());
+				self.scan_progress.reset();
+				pending
+			};
+			self.publish(IndexEvent::IndexStarted);
+			let start = Instant::now();
+			let mode = pending.mode();
+			if pending.full {
+				if let Err(e) = self.update_with_mode(mode) {
+					error!("Error while updating index: {}", e);
+				}
+			} else {
+				for path in &pending.paths {
+					if self.stop_scan.load(Ordering::SeqCst) {
+						break;
+					}
+					if let Err(e) = self.update_path_with_mode(path, mode) {
+						error!("Error while updating index for `{}`: {}", path.display(), e);
+					}
 				}
-				*pending = false;
 			}
-			if let Err(e) = self.update() {
-				error!("Error while updating index: {}", e);
+			self.metrics_manager.record_index_duration(start.elapsed());
+			let (lock, cvar) = &*self.reindex_state;
+			lock.lock().unwrap().running = None;
+			cvar.notify_all();
+			match self.get_status() {
+				Ok(status) => self.publish(IndexEvent::IndexFinished {
+					song_count: status.song_count,
+					directory_count: status.directory_count,
+				}),
+				Err(e) => error!("Could not count indexed content: {}", e),
 			}
 		}
 	}
 
 	fn automatic_reindex(&self) {
+		let start_delay = self
+			.settings_manager
+			.get_index_start_delay()
+			.unwrap_or_else(|e| {
+				error!("Could not retrieve index start delay: {}", e);
+				Duration::from_secs(0)
+			});
+		std::thread::sleep(start_delay);
+		let mut schedule = ScanSchedule::default();
 		loop {
-			self.trigger_reindex();
-			let sleep_duration = self
+			let default_interval = self
 				.settings_manager
 				.get_index_sleep_duration()
 				.unwrap_or_else(|e| {
 					error!("Could not retrieve index sleep duration: {}", e);
-					Duration::from_secs(1800)
+					Some(Duration::from_secs(1800))
 				});
+			let mounts: Vec<MountSchedule> = match self.vfs_manager.mount_dirs() {
+				Ok(mount_dirs) => mount_dirs
+					.iter()
+					.map(|m| MountSchedule::new(m, default_interval))
+					.collect(),
+				Err(e) => {
+					error!("Could not retrieve mount points: {}", e);
+					Vec::new()
+				}
+			};
+
+			// Periodic updates never pile up behind manual ones
+			if !self.is_updating() {
+				let due = schedule.take_due(&mounts, Instant::now());
+				self.trigger_scheduled_reindex(&due, mounts.len());
+			}
+
+			// Settings are read again after sleeping, so that changes apply without a restart
+			let refresh_interval = default_interval.unwrap_or(SCHEDULE_REFRESH_INTERVAL);
+			let sleep_duration = schedule
+				.time_until_next(&mounts, Instant::now())
+				.unwrap_or(refresh_interval)
+				.min(refresh_interval)
+				.max(Duration::from_secs(1));
 			std::thread::sleep(sleep_duration);
 		}
 	}
+
+	fn trigger_scheduled_reindex(&self, due_mounts: &[String], num_mounts: usize) {
+		// A full update also cleans up content from mounts which no longer exist
+		if !due_mounts.is_empty() && due_mounts.len() == num_mounts {
+			match self.trigger_reindex() {
+				Ok(()) | Err(TriggerError::AlreadyRunning) | Err(TriggerError::ShuttingDown) => (),
+				Err(e) => error!("Could not trigger index update: {}", e),
+			}
+			return;
+		}
+		for name in due_mounts {
+			match self.trigger_reindex_path(Path::new(name)) {
+				Ok(()) | Err(TriggerError::AlreadyRunning) | Err(TriggerError::ShuttingDown) => (),
+				Err(e) => error!("Could not trigger index update for `{}`: {}", name, e),
+			}
+		}
+	}
 }
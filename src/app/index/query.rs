@@ -3,15 +3,34 @@ use diesel;
 use diesel::dsl::sql;
 use diesel::prelude::*;
 use diesel::sql_types;
-use std::path::Path;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 use super::*;
-use crate::db::{directories, songs};
+use crate::db::{directories, misc_settings, song_genres, songs};
+
+const MAX_ALBUMS: i64 = 500;
+
+pub const DEFAULT_BROWSE_DEPTH: u32 = 1;
+pub const MAX_BROWSE_DEPTH: u32 = 4;
+
+pub const MAX_SONG_LOOKUP_PATHS: usize = 1000;
+
+// Keeps queries below the SQLite limit on bound parameters
+const SONG_LOOKUP_BATCH_SIZE: usize = 500;
 
 #[derive(thiserror::Error, Debug)]
 pub enum QueryError {
 	#[error("VFS path not found")]
 	VFSPathNotFound,
+	#[error("Browse depth must be between 1 and {}", MAX_BROWSE_DEPTH)]
+	UnsupportedDepth,
+	#[error("Song lookups are limited to {} paths", MAX_SONG_LOOKUP_PATHS)]
+	TooManyPaths,
 	#[error("Unspecified")]
 	Unspecified,
 }
@@ -22,6 +41,106 @@ impl From<anyhow::Error> for QueryError {
 	}
 }
 
+/// Position of a paginated browse query. Directories are listed before songs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BrowseCursor {
+	Directories(i64),
+	Songs(i64),
+}
+
+impl Default for BrowseCursor {
+	fn default() -> Self {
+		BrowseCursor::Directories(0)
+	}
+}
+
+/// Subset of the results of a query. Everything is returned by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Range {
+	pub offset: Option<i64>,
+	pub count: Option<i64>,
+}
+
+/// What browse and flatten results are ordered by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+	#[default]
+	Name,
+	DateAdded,
+	Year,
+	Artist,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+	#[default]
+	Asc,
+	Desc,
+}
+
+/// Ordering of browse and flatten results. Sorts by ascending name by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sort {
+	pub field: SortField,
+	pub order: SortOrder,
+}
+
+// SQL expressions that each sort field translates to for a given table
+struct SortColumns {
+	name: &'static [&'static str],
+	date_added: &'static str,
+	year: &'static str,
+	artist: &'static str,
+}
+
+const DIRECTORY_SORT_COLUMNS: SortColumns = SortColumns {
+	name: &["path COLLATE NOCASE"],
+	date_added: "date_added",
+	year: "year",
+	artist: "artist COLLATE NOCASE",
+};
+
+// Songs are dated by the directory they were found in
+const SONG_SORT_COLUMNS: SortColumns = SortColumns {
+	name: &["path COLLATE NOCASE"],
+	date_added: "(SELECT date_added FROM directories WHERE directories.path = songs.parent)",
+	year: "year",
+	artist: "COALESCE(album_artist, artist) COLLATE NOCASE",
+};
+
+// Songs of an album play in disc and track order, for gapless playback
+const FLATTEN_SORT_COLUMNS: SortColumns = SortColumns {
+	name: &["parent", "disc_number", "track_number", "path"],
+	..SONG_SORT_COLUMNS
+};
+
+impl Sort {
+	// Ties are broken by name so that results are always returned in the same order
+	fn order_by(&self, columns: &SortColumns) -> String {
+		let direction = match self.order {
+			SortOrder::Asc => "ASC",
+			SortOrder::Desc => "DESC",
+		};
+		let name = |direction| {
+			columns
+				.name
+				.iter()
+				.map(|c| format!("{} {}", c, direction))
+				.collect::<Vec<_>>()
+				.join(", ")
+		};
+		let key = match self.field {
+			SortField::Name => return name(direction),
+			SortField::DateAdded => columns.date_added,
+			SortField::Year => columns.year,
+			SortField::Artist => columns.artist,
+		};
+		format!("{} {}, {}", key, direction, name("ASC"))
+	}
+}
+
 no_arg_sql_function!(
 	random,
 	sql_types::Integer,
@@ -29,7 +148,7 @@ no_arg_sql_function!(
 );
 
 impl Index {
-	pub fn browse<P>(&self, virtual_path: P) -> Result<Vec<CollectionFile>, QueryError>
+	pub fn browse<P>(&self, virtual_path: P, sort: Sort) -> Result<Vec<CollectionFile>, QueryError>
 	where
 		P: AsRef<Path>,
 	{
@@ -41,6 +160,9 @@ impl Index {
 			// Browse top-level
 			let real_directories: Vec<Directory> = directories::table
 				.filter(directories::parent.is_null())
+				.order(sql::<sql_types::Bool>(
+					&sort.order_by(&DIRECTORY_SORT_COLUMNS),
+				))
 				.load(&connection)
 				.map_err(anyhow::Error::new)?;
 			let virtual_directories = real_directories
@@ -56,7 +178,9 @@ impl Index {
 
 			let real_directories: Vec<Directory> = directories::table
 				.filter(directories::parent.eq(&real_path_string))
-				.order(sql::<sql_types::Bool>("path COLLATE NOCASE ASC"))
+				.order(sql::<sql_types::Bool>(
+					&sort.order_by(&DIRECTORY_SORT_COLUMNS),
+				))
 				.load(&connection)
 				.map_err(anyhow::Error::new)?;
 			let virtual_directories = real_directories
@@ -66,17 +190,133 @@ impl Index {
 
 			let real_songs: Vec<Song> = songs::table
 				.filter(songs::parent.eq(&real_path_string))
-				.order(sql::<sql_types::Bool>("path COLLATE NOCASE ASC"))
+				.order(sql::<sql_types::Bool>(&sort.order_by(&SONG_SORT_COLUMNS)))
 				.load(&connection)
 				.map_err(anyhow::Error::new)?;
 			let virtual_songs = real_songs.into_iter().filter_map(|s| s.virtualize(&vfs));
-			output.extend(virtual_songs.map(CollectionFile::Song));
+			output.extend(virtual_songs.map(|s| CollectionFile::Song(Box::new(s))));
 		}
 
 		Ok(output)
 	}
 
-	pub fn flatten<P>(&self, virtual_path: P) -> Result<Vec<Song>, QueryError>
+	/// Lists the content of a directory, along with the content of its sub-directories down to
+	/// `depth` levels. A depth of 1 is equivalent to `browse`.
+	pub fn browse_tree<P>(
+		&self,
+		virtual_path: P,
+		sort: Sort,
+		depth: u32,
+	) -> Result<Vec<BrowseEntry>, QueryError>
+	where
+		P: AsRef<Path>,
+	{
+		if depth == 0 || depth > MAX_BROWSE_DEPTH {
+			return Err(QueryError::UnsupportedDepth);
+		}
+		self.browse(virtual_path, sort)?
+			.into_iter()
+			.map(|file| match file {
+				CollectionFile::Directory(directory) => {
+					let children = if depth > 1 {
+						Some(self.browse_tree(&directory.path, sort, depth - 1)?)
+					} else {
+						None
+					};
+					Ok(BrowseEntry::Directory(DirectoryTree {
+						directory,
+						children,
+					}))
+				}
+				CollectionFile::Song(song) => Ok(BrowseEntry::Song(song)),
+			})
+			.collect()
+	}
+
+	/// Returns the batch of files starting at `cursor`, along with the cursor of the next batch
+	/// (if any). Batches are never larger than `batch_size` but may be smaller.
+	pub fn browse_batch<P>(
+		&self,
+		virtual_path: P,
+		cursor: BrowseCursor,
+		batch_size: i64,
+		sort: Sort,
+	) -> Result<(Vec<CollectionFile>, Option<BrowseCursor>), QueryError>
+	where
+		P: AsRef<Path>,
+	{
+		let vfs = self.vfs_manager.get_vfs()?;
+		let connection = self.db.connect()?;
+
+		let real_path_string = if virtual_path.as_ref().components().count() == 0 {
+			None
+		} else {
+			let real_path = vfs
+				.virtual_to_real(virtual_path)
+				.map_err(|_| QueryError::VFSPathNotFound)?;
+			Some(real_path.as_path().to_string_lossy().into_owned())
+		};
+
+		match cursor {
+			BrowseCursor::Directories(offset) => {
+				let query = directories::table
+					.order(sql::<sql_types::Bool>(
+						&sort.order_by(&DIRECTORY_SORT_COLUMNS),
+					))
+					.limit(batch_size)
+					.offset(offset)
+					.into_boxed();
+				let query = match &real_path_string {
+					Some(p) => query.filter(directories::parent.eq(p)),
+					None => query.filter(directories::parent.is_null()),
+				};
+				let real_directories: Vec<Directory> =
+					query.load(&connection).map_err(anyhow::Error::new)?;
+				let next = if real_directories.len() as i64 == batch_size {
+					Some(BrowseCursor::Directories(offset + batch_size))
+				} else if real_path_string.is_some() {
+					Some(BrowseCursor::Songs(0))
+				} else {
+					None
+				};
+				let virtual_directories = real_directories
+					.into_iter()
+					.filter_map(|d| d.virtualize(&vfs))
+					.map(CollectionFile::Directory);
+				Ok((virtual_directories.collect(), next))
+			}
+			BrowseCursor::Songs(offset) => {
+				let real_path_string = match real_path_string {
+					Some(p) => p,
+					None => return Ok((Vec::new(), None)),
+				};
+				let real_songs: Vec<Song> = songs::table
+					.filter(songs::parent.eq(&real_path_string))
+					.order(sql::<sql_types::Bool>(&sort.order_by(&SONG_SORT_COLUMNS)))
+					.limit(batch_size)
+					.offset(offset)
+					.load(&connection)
+					.map_err(anyhow::Error::new)?;
+				let next = if real_songs.len() as i64 == batch_size {
+					Some(BrowseCursor::Songs(offset + batch_size))
+				} else {
+					None
+				};
+				let virtual_songs = real_songs
+					.into_iter()
+					.filter_map(|s| s.virtualize(&vfs))
+					.map(|s| CollectionFile::Song(Box::new(s)));
+				Ok((virtual_songs.collect(), next))
+			}
+		}
+	}
+
+	pub fn flatten<P>(
+		&self,
+		virtual_path: P,
+		range: Range,
+		sort: Sort,
+	) -> Result<Vec<Song>, QueryError>
 	where
 		P: AsRef<Path>,
 	{
@@ -84,7 +324,12 @@ impl Index {
 		let vfs = self.vfs_manager.get_vfs()?;
 		let connection = self.db.connect()?;
 
-		let real_songs: Vec<Song> = if virtual_path.as_ref().parent() != None {
+		let mut query = songs
+			.order(sql::<sql_types::Bool>(
+				&sort.order_by(&FLATTEN_SORT_COLUMNS),
+			))
+			.into_boxed();
+		if virtual_path.as_ref().parent().is_some() {
 			let real_path = vfs
 				.virtual_to_real(virtual_path)
 				.map_err(|_| QueryError::VFSPathNotFound)?;
@@ -93,30 +338,172 @@ impl Index {
 				path_buf.push("%");
 				path_buf.as_path().to_string_lossy().into_owned()
 			};
-			songs
-				.filter(path.like(&song_path_filter))
-				.order(path)
-				.load(&connection)
-				.map_err(anyhow::Error::new)?
-		} else {
-			songs
-				.order(path)
-				.load(&connection)
-				.map_err(anyhow::Error::new)?
-		};
+			query = query.filter(path.like(song_path_filter));
+		}
+		if range != Range::default() {
+			// SQLite only supports OFFSET after a LIMIT, where -1 stands for no limit
+			query = query
+				.limit(range.count.unwrap_or(-1))
+				.offset(range.offset.unwrap_or(0));
+		}
+		let real_songs: Vec<Song> = query.load(&connection).map_err(anyhow::Error::new)?;
 
 		let virtual_songs = real_songs.into_iter().filter_map(|s| s.virtualize(&vfs));
 		Ok(virtual_songs.collect::<Vec<_>>())
 	}
 
-	pub fn get_random_albums(&self, count: i64) -> Result<Vec<Directory>> {
+	/// Same as `flatten`, with each song pointing to the one after it when they are in the same
+	/// directory, so clients can preload it.
+	pub fn flatten_sequenced<P>(
+		&self,
+		virtual_path: P,
+		range: Range,
+		sort: Sort,
+	) -> Result<Vec<SequencedSong>, QueryError>
+	where
+		P: AsRef<Path>,
+	{
+		// One extra song is read so that the last song of the range knows what follows it
+		let lookahead = Range {
+			count: range.count.map(|c| c + 1),
+			..range
+		};
+		let songs = self.flatten(virtual_path, lookahead, sort)?;
+
+		let mut sequenced_songs: Vec<SequencedSong> = Vec::with_capacity(songs.len());
+		for song in songs.into_iter().rev() {
+			let next_path = sequenced_songs
+				.last()
+				.filter(|next| next.song.parent == song.parent)
+				.map(|next| next.song.path.clone());
+			sequenced_songs.push(SequencedSong { song, next_path });
+		}
+		sequenced_songs.reverse();
+		if let Some(count) = range.count {
+			sequenced_songs.truncate(count.max(0) as usize);
+		}
+		Ok(sequenced_songs)
+	}
+
+	pub fn get_album(&self, virtual_path: &Path) -> Result<Album, QueryError> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let connection = self.db.connect()?;
+
+		let real_path = vfs
+			.virtual_to_real(virtual_path)
+			.map_err(|_| QueryError::VFSPathNotFound)?;
+		let real_path_string = real_path.as_path().to_string_lossy().into_owned();
+
+		let real_directory: Directory = directories::table
+			.filter(directories::path.eq(&real_path_string))
+			.get_result(&connection)
+			.optional()
+			.map_err(anyhow::Error::new)?
+			.ok_or(QueryError::VFSPathNotFound)?;
+		let directory = real_directory
+			.virtualize(&vfs)
+			.ok_or(QueryError::VFSPathNotFound)?;
+
+		let real_songs: Vec<Song> = songs::table
+			.filter(songs::parent.eq(&real_path_string))
+			.order((songs::disc_number, songs::track_number, songs::path))
+			.load(&connection)
+			.map_err(anyhow::Error::new)?;
+
+		let mut discs: Vec<Disc> = Vec::new();
+		let mut disc_songs: Vec<Song> = Vec::new();
+		let mut disc_number = None;
+		for song in real_songs.into_iter().filter_map(|s| s.virtualize(&vfs)) {
+			if song.disc_number != disc_number && !disc_songs.is_empty() {
+				discs.push(Disc::new(disc_number, std::mem::take(&mut disc_songs)));
+			}
+			disc_number = song.disc_number;
+			disc_songs.push(song);
+		}
+		if !disc_songs.is_empty() {
+			discs.push(Disc::new(disc_number, disc_songs));
+		}
+
+		Ok(Album { directory, discs })
+	}
+
+	// Real path of the artwork of a song or directory. Embedded artwork is designated by the path
+	// of the audio file holding it.
+	pub fn get_artwork(&self, virtual_path: &Path) -> Result<Option<PathBuf>, QueryError> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let connection = self.db.connect()?;
+
+		let real_path = vfs
+			.virtual_to_real(virtual_path)
+			.map_err(|_| QueryError::VFSPathNotFound)?;
+		let real_path_string = real_path.as_path().to_string_lossy().into_owned();
+
+		let song_artwork: Option<Option<String>> = songs::table
+			.select(songs::artwork)
+			.filter(songs::path.eq(&real_path_string))
+			.get_result(&connection)
+			.optional()
+			.map_err(anyhow::Error::new)?;
+		let artwork = match song_artwork {
+			Some(artwork) => artwork,
+			None => directories::table
+				.select(directories::artwork)
+				.filter(directories::path.eq(&real_path_string))
+				.get_result(&connection)
+				.optional()
+				.map_err(anyhow::Error::new)?
+				.ok_or(QueryError::VFSPathNotFound)?,
+		};
+		Ok(artwork.map(PathBuf::from))
+	}
+
+	// Albums are shuffled differently on every call unless a seed is provided. Calls sharing a
+	// seed agree on the order, so a larger count returns the same albums followed by new ones.
+	pub fn get_random_albums(&self, count: i64, seed: Option<u64>) -> Result<Vec<Directory>> {
+		use self::directories::dsl::*;
+		let count = count.clamp(0, MAX_ALBUMS);
+		let vfs = self.vfs_manager.get_vfs()?;
+		let connection = self.db.connect()?;
+		let real_directories: Vec<Directory> = match seed {
+			None => directories
+				.filter(album.is_not_null())
+				.limit(count)
+				.order(random)
+				.load(&connection)?,
+			Some(seed) => {
+				let mut paths: Vec<String> = directories
+					.filter(album.is_not_null())
+					.select(path)
+					.order(path)
+					.load(&connection)?;
+				paths.shuffle(&mut StdRng::seed_from_u64(seed));
+				paths.truncate(count as usize);
+				let positions: HashMap<&str, usize> = paths
+					.iter()
+					.enumerate()
+					.map(|(i, p)| (p.as_str(), i))
+					.collect();
+				let mut albums: Vec<Directory> =
+					directories.filter(path.eq_any(&paths)).load(&connection)?;
+				albums.sort_by_key(|d| positions.get(d.path.as_str()).copied());
+				albums
+			}
+		};
+		let virtual_directories = real_directories
+			.into_iter()
+			.filter_map(|d| d.virtualize(&vfs));
+		Ok(virtual_directories.collect::<Vec<_>>())
+	}
+
+	pub fn get_recent_albums(&self, count: i64) -> Result<Vec<Directory>> {
 		use self::directories::dsl::*;
+		let count = count.clamp(0, MAX_ALBUMS);
 		let vfs = self.vfs_manager.get_vfs()?;
 		let connection = self.db.connect()?;
 		let real_directories: Vec<Directory> = directories
 			.filter(album.is_not_null())
+			.order(date_added.desc())
 			.limit(count)
-			.order(random)
 			.load(&connection)?;
 		let virtual_directories = real_directories
 			.into_iter()
@@ -124,14 +511,78 @@ impl Index {
 		Ok(virtual_directories.collect::<Vec<_>>())
 	}
 
-	pub fn get_recent_albums(&self, count: i64) -> Result<Vec<Directory>> {
+	/// Artists of the albums in the collection, grouped by album artist (falling back to the song
+	/// artist) regardless of how the album directories are laid out.
+	pub fn get_artists(&self) -> Result<Vec<Artist>> {
+		let connection = self.db.connect()?;
+		let artists = diesel::sql_query(format!(
+			r#"
+			SELECT {} AS name, COUNT(DISTINCT s.parent) AS album_count
+			FROM songs s
+			INNER JOIN directories d ON d.path = s.parent
+			WHERE d.album IS NOT NULL
+			GROUP BY name
+			ORDER BY name COLLATE NOCASE
+		"#,
+			GROUPING_ARTIST_SQL
+		))
+		.get_results(&connection)?;
+		Ok(artists)
+	}
+
+	pub fn get_artist_albums(&self, name: &str) -> Result<Vec<Directory>> {
 		use self::directories::dsl::*;
 		let vfs = self.vfs_manager.get_vfs()?;
 		let connection = self.db.connect()?;
 		let real_directories: Vec<Directory> = directories
 			.filter(album.is_not_null())
-			.order(date_added.desc())
-			.limit(count)
+			.filter(
+				sql::<sql_types::Bool>(&format!(
+					"path IN (SELECT s.parent FROM songs s WHERE {} = ",
+					GROUPING_ARTIST_SQL
+				))
+				.bind::<sql_types::Text, _>(name)
+				.sql(")"),
+			)
+			.order((year, album))
+			.load(&connection)?;
+		let virtual_directories = real_directories
+			.into_iter()
+			.filter_map(|d| d.virtualize(&vfs));
+		Ok(virtual_directories.collect::<Vec<_>>())
+	}
+
+	/// Genres found in the collection, counting each value of multi-valued genre tags separately.
+	pub fn get_genres(&self) -> Result<Vec<Genre>> {
+		let connection = self.db.connect()?;
+		let genres = diesel::sql_query(
+			r#"
+			SELECT g.genre AS name, COUNT(*) AS song_count, COUNT(DISTINCT s.parent) AS album_count
+			FROM song_genres g
+			INNER JOIN songs s ON g.path = s.path
+			GROUP BY g.genre
+			ORDER BY g.genre COLLATE NOCASE
+		"#,
+		)
+		.get_results(&connection)?;
+		Ok(genres)
+	}
+
+	pub fn get_albums_by_genre(&self, genre: &str) -> Result<Vec<Directory>> {
+		use self::directories::dsl::*;
+		let vfs = self.vfs_manager.get_vfs()?;
+		let connection = self.db.connect()?;
+		let tagged_songs = song_genres::table
+			.select(song_genres::path)
+			.filter(song_genres::genre.eq(genre));
+		// Kept as a subquery, binding every album path would exceed SQLite's variable limit
+		let album_paths = songs::table
+			.select(songs::parent)
+			.filter(songs::path.eq_any(tagged_songs));
+		let real_directories: Vec<Directory> = directories
+			.filter(album.is_not_null())
+			.filter(path.eq_any(album_paths))
+			.order((artist, year, album))
 			.load(&connection)?;
 		let virtual_directories = real_directories
 			.into_iter()
@@ -140,48 +591,124 @@ impl Index {
 	}
 
 	pub fn search(&self, query: &str) -> Result<Vec<CollectionFile>> {
+		let search_query = SearchQuery::parse(query);
+		if !search_query.filters.is_empty() {
+			return self.search_fields(&search_query);
+		}
+
 		let vfs = self.vfs_manager.get_vfs()?;
 		let connection = self.db.connect()?;
-		let like_test = format!("%{}%", query);
+		let like_test = format!("%{}%", normalize(query));
 		let mut output = Vec::new();
 
 		// Find dirs with matching path and parent not matching
+		let matching_directories: HashSet<String>;
 		{
 			use self::directories::dsl::*;
 			let real_directories: Vec<Directory> = directories
-				.filter(path.like(&like_test))
-				.filter(parent.not_like(&like_test))
+				.filter(search_text.like(&like_test))
 				.load(&connection)?;
+			matching_directories = real_directories.iter().map(|d| d.path.clone()).collect();
 
 			let virtual_directories = real_directories
 				.into_iter()
+				.filter(|d| match &d.parent {
+					Some(p) => !matching_directories.contains(p),
+					None => true,
+				})
 				.filter_map(|d| d.virtualize(&vfs));
 
 			output.extend(virtual_directories.map(CollectionFile::Directory));
 		}
 
-		// Find songs with matching title/album/artist and non-matching parent
+		// Find songs with matching path/title/album/artist and non-matching parent
 		{
 			use self::songs::dsl::*;
 			let real_songs: Vec<Song> = songs
-				.filter(
-					path.like(&like_test)
-						.or(title.like(&like_test))
-						.or(album.like(&like_test))
-						.or(artist.like(&like_test))
-						.or(album_artist.like(&like_test)),
-				)
-				.filter(parent.not_like(&like_test))
+				.filter(search_text.like(&like_test))
 				.load(&connection)?;
 
-			let virtual_songs = real_songs.into_iter().filter_map(|d| d.virtualize(&vfs));
+			let virtual_songs = real_songs
+				.into_iter()
+				.filter(|s| !matching_directories.contains(&s.parent))
+				.filter_map(|d| d.virtualize(&vfs));
+
+			output.extend(virtual_songs.map(|s| CollectionFile::Song(Box::new(s))));
+		}
+
+		Ok(output)
+	}
+
+	// Finds directories and songs matching every clause of the query. Songs within a matching
+	// directory are left out, like in regular searches.
+	fn search_fields(&self, query: &SearchQuery) -> Result<Vec<CollectionFile>> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let connection = self.db.connect()?;
+		let mut output = Vec::new();
+
+		let mut matching_directories = HashSet::new();
+		if let Some(directory_query) = directory_search(query) {
+			let real_directories: Vec<Directory> = directory_query.load(&connection)?;
+			matching_directories.extend(real_directories.iter().map(|d| d.path.clone()));
 
-			output.extend(virtual_songs.map(CollectionFile::Song));
+			let virtual_directories = real_directories
+				.into_iter()
+				.filter_map(|d| d.virtualize(&vfs));
+			output.extend(virtual_directories.map(CollectionFile::Directory));
+		}
+
+		{
+			let real_songs: Vec<Song> = song_search(query).load(&connection)?;
+
+			let virtual_songs = real_songs
+				.into_iter()
+				.filter(|s| !matching_directories.contains(&s.parent))
+				.filter_map(|s| s.virtualize(&vfs));
+			output.extend(virtual_songs.map(|s| CollectionFile::Song(Box::new(s))));
 		}
 
 		Ok(output)
 	}
 
+	/// Counts the directories and songs a search for `query` returns, without loading them.
+	pub fn search_summary(&self, query: &str) -> Result<SearchSummary> {
+		let mut search_query = SearchQuery::parse(query);
+		let nest_directories = search_query.filters.is_empty();
+		if nest_directories {
+			search_query.text = query.to_owned();
+		}
+
+		let connection = self.db.connect()?;
+
+		let directory_count = match directory_search(&search_query) {
+			None => 0,
+			Some(mut directory_query) => {
+				// Regular searches leave out directories within a matching directory
+				if nest_directories {
+					use self::directories::dsl::*;
+					let matching_paths = directory_search(&search_query)
+						.unwrap()
+						.select(path.nullable());
+					directory_query =
+						directory_query.filter(parent.is_null().or(parent.ne_all(matching_paths)));
+				}
+				directory_query.count().get_result(&connection)?
+			}
+		};
+
+		let mut song_query = song_search(&search_query);
+		if let Some(matching_directories) = directory_search(&search_query) {
+			let matching_paths = matching_directories.select(directories::path);
+			song_query = song_query.filter(songs::parent.ne_all(matching_paths));
+		}
+		let song_count = song_query.count().get_result(&connection)?;
+
+		Ok(SearchSummary {
+			directory_count,
+			song_count,
+		})
+	}
+
 	pub fn get_song(&self, virtual_path: &Path) -> Result<Song> {
 		let vfs = self.vfs_manager.get_vfs()?;
 		let connection = self.db.connect()?;
@@ -199,4 +726,116 @@ impl Index {
 			_ => bail!("Missing VFS mapping"),
 		}
 	}
+
+	/// Looks up songs by virtual path. Results are in the same order as `virtual_paths`, with `None`
+	/// for paths which are not indexed.
+	pub fn get_songs(&self, virtual_paths: &[PathBuf]) -> Result<Vec<Option<Song>>, QueryError> {
+		if virtual_paths.len() > MAX_SONG_LOOKUP_PATHS {
+			return Err(QueryError::TooManyPaths);
+		}
+
+		let vfs = self.vfs_manager.get_vfs()?;
+		let connection = self.db.connect()?;
+
+		let real_paths: Vec<Option<String>> = virtual_paths
+			.iter()
+			.map(|p| {
+				vfs.virtual_to_real(p)
+					.ok()
+					.map(|p| p.to_string_lossy().into_owned())
+			})
+			.collect();
+
+		let lookup: Vec<&String> = real_paths.iter().flatten().collect();
+		let mut found = HashMap::new();
+		for batch in lookup.chunks(SONG_LOOKUP_BATCH_SIZE) {
+			let batch_songs: Vec<Song> = songs::table
+				.filter(songs::path.eq_any(batch))
+				.load(&connection)
+				.map_err(anyhow::Error::from)?;
+			found.extend(batch_songs.into_iter().map(|s| (s.path.clone(), s)));
+		}
+
+		Ok(real_paths
+			.into_iter()
+			.map(|p| {
+				p.and_then(|p| found.get(&p).cloned())
+					.and_then(|s| s.virtualize(&vfs))
+			})
+			.collect())
+	}
+
+	pub fn get_status(&self) -> Result<Status> {
+		let connection = self.db.connect()?;
+		let song_count = songs::table.count().get_result(&connection)?;
+		let directory_count = directories::table.count().get_result(&connection)?;
+		let last_index_time: Option<Option<i64>> = misc_settings::table
+			.select(misc_settings::index_last_completed)
+			.get_result(&connection)
+			.optional()?;
+		Ok(Status {
+			song_count,
+			directory_count,
+			last_index_time: last_index_time.flatten(),
+			is_indexing: self.is_updating(),
+		})
+	}
+}
+
+// Directories matching the free text and every clause of a search query. Directories only have
+// artist, album and year columns, so there are none to find when other fields are targeted.
+fn directory_search<'a>(
+	query: &SearchQuery,
+) -> Option<directories::BoxedQuery<'a, diesel::sqlite::Sqlite>> {
+	use self::directories::dsl::*;
+	let mut directory_query = Some(directories.into_boxed());
+	if !query.text.is_empty() {
+		let like_text = format!("%{}%", normalize(&query.text));
+		directory_query = directory_query.map(|q| q.filter(search_text.like(like_text)));
+	}
+	for filter in &query.filters {
+		directory_query = directory_query.and_then(|q| match filter {
+			SearchFilter::Text(SearchField::Artist, value) => {
+				Some(q.filter(artist.like(format!("%{}%", value))))
+			}
+			SearchFilter::Text(SearchField::Album, value) => {
+				Some(q.filter(album.like(format!("%{}%", value))))
+			}
+			SearchFilter::Text(_, _) => None,
+			SearchFilter::Year(value) => Some(q.filter(year.eq(*value))),
+		});
+	}
+	directory_query
+}
+
+// Songs matching the free text and every clause of a search query
+fn song_search<'a>(query: &SearchQuery) -> songs::BoxedQuery<'a, diesel::sqlite::Sqlite> {
+	use self::songs::dsl::*;
+	let mut song_query = songs.into_boxed();
+	if !query.text.is_empty() {
+		let like_text = format!("%{}%", normalize(&query.text));
+		song_query = song_query.filter(search_text.like(like_text));
+	}
+	for filter in &query.filters {
+		song_query = match filter {
+			SearchFilter::Text(field, value) => {
+				let like_value = format!("%{}%", value);
+				match field {
+					SearchField::Artist => song_query.filter(
+						artist
+							.like(like_value.clone())
+							.or(album_artist.like(like_value)),
+					),
+					SearchField::Album => song_query.filter(album.like(like_value)),
+					SearchField::Title => song_query.filter(title.like(like_value)),
+					SearchField::Genre => song_query.filter(genre.like(like_value)),
+					SearchField::Composer => song_query.filter(composer.like(like_value)),
+					SearchField::Lyricist => song_query.filter(lyricist.like(like_value)),
+					SearchField::Label => song_query.filter(label.like(like_value)),
+				}
+			}
+			SearchFilter::Year(value) => song_query.filter(year.eq(*value)),
+		};
+	}
+	song_query
 }
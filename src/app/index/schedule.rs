@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::app::vfs;
+
+// How often a mount gets scanned automatically, `None` for mounts which are only scanned manually
+#[derive(Clone, Debug, PartialEq)]
+pub struct MountSchedule {
+	pub name: String,
+	pub interval: Option<Duration>,
+}
+
+impl MountSchedule {
+	pub fn new(mount_dir: &vfs::MountDir, default_interval: Option<Duration>) -> Self {
+		let interval = match mount_dir.scan_interval_seconds {
+			None => default_interval,
+			Some(seconds) if seconds <= 0 => None,
+			Some(seconds) => Some(Duration::from_secs(seconds as u64)),
+		};
+		Self {
+			name: mount_dir.name.clone(),
+			interval,
+		}
+	}
+}
+
+// Keeps track of when each mount was last scanned by the automatic updater
+#[derive(Debug, Default)]
+pub struct ScanSchedule {
+	last_scans: HashMap<String, Instant>,
+}
+
+impl ScanSchedule {
+	// Returns the names of the mounts due for a scan, and records them as scanned at `now`
+	pub fn take_due(&mut self, mounts: &[MountSchedule], now: Instant) -> Vec<String> {
+		self.last_scans
+			.retain(|name, _| mounts.iter().any(|m| &m.name == name));
+		let due: Vec<String> = mounts
+			.iter()
+			.filter(|m| self.is_due(m, now))
+			.map(|m| m.name.clone())
+			.collect();
+		for name in &due {
+			self.last_scans.insert(name.clone(), now);
+		}
+		due
+	}
+
+	// Time left until the next mount is due for a scan, `None` if no mount is scanned automatically
+	pub fn time_until_next(&self, mounts: &[MountSchedule], now: Instant) -> Option<Duration> {
+		mounts
+			.iter()
+			.filter_map(|m| {
+				let interval = m.interval?;
+				Some(match self.last_scans.get(&m.name) {
+					None => Duration::from_secs(0),
+					Some(last_scan) => (*last_scan + interval).saturating_duration_since(now),
+				})
+			})
+			.min()
+	}
+
+	fn is_due(&self, mount: &MountSchedule, now: Instant) -> bool {
+		match (mount.interval, self.last_scans.get(&mount.name)) {
+			(None, _) => false,
+			(Some(_), None) => true,
+			(Some(interval), Some(last_scan)) => {
+				now.saturating_duration_since(*last_scan) >= interval
+			}
+		}
+	}
+}
@@ -0,0 +1,117 @@
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+// Song fields which can be targeted with a `field:value` clause in search queries. Years are
+// matched exactly and have their own filter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchField {
+	Artist,
+	Album,
+	Title,
+	Genre,
+	Composer,
+	Lyricist,
+	Label,
+}
+
+impl SearchField {
+	fn parse(name: &str) -> Option<Self> {
+		match name.to_lowercase().as_str() {
+			"artist" => Some(SearchField::Artist),
+			"album" => Some(SearchField::Album),
+			"title" => Some(SearchField::Title),
+			"genre" => Some(SearchField::Genre),
+			"composer" => Some(SearchField::Composer),
+			"lyricist" => Some(SearchField::Lyricist),
+			"label" => Some(SearchField::Label),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchFilter {
+	Text(SearchField, String),
+	Year(i32),
+}
+
+// Search query split into field clauses and the remaining free text. Tokens which look like
+// clauses but name an unknown field (eg. `Re:Zero`) are kept as free text.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+	pub text: String,
+	pub filters: Vec<SearchFilter>,
+}
+
+impl SearchQuery {
+	pub fn parse(query: &str) -> Self {
+		let mut text = Vec::new();
+		let mut filters = Vec::new();
+		for token in tokenize(query) {
+			match parse_filter(&token) {
+				Some(filter) => filters.push(filter),
+				None => text.push(token.trim_matches('"').to_owned()),
+			}
+		}
+		Self {
+			text: text.join(" "),
+			filters,
+		}
+	}
+}
+
+fn parse_filter(token: &str) -> Option<SearchFilter> {
+	let (name, value) = token.split_once(':')?;
+	let value = value.trim_matches('"');
+	if value.is_empty() {
+		return None;
+	}
+	if name.eq_ignore_ascii_case("year") {
+		return value.parse().ok().map(SearchFilter::Year);
+	}
+	let field = SearchField::parse(name)?;
+	Some(SearchFilter::Text(field, value.to_owned()))
+}
+
+// Splits a query on whitespace, except within double quotes
+fn tokenize(query: &str) -> Vec<String> {
+	let mut tokens = Vec::new();
+	let mut current = String::new();
+	let mut quoted = false;
+	for c in query.chars() {
+		match c {
+			'"' => {
+				quoted = !quoted;
+				current.push(c);
+			}
+			c if c.is_whitespace() && !quoted => {
+				if !current.is_empty() {
+					tokens.push(std::mem::take(&mut current));
+				}
+			}
+			c => current.push(c),
+		}
+	}
+	if !current.is_empty() {
+		tokens.push(current);
+	}
+	tokens
+}
+
+// Lowercases text and strips its diacritics, so that searching for "bjork" finds "Björk"
+pub fn normalize(text: &str) -> String {
+	text.nfd()
+		.filter(|c| !is_combining_mark(*c))
+		.flat_map(char::to_lowercase)
+		.collect()
+}
+
+// Normalized text matched by free text searches. Fields go on separate lines so a query cannot
+// match across two of them.
+pub fn search_text(fields: &[Option<&str>]) -> String {
+	fields
+		.iter()
+		.flatten()
+		.map(|f| normalize(f))
+		.collect::<Vec<_>>()
+		.join("\n")
+}
@@ -1,10 +1,11 @@
 use diesel::prelude::*;
 use std::default::Default;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use super::*;
-use crate::app::test;
-use crate::db::{directories, songs};
+use crate::app::{test, vfs};
+use crate::db::{directories, misc_settings, song_genres, songs};
 use crate::test_name;
 
 const TEST_MOUNT_NAME: &str = "root";
@@ -66,6 +67,280 @@ fn update_removes_missing_content() {
 	}
 }
 
+#[test]
+fn status_reports_index_contents() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	let status = ctx.index.get_status().unwrap();
+	assert_eq!(status.song_count, 0);
+	assert_eq!(status.directory_count, 0);
+	assert_eq!(status.last_index_time, None);
+	assert!(!status.is_indexing);
+
+	let before_update = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.unwrap()
+		.as_secs() as i64;
+	ctx.index.update().unwrap();
+
+	let status = ctx.index.get_status().unwrap();
+	assert_eq!(status.song_count, 13);
+	assert_eq!(status.directory_count, 6);
+	assert!(status.last_index_time.unwrap() >= before_update);
+}
+
+#[test]
+fn update_tracks_scan_progress() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+	assert_eq!(ctx.index.scan_progress.total(), 13);
+	assert_eq!(ctx.index.scan_progress.processed(), 13);
+	let current_path = ctx.index.scan_progress.current_path().unwrap();
+	assert!(current_path.starts_with("test-data/small-collection"));
+
+	// Only updates started through the command queue are reported
+	let progress = ctx.index.get_progress().unwrap();
+	assert_eq!(progress.state, ScanState::Idle);
+	assert_eq!(progress.total, 0);
+}
+
+#[test]
+fn update_records_file_stats() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let song_virtual_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let song = ctx.index.get_song(&song_virtual_path).unwrap();
+	assert_eq!(song.file_size, Some(24_142));
+	assert!(song.file_modified.is_some());
+	// Test files are too short to report a duration, so their bitrate is unknown
+	assert_eq!(song.duration, Some(0));
+	assert_eq!(song.bitrate, None);
+}
+
+#[test]
+fn incremental_update_only_reads_changed_files() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let original_collection_dir: PathBuf = ["test-data", "small-collection"].iter().collect();
+	let test_collection_dir: PathBuf = builder.test_directory.join("small-collection");
+
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy(
+		&original_collection_dir,
+		&builder.test_directory,
+		&copy_options,
+	)
+	.unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, test_collection_dir.to_str().unwrap())
+		.build();
+
+	ctx.index.update().unwrap();
+
+	// Tamper with the index so we can tell which files get read again
+	{
+		let connection = ctx.db.connect().unwrap();
+		diesel::update(songs::table)
+			.set(songs::title.eq("Stale"))
+			.execute(&connection)
+			.unwrap();
+	}
+
+	let hunted_directory = test_collection_dir.join("Khemmis").join("Hunted");
+	let changed_song = hunted_directory.join("02 - Candlelight.mp3");
+	let mut content = std::fs::read(&changed_song).unwrap();
+	content.extend_from_slice(&[0; 16]);
+	std::fs::write(&changed_song, content).unwrap();
+	std::fs::remove_file(hunted_directory.join("01 - Above The Water.mp3")).unwrap();
+
+	ctx.index.update_incremental().unwrap();
+
+	let connection = ctx.db.connect().unwrap();
+	let all_songs: Vec<Song> = songs::table.load(&connection).unwrap();
+	assert_eq!(all_songs.len(), 12);
+	for song in &all_songs {
+		if Path::new(&song.path) == changed_song {
+			assert_eq!(song.title, Some("Candlelight".to_owned()));
+			assert_eq!(song.file_size, Some(24_158));
+		} else {
+			assert_eq!(song.title, Some("Stale".to_owned()));
+		}
+	}
+}
+
+#[test]
+fn update_path_adds_content_within_subtree() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	let khemmis_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	ctx.index.update_path(&khemmis_path).unwrap();
+
+	let connection = ctx.db.connect().unwrap();
+	let all_directories: Vec<Directory> = directories::table.load(&connection).unwrap();
+	let all_songs: Vec<Song> = songs::table.load(&connection).unwrap();
+	assert_eq!(all_directories.len(), 2);
+	assert_eq!(all_songs.len(), 5);
+
+	let files = ctx
+		.index
+		.browse(Path::new(TEST_MOUNT_NAME), Sort::default())
+		.unwrap();
+	assert_eq!(files.len(), 1);
+}
+
+#[test]
+fn update_path_removes_missing_content_within_subtree() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let original_collection_dir: PathBuf = ["test-data", "small-collection"].iter().collect();
+	let test_collection_dir: PathBuf = builder.test_directory.join("small-collection");
+
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy(
+		&original_collection_dir,
+		&builder.test_directory,
+		&copy_options,
+	)
+	.unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, test_collection_dir.to_str().unwrap())
+		.build();
+
+	ctx.index.update().unwrap();
+
+	std::fs::remove_dir_all(test_collection_dir.join("Khemmis")).unwrap();
+	std::fs::remove_dir_all(
+		test_collection_dir
+			.join("Tobokegao")
+			.join("Picnic (Remixes)"),
+	)
+	.unwrap();
+
+	let tobokegao_path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao"].iter().collect();
+	ctx.index.update_path(&tobokegao_path).unwrap();
+	{
+		let connection = ctx.db.connect().unwrap();
+		let all_directories: Vec<Directory> = directories::table.load(&connection).unwrap();
+		let all_songs: Vec<Song> = songs::table.load(&connection).unwrap();
+		assert_eq!(all_directories.len(), 5);
+		assert_eq!(all_songs.len(), 12);
+	}
+
+	let files = ctx
+		.index
+		.browse(Path::new(TEST_MOUNT_NAME), Sort::default())
+		.unwrap();
+	assert_eq!(files.len(), 2);
+}
+
+#[test]
+fn update_path_does_not_treat_scope_as_pattern() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let original_collection_dir: PathBuf = ["test-data", "small-collection"].iter().collect();
+	let test_collection_dir: PathBuf = builder.test_directory.join("small-collection");
+
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy(
+		&original_collection_dir,
+		&builder.test_directory,
+		&copy_options,
+	)
+	.unwrap();
+	let mut copy_options = fs_extra::dir::CopyOptions::new();
+	copy_options.content_only = true;
+	fs_extra::dir::copy(
+		test_collection_dir.join("Khemmis"),
+		test_collection_dir.join("Khemmi_"),
+		&copy_options,
+	)
+	.unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, test_collection_dir.to_str().unwrap())
+		.build();
+
+	ctx.index.update().unwrap();
+
+	std::fs::remove_dir_all(test_collection_dir.join("Khemmis")).unwrap();
+
+	let scope: PathBuf = [TEST_MOUNT_NAME, "Khemmi_"].iter().collect();
+	ctx.index.update_path(&scope).unwrap();
+
+	let connection = ctx.db.connect().unwrap();
+	let all_directories: Vec<Directory> = directories::table.load(&connection).unwrap();
+	let all_songs: Vec<Song> = songs::table.load(&connection).unwrap();
+	assert_eq!(all_directories.len(), 8);
+	assert_eq!(all_songs.len(), 18);
+}
+
+#[test]
+fn update_path_removes_deleted_subtree() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let original_collection_dir: PathBuf = ["test-data", "small-collection"].iter().collect();
+	let test_collection_dir: PathBuf = builder.test_directory.join("small-collection");
+
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy(
+		&original_collection_dir,
+		&builder.test_directory,
+		&copy_options,
+	)
+	.unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, test_collection_dir.to_str().unwrap())
+		.build();
+
+	ctx.index.update().unwrap();
+
+	std::fs::remove_dir_all(test_collection_dir.join("Khemmis")).unwrap();
+
+	let khemmis_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	ctx.index.update_path(&khemmis_path).unwrap();
+	{
+		let connection = ctx.db.connect().unwrap();
+		let all_directories: Vec<Directory> = directories::table.load(&connection).unwrap();
+		let all_songs: Vec<Song> = songs::table.load(&connection).unwrap();
+		assert_eq!(all_directories.len(), 4);
+		assert_eq!(all_songs.len(), 8);
+	}
+
+	let files = ctx
+		.index
+		.browse(Path::new(TEST_MOUNT_NAME), Sort::default())
+		.unwrap();
+	assert_eq!(files.len(), 1);
+}
+
+#[test]
+fn trigger_reindex_path_rejects_paths_outside_mounts() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	let result = ctx
+		.index
+		.trigger_reindex_path(Path::new("not_a_mount/Khemmis"));
+	assert!(matches!(result, Err(TriggerError::VFSPathNotFound)));
+}
+
 #[test]
 fn can_browse_top_level() {
 	let ctx = test::ContextBuilder::new(test_name!())
@@ -74,7 +349,7 @@ fn can_browse_top_level() {
 	ctx.index.update().unwrap();
 
 	let root_path = Path::new(TEST_MOUNT_NAME);
-	let files = ctx.index.browse(Path::new("")).unwrap();
+	let files = ctx.index.browse(Path::new(""), Sort::default()).unwrap();
 	assert_eq!(files.len(), 1);
 	match files[0] {
 		CollectionFile::Directory(ref d) => assert_eq!(d.path, root_path.to_str().unwrap()),
@@ -82,6 +357,33 @@ fn can_browse_top_level() {
 	}
 }
 
+#[test]
+fn can_browse_multiple_mounts() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount("khemmis", "test-data/small-collection/Khemmis")
+		.mount("tobokegao", "test-data/small-collection/Tobokegao")
+		.build();
+	ctx.index.update().unwrap();
+
+	let files = ctx.index.browse(Path::new(""), Sort::default()).unwrap();
+	let mut paths: Vec<String> = files
+		.iter()
+		.map(|f| match f {
+			CollectionFile::Directory(d) => d.path.clone(),
+			_ => panic!("Expected directory"),
+		})
+		.collect();
+	paths.sort();
+	assert_eq!(paths, vec!["khemmis".to_owned(), "tobokegao".to_owned()]);
+
+	let songs = ctx
+		.index
+		.flatten(Path::new("tobokegao"), Range::default(), Sort::default())
+		.unwrap();
+	assert_eq!(songs.len(), 8);
+	assert!(songs.iter().all(|s| s.path.starts_with("tobokegao")));
+}
+
 #[test]
 fn can_browse_directory() {
 	let khemmis_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
@@ -92,7 +394,10 @@ fn can_browse_directory() {
 		.build();
 	ctx.index.update().unwrap();
 
-	let files = ctx.index.browse(Path::new(TEST_MOUNT_NAME)).unwrap();
+	let files = ctx
+		.index
+		.browse(Path::new(TEST_MOUNT_NAME), Sort::default())
+		.unwrap();
 
 	assert_eq!(files.len(), 2);
 	match files[0] {
@@ -106,13 +411,95 @@ fn can_browse_directory() {
 	}
 }
 
+#[test]
+fn can_browse_directory_tree() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let children = |entry: &BrowseEntry| match entry {
+		BrowseEntry::Directory(d) => d.children.as_ref().map(Vec::len),
+		_ => panic!("Expected directory"),
+	};
+
+	let root = Path::new(TEST_MOUNT_NAME);
+	let files = ctx.index.browse_tree(root, Sort::default(), 1).unwrap();
+	assert_eq!(files.len(), 2);
+	assert_eq!(children(&files[0]), None);
+
+	let files = ctx.index.browse_tree(root, Sort::default(), 3).unwrap();
+	let khemmis = &files[0];
+	assert_eq!(children(khemmis), Some(1));
+	let hunted = match khemmis {
+		BrowseEntry::Directory(d) => &d.children.as_ref().unwrap()[0],
+		_ => panic!("Expected directory"),
+	};
+	match hunted {
+		BrowseEntry::Directory(d) => {
+			let hunted_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+			assert_eq!(d.directory.path, hunted_path.to_str().unwrap());
+			let songs = d.children.as_ref().unwrap();
+			assert_eq!(songs.len(), 5);
+			assert!(songs.iter().all(|s| matches!(s, BrowseEntry::Song(_))));
+		}
+		_ => panic!("Expected directory"),
+	}
+}
+
+#[test]
+fn browse_tree_rejects_unsupported_depth() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let root = Path::new(TEST_MOUNT_NAME);
+	for depth in [0, MAX_BROWSE_DEPTH + 1] {
+		assert!(matches!(
+			ctx.index.browse_tree(root, Sort::default(), depth),
+			Err(QueryError::UnsupportedDepth)
+		));
+	}
+}
+
+#[test]
+fn can_browse_directory_in_batches() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao"].iter().collect();
+	let mut files = Vec::new();
+	let mut cursor = Some(BrowseCursor::default());
+	while let Some(c) = cursor {
+		let (batch, next) = ctx
+			.index
+			.browse_batch(&path, c, 1, Sort::default())
+			.unwrap();
+		assert!(batch.len() <= 1);
+		files.extend(batch);
+		cursor = next;
+	}
+
+	assert_eq!(files, ctx.index.browse(&path, Sort::default()).unwrap());
+}
+
 #[test]
 fn can_flatten_root() {
 	let ctx = test::ContextBuilder::new(test_name!())
 		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
 		.build();
 	ctx.index.update().unwrap();
-	let songs = ctx.index.flatten(Path::new(TEST_MOUNT_NAME)).unwrap();
+	let songs = ctx
+		.index
+		.flatten(
+			Path::new(TEST_MOUNT_NAME),
+			Range::default(),
+			Sort::default(),
+		)
+		.unwrap();
 	assert_eq!(songs.len(), 13);
 	assert_eq!(songs[0].title, Some("Above The Water".to_owned()));
 }
@@ -124,7 +511,10 @@ fn can_flatten_directory() {
 		.build();
 	ctx.index.update().unwrap();
 	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao"].iter().collect();
-	let songs = ctx.index.flatten(&path).unwrap();
+	let songs = ctx
+		.index
+		.flatten(&path, Range::default(), Sort::default())
+		.unwrap();
 	assert_eq!(songs.len(), 8);
 }
 
@@ -135,45 +525,256 @@ fn can_flatten_directory_with_shared_prefix() {
 		.build();
 	ctx.index.update().unwrap();
 	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic"].iter().collect(); // Prefix of '(Picnic Remixes)'
-	let songs = ctx.index.flatten(&path).unwrap();
+	let songs = ctx
+		.index
+		.flatten(&path, Range::default(), Sort::default())
+		.unwrap();
 	assert_eq!(songs.len(), 7);
 }
 
 #[test]
-fn can_get_random_albums() {
+fn can_flatten_a_range() {
 	let ctx = test::ContextBuilder::new(test_name!())
 		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
 		.build();
 	ctx.index.update().unwrap();
-	let albums = ctx.index.get_random_albums(1).unwrap();
-	assert_eq!(albums.len(), 1);
+	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao"].iter().collect();
+	let all_songs = ctx
+		.index
+		.flatten(&path, Range::default(), Sort::default())
+		.unwrap();
+
+	let range = |offset, count| Range { offset, count };
+	let songs = ctx
+		.index
+		.flatten(&path, range(Some(2), Some(3)), Sort::default())
+		.unwrap();
+	assert_eq!(songs, all_songs[2..5]);
+	let songs = ctx
+		.index
+		.flatten(&path, range(None, Some(3)), Sort::default())
+		.unwrap();
+	assert_eq!(songs, all_songs[..3]);
+	let songs = ctx
+		.index
+		.flatten(&path, range(Some(6), None), Sort::default())
+		.unwrap();
+	assert_eq!(songs, all_songs[6..]);
+	let songs = ctx
+		.index
+		.flatten(&path, range(Some(20), Some(3)), Sort::default())
+		.unwrap();
+	assert!(songs.is_empty());
 }
 
 #[test]
-fn can_get_recent_albums() {
+fn can_browse_with_sort() {
 	let ctx = test::ContextBuilder::new(test_name!())
 		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
 		.build();
 	ctx.index.update().unwrap();
-	let albums = ctx.index.get_recent_albums(2).unwrap();
-	assert_eq!(albums.len(), 2);
-	assert!(albums[0].date_added >= albums[1].date_added);
+
+	let picnic_real_path: PathBuf = ["test-data", "small-collection", "Tobokegao", "Picnic"]
+		.iter()
+		.collect();
+	diesel::update(directories::table)
+		.filter(directories::path.eq(picnic_real_path.to_string_lossy().as_ref()))
+		.set(directories::year.eq(2020))
+		.execute(&ctx.db.connect().unwrap())
+		.unwrap();
+
+	let directory_names = |path: &Path, sort| -> Vec<String> {
+		ctx.index
+			.browse(path, sort)
+			.unwrap()
+			.into_iter()
+			.map(|f| match f {
+				CollectionFile::Directory(d) => Path::new(&d.path)
+					.file_name()
+					.unwrap()
+					.to_string_lossy()
+					.into_owned(),
+				_ => panic!("Expected directory"),
+			})
+			.collect()
+	};
+	let sort = |field, order| Sort { field, order };
+
+	let root = Path::new(TEST_MOUNT_NAME);
+	assert_eq!(
+		directory_names(root, sort(SortField::Name, SortOrder::Desc)),
+		vec!["Tobokegao", "Khemmis"]
+	);
+
+	let tobokegao: PathBuf = [TEST_MOUNT_NAME, "Tobokegao"].iter().collect();
+	assert_eq!(
+		directory_names(&tobokegao, Sort::default()),
+		vec!["Picnic", "Picnic (Remixes)"]
+	);
+	assert_eq!(
+		directory_names(&tobokegao, sort(SortField::Year, SortOrder::Asc)),
+		vec!["Picnic (Remixes)", "Picnic"]
+	);
+	assert_eq!(
+		directory_names(&tobokegao, sort(SortField::Year, SortOrder::Desc)),
+		vec!["Picnic", "Picnic (Remixes)"]
+	);
 }
 
 #[test]
-fn can_get_a_song() {
+fn can_flatten_with_sort() {
 	let ctx = test::ContextBuilder::new(test_name!())
 		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
 		.build();
-
 	ctx.index.update().unwrap();
 
-	let picnic_virtual_dir: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic"].iter().collect();
-	let song_virtual_path = picnic_virtual_dir.join("05 - シャーベット (Sherbet).mp3");
-	let artwork_virtual_path = picnic_virtual_dir.join("Folder.png");
+	let picnic_real_path: PathBuf = ["test-data", "small-collection", "Tobokegao", "Picnic"]
+		.iter()
+		.collect();
+	diesel::update(directories::table)
+		.filter(directories::path.eq(picnic_real_path.to_string_lossy().as_ref()))
+		.set(directories::date_added.eq(directories::date_added + 1000))
+		.execute(&ctx.db.connect().unwrap())
+		.unwrap();
 
-	let song = ctx.index.get_song(&song_virtual_path).unwrap();
-	assert_eq!(song.path, song_virtual_path.to_string_lossy().as_ref());
+	let picnic_path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic"].iter().collect();
+	let remixes_path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic (Remixes)"]
+		.iter()
+		.collect();
+	let tobokegao: PathBuf = [TEST_MOUNT_NAME, "Tobokegao"].iter().collect();
+
+	let songs = ctx
+		.index
+		.flatten(&tobokegao, Range::default(), Sort::default())
+		.unwrap();
+	assert!(Path::new(&songs[0].path).starts_with(&picnic_path));
+	assert!(Path::new(&songs[7].path).starts_with(&remixes_path));
+
+	let newest_first = Sort {
+		field: SortField::DateAdded,
+		order: SortOrder::Desc,
+	};
+	let songs = ctx
+		.index
+		.flatten(&tobokegao, Range::default(), newest_first)
+		.unwrap();
+	assert_eq!(songs.len(), 8);
+	assert!(songs[..7]
+		.iter()
+		.all(|s| Path::new(&s.path).starts_with(&picnic_path)));
+	assert!(Path::new(&songs[7].path).starts_with(&remixes_path));
+}
+
+#[test]
+fn flatten_sequences_songs_within_directories() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let tobokegao: PathBuf = [TEST_MOUNT_NAME, "Tobokegao"].iter().collect();
+	let songs = ctx
+		.index
+		.flatten_sequenced(&tobokegao, Range::default(), Sort::default())
+		.unwrap();
+	assert_eq!(songs.len(), 8);
+
+	let track_numbers: Vec<_> = songs.iter().map(|s| s.song.track_number).collect();
+	assert_eq!(
+		track_numbers,
+		vec![1, 2, 3, 4, 5, 6, 7, 1]
+			.into_iter()
+			.map(Some)
+			.collect::<Vec<_>>()
+	);
+	for pair in songs[..6].windows(2) {
+		assert_eq!(pair[0].next_path.as_ref(), Some(&pair[1].song.path));
+	}
+	assert_eq!(songs[6].next_path, None);
+	assert_eq!(songs[7].next_path, None);
+
+	let range = Range {
+		offset: Some(1),
+		count: Some(1),
+	};
+	let songs = ctx
+		.index
+		.flatten_sequenced(&tobokegao, range, Sort::default())
+		.unwrap();
+	assert_eq!(songs.len(), 1);
+	assert_eq!(songs[0].song.track_number, Some(2));
+	assert!(songs[0].next_path.is_some());
+}
+
+#[test]
+fn can_get_random_albums() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+	let albums = ctx.index.get_random_albums(1, None).unwrap();
+	assert_eq!(albums.len(), 1);
+}
+
+#[test]
+fn random_albums_are_stable_with_seed() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let paths = |count: i64| -> Vec<String> {
+		ctx.index
+			.get_random_albums(count, Some(42))
+			.unwrap()
+			.into_iter()
+			.map(|d| d.path)
+			.collect()
+	};
+	let all_albums = paths(3);
+	assert_eq!(all_albums.len(), 3);
+	assert_eq!(paths(3), all_albums);
+	assert_eq!(paths(2), all_albums[..2]);
+	assert_eq!(paths(1), all_albums[..1]);
+}
+
+#[test]
+fn album_count_is_clamped() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+	assert!(ctx.index.get_random_albums(-1, None).unwrap().is_empty());
+	assert!(ctx.index.get_random_albums(-1, Some(0)).unwrap().is_empty());
+	assert!(ctx.index.get_recent_albums(-1).unwrap().is_empty());
+	assert_eq!(ctx.index.get_recent_albums(i64::MAX).unwrap().len(), 3);
+}
+
+#[test]
+fn can_get_recent_albums() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+	let albums = ctx.index.get_recent_albums(2).unwrap();
+	assert_eq!(albums.len(), 2);
+	assert!(albums[0].date_added >= albums[1].date_added);
+}
+
+#[test]
+fn can_get_a_song() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let picnic_virtual_dir: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic"].iter().collect();
+	let song_virtual_path = picnic_virtual_dir.join("05 - シャーベット (Sherbet).mp3");
+	let artwork_virtual_path = picnic_virtual_dir.join("Folder.png");
+
+	let song = ctx.index.get_song(&song_virtual_path).unwrap();
+	assert_eq!(song.path, song_virtual_path.to_string_lossy().as_ref());
 	assert_eq!(song.track_number, Some(5));
 	assert_eq!(song.disc_number, None);
 	assert_eq!(song.title, Some("シャーベット (Sherbet)".to_owned()));
@@ -227,10 +828,1083 @@ fn album_art_pattern_is_case_insensitive() {
 
 		let hunted_virtual_dir: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
 		let artwork_virtual_path = hunted_virtual_dir.join("Folder.jpg");
-		let song = &ctx.index.flatten(&hunted_virtual_dir).unwrap()[0];
+		let song = &ctx
+			.index
+			.flatten(&hunted_virtual_dir, Range::default(), Sort::default())
+			.unwrap()[0];
 		assert_eq!(
 			song.artwork,
 			Some(artwork_virtual_path.to_string_lossy().into_owned())
 		);
 	}
 }
+
+#[test]
+fn album_art_names_are_picked_in_order() {
+	let builder = test::ContextBuilder::new(test_name!());
+	let album_dir = builder.test_directory.join("collection").join("Album");
+	std::fs::create_dir_all(&album_dir).unwrap();
+	std::fs::copy(
+		"test-data/small-collection/Khemmis/Hunted/02 - Candlelight.mp3",
+		album_dir.join("Candlelight.mp3"),
+	)
+	.unwrap();
+	for name in &["Folder.jpg", "cover.png", "Front.jpg"] {
+		std::fs::copy(
+			"test-data/small-collection/Khemmis/Hunted/Folder.jpg",
+			album_dir.join(name),
+		)
+		.unwrap();
+	}
+
+	let ctx = builder
+		.mount(
+			TEST_MOUNT_NAME,
+			album_dir.parent().unwrap().to_str().unwrap(),
+		)
+		.build();
+	let album_virtual_dir: PathBuf = [TEST_MOUNT_NAME, "Album"].iter().collect();
+
+	for (names, expected) in &[
+		(vec!["front.jpg", "cover.png"], "Front.jpg"),
+		(vec!["back.jpg", "cover.png", "front.jpg"], "cover.png"),
+		(vec!["back.jpg"], "Folder.jpg"),
+	] {
+		ctx.settings_manager
+			.amend(&settings::NewSettings {
+				album_art_names: Some(names.iter().map(|n| n.to_string()).collect()),
+				..Default::default()
+			})
+			.unwrap();
+		ctx.index.update().unwrap();
+
+		let song = &ctx
+			.index
+			.flatten(&album_virtual_dir, Range::default(), Sort::default())
+			.unwrap()[0];
+		assert_eq!(
+			song.artwork,
+			Some(
+				album_virtual_dir
+					.join(expected)
+					.to_string_lossy()
+					.into_owned()
+			)
+		);
+	}
+}
+
+#[test]
+fn update_truncates_long_tags() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			index_max_tag_length: Some(10),
+			..Default::default()
+		})
+		.unwrap();
+	ctx.index.update().unwrap();
+
+	let song_virtual_path: PathBuf = [
+		TEST_MOUNT_NAME,
+		"Tobokegao",
+		"Picnic",
+		"05 - シャーベット (Sherbet).mp3",
+	]
+	.iter()
+	.collect();
+	let song = ctx.index.get_song(&song_virtual_path).unwrap();
+	assert_eq!(song.title, Some("シャーベット (S…".to_owned()));
+	assert_eq!(song.artist, Some("Tobokegao".to_owned()));
+}
+
+#[cfg(unix)]
+#[test]
+fn update_follows_symlinks_unless_disabled() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let original_collection_dir: PathBuf = ["test-data", "small-collection"].iter().collect();
+	let test_collection_dir: PathBuf = builder.test_directory.join("small-collection");
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy(
+		&original_collection_dir,
+		&builder.test_directory,
+		&copy_options,
+	)
+	.unwrap();
+	std::os::unix::fs::symlink("Khemmis", test_collection_dir.join("Favorites")).unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, test_collection_dir.to_str().unwrap())
+		.build();
+
+	ctx.index.update().unwrap();
+	{
+		let connection = ctx.db.connect().unwrap();
+		let all_directories: Vec<Directory> = directories::table.load(&connection).unwrap();
+		let all_songs: Vec<Song> = songs::table.load(&connection).unwrap();
+		assert_eq!(all_directories.len(), 8);
+		assert_eq!(all_songs.len(), 18);
+	}
+
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			follow_symlinks: Some(false),
+			..Default::default()
+		})
+		.unwrap();
+	ctx.index.update().unwrap();
+	{
+		let connection = ctx.db.connect().unwrap();
+		let all_directories: Vec<Directory> = directories::table.load(&connection).unwrap();
+		let all_songs: Vec<Song> = songs::table.load(&connection).unwrap();
+		assert_eq!(all_directories.len(), 6);
+		assert_eq!(all_songs.len(), 13);
+	}
+}
+
+#[cfg(unix)]
+#[test]
+fn update_breaks_symlink_cycles() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let collection_dir = builder.test_directory.join("collection");
+	let first_dir = collection_dir.join("first");
+	let second_dir = collection_dir.join("second");
+	std::fs::create_dir_all(&first_dir).unwrap();
+	std::fs::create_dir_all(&second_dir).unwrap();
+	std::fs::copy("test-data/formats/sample.mp3", first_dir.join("a.mp3")).unwrap();
+	std::fs::copy("test-data/formats/sample.mp3", second_dir.join("b.mp3")).unwrap();
+	std::os::unix::fs::symlink(".", collection_dir.join("itself")).unwrap();
+	std::os::unix::fs::symlink("../second", first_dir.join("to_second")).unwrap();
+	std::os::unix::fs::symlink("../first", second_dir.join("to_first")).unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, collection_dir.to_str().unwrap())
+		.build();
+
+	ctx.index.update().unwrap();
+
+	// first, first/to_second, second and second/to_first are each walked once
+	let connection = ctx.db.connect().unwrap();
+	let all_songs: Vec<Song> = songs::table.load(&connection).unwrap();
+	assert_eq!(all_songs.len(), 4);
+}
+
+#[test]
+fn update_ignores_unlisted_extensions() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			index_audio_extensions: Some(vec!["flac".to_owned()]),
+			..Default::default()
+		})
+		.unwrap();
+	ctx.index.update().unwrap();
+
+	let connection = ctx.db.connect().unwrap();
+	let all_directories: Vec<Directory> = directories::table.load(&connection).unwrap();
+	let all_songs: Vec<Song> = songs::table.load(&connection).unwrap();
+	assert_eq!(all_directories.len(), 6);
+	assert_eq!(all_songs.len(), 0);
+}
+
+#[test]
+fn update_removes_songs_with_unlisted_extensions() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+	{
+		let connection = ctx.db.connect().unwrap();
+		let all_songs: Vec<Song> = songs::table.load(&connection).unwrap();
+		assert_eq!(all_songs.len(), 13);
+	}
+
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			index_audio_extensions: Some(vec!["flac".to_owned()]),
+			..Default::default()
+		})
+		.unwrap();
+	ctx.index.update_incremental().unwrap();
+
+	let connection = ctx.db.connect().unwrap();
+	let all_songs: Vec<Song> = songs::table.load(&connection).unwrap();
+	assert_eq!(all_songs.len(), 0);
+}
+
+#[test]
+fn update_indexes_listed_extensions_without_tag_support() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let collection_dir = builder.test_directory.join("collection");
+	std::fs::create_dir_all(&collection_dir).unwrap();
+	std::fs::copy(
+		"test-data/formats/sample.mp3",
+		collection_dir.join("sample.WV"),
+	)
+	.unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, collection_dir.to_str().unwrap())
+		.build();
+
+	ctx.index.update().unwrap();
+	{
+		let connection = ctx.db.connect().unwrap();
+		let all_songs: Vec<Song> = songs::table.load(&connection).unwrap();
+		assert_eq!(all_songs.len(), 0);
+	}
+
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			index_audio_extensions: Some(vec!["mp3".to_owned(), "wv".to_owned()]),
+			..Default::default()
+		})
+		.unwrap();
+	ctx.index.update().unwrap();
+
+	let song_virtual_path: PathBuf = [TEST_MOUNT_NAME, "sample.WV"].iter().collect();
+	let song = ctx.index.get_song(&song_virtual_path).unwrap();
+	assert_eq!(song.title, None);
+	assert!(song.file_size.is_some());
+}
+
+#[test]
+fn grouping_artist_prefers_album_artist() {
+	assert_eq!(
+		grouping_artist(Some("Khemmis"), Some("Dio")),
+		Some("Khemmis")
+	);
+	assert_eq!(grouping_artist(None, Some("Dio")), Some("Dio"));
+	assert_eq!(grouping_artist(Some(""), Some("Dio")), Some("Dio"));
+	assert_eq!(grouping_artist(None, Some("")), None);
+	assert_eq!(grouping_artist(None, None), None);
+}
+
+#[test]
+fn reject_policy_refuses_triggers_while_updating() {
+	let khemmis_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	let mut state = ReindexState::default();
+	let policy = settings::IndexTriggerPolicy::Reject;
+
+	state.request(policy, None, ScanMode::Full).unwrap();
+	assert!(matches!(
+		state.request(policy, None, ScanMode::Full),
+		Err(TriggerError::AlreadyRunning)
+	));
+
+	state.running = Some(std::mem::take(&mut state.pending));
+	assert!(matches!(
+		state.request(policy, Some(&khemmis_path), ScanMode::Full),
+		Err(TriggerError::AlreadyRunning)
+	));
+	assert!(state.pending.is_empty());
+
+	state.running = None;
+	state
+		.request(policy, Some(&khemmis_path), ScanMode::Full)
+		.unwrap();
+	assert_eq!(state.pending.paths, vec![khemmis_path]);
+}
+
+#[test]
+fn coalesce_policy_merges_triggers_into_running_update() {
+	let khemmis_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	let hunted_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let tobokegao_path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao"].iter().collect();
+	let mut state = ReindexState::default();
+	let policy = settings::IndexTriggerPolicy::Coalesce;
+
+	state
+		.request(policy, Some(&khemmis_path), ScanMode::Full)
+		.unwrap();
+	state.running = Some(std::mem::take(&mut state.pending));
+
+	state
+		.request(policy, Some(&khemmis_path), ScanMode::Full)
+		.unwrap();
+	state
+		.request(policy, Some(&hunted_path), ScanMode::Full)
+		.unwrap();
+	assert!(state.pending.is_empty());
+
+	state
+		.request(policy, Some(&tobokegao_path), ScanMode::Full)
+		.unwrap();
+	state.request(policy, None, ScanMode::Full).unwrap();
+	state.request(policy, None, ScanMode::Full).unwrap();
+	assert!(state.pending.full);
+	assert_eq!(state.pending.paths, vec![tobokegao_path]);
+
+	state.running = Some(std::mem::take(&mut state.pending));
+	state
+		.request(policy, Some(&khemmis_path), ScanMode::Full)
+		.unwrap();
+	state.request(policy, None, ScanMode::Full).unwrap();
+	assert!(state.pending.is_empty());
+}
+
+#[test]
+fn queue_policy_queues_a_single_follow_up() {
+	let khemmis_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	let mut state = ReindexState::default();
+	let policy = settings::IndexTriggerPolicy::Queue;
+
+	state.request(policy, None, ScanMode::Full).unwrap();
+	state.running = Some(std::mem::take(&mut state.pending));
+
+	for _ in 0..10 {
+		state.request(policy, None, ScanMode::Full).unwrap();
+		state
+			.request(policy, Some(&khemmis_path), ScanMode::Full)
+			.unwrap();
+	}
+	assert_eq!(
+		state.pending,
+		PendingReindex {
+			full: true,
+			paths: vec![khemmis_path],
+			incremental: false,
+		}
+	);
+}
+
+#[test]
+fn updates_are_only_incremental_if_all_requests_are() {
+	let khemmis_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	let mut state = ReindexState::default();
+	let policy = settings::IndexTriggerPolicy::Queue;
+
+	state.request(policy, None, ScanMode::Incremental).unwrap();
+	state
+		.request(policy, Some(&khemmis_path), ScanMode::Incremental)
+		.unwrap();
+	assert_eq!(state.pending.mode(), ScanMode::Incremental);
+
+	state.request(policy, None, ScanMode::Full).unwrap();
+	state.request(policy, None, ScanMode::Incremental).unwrap();
+	assert_eq!(state.pending.mode(), ScanMode::Full);
+}
+
+#[test]
+fn coalesce_policy_does_not_merge_full_scans_into_incremental_ones() {
+	let mut state = ReindexState::default();
+	let policy = settings::IndexTriggerPolicy::Coalesce;
+
+	state.request(policy, None, ScanMode::Incremental).unwrap();
+	state.running = Some(std::mem::take(&mut state.pending));
+
+	state.request(policy, None, ScanMode::Incremental).unwrap();
+	assert!(state.pending.is_empty());
+
+	state.request(policy, None, ScanMode::Full).unwrap();
+	assert!(state.pending.full);
+	assert_eq!(state.pending.mode(), ScanMode::Full);
+}
+
+#[test]
+fn rapid_triggers_update_index() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	for _ in 0..10 {
+		ctx.index.trigger_reindex().unwrap();
+	}
+	while ctx.index.is_updating() {
+		std::thread::sleep(std::time::Duration::from_millis(10));
+	}
+
+	let connection = ctx.db.connect().unwrap();
+	let all_songs: Vec<Song> = songs::table.load(&connection).unwrap();
+	assert_eq!(all_songs.len(), 13);
+}
+
+#[test]
+fn updates_publish_lifecycle_events() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	let mut events = ctx.index.subscribe();
+	ctx.index.trigger_reindex().unwrap();
+
+	let mut received = Vec::new();
+	while received.len() < 2 {
+		match events.try_recv() {
+			Ok(event) => received.push(event),
+			Err(_) => std::thread::sleep(std::time::Duration::from_millis(10)),
+		}
+	}
+	assert_eq!(
+		received,
+		vec![
+			IndexEvent::IndexStarted,
+			IndexEvent::IndexFinished {
+				song_count: 13,
+				directory_count: 6,
+			}
+		]
+	);
+}
+
+#[test]
+fn insert_buffer_size_does_not_affect_results() {
+	for size in &[0, 1, 4, usize::MAX] {
+		let ctx = test::ContextBuilder::new(format!("{}_{}", test_name!(), size))
+			.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+			.insert_buffer_size(*size)
+			.build();
+		ctx.index.update().unwrap();
+
+		let connection = ctx.db.connect().unwrap();
+		let all_songs: Vec<Song> = songs::table.load(&connection).unwrap();
+		assert_eq!(all_songs.len(), 13);
+		let all_directories: Vec<Directory> = directories::table.load(&connection).unwrap();
+		assert_eq!(all_directories.len(), 6);
+		assert_eq!(ctx.index.get_genres().unwrap().len(), 4);
+	}
+}
+
+#[test]
+fn album_groups_songs_by_disc() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/multi-disc")
+		.build();
+	ctx.index.update().unwrap();
+
+	let album_path: PathBuf = [TEST_MOUNT_NAME, "Box Set"].iter().collect();
+	let album = ctx.index.get_album(&album_path).unwrap();
+	assert_eq!(album.directory.path, album_path.to_str().unwrap());
+	assert_eq!(album.directory.album, Some("Box Set".to_owned()));
+
+	let discs: Vec<(Option<i32>, &str, usize)> = album
+		.discs
+		.iter()
+		.map(|d| (d.number, d.name.as_str(), d.songs.len()))
+		.collect();
+	assert_eq!(
+		discs,
+		vec![
+			(Some(1), "The Studio Album", 2),
+			(Some(2), "Live at Roadburn", 1),
+			(Some(3), "Disc 3", 1),
+		]
+	);
+	assert_eq!(album.discs[2].subtitle, None);
+	assert_eq!(
+		album.discs[0].songs[1].title,
+		Some("Candlelight".to_owned())
+	);
+}
+
+#[test]
+fn songs_carry_replay_gain() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/multi-disc")
+		.build();
+	ctx.index.update().unwrap();
+
+	let songs = ctx
+		.index
+		.flatten(
+			Path::new(TEST_MOUNT_NAME),
+			Range::default(),
+			Sort::default(),
+		)
+		.unwrap();
+	let replay_gains: Vec<(Option<f32>, Option<f32>)> = songs
+		.iter()
+		.map(|s| (s.replay_gain_track, s.replay_gain_album))
+		.collect();
+	assert_eq!(
+		replay_gains,
+		vec![
+			(Some(-6.48), Some(-7.12)),
+			(None, None),
+			(Some(1.25), Some(-7.12)),
+			(None, None),
+		]
+	);
+}
+
+#[test]
+fn can_get_artwork() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let artwork: PathBuf = [
+		"test-data",
+		"small-collection",
+		"Khemmis",
+		"Hunted",
+		"Folder.jpg",
+	]
+	.iter()
+	.collect();
+	let song_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	assert_eq!(
+		ctx.index.get_artwork(&song_path).unwrap(),
+		Some(artwork.clone())
+	);
+	let directory_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	assert_eq!(
+		ctx.index.get_artwork(&directory_path).unwrap(),
+		Some(artwork)
+	);
+
+	let directory_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	assert_eq!(ctx.index.get_artwork(&directory_path).unwrap(), None);
+
+	let unknown_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Nope"].iter().collect();
+	assert!(matches!(
+		ctx.index.get_artwork(&unknown_path),
+		Err(QueryError::VFSPathNotFound)
+	));
+}
+
+#[test]
+fn album_rejects_unknown_directory() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/multi-disc")
+		.build();
+	ctx.index.update().unwrap();
+
+	let album_path: PathBuf = [TEST_MOUNT_NAME, "Not An Album"].iter().collect();
+	assert!(matches!(
+		ctx.index.get_album(&album_path),
+		Err(QueryError::VFSPathNotFound)
+	));
+}
+
+fn mount_schedule(name: &str, interval_seconds: Option<u64>) -> MountSchedule {
+	MountSchedule {
+		name: name.to_owned(),
+		interval: interval_seconds.map(Duration::from_secs),
+	}
+}
+
+#[test]
+fn mount_schedule_reads_scan_interval() {
+	let default_interval = Some(Duration::from_secs(1800));
+	let mount_dir = |scan_interval_seconds| vfs::MountDir {
+		source: "test-data/small-collection".to_owned(),
+		name: TEST_MOUNT_NAME.to_owned(),
+		scan_interval_seconds,
+	};
+
+	let schedule = MountSchedule::new(&mount_dir(None), default_interval);
+	assert_eq!(schedule.interval, default_interval);
+
+	let schedule = MountSchedule::new(&mount_dir(Some(60)), default_interval);
+	assert_eq!(schedule.interval, Some(Duration::from_secs(60)));
+
+	let schedule = MountSchedule::new(&mount_dir(Some(0)), default_interval);
+	assert_eq!(schedule.interval, None);
+}
+
+#[test]
+fn periodic_scans_can_be_disabled() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.reindex_every_n_seconds(0)
+		.build();
+	let default_interval = ctx.settings_manager.get_index_sleep_duration().unwrap();
+	assert_eq!(default_interval, None);
+
+	let mount_dirs = ctx.vfs_manager.mount_dirs().unwrap();
+	let schedule = MountSchedule::new(&mount_dirs[0], default_interval);
+	assert_eq!(schedule.interval, None);
+
+	let mount_dir = vfs::MountDir {
+		scan_interval_seconds: Some(60),
+		..mount_dirs[0].clone()
+	};
+	let schedule = MountSchedule::new(&mount_dir, default_interval);
+	assert_eq!(schedule.interval, Some(Duration::from_secs(60)));
+}
+
+#[test]
+fn mounts_are_scanned_on_independent_schedules() {
+	let mounts = vec![
+		mount_schedule("local", Some(60)),
+		mount_schedule("network", Some(3600)),
+	];
+	let mut schedule = ScanSchedule::default();
+	let start = Instant::now();
+	let at = |seconds| start + Duration::from_secs(seconds);
+
+	assert_eq!(schedule.take_due(&mounts, at(0)), vec!["local", "network"]);
+	assert_eq!(
+		schedule.time_until_next(&mounts, at(0)),
+		Some(Duration::from_secs(60))
+	);
+	assert!(schedule.take_due(&mounts, at(30)).is_empty());
+	assert_eq!(schedule.take_due(&mounts, at(60)), vec!["local"]);
+	assert_eq!(schedule.take_due(&mounts, at(150)), vec!["local"]);
+	assert!(schedule.take_due(&mounts, at(180)).is_empty());
+	assert_eq!(
+		schedule.time_until_next(&mounts, at(180)),
+		Some(Duration::from_secs(30))
+	);
+	assert_eq!(
+		schedule.take_due(&mounts, at(3600)),
+		vec!["local", "network"]
+	);
+}
+
+#[test]
+fn mounts_with_zero_interval_are_never_scheduled() {
+	let mounts = vec![mount_schedule("manual", None)];
+	let mut schedule = ScanSchedule::default();
+	let start = Instant::now();
+
+	assert!(schedule.take_due(&mounts, start).is_empty());
+	assert!(schedule
+		.take_due(&mounts, start + Duration::from_secs(1_000_000))
+		.is_empty());
+	assert_eq!(schedule.time_until_next(&mounts, start), None);
+}
+
+#[test]
+fn new_mounts_are_scanned_immediately() {
+	let mut schedule = ScanSchedule::default();
+	let start = Instant::now();
+	let at = |seconds| start + Duration::from_secs(seconds);
+
+	let mounts = vec![mount_schedule("a", Some(600))];
+	assert_eq!(schedule.take_due(&mounts, at(0)), vec!["a"]);
+
+	let mounts = vec![
+		mount_schedule("a", Some(600)),
+		mount_schedule("b", Some(600)),
+	];
+	assert_eq!(schedule.take_due(&mounts, at(10)), vec!["b"]);
+
+	// A mount which gets removed and added back starts over
+	let mounts = vec![mount_schedule("b", Some(600))];
+	assert!(schedule.take_due(&mounts, at(20)).is_empty());
+	let mounts = vec![
+		mount_schedule("a", Some(600)),
+		mount_schedule("b", Some(600)),
+	];
+	assert_eq!(schedule.take_due(&mounts, at(30)), vec!["a"]);
+}
+
+#[test]
+fn search_query_parses_field_clauses() {
+	assert_eq!(
+		SearchQuery::parse("artist:miles album:kind"),
+		SearchQuery {
+			text: "".to_owned(),
+			filters: vec![
+				SearchFilter::Text(SearchField::Artist, "miles".to_owned()),
+				SearchFilter::Text(SearchField::Album, "kind".to_owned()),
+			],
+		}
+	);
+	assert_eq!(
+		SearchQuery::parse("Artist:\"miles davis\" blue year:1959"),
+		SearchQuery {
+			text: "blue".to_owned(),
+			filters: vec![
+				SearchFilter::Text(SearchField::Artist, "miles davis".to_owned()),
+				SearchFilter::Year(1959),
+			],
+		}
+	);
+}
+
+#[test]
+fn search_query_keeps_unknown_fields_as_text() {
+	assert_eq!(
+		SearchQuery::parse("Re:Zero year:soon artist:"),
+		SearchQuery {
+			text: "Re:Zero year:soon artist:".to_owned(),
+			filters: vec![],
+		}
+	);
+}
+
+#[test]
+fn can_search_by_field() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let hunted_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let results = ctx.index.search("artist:khemmis").unwrap();
+	assert_eq!(results.len(), 1);
+	match results[0] {
+		CollectionFile::Directory(ref d) => assert_eq!(d.path, hunted_path.to_str().unwrap()),
+		_ => panic!("Expected directory"),
+	}
+
+	let results = ctx.index.search("genre:metal").unwrap();
+	assert_eq!(results.len(), 5);
+	assert!(results.iter().all(|f| match f {
+		CollectionFile::Song(s) => s.artist == Some("Khemmis".to_owned()),
+		_ => false,
+	}));
+
+	let results = ctx.index.search("year:2016").unwrap();
+	assert_eq!(results.len(), 3);
+}
+
+#[test]
+fn search_normalizes_text() {
+	assert_eq!(search::normalize("Björk"), "bjork");
+	assert_eq!(search::normalize("SIGUR RÓS"), "sigur ros");
+	assert_eq!(
+		search::search_text(&[Some("Ágætis byrjun"), None, Some("Sigur Rós")]),
+		"agætis byrjun\nsigur ros"
+	);
+}
+
+#[test]
+fn search_ignores_case_and_accents() {
+	let builder = test::ContextBuilder::new(test_name!());
+	let collection_dir = builder.test_directory.join("collection");
+	let artist_dir = collection_dir.join("Björk");
+	std::fs::create_dir_all(&artist_dir).unwrap();
+	std::fs::copy(
+		"test-data/small-collection/Khemmis/Hunted/02 - Candlelight.mp3",
+		artist_dir.join("Jóga.mp3"),
+	)
+	.unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, collection_dir.to_str().unwrap())
+		.build();
+	ctx.index.update().unwrap();
+
+	let artist_path: PathBuf = [TEST_MOUNT_NAME, "Björk"].iter().collect();
+	let results = ctx.index.search("BJORK").unwrap();
+	assert_eq!(results.len(), 1);
+	match results[0] {
+		CollectionFile::Directory(ref d) => assert_eq!(d.path, artist_path.to_str().unwrap()),
+		_ => panic!("Expected directory"),
+	}
+
+	let song_path: PathBuf = [TEST_MOUNT_NAME, "Björk", "Jóga.mp3"].iter().collect();
+	let results = ctx.index.search("joga").unwrap();
+	assert_eq!(results.len(), 1);
+	match results[0] {
+		CollectionFile::Song(ref s) => {
+			assert_eq!(s.path, song_path.to_str().unwrap());
+			assert_eq!(s.title, Some("Candlelight".to_owned()));
+		}
+		_ => panic!("Expected song"),
+	}
+}
+
+#[test]
+fn search_clauses_are_combined() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let results = ctx.index.search("artist:khemmis title:door").unwrap();
+	assert_eq!(results.len(), 1);
+	match results[0] {
+		CollectionFile::Song(ref s) => assert_eq!(s.title, Some("Beyond The Door".to_owned())),
+		_ => panic!("Expected song"),
+	}
+
+	let results = ctx.index.search("artist:tobokegao candle").unwrap();
+	assert!(results.is_empty());
+}
+
+#[test]
+fn search_summary_counts_results() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	for query in &[
+		"",
+		"door",
+		"khemmis",
+		"artist:khemmis",
+		"genre:metal",
+		"year:2016",
+	] {
+		let results = ctx.index.search(query).unwrap();
+		let summary = ctx.index.search_summary(query).unwrap();
+		let directory_count = results
+			.iter()
+			.filter(|f| matches!(f, CollectionFile::Directory(_)))
+			.count();
+		assert_eq!(
+			summary.directory_count as usize, directory_count,
+			"{}",
+			query
+		);
+		assert_eq!(
+			summary.song_count as usize,
+			results.len() - directory_count,
+			"{}",
+			query
+		);
+	}
+
+	let summary = ctx.index.search_summary("door").unwrap();
+	assert_eq!(summary.directory_count, 0);
+	assert_eq!(summary.song_count, 1);
+}
+
+#[test]
+fn lists_genres_with_counts() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let genre = |name: &str, song_count: i64, album_count: i64| Genre {
+		name: name.to_owned(),
+		song_count,
+		album_count,
+	};
+	assert_eq!(
+		ctx.index.get_genres().unwrap(),
+		vec![
+			genre("Chiptune", 7, 1),
+			genre("Doom Metal", 5, 1),
+			genre("Electronic", 8, 2),
+			genre("Metal", 5, 1),
+		]
+	);
+}
+
+#[test]
+fn genres_survive_updates() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+	let genres = ctx.index.get_genres().unwrap();
+
+	ctx.index.update().unwrap();
+	assert_eq!(ctx.index.get_genres().unwrap(), genres);
+
+	ctx.index.update_incremental().unwrap();
+	assert_eq!(ctx.index.get_genres().unwrap(), genres);
+}
+
+#[test]
+fn can_get_albums_by_genre() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let picnic_path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic"].iter().collect();
+	let remixes_path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic (Remixes)"]
+		.iter()
+		.collect();
+	let albums = ctx.index.get_albums_by_genre("Electronic").unwrap();
+	let mut paths: Vec<&str> = albums.iter().map(|d| d.path.as_str()).collect();
+	paths.sort();
+	assert_eq!(
+		paths,
+		vec![
+			picnic_path.to_str().unwrap(),
+			remixes_path.to_str().unwrap()
+		]
+	);
+
+	let albums = ctx.index.get_albums_by_genre("Doom Metal").unwrap();
+	assert_eq!(albums.len(), 1);
+	assert_eq!(albums[0].album, Some("Hunted".to_owned()));
+
+	assert!(ctx.index.get_albums_by_genre("Polka").unwrap().is_empty());
+}
+
+#[test]
+fn can_get_albums_by_genre_with_many_albums() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	// More albums than SQLite's limit of 999 bound variables
+	let connection = ctx.db.connect().unwrap();
+	let artist_path: String = directories::table
+		.filter(directories::album.eq("Hunted"))
+		.select(directories::parent)
+		.get_result::<Option<String>>(&connection)
+		.unwrap()
+		.unwrap();
+	connection
+		.transaction::<_, diesel::result::Error, _>(|| {
+			for i in 0..1000 {
+				let album_path = format!("{}/Generated {}", artist_path, i);
+				let song_path = format!("{}/song.mp3", album_path);
+				diesel::insert_into(directories::table)
+					.values((
+						directories::path.eq(&album_path),
+						directories::parent.eq(&artist_path),
+						directories::album.eq(format!("Generated {}", i)),
+						directories::date_added.eq(0),
+					))
+					.execute(&connection)?;
+				diesel::insert_into(songs::table)
+					.values((songs::path.eq(&song_path), songs::parent.eq(&album_path)))
+					.execute(&connection)?;
+				diesel::insert_into(song_genres::table)
+					.values((
+						song_genres::path.eq(&song_path),
+						song_genres::genre.eq("Polka"),
+					))
+					.execute(&connection)?;
+			}
+			Ok(())
+		})
+		.unwrap();
+
+	let albums = ctx.index.get_albums_by_genre("Polka").unwrap();
+	assert_eq!(albums.len(), 1000);
+}
+
+#[test]
+fn lists_artists_with_album_counts() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.mount("box", "test-data/multi-disc")
+		.build();
+	ctx.index.update().unwrap();
+
+	let artist = |name: &str, album_count: i64| Artist {
+		name: name.to_owned(),
+		album_count,
+	};
+	assert_eq!(
+		ctx.index.get_artists().unwrap(),
+		vec![artist("Khemmis", 2), artist("Tobokegao", 2)]
+	);
+}
+
+#[test]
+fn can_get_artist_albums() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.mount("box", "test-data/multi-disc")
+		.build();
+	ctx.index.update().unwrap();
+
+	let hunted_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let box_set_path: PathBuf = ["box", "Box Set"].iter().collect();
+	let albums = ctx.index.get_artist_albums("Khemmis").unwrap();
+	let paths: Vec<&str> = albums.iter().map(|d| d.path.as_str()).collect();
+	assert_eq!(
+		paths,
+		vec![
+			hunted_path.to_str().unwrap(),
+			box_set_path.to_str().unwrap()
+		]
+	);
+
+	assert!(ctx.index.get_artist_albums("Metallica").unwrap().is_empty());
+}
+
+#[test]
+fn status_reports_index_time_past_2038() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+
+	let timestamp = i64::from(i32::MAX) + 1;
+	{
+		let connection = ctx.db.connect().unwrap();
+		diesel::update(misc_settings::table)
+			.set(misc_settings::index_last_completed.eq(timestamp))
+			.execute(&connection)
+			.unwrap();
+	}
+
+	let status = ctx.index.get_status().unwrap();
+	assert_eq!(status.last_index_time, Some(timestamp));
+}
+
+#[test]
+fn shutdown_interrupts_updates() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.shutdown();
+	assert!(matches!(
+		ctx.index.trigger_reindex(),
+		Err(TriggerError::ShuttingDown)
+	));
+
+	ctx.index.update().unwrap();
+	let status = ctx.index.get_status().unwrap();
+	assert_eq!(status.last_index_time, None);
+	assert!(!status.is_indexing);
+}
+
+#[test]
+fn dry_run_reports_changes_without_writing() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let original_collection_dir: PathBuf = ["test-data", "small-collection"].iter().collect();
+	let test_collection_dir: PathBuf = builder.test_directory.join("small-collection");
+
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy(
+		&original_collection_dir,
+		&builder.test_directory,
+		&copy_options,
+	)
+	.unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, test_collection_dir.to_str().unwrap())
+		.build();
+
+	let preview = ctx.index.dry_run().unwrap();
+	assert_eq!(preview.songs_added, 13);
+	assert_eq!(ctx.index.get_status().unwrap().song_count, 0);
+
+	ctx.index.update().unwrap();
+	assert_eq!(ctx.index.dry_run().unwrap(), UpdatePreview::default());
+
+	let picnic_directory = test_collection_dir.join("Tobokegao").join("Picnic");
+	std::fs::remove_dir_all(test_collection_dir.join("Khemmis")).unwrap();
+	std::fs::copy(
+		picnic_directory.join("07 - なぜ (Why).mp3"),
+		picnic_directory.join("08 - なぜ (Why).mp3"),
+	)
+	.unwrap();
+	let mut modified_song = std::fs::OpenOptions::new()
+		.append(true)
+		.open(picnic_directory.join("05 - シャーベット (Sherbet).mp3"))
+		.unwrap();
+	std::io::Write::write_all(&mut modified_song, &[0]).unwrap();
+
+	let preview = ctx.index.dry_run().unwrap();
+	assert_eq!(
+		preview,
+		UpdatePreview {
+			songs_added: 1,
+			songs_removed: 5,
+			songs_updated: 1,
+		}
+	);
+	assert_eq!(ctx.index.get_status().unwrap().song_count, 13);
+}
@@ -1,3 +1,4 @@
+use diesel::sql_types;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
@@ -7,10 +8,27 @@ use crate::db::songs;
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum CollectionFile {
 	Directory(Directory),
-	Song(Song),
+	Song(Box<Song>),
 }
 
-#[derive(Debug, PartialEq, Queryable, QueryableByName, Serialize, Deserialize)]
+/// Browse result that may include the content of its directories. Serializes like `CollectionFile`
+/// when directories were not expanded.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum BrowseEntry {
+	Directory(DirectoryTree),
+	Song(Box<Song>),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryTree {
+	#[serde(flatten)]
+	pub directory: Directory,
+	// Absent for directories on the last level that was browsed
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub children: Option<Vec<BrowseEntry>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Queryable, QueryableByName, Serialize, Deserialize)]
 #[table_name = "songs"]
 pub struct Song {
 	#[serde(skip_serializing, skip_deserializing)]
@@ -31,6 +49,15 @@ pub struct Song {
 	pub composer: Option<String>,
 	pub genre: Option<String>,
 	pub label: Option<String>,
+	pub disc_subtitle: Option<String>,
+	pub file_size: Option<i64>,
+	#[serde(skip_serializing, skip_deserializing)]
+	pub file_modified: Option<i64>,
+	pub replay_gain_track: Option<f32>,
+	pub replay_gain_album: Option<f32>,
+	#[serde(skip_serializing, skip_deserializing)]
+	pub search_text: Option<String>,
+	pub bitrate: Option<i32>,
 }
 
 impl Song {
@@ -49,6 +76,15 @@ impl Song {
 	}
 }
 
+/// Song listed by flatten, along with the path of the song that follows it in the same directory.
+/// `next_path` is absent for the last song of a directory.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SequencedSong {
+	#[serde(flatten)]
+	pub song: Song,
+	pub next_path: Option<String>,
+}
+
 #[derive(Debug, PartialEq, Queryable, Serialize, Deserialize)]
 pub struct Directory {
 	#[serde(skip_serializing, skip_deserializing)]
@@ -61,6 +97,8 @@ pub struct Directory {
 	pub album: Option<String>,
 	pub artwork: Option<String>,
 	pub date_added: i32,
+	#[serde(skip_serializing, skip_deserializing)]
+	pub search_text: Option<String>,
 }
 
 impl Directory {
@@ -78,3 +116,112 @@ impl Directory {
 		Some(self)
 	}
 }
+
+/// Contents of an album directory, with songs grouped by disc.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Album {
+	#[serde(flatten)]
+	pub directory: Directory,
+	pub discs: Vec<Disc>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Disc {
+	pub number: Option<i32>,
+	pub subtitle: Option<String>,
+	/// Subtitle of the disc when tagged, "Disc N" otherwise.
+	pub name: String,
+	pub songs: Vec<Song>,
+}
+
+impl Disc {
+	pub fn new(number: Option<i32>, songs: Vec<Song>) -> Self {
+		let subtitle = songs
+			.iter()
+			.filter_map(|s| s.disc_subtitle.as_ref())
+			.find(|s| !s.is_empty())
+			.cloned();
+		let name = subtitle
+			.clone()
+			.unwrap_or_else(|| format!("Disc {}", number.unwrap_or(1)));
+		Self {
+			number,
+			subtitle,
+			name,
+			songs,
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, QueryableByName, Serialize, Deserialize)]
+pub struct Artist {
+	#[sql_type = "sql_types::Text"]
+	pub name: String,
+	#[sql_type = "sql_types::BigInt"]
+	pub album_count: i64,
+}
+
+#[derive(Debug, PartialEq, QueryableByName, Serialize, Deserialize)]
+pub struct Genre {
+	#[sql_type = "sql_types::Text"]
+	pub name: String,
+	#[sql_type = "sql_types::BigInt"]
+	pub song_count: i64,
+	#[sql_type = "sql_types::BigInt"]
+	pub album_count: i64,
+}
+
+/// Artist under which songs and albums are grouped: the album artist when present, otherwise the
+/// song artist. When neither is known, callers should display the `unknown_artist_label` setting.
+pub fn grouping_artist<'a>(
+	album_artist: Option<&'a str>,
+	artist: Option<&'a str>,
+) -> Option<&'a str> {
+	album_artist
+		.filter(|a| !a.is_empty())
+		.or_else(|| artist.filter(|a| !a.is_empty()))
+}
+
+/// SQL equivalent of `grouping_artist` for a `songs` table aliased as `s`, including the fallback
+/// to the `unknown_artist_label` setting.
+pub const GROUPING_ARTIST_SQL: &str = "COALESCE(NULLIF(s.album_artist, ''), NULLIF(s.artist, ''), (SELECT unknown_artist_label FROM misc_settings))";
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+	pub song_count: i64,
+	pub directory_count: i64,
+	// Unix timestamp (in seconds) of the last completed index update
+	pub last_index_time: Option<i64>,
+	pub is_indexing: bool,
+}
+
+// Number of directories and songs found by a search
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SearchSummary {
+	pub directory_count: i64,
+	pub song_count: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanState {
+	Idle,
+	Scanning,
+}
+
+// Songs processed by the index update in progress. `total` grows as directories are discovered.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+	pub state: ScanState,
+	pub processed: u64,
+	pub total: u64,
+	pub current_path: Option<String>,
+}
+
+// Songs an index update would change, as found by a dry run
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct UpdatePreview {
+	pub songs_added: i64,
+	pub songs_removed: i64,
+	pub songs_updated: i64,
+}
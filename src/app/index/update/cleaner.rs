@@ -2,36 +2,70 @@ use anyhow::*;
 use diesel;
 use diesel::prelude::*;
 use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fs;
 use std::path::Path;
 
+use super::{descendants_filter, has_audio_extension};
 use crate::app::vfs;
-use crate::db::{directories, songs, DB};
+use crate::db::{directories, song_artists, song_genres, songs, DB};
 
 const INDEX_BUILDING_CLEAN_BUFFER_SIZE: usize = 500; // Deletions in each transaction
 
 pub struct Cleaner {
 	db: DB,
 	vfs_manager: vfs::Manager,
+	audio_extensions: HashSet<String>,
+	follow_symlinks: bool,
 }
 
 impl Cleaner {
-	pub fn new(db: DB, vfs_manager: vfs::Manager) -> Self {
-		Self { db, vfs_manager }
+	pub fn new(
+		db: DB,
+		vfs_manager: vfs::Manager,
+		audio_extensions: HashSet<String>,
+		follow_symlinks: bool,
+	) -> Self {
+		Self {
+			db,
+			vfs_manager,
+			audio_extensions,
+			follow_symlinks,
+		}
 	}
 
-	pub fn clean(&self) -> Result<()> {
+	pub fn clean(&self, scope: Option<&Path>) -> Result<()> {
 		let vfs = self.vfs_manager.get_vfs()?;
 
 		let all_directories: Vec<String> = {
 			let connection = self.db.connect()?;
-			directories::table
-				.select(directories::path)
-				.load(&connection)?
+			match scope {
+				Some(scope) => {
+					let (scope_path, descendants_filter) = Self::scope_filters(scope);
+					directories::table
+						.select(directories::path)
+						.filter(directories::path.eq(&scope_path))
+						.or_filter(directories::path.like(&descendants_filter).escape('\\'))
+						.load(&connection)?
+				}
+				None => directories::table
+					.select(directories::path)
+					.load(&connection)?,
+			}
 		};
 
 		let all_songs: Vec<String> = {
 			let connection = self.db.connect()?;
-			songs::table.select(songs::path).load(&connection)?
+			match scope {
+				Some(scope) => {
+					let (_, descendants_filter) = Self::scope_filters(scope);
+					songs::table
+						.select(songs::path)
+						.filter(songs::path.like(&descendants_filter).escape('\\'))
+						.load(&connection)?
+				}
+				None => songs::table.select(songs::path).load(&connection)?,
+			}
 		};
 
 		let list_missing_directories = || {
@@ -39,7 +73,9 @@ impl Cleaner {
 				.par_iter()
 				.filter(|ref directory_path| {
 					let path = Path::new(&directory_path);
-					!path.exists() || vfs.real_to_virtual(path).is_err()
+					!path.exists()
+						|| vfs.real_to_virtual(path).is_err()
+						|| (!self.follow_symlinks && Self::is_behind_symlink(path, &vfs))
 				})
 				.collect::<Vec<_>>()
 		};
@@ -49,7 +85,10 @@ impl Cleaner {
 				.par_iter()
 				.filter(|ref song_path| {
 					let path = Path::new(&song_path);
-					!path.exists() || vfs.real_to_virtual(path).is_err()
+					!path.exists()
+						|| vfs.real_to_virtual(path).is_err()
+						|| !has_audio_extension(path, &self.audio_extensions)
+						|| (!self.follow_symlinks && Self::is_behind_symlink(path, &vfs))
 				})
 				.collect::<Vec<_>>()
 		};
@@ -67,9 +106,35 @@ impl Cleaner {
 			for chunk in missing_songs[..].chunks(INDEX_BUILDING_CLEAN_BUFFER_SIZE) {
 				diesel::delete(songs::table.filter(songs::path.eq_any(chunk)))
 					.execute(&connection)?;
+				diesel::delete(song_artists::table.filter(song_artists::path.eq_any(chunk)))
+					.execute(&connection)?;
+				diesel::delete(song_genres::table.filter(song_genres::path.eq_any(chunk)))
+					.execute(&connection)?;
 			}
 		}
 
 		Ok(())
 	}
+
+	// Whether the path, or any of its parents within a mount, is a symbolic link
+	fn is_behind_symlink(path: &Path, vfs: &vfs::VFS) -> bool {
+		for ancestor in path.ancestors() {
+			if vfs.mounts().iter().any(|m| m.source == ancestor) {
+				return false;
+			}
+			let is_symlink = fs::symlink_metadata(ancestor)
+				.map(|m| m.file_type().is_symlink())
+				.unwrap_or(false);
+			if is_symlink {
+				return true;
+			}
+		}
+		false
+	}
+
+	fn scope_filters(scope: &Path) -> (String, String) {
+		let scope_path = scope.to_string_lossy().into_owned();
+		let descendants_filter = descendants_filter(scope);
+		(scope_path, descendants_filter)
+	}
 }
@@ -1,25 +1,36 @@
 use crossbeam_channel::{Receiver, Sender};
-use log::error;
+use log::{error, info};
 use regex::Regex;
+use std::sync::Arc;
 
 use super::*;
+use crate::app::index::search;
 
 pub struct Collector {
 	receiver: Receiver<traverser::Directory>,
 	sender: Sender<inserter::Item>,
+	album_art_names: Vec<String>,
 	album_art_pattern: Regex,
+	max_tag_length: Option<usize>,
+	progress: Arc<ScanProgress>,
 }
 
 impl Collector {
 	pub fn new(
 		receiver: Receiver<traverser::Directory>,
 		sender: Sender<inserter::Item>,
+		album_art_names: Vec<String>,
 		album_art_pattern: Regex,
+		max_tag_length: Option<usize>,
+		progress: Arc<ScanProgress>,
 	) -> Self {
 		Self {
 			receiver,
 			sender,
+			album_art_names,
 			album_art_pattern,
+			max_tag_length,
+			progress,
 		}
 	}
 
@@ -45,9 +56,18 @@ impl Collector {
 		let directory_parent_string = directory.parent.map(|p| p.to_string_lossy().to_string());
 
 		for song in directory.songs {
-			let tags = song.metadata;
+			let mut tags = song.metadata;
 			let path_string = song.path.to_string_lossy().to_string();
 
+			if let Some(max_tag_length) = self.max_tag_length {
+				for field in tags.truncate(max_tag_length) {
+					info!(
+						"Truncated {} tag of `{}` to {} characters",
+						field, path_string, max_tag_length
+					);
+				}
+			}
+
 			if tags.year.is_some() {
 				inconsistent_directory_year |=
 					directory_year.is_some() && directory_year != tags.year;
@@ -60,41 +80,58 @@ impl Collector {
 				directory_album = tags.album.as_ref().cloned();
 			}
 
-			if tags.album_artist.is_some() {
+			let artist = grouping_artist(tags.album_artist.as_deref(), tags.artist.as_deref());
+			if artist.is_some() {
 				inconsistent_directory_artist |=
-					directory_artist.is_some() && directory_artist != tags.album_artist;
-				directory_artist = tags.album_artist.as_ref().cloned();
-			} else if tags.artist.is_some() {
-				inconsistent_directory_artist |=
-					directory_artist.is_some() && directory_artist != tags.artist;
-				directory_artist = tags.artist.as_ref().cloned();
+					directory_artist.is_some() && directory_artist.as_deref() != artist;
+				directory_artist = artist.map(|a| a.to_owned());
 			}
 
+			let search_text = search::search_text(&[
+				Some(&path_string),
+				tags.title.as_deref(),
+				tags.album.as_deref(),
+				tags.artist.as_deref(),
+				tags.album_artist.as_deref(),
+			]);
+
 			let artwork_path = if tags.has_artwork {
 				Some(path_string.clone())
 			} else {
 				directory_artwork.as_ref().cloned()
 			};
 
-			if let Err(e) = self.sender.send(inserter::Item::Song(inserter::Song {
-				path: path_string,
-				parent: directory_path_string.clone(),
-				disc_number: tags.disc_number.map(|n| n as i32),
-				track_number: tags.track_number.map(|n| n as i32),
-				title: tags.title,
-				duration: tags.duration.map(|n| n as i32),
-				artist: tags.artist,
-				album_artist: tags.album_artist,
-				album: tags.album,
-				year: tags.year,
-				artwork: artwork_path,
-				lyricist: tags.lyricist,
-				composer: tags.composer,
-				genre: tags.genre,
-				label: tags.label,
-			})) {
+			if let Err(e) = self.sender.send(inserter::Item::Song {
+				song: Box::new(inserter::Song {
+					path: path_string,
+					parent: directory_path_string.clone(),
+					disc_number: tags.disc_number.map(|n| n as i32),
+					track_number: tags.track_number.map(|n| n as i32),
+					title: tags.title,
+					duration: tags.duration.map(|n| n as i32),
+					artist: tags.artist,
+					album_artist: tags.album_artist,
+					album: tags.album,
+					year: tags.year,
+					artwork: artwork_path,
+					lyricist: tags.lyricist,
+					composer: tags.composer,
+					genre: tags.genre,
+					label: tags.label,
+					disc_subtitle: tags.disc_subtitle,
+					file_size: song.file_size,
+					file_modified: song.file_modified,
+					replay_gain_track: tags.replay_gain_track,
+					replay_gain_album: tags.replay_gain_album,
+					search_text: Some(search_text),
+					bitrate: average_bitrate(song.file_size, tags.duration),
+				}),
+				artists: tags.artists,
+				genres: tags.genres,
+			}) {
 				error!("Error while sending song from collector: {}", e);
 			}
+			self.progress.add_processed(&song.path);
 		}
 
 		if inconsistent_directory_year {
@@ -107,6 +144,7 @@ impl Collector {
 			directory_artist = None;
 		}
 
+		let directory_search_text = search::normalize(&directory_path_string);
 		if let Err(e) = self
 			.sender
 			.send(inserter::Item::Directory(inserter::Directory {
@@ -117,12 +155,30 @@ impl Collector {
 				artist: directory_artist,
 				year: directory_year,
 				date_added: directory.created,
+				search_text: Some(directory_search_text),
 			})) {
 			error!("Error while sending directory from collector: {}", e);
 		}
 	}
 
+	// Album art file names take precedence over the pattern, in the order they are listed. Songs
+	// with embedded artwork are the last resort.
 	fn get_artwork(&self, directory: &traverser::Directory) -> Option<String> {
+		let named_artwork = self.album_art_names.iter().find_map(|name| {
+			directory.other_files.iter().find_map(|path| {
+				let matches = path
+					.file_name()
+					.and_then(|n| n.to_str())
+					.map(|n| n.to_lowercase() == *name)
+					.unwrap_or(false);
+				if matches {
+					Some(path.to_string_lossy().to_string())
+				} else {
+					None
+				}
+			})
+		});
+
 		let regex_artwork = directory.other_files.iter().find_map(|path| {
 			let matches = path
 				.file_name()
@@ -144,6 +200,24 @@ impl Collector {
 			}
 		});
 
-		regex_artwork.or(embedded_artwork)
+		named_artwork.or(regex_artwork).or(embedded_artwork)
 	}
 }
+
+// In kbps, for the whole file including tags and embedded artwork
+fn average_bitrate(file_size: Option<i64>, duration: Option<u32>) -> Option<i32> {
+	match (file_size, duration) {
+		(Some(size), Some(duration)) if duration > 0 => {
+			Some((size * 8 / (i64::from(duration) * 1000)) as i32)
+		}
+		_ => None,
+	}
+}
+
+#[test]
+fn computes_average_bitrate() {
+	assert_eq!(average_bitrate(Some(8_000_000), Some(200)), Some(320));
+	assert_eq!(average_bitrate(Some(8_000_000), Some(0)), None);
+	assert_eq!(average_bitrate(Some(8_000_000), None), None);
+	assert_eq!(average_bitrate(None, Some(200)), None);
+}
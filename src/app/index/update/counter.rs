@@ -0,0 +1,47 @@
+use crossbeam_channel::Receiver;
+use std::collections::HashMap;
+
+use super::*;
+
+// Tallies the songs found by a dry run against those already in the index
+pub struct Counter {
+	receiver: Receiver<inserter::Item>,
+	indexed_songs: HashMap<String, (Option<i64>, Option<i64>)>,
+}
+
+impl Counter {
+	pub fn new(
+		receiver: Receiver<inserter::Item>,
+		indexed_songs: Vec<(String, Option<i64>, Option<i64>)>,
+	) -> Self {
+		Self {
+			receiver,
+			indexed_songs: indexed_songs
+				.into_iter()
+				.map(|(path, file_size, file_modified)| (path, (file_size, file_modified)))
+				.collect(),
+		}
+	}
+
+	pub fn count(mut self) -> UpdatePreview {
+		let mut preview = UpdatePreview::default();
+		while let Ok(item) = self.receiver.recv() {
+			if let inserter::Item::Song { song, .. } = item {
+				match self.indexed_songs.remove(&song.path) {
+					None => preview.songs_added += 1,
+					Some((file_size, file_modified)) => {
+						let unchanged = file_size.is_some()
+							&& file_size == song.file_size
+							&& file_modified == song.file_modified;
+						if !unchanged {
+							preview.songs_updated += 1;
+						}
+					}
+				}
+			}
+		}
+		// Whatever was not found on disk would be removed
+		preview.songs_removed = self.indexed_songs.len() as i64;
+		preview
+	}
+}
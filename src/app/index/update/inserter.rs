@@ -4,9 +4,11 @@ use diesel;
 use diesel::prelude::*;
 use log::error;
 
-use crate::db::{directories, songs, DB};
+use crate::db::{directories, song_artists, song_genres, songs, DB};
 
-const INDEX_BUILDING_INSERT_BUFFER_SIZE: usize = 1000; // Insertions in each transaction
+pub const DEFAULT_INSERT_BUFFER_SIZE: usize = 1000; // Insertions in each transaction
+pub const MAX_INSERT_BUFFER_SIZE: usize = 50_000; // Bounds memory reserved for the buffers
+const INDEX_BUILDING_DELETE_CHUNK_SIZE: usize = 500; // Stays below SQLite's limit of 999 bound variables
 
 #[derive(Debug, Insertable)]
 #[table_name = "songs"]
@@ -26,6 +28,27 @@ pub struct Song {
 	pub composer: Option<String>,
 	pub genre: Option<String>,
 	pub label: Option<String>,
+	pub disc_subtitle: Option<String>,
+	pub file_size: Option<i64>,
+	pub file_modified: Option<i64>,
+	pub replay_gain_track: Option<f32>,
+	pub replay_gain_album: Option<f32>,
+	pub search_text: Option<String>,
+	pub bitrate: Option<i32>,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "song_artists"]
+struct SongArtist {
+	path: String,
+	artist: String,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "song_genres"]
+struct SongGenre {
+	path: String,
+	genre: String,
 }
 
 #[derive(Debug, Insertable)]
@@ -38,29 +61,40 @@ pub struct Directory {
 	pub album: Option<String>,
 	pub artwork: Option<String>,
 	pub date_added: i32,
+	pub search_text: Option<String>,
 }
 
 pub enum Item {
 	Directory(Directory),
-	Song(Song),
+	Song {
+		song: Box<Song>,
+		artists: Vec<String>,
+		genres: Vec<String>,
+	},
 }
 
 pub struct Inserter {
 	receiver: Receiver<Item>,
 	new_directories: Vec<Directory>,
 	new_songs: Vec<Song>,
+	new_song_artists: Vec<SongArtist>,
+	new_song_genres: Vec<SongGenre>,
+	buffer_size: usize,
 	db: DB,
 }
 
 impl Inserter {
-	pub fn new(db: DB, receiver: Receiver<Item>) -> Self {
-		let new_directories = Vec::with_capacity(INDEX_BUILDING_INSERT_BUFFER_SIZE);
-		let new_songs = Vec::with_capacity(INDEX_BUILDING_INSERT_BUFFER_SIZE);
+	pub fn new(db: DB, receiver: Receiver<Item>, buffer_size: usize) -> Self {
+		let new_directories = Vec::with_capacity(buffer_size);
+		let new_songs = Vec::with_capacity(buffer_size);
 		Self {
 			db,
 			receiver,
 			new_directories,
 			new_songs,
+			new_song_artists: Vec::new(),
+			new_song_genres: Vec::new(),
+			buffer_size,
 		}
 	}
 
@@ -77,13 +111,28 @@ impl Inserter {
 		match insert {
 			Item::Directory(d) => {
 				self.new_directories.push(d);
-				if self.new_directories.len() >= INDEX_BUILDING_INSERT_BUFFER_SIZE {
+				if self.new_directories.len() >= self.buffer_size {
 					self.flush_directories();
 				}
 			}
-			Item::Song(s) => {
-				self.new_songs.push(s);
-				if self.new_songs.len() >= INDEX_BUILDING_INSERT_BUFFER_SIZE {
+			Item::Song {
+				song,
+				artists,
+				genres,
+			} => {
+				let path = &song.path;
+				self.new_song_artists
+					.extend(artists.into_iter().map(|artist| SongArtist {
+						path: path.clone(),
+						artist,
+					}));
+				self.new_song_genres
+					.extend(genres.into_iter().map(|genre| SongGenre {
+						path: path.clone(),
+						genre,
+					}));
+				self.new_songs.push(*song);
+				if self.new_songs.len() >= self.buffer_size {
 					self.flush_songs();
 				}
 			}
@@ -108,13 +157,35 @@ impl Inserter {
 	}
 
 	fn flush_songs(&mut self) {
+		let paths: Vec<&str> = self.new_songs.iter().map(|s| s.path.as_str()).collect();
 		if self
 			.db
 			.connect()
 			.and_then(|connection| {
-				diesel::insert_into(songs::table)
-					.values(&self.new_songs)
-					.execute(&*connection) // TODO https://github.com/diesel-rs/diesel/issues/1822
+				connection
+					.transaction::<_, diesel::result::Error, _>(|| {
+						// Songs being indexed again may have lost some of their artists or genres
+						for chunk in paths.chunks(INDEX_BUILDING_DELETE_CHUNK_SIZE) {
+							diesel::delete(
+								song_artists::table.filter(song_artists::path.eq_any(chunk)),
+							)
+							.execute(&*connection)?;
+							diesel::delete(
+								song_genres::table.filter(song_genres::path.eq_any(chunk)),
+							)
+							.execute(&*connection)?;
+						}
+						diesel::insert_into(songs::table)
+							.values(&self.new_songs)
+							.execute(&*connection)?; // TODO https://github.com/diesel-rs/diesel/issues/1822
+						diesel::insert_into(song_artists::table)
+							.values(&self.new_song_artists)
+							.execute(&*connection)?;
+						diesel::insert_into(song_genres::table)
+							.values(&self.new_song_genres)
+							.execute(&*connection)?;
+						Ok(())
+					})
 					.map_err(Error::new)
 			})
 			.is_err()
@@ -122,6 +193,8 @@ impl Inserter {
 			error!("Could not insert new songs in database");
 		}
 		self.new_songs.clear();
+		self.new_song_artists.clear();
+		self.new_song_genres.clear();
 	}
 }
 
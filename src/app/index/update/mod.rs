@@ -1,46 +1,160 @@
 use anyhow::*;
+use diesel::prelude::*;
 use log::{error, info};
-use std::time;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::{self, SystemTime, UNIX_EPOCH};
 
 mod cleaner;
 mod collector;
+mod counter;
 mod inserter;
+mod progress;
 mod traverser;
 
 use super::*;
+use crate::db::{misc_settings, song_artists, song_genres, songs};
 use cleaner::Cleaner;
 use collector::Collector;
+use counter::Counter;
 use inserter::Inserter;
-use traverser::Traverser;
+pub(super) use progress::ScanProgress;
+use traverser::{KnownSong, Traverser, WorkItem};
+
+pub use inserter::{DEFAULT_INSERT_BUFFER_SIZE, MAX_INSERT_BUFFER_SIZE};
 
 impl Index {
+	#[cfg(test)]
 	pub fn update(&self) -> Result<()> {
+		self.update_with_mode(ScanMode::Full)
+	}
+
+	#[cfg(test)]
+	pub fn update_incremental(&self) -> Result<()> {
+		self.update_with_mode(ScanMode::Incremental)
+	}
+
+	#[cfg(test)]
+	pub fn update_path(&self, virtual_path: &Path) -> Result<()> {
+		self.update_path_with_mode(virtual_path, ScanMode::Full)
+	}
+
+	pub(super) fn update_with_mode(&self, mode: ScanMode) -> Result<()> {
 		let start = time::Instant::now();
 		info!("Beginning library index update");
 
+		let vfs = self.vfs_manager.get_vfs()?;
+		let roots = vfs
+			.mounts()
+			.iter()
+			.map(|m| WorkItem::new(None, m.source.clone()))
+			.collect();
+		self.update_roots(None, roots, mode)?;
+
+		info!(
+			"Library index update took {} seconds",
+			start.elapsed().as_millis() as f32 / 1000.0
+		);
+
+		Ok(())
+	}
+
+	pub(super) fn update_path_with_mode(&self, virtual_path: &Path, mode: ScanMode) -> Result<()> {
+		let start = time::Instant::now();
+		info!(
+			"Beginning library index update for `{}`",
+			virtual_path.display()
+		);
+
+		let vfs = self.vfs_manager.get_vfs()?;
+		let real_path = vfs.virtual_to_real(virtual_path)?;
+		let is_mount_root = vfs.mounts().iter().any(|m| m.source == real_path);
+		let parent = if is_mount_root {
+			None
+		} else {
+			real_path.parent().map(Path::to_path_buf)
+		};
+		// Content of a deleted directory is only cleaned up
+		let roots = if real_path.exists() {
+			vec![WorkItem::new(parent, real_path.clone())]
+		} else {
+			info!(
+				"`{}` no longer exists, removing its content from the index",
+				virtual_path.display()
+			);
+			Vec::new()
+		};
+		self.update_roots(Some(real_path), roots, mode)?;
+
+		info!(
+			"Library index update for `{}` took {} seconds",
+			virtual_path.display(),
+			start.elapsed().as_millis() as f32 / 1000.0
+		);
+
+		Ok(())
+	}
+
+	fn update_roots(
+		&self,
+		scope: Option<PathBuf>,
+		roots: Vec<WorkItem>,
+		mode: ScanMode,
+	) -> Result<()> {
 		let album_art_pattern = self.settings_manager.get_index_album_art_pattern()?;
+		let album_art_names = self.settings_manager.get_index_album_art_names()?;
+		let max_tag_length = self.settings_manager.get_index_max_tag_length()?;
+		let audio_extensions = self.get_audio_extensions()?;
+		let follow_symlinks = self.settings_manager.get_follow_symlinks()?;
 
-		let cleaner = Cleaner::new(self.db.clone(), self.vfs_manager.clone());
-		cleaner.clean()?;
+		let cleaner = Cleaner::new(
+			self.db.clone(),
+			self.vfs_manager.clone(),
+			audio_extensions.clone(),
+			follow_symlinks,
+		);
+		cleaner.clean(scope.as_deref())?;
+
+		let known_songs = match mode {
+			ScanMode::Full => HashMap::new(),
+			ScanMode::Incremental => self.get_known_songs(scope.as_deref())?,
+		};
 
 		let (insert_sender, insert_receiver) = crossbeam_channel::unbounded();
 		let inserter_db = self.db.clone();
+		let insert_buffer_size = self.insert_buffer_size;
 		let insertion_thread = std::thread::spawn(move || {
-			let mut inserter = Inserter::new(inserter_db, insert_receiver);
+			let mut inserter = Inserter::new(inserter_db, insert_receiver, insert_buffer_size);
 			inserter.insert();
 		});
 
 		let (collect_sender, collect_receiver) = crossbeam_channel::unbounded();
+		let collector_progress = self.scan_progress.clone();
 		let collector_thread = std::thread::spawn(move || {
-			let collector = Collector::new(collect_receiver, insert_sender, album_art_pattern);
+			let collector = Collector::new(
+				collect_receiver,
+				insert_sender,
+				album_art_names,
+				album_art_pattern,
+				max_tag_length,
+				collector_progress,
+			);
 			collector.collect();
 		});
 
-		let vfs = self.vfs_manager.get_vfs()?;
+		let stop_scan = self.stop_scan.clone();
+		let traverser_progress = self.scan_progress.clone();
 		let traverser_thread = std::thread::spawn(move || {
-			let mounts = vfs.mounts();
-			let traverser = Traverser::new(collect_sender);
-			traverser.traverse(mounts.iter().map(|p| p.source.clone()).collect());
+			let traverser = Traverser::new(
+				collect_sender,
+				known_songs,
+				audio_extensions,
+				follow_symlinks,
+				stop_scan,
+				traverser_progress,
+			);
+			traverser.traverse(roots);
 		});
 
 		if let Err(e) = traverser_thread.join() {
@@ -55,11 +169,194 @@ impl Index {
 			error!("Error joining on inserter thread: {:?}", e);
 		}
 
-		info!(
-			"Library index update took {} seconds",
-			start.elapsed().as_millis() as f32 / 1000.0
-		);
+		// Content found before the interruption is saved, but the update is not complete
+		if self.stop_scan.load(Ordering::SeqCst) {
+			info!("Library index update was interrupted");
+			return Ok(());
+		}
+
+		self.record_completion()
+	}
+
+	/// Walks the collection and reports how an update would change the indexed songs, without
+	/// writing to the database.
+	pub fn dry_run(&self) -> Result<UpdatePreview> {
+		let album_art_pattern = self.settings_manager.get_index_album_art_pattern()?;
+		let album_art_names = self.settings_manager.get_index_album_art_names()?;
+		let max_tag_length = self.settings_manager.get_index_max_tag_length()?;
+		let audio_extensions = self.get_audio_extensions()?;
+		let follow_symlinks = self.settings_manager.get_follow_symlinks()?;
+
+		let vfs = self.vfs_manager.get_vfs()?;
+		let roots = vfs
+			.mounts()
+			.iter()
+			.map(|m| WorkItem::new(None, m.source.clone()))
+			.collect();
+
+		let indexed_songs: Vec<(String, Option<i64>, Option<i64>)> = {
+			let connection = self.db.connect()?;
+			songs::table
+				.select((songs::path, songs::file_size, songs::file_modified))
+				.load(&connection)?
+		};
+
+		// Unchanged files are not read again, as in incremental updates
+		let known_songs = self.get_known_songs(None)?;
+
+		let (count_sender, count_receiver) = crossbeam_channel::unbounded();
+		let counting_thread = std::thread::spawn(move || {
+			let counter = Counter::new(count_receiver, indexed_songs);
+			counter.count()
+		});
+
+		// Dry runs are not reported as scan progress
+		let progress = Arc::new(ScanProgress::default());
 
+		let (collect_sender, collect_receiver) = crossbeam_channel::unbounded();
+		let collector_progress = progress.clone();
+		let collector_thread = std::thread::spawn(move || {
+			let collector = Collector::new(
+				collect_receiver,
+				count_sender,
+				album_art_names,
+				album_art_pattern,
+				max_tag_length,
+				collector_progress,
+			);
+			collector.collect();
+		});
+
+		let stop_scan = self.stop_scan.clone();
+		let traverser_thread = std::thread::spawn(move || {
+			let traverser = Traverser::new(
+				collect_sender,
+				known_songs,
+				audio_extensions,
+				follow_symlinks,
+				stop_scan,
+				progress,
+			);
+			traverser.traverse(roots);
+		});
+
+		if let Err(e) = traverser_thread.join() {
+			error!("Error joining on traverser thread: {:?}", e);
+		}
+
+		if let Err(e) = collector_thread.join() {
+			error!("Error joining on collector thread: {:?}", e);
+		}
+
+		counting_thread
+			.join()
+			.map_err(|e| anyhow!("Error joining on counting thread: {:?}", e))
+	}
+
+	fn record_completion(&self) -> Result<()> {
+		let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+		let connection = self.db.connect()?;
+		diesel::update(misc_settings::table)
+			.set(misc_settings::index_last_completed.eq(now))
+			.execute(&connection)?;
 		Ok(())
 	}
+
+	fn get_audio_extensions(&self) -> Result<HashSet<String>> {
+		let extensions = self.settings_manager.get_index_audio_extensions()?;
+		Ok(extensions.into_iter().collect())
+	}
+
+	fn get_known_songs(&self, scope: Option<&Path>) -> Result<HashMap<PathBuf, KnownSong>> {
+		let connection = self.db.connect()?;
+		let descendants_filter = scope.map(descendants_filter);
+		let known_songs: Vec<Song> = match &descendants_filter {
+			Some(filter) => songs::table
+				.filter(songs::path.like(filter).escape('\\'))
+				.load(&connection)?,
+			None => songs::table.load(&connection)?,
+		};
+		let known_artists: Vec<(String, String)> = match &descendants_filter {
+			Some(filter) => song_artists::table
+				.select((song_artists::path, song_artists::artist))
+				.filter(song_artists::path.like(filter).escape('\\'))
+				.order(song_artists::id)
+				.load(&connection)?,
+			None => song_artists::table
+				.select((song_artists::path, song_artists::artist))
+				.order(song_artists::id)
+				.load(&connection)?,
+		};
+		let known_genres: Vec<(String, String)> = match &descendants_filter {
+			Some(filter) => song_genres::table
+				.select((song_genres::path, song_genres::genre))
+				.filter(song_genres::path.like(filter).escape('\\'))
+				.order(song_genres::id)
+				.load(&connection)?,
+			None => song_genres::table
+				.select((song_genres::path, song_genres::genre))
+				.order(song_genres::id)
+				.load(&connection)?,
+		};
+		let mut artists = group_by_path(known_artists);
+		let mut genres = group_by_path(known_genres);
+		Ok(known_songs
+			.into_iter()
+			.filter_map(|song| {
+				let known_song = KnownSong {
+					file_size: song.file_size?,
+					file_modified: song.file_modified?,
+					metadata: metadata::SongTags {
+						disc_number: song.disc_number.map(|n| n as u32),
+						track_number: song.track_number.map(|n| n as u32),
+						title: song.title,
+						duration: song.duration.map(|n| n as u32),
+						artists: artists.remove(&song.path).unwrap_or_default(),
+						artist: song.artist,
+						album_artist: song.album_artist,
+						album: song.album,
+						year: song.year,
+						has_artwork: song.artwork.as_ref() == Some(&song.path),
+						lyricist: song.lyricist,
+						composer: song.composer,
+						genres: genres.remove(&song.path).unwrap_or_default(),
+						genre: song.genre,
+						label: song.label,
+						disc_subtitle: song.disc_subtitle,
+						replay_gain_track: song.replay_gain_track,
+						replay_gain_album: song.replay_gain_album,
+					},
+				};
+				Some((PathBuf::from(song.path), known_song))
+			})
+			.collect())
+	}
+}
+
+fn group_by_path(rows: Vec<(String, String)>) -> HashMap<String, Vec<String>> {
+	let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+	for (path, value) in rows {
+		groups.entry(path).or_default().push(value);
+	}
+	groups
+}
+
+fn has_audio_extension(path: &Path, audio_extensions: &HashSet<String>) -> bool {
+	path.extension()
+		.and_then(|e| e.to_str())
+		.map(|e| audio_extensions.contains(&e.to_lowercase()))
+		.unwrap_or(false)
+}
+
+// LIKE pattern matching everything under a directory, to be used with `\` as the escape
+// character. Wildcards in the directory path are escaped so they only match themselves.
+fn descendants_filter(directory: &Path) -> String {
+	let prefix = directory.join("").to_string_lossy().into_owned();
+	format!(
+		"{}%",
+		prefix
+			.replace('\\', "\\\\")
+			.replace('%', "\\%")
+			.replace('_', "\\_")
+	)
 }
@@ -0,0 +1,42 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+// Counters shared by the threads of an index update. Songs are counted as discovered once the
+// traverser has read their directory, and as processed once the collector hands them to the
+// inserter.
+#[derive(Debug, Default)]
+pub struct ScanProgress {
+	total: AtomicU64,
+	processed: AtomicU64,
+	current_path: Mutex<Option<PathBuf>>,
+}
+
+impl ScanProgress {
+	pub fn reset(&self) {
+		self.total.store(0, Ordering::SeqCst);
+		self.processed.store(0, Ordering::SeqCst);
+		*self.current_path.lock().unwrap() = None;
+	}
+
+	pub fn add_discovered(&self, num_songs: usize) {
+		self.total.fetch_add(num_songs as u64, Ordering::SeqCst);
+	}
+
+	pub fn add_processed(&self, path: &Path) {
+		*self.current_path.lock().unwrap() = Some(path.to_owned());
+		self.processed.fetch_add(1, Ordering::SeqCst);
+	}
+
+	pub fn total(&self) -> u64 {
+		self.total.load(Ordering::SeqCst)
+	}
+
+	pub fn processed(&self) -> u64 {
+		self.processed.load(Ordering::SeqCst)
+	}
+
+	pub fn current_path(&self) -> Option<PathBuf> {
+		self.current_path.lock().unwrap().clone()
+	}
+}
@@ -1,20 +1,34 @@
 use crossbeam_channel::{self, Receiver, Sender};
-use log::{error, info};
+use log::{error, info, warn};
 use std::cmp::min;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
+use super::{has_audio_extension, ScanProgress};
 use crate::app::index::metadata::{self, SongTags};
+use crate::utils;
 
 #[derive(Debug)]
 pub struct Song {
 	pub path: PathBuf,
 	pub metadata: SongTags,
+	pub file_size: Option<i64>,
+	pub file_modified: Option<i64>,
+}
+
+// Song which was indexed by a previous scan, and whose metadata can be re-used as long as the
+// file has not changed since
+#[derive(Debug)]
+pub struct KnownSong {
+	pub metadata: SongTags,
+	pub file_size: i64,
+	pub file_modified: i64,
 }
 
 #[derive(Debug)]
@@ -28,20 +42,51 @@ pub struct Directory {
 
 pub struct Traverser {
 	directory_sender: Sender<Directory>,
+	known_songs: Arc<HashMap<PathBuf, KnownSong>>,
+	audio_extensions: Arc<HashSet<String>>,
+	follow_symlinks: bool,
+	stop: Arc<AtomicBool>,
+	progress: Arc<ScanProgress>,
 }
 
 #[derive(Debug)]
-struct WorkItem {
-	parent: Option<PathBuf>,
-	path: PathBuf,
+pub struct WorkItem {
+	pub parent: Option<PathBuf>,
+	pub path: PathBuf,
+	// Canonical paths of the directories leading to this one, only tracked when following symlinks
+	ancestors: Vec<PathBuf>,
+}
+
+impl WorkItem {
+	pub fn new(parent: Option<PathBuf>, path: PathBuf) -> Self {
+		Self {
+			parent,
+			path,
+			ancestors: Vec::new(),
+		}
+	}
 }
 
 impl Traverser {
-	pub fn new(directory_sender: Sender<Directory>) -> Self {
-		Self { directory_sender }
+	pub fn new(
+		directory_sender: Sender<Directory>,
+		known_songs: HashMap<PathBuf, KnownSong>,
+		audio_extensions: HashSet<String>,
+		follow_symlinks: bool,
+		stop: Arc<AtomicBool>,
+		progress: Arc<ScanProgress>,
+	) -> Self {
+		Self {
+			directory_sender,
+			known_songs: Arc::new(known_songs),
+			audio_extensions: Arc::new(audio_extensions),
+			follow_symlinks,
+			stop,
+			progress,
+		}
 	}
 
-	pub fn traverse(&self, roots: Vec<PathBuf>) {
+	pub fn traverse(&self, roots: Vec<WorkItem>) {
 		let num_pending_work_items = Arc::new(AtomicUsize::new(roots.len()));
 		let (work_item_sender, work_item_receiver) = crossbeam_channel::unbounded();
 
@@ -58,22 +103,28 @@ impl Traverser {
 			let work_item_receiver = work_item_receiver.clone();
 			let directory_sender = self.directory_sender.clone();
 			let num_pending_work_items = num_pending_work_items.clone();
+			let known_songs = self.known_songs.clone();
+			let audio_extensions = self.audio_extensions.clone();
+			let follow_symlinks = self.follow_symlinks;
+			let stop = self.stop.clone();
+			let progress = self.progress.clone();
 			threads.push(thread::spawn(move || {
 				let worker = Worker {
 					work_item_sender,
 					work_item_receiver,
 					directory_sender,
 					num_pending_work_items,
+					known_songs,
+					audio_extensions,
+					follow_symlinks,
+					stop,
+					progress,
 				};
 				worker.run();
 			}));
 		}
 
-		for root in roots {
-			let work_item = WorkItem {
-				parent: None,
-				path: root,
-			};
+		for work_item in roots {
 			if let Err(e) = work_item_sender.send(work_item) {
 				error!("Error initializing traverser: {:#?}", e);
 			}
@@ -92,6 +143,11 @@ struct Worker {
 	work_item_receiver: Receiver<WorkItem>,
 	directory_sender: Sender<Directory>,
 	num_pending_work_items: Arc<AtomicUsize>,
+	known_songs: Arc<HashMap<PathBuf, KnownSong>>,
+	audio_extensions: Arc<HashSet<String>>,
+	follow_symlinks: bool,
+	stop: Arc<AtomicBool>,
+	progress: Arc<ScanProgress>,
 }
 
 impl Worker {
@@ -104,7 +160,7 @@ impl Worker {
 
 	fn find_work_item(&self) -> Option<WorkItem> {
 		loop {
-			if self.is_all_work_done() {
+			if self.is_all_work_done() || self.stop.load(Ordering::SeqCst) {
 				return None;
 			}
 			{
@@ -148,13 +204,20 @@ impl Worker {
 			}
 		};
 
+		let mut ancestors = work_item.ancestors;
+		if self.follow_symlinks {
+			if let Ok(canonical_path) = work_item.path.canonicalize() {
+				ancestors.push(canonical_path);
+			}
+		}
+
 		let mut sub_directories = Vec::new();
 		let mut songs = Vec::new();
 		let mut other_files = Vec::new();
 
 		for entry in read_dir {
-			let path = match entry {
-				Ok(ref f) => f.path(),
+			let entry = match entry {
+				Ok(entry) => entry,
 				Err(e) => {
 					error!(
 						"File read error within `{}`: {}",
@@ -164,20 +227,43 @@ impl Worker {
 					break;
 				}
 			};
+			let path = entry.path();
+
+			let is_symlink = entry.file_type().map(|t| t.is_symlink()).unwrap_or(false);
+			if is_symlink && !self.follow_symlinks {
+				continue;
+			}
 
 			if path.is_dir() {
+				if is_symlink && Self::is_cycle(&path, &ancestors) {
+					warn!(
+						"Symlink cycle detected at `{}`, not following it",
+						path.display()
+					);
+					continue;
+				}
 				sub_directories.push(path);
-			} else {
-				if let Some(metadata) = metadata::read(&path) {
-					songs.push(Song { path, metadata });
+			} else if has_audio_extension(&path, &self.audio_extensions) {
+				let (file_size, file_modified) = Self::get_file_stats(&path);
+				if let Some(metadata) = self.read_metadata(&path, file_size, file_modified) {
+					songs.push(Song {
+						path,
+						metadata,
+						file_size,
+						file_modified,
+					});
 				} else {
 					other_files.push(path);
 				}
+			} else {
+				other_files.push(path);
 			}
 		}
 
 		let created = Self::get_date_created(&work_item.path).unwrap_or_default();
 
+		self.progress.add_discovered(songs.len());
+
 		self.emit_directory(Directory {
 			path: work_item.path.to_owned(),
 			parent: work_item.parent.map(|p| p.to_owned()),
@@ -190,10 +276,52 @@ impl Worker {
 			self.queue_work(WorkItem {
 				parent: Some(work_item.path.clone()),
 				path: sub_directory,
+				ancestors: ancestors.clone(),
 			});
 		}
 	}
 
+	fn read_metadata(
+		&self,
+		path: &Path,
+		file_size: Option<i64>,
+		file_modified: Option<i64>,
+	) -> Option<SongTags> {
+		if let Some(known_song) = self.known_songs.get(path) {
+			if file_size == Some(known_song.file_size)
+				&& file_modified == Some(known_song.file_modified)
+			{
+				return Some(known_song.metadata.clone());
+			}
+		}
+		// Allowed files in a format we cannot parse are indexed without tags
+		if utils::get_audio_format(path).is_none() {
+			return Some(SongTags::default());
+		}
+		metadata::read(path)
+	}
+
+	// A symlink leading back to a directory being traversed would be walked forever
+	fn is_cycle(path: &Path, ancestors: &[PathBuf]) -> bool {
+		path.canonicalize()
+			.map(|target| ancestors.contains(&target))
+			.unwrap_or(false)
+	}
+
+	fn get_file_stats(path: &Path) -> (Option<i64>, Option<i64>) {
+		match fs::metadata(path) {
+			Ok(m) => {
+				let modified = m
+					.modified()
+					.ok()
+					.and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+					.map(|d| d.as_millis() as i64);
+				(Some(m.len() as i64), modified)
+			}
+			Err(_) => (None, None),
+		}
+	}
+
 	fn get_date_created(path: &Path) -> Option<i32> {
 		if let Ok(t) = fs::metadata(path).and_then(|m| m.created().or(m.modified())) {
 			t.duration_since(std::time::UNIX_EPOCH)
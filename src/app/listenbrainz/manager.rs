@@ -0,0 +1,116 @@
+use anyhow::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::app::{index::Index, user};
+
+const LISTENBRAINZ_API_URL: &str = "https://api.listenbrainz.org/1";
+
+#[derive(Debug, Serialize)]
+struct TrackMetadata {
+	artist_name: String,
+	track_name: String,
+	release_name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Listen {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	listened_at: Option<u64>,
+	track_metadata: TrackMetadata,
+}
+
+#[derive(Debug, Serialize)]
+struct Submission {
+	listen_type: &'static str,
+	payload: Vec<Listen>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ValidateTokenResponse {
+	valid: bool,
+}
+
+#[derive(Clone)]
+pub struct Manager {
+	index: Index,
+	user_manager: user::Manager,
+}
+
+impl Manager {
+	pub fn new(index: Index, user_manager: user::Manager) -> Self {
+		Self {
+			index,
+			user_manager,
+		}
+	}
+
+	pub fn link(&self, username: &str, listenbrainz_token: &str) -> Result<()> {
+		let url = format!("{}/validate-token", LISTENBRAINZ_API_URL);
+		let response = ureq::get(&url)
+			.set("Authorization", &format!("Token {}", listenbrainz_token))
+			.call();
+		if !response.ok() {
+			bail!(
+				"ListenBrainz token validation failed with status code: {}",
+				response.status()
+			);
+		}
+		let validation: ValidateTokenResponse = serde_json::from_str(&response.into_string()?)?;
+		if !validation.valid {
+			bail!("ListenBrainz token is not valid");
+		}
+
+		self.user_manager
+			.listenbrainz_link(username, listenbrainz_token)
+			.map_err(|e| e.into())
+	}
+
+	pub fn unlink(&self, username: &str) -> Result<()> {
+		self.user_manager.listenbrainz_unlink(username)
+	}
+
+	pub fn scrobble(&self, username: &str, track: &Path) -> Result<()> {
+		let mut listen = self.listen_from_path(track)?;
+		listen.listened_at = Some(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
+		self.submit(username, "single", listen)
+	}
+
+	pub fn now_playing(&self, username: &str, track: &Path) -> Result<()> {
+		let listen = self.listen_from_path(track)?;
+		self.submit(username, "playing_now", listen)
+	}
+
+	fn submit(&self, username: &str, listen_type: &'static str, listen: Listen) -> Result<()> {
+		let token = self.user_manager.get_listenbrainz_token(username)?;
+		let submission = Submission {
+			listen_type,
+			payload: vec![listen],
+		};
+		let url = format!("{}/submit-listens", LISTENBRAINZ_API_URL);
+		let response = ureq::post(&url)
+			.set("Authorization", &format!("Token {}", token))
+			.set("Content-Type", "application/json")
+			.send_string(&serde_json::to_string(&submission)?);
+		if !response.ok() {
+			bail!(
+				"ListenBrainz submission failed with status code: {}",
+				response.status()
+			);
+		}
+		Ok(())
+	}
+
+	fn listen_from_path(&self, track: &Path) -> Result<Listen> {
+		let song = self.index.get_song(track)?;
+		Ok(Listen {
+			listened_at: None,
+			track_metadata: TrackMetadata {
+				artist_name: song.artist.unwrap_or_default(),
+				track_name: song.title.unwrap_or_default(),
+				release_name: song.album.unwrap_or_default(),
+			},
+		})
+	}
+}
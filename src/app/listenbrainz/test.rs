@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+use crate::app::test;
+use crate::test_name;
+
+const TEST_USER: &str = "test_user";
+const TEST_PASSWORD: &str = "password";
+const TEST_MOUNT_NAME: &str = "root";
+
+#[test]
+fn scrobble_requires_linked_account() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	assert!(ctx.listenbrainz_manager.scrobble(TEST_USER, &path).is_err());
+	assert!(ctx
+		.listenbrainz_manager
+		.now_playing(TEST_USER, &path)
+		.is_err());
+}
+
+#[test]
+fn unlink_forgets_token() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.build();
+
+	ctx.user_manager
+		.listenbrainz_link(TEST_USER, "some_token")
+		.unwrap();
+	assert!(ctx.user_manager.is_listenbrainz_linked(TEST_USER));
+
+	ctx.listenbrainz_manager.unlink(TEST_USER).unwrap();
+	assert!(!ctx.user_manager.is_listenbrainz_linked(TEST_USER));
+}
@@ -0,0 +1,107 @@
+use diesel;
+use diesel::prelude::*;
+use diesel::sql_types;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::*;
+use crate::db::DB;
+
+// User data is keyed by real path, so it outlives songs that were moved or deleted
+const ORPHANED_PLAYLIST_SONGS_SQL: &str =
+	"NOT EXISTS (SELECT 1 FROM songs s WHERE s.path = playlist_songs.path)";
+const ORPHANED_PLAYS_SQL: &str =
+	"NOT EXISTS (SELECT 1 FROM songs s WHERE s.path = play_history.path)";
+
+#[derive(Clone)]
+pub struct Manager {
+	db: DB,
+}
+
+impl Manager {
+	pub fn new(db: DB) -> Self {
+		Self { db }
+	}
+
+	pub fn find_orphans(&self) -> Result<OrphanReport, Error> {
+		let connection = self.db.connect()?;
+
+		// Not using Diesel because anti-joins on a custom column are not expressible
+		let playlist_songs: Vec<OrphanedPlaylistSong> = diesel::sql_query(
+			r#"
+			SELECT u.name AS username, p.name AS playlist, ps.path AS path
+			FROM playlist_songs ps
+			INNER JOIN playlists p ON ps.playlist = p.id
+			INNER JOIN users u ON p.owner = u.id
+			LEFT JOIN songs s ON ps.path = s.path
+			WHERE s.id IS NULL
+			ORDER BY u.name, p.name, ps.ordering
+		"#,
+		)
+		.get_results(&connection)
+		.map_err(anyhow::Error::new)?;
+
+		let play_history: Vec<OrphanedPlay> = diesel::sql_query(
+			r#"
+			SELECT u.name AS username, h.path AS path, COUNT(*) AS play_count
+			FROM play_history h
+			INNER JOIN users u ON h.owner = u.id
+			LEFT JOIN songs s ON h.path = s.path
+			WHERE s.id IS NULL
+			GROUP BY u.name, h.path
+			ORDER BY u.name, h.path
+		"#,
+		)
+		.get_results(&connection)
+		.map_err(anyhow::Error::new)?;
+
+		Ok(OrphanReport {
+			playlist_songs,
+			play_history,
+		})
+	}
+
+	pub fn check_database(&self) -> Result<(), Error> {
+		Ok(self.db.check_health()?)
+	}
+
+	pub fn clean_orphans(&self) -> Result<OrphanCleanup, Error> {
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_millis() as i64)
+			.unwrap_or_default();
+
+		let connection = self.db.connect()?;
+		connection
+			.transaction::<_, diesel::result::Error, _>(|| {
+				// Playlists losing songs get a new version so clients drop their cached copy
+				diesel::sql_query(format!(
+					r#"
+					UPDATE playlists
+					SET version = MAX(?, version + 1)
+					WHERE id IN (SELECT playlist FROM playlist_songs WHERE {})
+				"#,
+					ORPHANED_PLAYLIST_SONGS_SQL
+				))
+				.bind::<sql_types::BigInt, _>(now)
+				.execute(&connection)?;
+
+				let playlist_songs = diesel::sql_query(format!(
+					"DELETE FROM playlist_songs WHERE {}",
+					ORPHANED_PLAYLIST_SONGS_SQL
+				))
+				.execute(&connection)?;
+
+				let play_history = diesel::sql_query(format!(
+					"DELETE FROM play_history WHERE {}",
+					ORPHANED_PLAYS_SQL
+				))
+				.execute(&connection)?;
+
+				Ok(OrphanCleanup {
+					playlist_songs,
+					play_history,
+				})
+			})
+			.map_err(|e| anyhow::Error::new(e).into())
+	}
+}
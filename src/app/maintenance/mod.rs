@@ -0,0 +1,42 @@
+use diesel::sql_types;
+use serde::{Deserialize, Serialize};
+
+mod error;
+mod manager;
+#[cfg(test)]
+mod test;
+
+pub use error::*;
+pub use manager::*;
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrphanReport {
+	pub playlist_songs: Vec<OrphanedPlaylistSong>,
+	pub play_history: Vec<OrphanedPlay>,
+}
+
+#[derive(Debug, PartialEq, QueryableByName, Serialize, Deserialize)]
+pub struct OrphanedPlaylistSong {
+	#[sql_type = "sql_types::Text"]
+	pub username: String,
+	#[sql_type = "sql_types::Text"]
+	pub playlist: String,
+	#[sql_type = "sql_types::Text"]
+	pub path: String,
+}
+
+#[derive(Debug, PartialEq, QueryableByName, Serialize, Deserialize)]
+pub struct OrphanedPlay {
+	#[sql_type = "sql_types::Text"]
+	pub username: String,
+	#[sql_type = "sql_types::Text"]
+	pub path: String,
+	#[sql_type = "sql_types::BigInt"]
+	pub play_count: i64,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrphanCleanup {
+	pub playlist_songs: usize,
+	pub play_history: usize,
+}
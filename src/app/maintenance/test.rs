@@ -0,0 +1,127 @@
+use diesel::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::app::{index, test, vfs};
+use crate::test_name;
+
+const TEST_USER: &str = "test_user";
+const TEST_PASSWORD: &str = "password";
+const TEST_PLAYLIST_NAME: &str = "Chill & Grill";
+const TEST_MOUNT_NAME: &str = "root";
+
+fn build_context_with_user_data(test_name: String) -> test::Context {
+	let ctx = test::ContextBuilder::new(test_name)
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let songs: Vec<String> = ctx
+		.index
+		.flatten(
+			Path::new(TEST_MOUNT_NAME),
+			index::Range::default(),
+			index::Sort::default(),
+		)
+		.unwrap()
+		.into_iter()
+		.map(|s| s.path)
+		.collect();
+	ctx.playlist_manager
+		.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, &songs)
+		.unwrap();
+	for path in &songs {
+		ctx.stats_manager
+			.record_play(TEST_USER, Path::new(path))
+			.unwrap();
+	}
+
+	ctx
+}
+
+fn remove_tobokegao(ctx: &test::Context) {
+	ctx.vfs_manager
+		.set_mount_dirs(&vec![vfs::MountDir {
+			name: TEST_MOUNT_NAME.to_owned(),
+			source: "test-data/small-collection/Khemmis".to_owned(),
+			scan_interval_seconds: None,
+		}])
+		.unwrap();
+	ctx.index.update().unwrap();
+}
+
+#[test]
+fn finds_no_orphans_in_intact_collection() {
+	let ctx = build_context_with_user_data(test_name!());
+	let report = ctx.maintenance_manager.find_orphans().unwrap();
+	assert!(report.playlist_songs.is_empty());
+	assert!(report.play_history.is_empty());
+}
+
+#[test]
+fn finds_orphans_after_songs_are_removed() {
+	let ctx = build_context_with_user_data(test_name!());
+	remove_tobokegao(&ctx);
+
+	let report = ctx.maintenance_manager.find_orphans().unwrap();
+	assert_eq!(report.playlist_songs.len(), 8);
+	assert_eq!(report.play_history.len(), 8);
+
+	let tobokegao: PathBuf = ["test-data", "small-collection", "Tobokegao"]
+		.iter()
+		.collect();
+	for orphan in &report.playlist_songs {
+		assert_eq!(orphan.username, TEST_USER);
+		assert_eq!(orphan.playlist, TEST_PLAYLIST_NAME);
+		assert!(Path::new(&orphan.path).starts_with(&tobokegao));
+	}
+	for orphan in &report.play_history {
+		assert_eq!(orphan.username, TEST_USER);
+		assert_eq!(orphan.play_count, 1);
+		assert!(Path::new(&orphan.path).starts_with(&tobokegao));
+	}
+}
+
+#[test]
+fn clean_orphans_removes_orphaned_user_data() {
+	let ctx = build_context_with_user_data(test_name!());
+	remove_tobokegao(&ctx);
+	let version_before = ctx
+		.playlist_manager
+		.get_playlist_version(TEST_PLAYLIST_NAME, TEST_USER)
+		.unwrap();
+
+	let cleanup = ctx.maintenance_manager.clean_orphans().unwrap();
+	assert_eq!(cleanup.playlist_songs, 8);
+	assert_eq!(cleanup.play_history, 8);
+
+	let report = ctx.maintenance_manager.find_orphans().unwrap();
+	assert!(report.playlist_songs.is_empty());
+	assert!(report.play_history.is_empty());
+
+	let songs = ctx
+		.playlist_manager
+		.read_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+		.unwrap();
+	assert_eq!(songs.len(), 5);
+	let version_after = ctx
+		.playlist_manager
+		.get_playlist_version(TEST_PLAYLIST_NAME, TEST_USER)
+		.unwrap();
+	assert!(version_after > version_before);
+
+	let cleanup = ctx.maintenance_manager.clean_orphans().unwrap();
+	assert_eq!(cleanup, Default::default());
+}
+
+#[test]
+fn check_database_detects_unreadable_database() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	assert!(ctx.maintenance_manager.check_database().is_ok());
+
+	let connection = ctx.db.connect().unwrap();
+	diesel::sql_query("DROP TABLE misc_settings")
+		.execute(&connection)
+		.unwrap();
+	assert!(ctx.maintenance_manager.check_database().is_err());
+}
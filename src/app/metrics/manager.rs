@@ -0,0 +1,151 @@
+use std::fmt::{Display, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Upper bounds (in seconds) of the index duration histogram buckets.
+const INDEX_DURATION_BUCKETS: [f64; 8] = [1.0, 5.0, 15.0, 60.0, 300.0, 900.0, 1800.0, 3600.0];
+
+/// Clients allowed to read metrics without logging in when no allowlist is configured.
+pub const DEFAULT_ALLOWLIST: [IpAddr; 2] = [
+	IpAddr::V4(Ipv4Addr::LOCALHOST),
+	IpAddr::V6(Ipv6Addr::LOCALHOST),
+];
+
+/// Text in the Prometheus exposition format.
+#[derive(Default)]
+pub struct Exposition {
+	text: String,
+}
+
+impl Exposition {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Describes the samples which follow. `kind` is one of `counter`, `gauge` or `histogram`.
+	pub fn family(&mut self, name: &str, kind: &str, help: &str) {
+		let _ = write!(
+			self.text,
+			"# HELP {} {}\n# TYPE {} {}\n",
+			name, help, name, kind
+		);
+	}
+
+	pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+		self.text += name;
+		if !labels.is_empty() {
+			let labels: Vec<String> = labels
+				.iter()
+				.map(|(key, value)| format!("{}=\"{}\"", key, value))
+				.collect();
+			let _ = write!(self.text, "{{{}}}", labels.join(","));
+		}
+		let _ = writeln!(self.text, " {}", value);
+	}
+
+	pub fn into_string(self) -> String {
+		self.text
+	}
+}
+
+#[derive(Default)]
+struct Histogram {
+	bucket_counts: [u64; INDEX_DURATION_BUCKETS.len()],
+	sum: f64,
+	count: u64,
+}
+
+#[derive(Default)]
+struct Registry {
+	// Indexed by status class, from 1xx to 5xx
+	responses: [AtomicU64; 5],
+	auth_successes: AtomicU64,
+	auth_failures: AtomicU64,
+	index_durations: Mutex<Histogram>,
+}
+
+/// Collects counters about the server activity, to be scraped by Prometheus.
+#[derive(Clone)]
+pub struct Manager {
+	allowlist: Arc<Vec<IpAddr>>,
+	registry: Arc<Registry>,
+}
+
+impl Manager {
+	pub fn new(allowlist: Vec<IpAddr>) -> Self {
+		Self {
+			allowlist: Arc::new(allowlist),
+			registry: Arc::new(Registry::default()),
+		}
+	}
+
+	/// Whether `client` may read metrics without logging in.
+	pub fn is_allowed(&self, client: IpAddr) -> bool {
+		self.allowlist.contains(&client)
+	}
+
+	pub fn record_response(&self, status: u16) {
+		if let Some(counter) = (status as usize / 100)
+			.checked_sub(1)
+			.and_then(|i| self.registry.responses.get(i))
+		{
+			counter.fetch_add(1, Ordering::Relaxed);
+		}
+	}
+
+	pub fn record_login(&self, success: bool) {
+		let counter = if success {
+			&self.registry.auth_successes
+		} else {
+			&self.registry.auth_failures
+		};
+		counter.fetch_add(1, Ordering::Relaxed);
+	}
+
+	pub fn record_index_duration(&self, duration: Duration) {
+		let seconds = duration.as_secs_f64();
+		let mut histogram = self.registry.index_durations.lock().unwrap();
+		for (bound, count) in INDEX_DURATION_BUCKETS
+			.iter()
+			.zip(histogram.bucket_counts.iter_mut())
+		{
+			if seconds <= *bound {
+				*count += 1;
+			}
+		}
+		histogram.sum += seconds;
+		histogram.count += 1;
+	}
+
+	pub fn write(&self, exposition: &mut Exposition) {
+		let name = "polaris_http_responses_total";
+		exposition.family(name, "counter", "API responses sent, by status class.");
+		for (i, counter) in self.registry.responses.iter().enumerate() {
+			let class = format!("{}xx", i + 1);
+			exposition.sample(name, &[("status", &class)], counter.load(Ordering::Relaxed));
+		}
+
+		let name = "polaris_logins_total";
+		exposition.family(name, "counter", "Login attempts, by outcome.");
+		let successes = self.registry.auth_successes.load(Ordering::Relaxed);
+		let failures = self.registry.auth_failures.load(Ordering::Relaxed);
+		exposition.sample(name, &[("result", "success")], successes);
+		exposition.sample(name, &[("result", "failure")], failures);
+
+		let name = "polaris_index_duration_seconds";
+		exposition.family(name, "histogram", "Time spent updating the index.");
+		let histogram = self.registry.index_durations.lock().unwrap();
+		let bucket_name = format!("{}_bucket", name);
+		for (bound, count) in INDEX_DURATION_BUCKETS
+			.iter()
+			.zip(histogram.bucket_counts.iter())
+		{
+			exposition.sample(&bucket_name, &[("le", &bound.to_string())], count);
+		}
+		exposition.sample(&bucket_name, &[("le", "+Inf")], histogram.count);
+		exposition.sample(&format!("{}_sum", name), &[], histogram.sum);
+		exposition.sample(&format!("{}_count", name), &[], histogram.count);
+	}
+}
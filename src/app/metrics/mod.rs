@@ -0,0 +1,5 @@
+mod manager;
+#[cfg(test)]
+mod test;
+
+pub use manager::*;
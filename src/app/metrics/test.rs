@@ -0,0 +1,57 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+use super::*;
+
+fn render(manager: &Manager) -> String {
+	let mut exposition = Exposition::new();
+	manager.write(&mut exposition);
+	exposition.into_string()
+}
+
+#[test]
+fn counts_responses_by_status_class() {
+	let manager = Manager::new(Vec::new());
+	manager.record_response(200);
+	manager.record_response(204);
+	manager.record_response(404);
+	manager.record_response(600);
+	let text = render(&manager);
+	assert!(text.contains("# TYPE polaris_http_responses_total counter\n"));
+	assert!(text.contains("polaris_http_responses_total{status=\"2xx\"} 2\n"));
+	assert!(text.contains("polaris_http_responses_total{status=\"4xx\"} 1\n"));
+	assert!(text.contains("polaris_http_responses_total{status=\"5xx\"} 0\n"));
+}
+
+#[test]
+fn counts_logins_by_outcome() {
+	let manager = Manager::new(Vec::new());
+	manager.record_login(true);
+	manager.record_login(false);
+	manager.record_login(false);
+	let text = render(&manager);
+	assert!(text.contains("polaris_logins_total{result=\"success\"} 1\n"));
+	assert!(text.contains("polaris_logins_total{result=\"failure\"} 2\n"));
+}
+
+#[test]
+fn index_durations_fill_cumulative_buckets() {
+	let manager = Manager::new(Vec::new());
+	manager.record_index_duration(Duration::from_millis(500));
+	manager.record_index_duration(Duration::from_secs(120));
+	let text = render(&manager);
+	assert!(text.contains("# TYPE polaris_index_duration_seconds histogram\n"));
+	assert!(text.contains("polaris_index_duration_seconds_bucket{le=\"1\"} 1\n"));
+	assert!(text.contains("polaris_index_duration_seconds_bucket{le=\"60\"} 1\n"));
+	assert!(text.contains("polaris_index_duration_seconds_bucket{le=\"300\"} 2\n"));
+	assert!(text.contains("polaris_index_duration_seconds_bucket{le=\"+Inf\"} 2\n"));
+	assert!(text.contains("polaris_index_duration_seconds_sum 120.5\n"));
+	assert!(text.contains("polaris_index_duration_seconds_count 2\n"));
+}
+
+#[test]
+fn only_allowlisted_clients_are_allowed() {
+	let manager = Manager::new(DEFAULT_ALLOWLIST.to_vec());
+	assert!(manager.is_allowed(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+	assert!(!manager.is_allowed(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10))));
+}
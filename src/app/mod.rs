@@ -1,54 +1,137 @@
+use log::warn;
 use std::fs;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::PathBuf;
+use std::time::Duration;
 
 use crate::db::DB;
 use crate::paths::Paths;
 
+pub mod artist;
 pub mod config;
 pub mod ddns;
 pub mod index;
 pub mod lastfm;
+pub mod listenbrainz;
+pub mod maintenance;
+pub mod metrics;
+pub mod now_playing;
+pub mod operation;
 pub mod playlist;
+pub mod scheduler;
 pub mod settings;
+pub mod stats;
+pub mod throttle;
 pub mod thumbnail;
+pub mod tls;
+pub mod transcode;
 pub mod user;
 pub mod vfs;
+pub mod waveform;
 
 #[cfg(test)]
 pub mod test;
 
+/// Network address the server listens on when none is specified.
+pub const DEFAULT_BIND_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+
+/// Largest JSON request body accepted when none is specified.
+pub const DEFAULT_MAX_JSON_BODY_SIZE: usize = 4 * 1024 * 1024;
+
+pub struct Options {
+	pub address: SocketAddr,
+	pub collection_cache_max_age: Duration,
+	// Absent when transfers are not limited
+	pub max_concurrent_transfers: Option<usize>,
+	pub index_insert_buffer_size: usize,
+	pub db_busy_timeout: Duration,
+	pub max_json_body_size: usize,
+	pub metrics_allowlist: Vec<IpAddr>,
+}
+
 #[derive(Clone)]
 pub struct App {
-	pub port: u16,
+	pub address: SocketAddr,
+	#[cfg(unix)]
+	pub socket_file_path: Option<PathBuf>,
+	pub tls_config: Option<rustls::ServerConfig>,
+	pub collection_cache_max_age: Duration,
+	pub max_json_body_size: usize,
 	pub auth_secret: settings::AuthSecret,
 	pub web_dir_path: PathBuf,
-	pub swagger_dir_path: PathBuf,
+	pub swagger_dir_path: Option<PathBuf>,
 	pub db: DB,
 	pub index: index::Index,
+	pub artist_manager: artist::Manager,
 	pub config_manager: config::Manager,
 	pub ddns_manager: ddns::Manager,
 	pub lastfm_manager: lastfm::Manager,
+	pub listenbrainz_manager: listenbrainz::Manager,
+	pub maintenance_manager: maintenance::Manager,
+	pub metrics_manager: metrics::Manager,
+	pub now_playing_manager: now_playing::Manager,
+	pub operation_manager: operation::Manager,
 	pub playlist_manager: playlist::Manager,
+	pub scheduler_manager: scheduler::Manager,
 	pub settings_manager: settings::Manager,
+	pub stats_manager: stats::Manager,
 	pub thumbnail_manager: thumbnail::Manager,
+	pub throttle_manager: throttle::Manager,
+	pub transcode_manager: transcode::Manager,
 	pub user_manager: user::Manager,
 	pub vfs_manager: vfs::Manager,
+	pub waveform_manager: waveform::Manager,
 }
 
 impl App {
-	pub fn new(port: u16, paths: Paths) -> anyhow::Result<Self> {
-		let db = DB::new(&paths.db_file_path)?;
+	pub fn new(options: Options, paths: Paths) -> anyhow::Result<Self> {
+		let Options {
+			address,
+			collection_cache_max_age,
+			max_concurrent_transfers,
+			index_insert_buffer_size,
+			db_busy_timeout,
+			max_json_body_size,
+			metrics_allowlist,
+		} = options;
+		let tls_config = tls::load_config(
+			paths.tls_cert_file_path.as_deref(),
+			paths.tls_key_file_path.as_deref(),
+		)?;
+		#[cfg(unix)]
+		if tls_config.is_some() && paths.socket_file_path.is_some() {
+			anyhow::bail!("TLS cannot be used when listening on a unix domain socket");
+		}
+		let db = DB::new(&paths.db_file_path, db_busy_timeout)?;
 		fs::create_dir_all(&paths.web_dir_path)?;
-		fs::create_dir_all(&paths.swagger_dir_path)?;
+		let swagger_dir_path = paths.swagger_dir_path.filter(|path| {
+			let exists = path.is_dir();
+			if !exists {
+				warn!(
+					"Swagger directory `{}` does not exist, the Swagger UI will not be served",
+					path.display()
+				);
+			}
+			exists
+		});
 
 		let thumbnails_dir_path = paths.cache_dir_path.join("thumbnails");
+		let artist_images_dir_path = paths.cache_dir_path.join("artists");
+		let waveforms_dir_path = paths.cache_dir_path.join("waveforms");
 
 		let vfs_manager = vfs::Manager::new(db.clone());
 		let settings_manager = settings::Manager::new(db.clone());
 		let auth_secret = settings_manager.get_auth_secret()?;
 		let ddns_manager = ddns::Manager::new(db.clone());
-		let user_manager = user::Manager::new(db.clone(), auth_secret);
-		let index = index::Index::new(db.clone(), vfs_manager.clone(), settings_manager.clone());
+		let user_manager = user::Manager::new(db.clone(), auth_secret, settings_manager.clone());
+		let metrics_manager = metrics::Manager::new(metrics_allowlist);
+		let index = index::Index::new(
+			db.clone(),
+			vfs_manager.clone(),
+			settings_manager.clone(),
+			metrics_manager.clone(),
+			index_insert_buffer_size,
+		);
 		let config_manager = config::Manager::new(
 			settings_manager.clone(),
 			user_manager.clone(),
@@ -56,8 +139,21 @@ impl App {
 			ddns_manager.clone(),
 		);
 		let playlist_manager = playlist::Manager::new(db.clone(), vfs_manager.clone());
-		let thumbnail_manager = thumbnail::Manager::new(thumbnails_dir_path);
+		let artist_manager = artist::Manager::new(db.clone(), artist_images_dir_path);
+		let stats_manager = stats::Manager::new(db.clone(), vfs_manager.clone());
+		let thumbnail_manager =
+			thumbnail::Manager::new(thumbnails_dir_path, settings_manager.clone());
+		let throttle_manager = throttle::Manager::new(settings_manager.clone());
+		let transcode_manager = transcode::Manager::new(PathBuf::from("ffmpeg"));
+		let waveform_manager =
+			waveform::Manager::new(waveforms_dir_path, transcode_manager.clone());
 		let lastfm_manager = lastfm::Manager::new(index.clone(), user_manager.clone());
+		let listenbrainz_manager = listenbrainz::Manager::new(index.clone(), user_manager.clone());
+		let now_playing_manager =
+			now_playing::Manager::new(index.clone(), now_playing::DEFAULT_EXPIRATION);
+		let operation_manager = operation::Manager::new();
+		let scheduler_manager = scheduler::Manager::new(max_concurrent_transfers);
+		let maintenance_manager = maintenance::Manager::new(db.clone());
 
 		if let Some(config_path) = paths.config_file_path {
 			let config = config::Config::from_path(&config_path)?;
@@ -67,19 +163,35 @@ impl App {
 		let auth_secret = settings_manager.get_auth_secret()?;
 
 		Ok(Self {
-			port,
+			address,
+			#[cfg(unix)]
+			socket_file_path: paths.socket_file_path,
+			tls_config,
+			collection_cache_max_age,
+			max_json_body_size,
 			auth_secret,
 			web_dir_path: paths.web_dir_path,
-			swagger_dir_path: paths.swagger_dir_path,
+			swagger_dir_path,
 			index,
+			artist_manager,
 			config_manager,
 			ddns_manager,
 			lastfm_manager,
+			listenbrainz_manager,
+			maintenance_manager,
+			metrics_manager,
+			now_playing_manager,
+			operation_manager,
 			playlist_manager,
+			scheduler_manager,
 			settings_manager,
+			stats_manager,
 			thumbnail_manager,
+			throttle_manager,
+			transcode_manager,
 			user_manager,
 			vfs_manager,
+			waveform_manager,
 			db,
 		})
 	}
@@ -0,0 +1,5 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("Song not found")]
+	SongNotFound,
+}
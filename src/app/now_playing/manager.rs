@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use super::*;
+use crate::app::index::{Index, Song};
+
+/// How long a user stays listed after their last now playing report.
+pub const DEFAULT_EXPIRATION: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct NowPlaying {
+	pub username: String,
+	pub song: Song,
+	/// Seconds since the epoch when the user started playing this song
+	pub since: u64,
+}
+
+struct Entry {
+	song: Song,
+	since: u64,
+	last_report: Instant,
+}
+
+/// Remembers what each user is currently listening to, so that it can be shared with other users.
+/// Nothing is persisted, the list starts empty when the server restarts.
+#[derive(Clone)]
+pub struct Manager {
+	index: Index,
+	expiration: Duration,
+	entries: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+impl Manager {
+	pub fn new(index: Index, expiration: Duration) -> Self {
+		Self {
+			index,
+			expiration,
+			entries: Arc::new(Mutex::new(HashMap::new())),
+		}
+	}
+
+	pub fn record(&self, username: &str, track: &Path) -> Result<(), Error> {
+		let song = self
+			.index
+			.get_song(track)
+			.map_err(|_| Error::SongNotFound)?;
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs())
+			.unwrap_or_default();
+
+		let mut entries = self.entries.lock().unwrap();
+		// Clients may report the same song several times while it plays
+		let since = match entries.get(username) {
+			Some(entry) if entry.song.path == song.path => entry.since,
+			_ => now,
+		};
+		entries.insert(
+			username.to_owned(),
+			Entry {
+				song,
+				since,
+				last_report: Instant::now(),
+			},
+		);
+		Ok(())
+	}
+
+	/// Lists what users are playing, most recently started songs first.
+	pub fn list(&self) -> Vec<NowPlaying> {
+		let expiration = self.expiration;
+		let mut entries = self.entries.lock().unwrap();
+		entries.retain(|_, entry| entry.last_report.elapsed() < expiration);
+
+		let mut now_playing: Vec<NowPlaying> = entries
+			.iter()
+			.map(|(username, entry)| NowPlaying {
+				username: username.clone(),
+				song: entry.song.clone(),
+				since: entry.since,
+			})
+			.collect();
+		now_playing.sort_by(|a, b| b.since.cmp(&a.since).then(a.username.cmp(&b.username)));
+		now_playing
+	}
+}
@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::*;
+use crate::app::test;
+use crate::test_name;
+
+const TEST_MOUNT_NAME: &str = "root";
+
+fn song_path(file_name: &str) -> PathBuf {
+	[TEST_MOUNT_NAME, "Khemmis", "Hunted", file_name]
+		.iter()
+		.collect()
+}
+
+#[test]
+fn lists_what_users_are_playing() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+	let manager = Manager::new(ctx.index.clone(), DEFAULT_EXPIRATION);
+
+	manager
+		.record("Walter", &song_path("02 - Candlelight.mp3"))
+		.unwrap();
+	manager
+		.record("Skyler", &song_path("05 - Hunted.mp3"))
+		.unwrap();
+
+	let now_playing = manager.list();
+	assert_eq!(now_playing.len(), 2);
+	let walter = now_playing.iter().find(|e| e.username == "Walter").unwrap();
+	assert_eq!(walter.song.title, Some("Candlelight".to_owned()));
+	let skyler = now_playing.iter().find(|e| e.username == "Skyler").unwrap();
+	assert_eq!(skyler.song.title, Some("Hunted".to_owned()));
+}
+
+#[test]
+fn keeps_one_song_per_user() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+	let manager = Manager::new(ctx.index.clone(), DEFAULT_EXPIRATION);
+
+	manager
+		.record("Walter", &song_path("02 - Candlelight.mp3"))
+		.unwrap();
+	manager
+		.record("Walter", &song_path("05 - Hunted.mp3"))
+		.unwrap();
+
+	let now_playing = manager.list();
+	assert_eq!(now_playing.len(), 1);
+	assert_eq!(now_playing[0].song.title, Some("Hunted".to_owned()));
+}
+
+#[test]
+fn entries_expire() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+	let manager = Manager::new(ctx.index.clone(), Duration::from_secs(0));
+
+	manager
+		.record("Walter", &song_path("02 - Candlelight.mp3"))
+		.unwrap();
+	assert!(manager.list().is_empty());
+}
+
+#[test]
+fn cannot_record_unknown_song() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+	let manager = Manager::new(ctx.index.clone(), DEFAULT_EXPIRATION);
+
+	assert!(matches!(
+		manager.record("Walter", &song_path("not_a_song.mp3")),
+		Err(Error::SongNotFound)
+	));
+	assert!(manager.list().is_empty());
+}
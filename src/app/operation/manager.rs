@@ -0,0 +1,92 @@
+use futures_util::task::AtomicWaker;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::Context;
+
+use super::*;
+
+/// Keeps track of long-running operations (eg. audio streams) so they can be aborted on request.
+#[derive(Clone, Default)]
+pub struct Manager {
+	next_id: Arc<AtomicU64>,
+	operations: Arc<Mutex<HashMap<u64, Entry>>>,
+}
+
+struct Entry {
+	owner: String,
+	token: Arc<Token>,
+}
+
+#[derive(Default)]
+struct Token {
+	cancelled: AtomicBool,
+	waker: AtomicWaker,
+}
+
+impl Manager {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn begin(&self, owner: &str) -> Operation {
+		let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+		let token = Arc::new(Token::default());
+		self.operations.lock().unwrap().insert(
+			id,
+			Entry {
+				owner: owner.to_owned(),
+				token: token.clone(),
+			},
+		);
+		Operation {
+			id,
+			token,
+			manager: self.clone(),
+		}
+	}
+
+	pub fn cancel(&self, id: u64, owner: &str) -> Result<(), Error> {
+		let operations = self.operations.lock().unwrap();
+		let entry = operations
+			.get(&id)
+			.filter(|e| e.owner == owner)
+			.ok_or(Error::OperationNotFound)?;
+		entry.token.cancelled.store(true, Ordering::Release);
+		entry.token.waker.wake();
+		Ok(())
+	}
+
+	fn end(&self, id: u64) {
+		self.operations.lock().unwrap().remove(&id);
+	}
+}
+
+/// Handle to a registered operation. The operation is unregistered when this is dropped.
+pub struct Operation {
+	id: u64,
+	token: Arc<Token>,
+	manager: Manager,
+}
+
+impl Operation {
+	pub fn id(&self) -> u64 {
+		self.id
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.token.cancelled.load(Ordering::Acquire)
+	}
+
+	/// Same as `is_cancelled`, but also schedules the current task to be woken up on cancellation.
+	pub fn poll_cancelled(&self, cx: &mut Context<'_>) -> bool {
+		self.token.waker.register(cx.waker());
+		self.is_cancelled()
+	}
+}
+
+impl Drop for Operation {
+	fn drop(&mut self) {
+		self.manager.end(self.id);
+	}
+}
@@ -0,0 +1,27 @@
+use super::*;
+
+const TEST_USER: &str = "test_user";
+
+#[test]
+fn cancel_golden_path() {
+	let manager = Manager::new();
+	let operation = manager.begin(TEST_USER);
+	assert!(!operation.is_cancelled());
+	manager.cancel(operation.id(), TEST_USER).unwrap();
+	assert!(operation.is_cancelled());
+}
+
+#[test]
+fn cannot_cancel_operation_of_other_user() {
+	let manager = Manager::new();
+	let operation = manager.begin(TEST_USER);
+	assert!(manager.cancel(operation.id(), "other_user").is_err());
+	assert!(!operation.is_cancelled());
+}
+
+#[test]
+fn cannot_cancel_finished_operation() {
+	let manager = Manager::new();
+	let id = manager.begin(TEST_USER).id();
+	assert!(manager.cancel(id, TEST_USER).is_err());
+}
@@ -4,6 +4,10 @@ pub enum Error {
 	UserNotFound,
 	#[error("Playlist not found")]
 	PlaylistNotFound,
+	#[error("Playlist name cannot be empty")]
+	EmptyPlaylistName,
+	#[error("A playlist with this name already exists")]
+	PlaylistAlreadyExists,
 	#[error("Unspecified")]
 	Unspecified,
 }
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::app::index::Song;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+	/// Extended M3U restricted to Latin-1, for older players.
+	M3u,
+	/// Extended M3U encoded as UTF-8.
+	#[default]
+	M3u8,
+}
+
+impl ExportFormat {
+	pub fn extension(&self) -> &'static str {
+		match self {
+			ExportFormat::M3u => "m3u",
+			ExportFormat::M3u8 => "m3u8",
+		}
+	}
+
+	pub fn content_type(&self) -> &'static str {
+		match self {
+			ExportFormat::M3u => "audio/x-mpegurl",
+			ExportFormat::M3u8 => "audio/x-mpegurl; charset=utf-8",
+		}
+	}
+}
+
+pub fn export(songs: &[Song], format: ExportFormat) -> Vec<u8> {
+	let mut playlist = String::from("#EXTM3U\n");
+	for song in songs {
+		let duration = song.duration.unwrap_or(-1);
+		let title = song.title.clone().unwrap_or_else(|| {
+			Path::new(&song.path)
+				.file_stem()
+				.map(|s| s.to_string_lossy().into_owned())
+				.unwrap_or_default()
+		});
+		let label = match song.artist.as_ref().or(song.album_artist.as_ref()) {
+			Some(artist) => format!("{} - {}", artist, title),
+			None => title,
+		};
+		// Tags and paths must not be able to start extra entries
+		let label = label.replace(&['\r', '\n'][..], " ");
+		let path = song.path.replace(&['\r', '\n'][..], " ");
+		playlist.push_str(&format!("#EXTINF:{},{}\n{}\n", duration, label, path));
+	}
+
+	match format {
+		ExportFormat::M3u8 => playlist.into_bytes(),
+		ExportFormat::M3u => playlist
+			.chars()
+			.map(|c| if (c as u32) < 0x100 { c as u8 } else { b'?' })
+			.collect(),
+	}
+}
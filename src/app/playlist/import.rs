@@ -0,0 +1,56 @@
+/// A song listed in an M3U file, along with the artist and title from its `#EXTINF` line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportEntry {
+	pub path: String,
+	pub artist: Option<String>,
+	pub title: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportReport {
+	pub imported: usize,
+	/// Paths of the entries which did not match any song in the collection, in file order.
+	pub unmatched: Vec<String>,
+}
+
+pub fn parse(content: &[u8]) -> Vec<ImportEntry> {
+	// Files which are not valid UTF-8 are assumed to be plain M3U (Latin-1)
+	let content: String = match std::str::from_utf8(content) {
+		Ok(s) => s.to_owned(),
+		Err(_) => content.iter().map(|&b| b as char).collect(),
+	};
+
+	let mut entries = Vec::new();
+	let mut artist = None;
+	let mut title = None;
+	for line in content.trim_start_matches('\u{feff}').lines() {
+		let line = line.trim();
+		if let Some(info) = line.strip_prefix("#EXTINF:") {
+			let label = info
+				.split_once(',')
+				.map(|(_, l)| l.trim())
+				.unwrap_or_default();
+			match label.split_once(" - ") {
+				Some((a, t)) => {
+					artist = Some(a.trim().to_owned());
+					title = Some(t.trim().to_owned());
+				}
+				None if !label.is_empty() => {
+					artist = None;
+					title = Some(label.to_owned());
+				}
+				None => {
+					artist = None;
+					title = None;
+				}
+			}
+		} else if !line.is_empty() && !line.starts_with('#') {
+			entries.push(ImportEntry {
+				path: line.to_owned(),
+				artist: artist.take(),
+				title: title.take(),
+			});
+		}
+	}
+	entries
+}
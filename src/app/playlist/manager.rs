@@ -4,12 +4,40 @@ use diesel;
 use diesel::prelude::*;
 use diesel::sql_types;
 use diesel::BelongingToDsl;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use super::*;
 use crate::app::index::Song;
 use crate::app::vfs;
-use crate::db::{playlist_songs, playlists, users, DB};
+use crate::db::{playlist_songs, playlists, songs, users, DB};
+
+/// What playlist listings are ordered by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaylistSort {
+	#[default]
+	Name,
+	// Most recently saved first
+	Updated,
+}
+
+// Timestamps are in seconds since the Unix epoch. Songs of unknown duration do not count towards
+// the total duration.
+#[derive(Clone, Debug, PartialEq, Eq, QueryableByName)]
+pub struct PlaylistHeader {
+	#[sql_type = "sql_types::Text"]
+	pub name: String,
+	#[sql_type = "sql_types::BigInt"]
+	pub created_at: i64,
+	#[sql_type = "sql_types::BigInt"]
+	pub updated_at: i64,
+	#[sql_type = "sql_types::BigInt"]
+	pub song_count: i64,
+	#[sql_type = "sql_types::BigInt"]
+	pub total_duration: i64,
+}
 
 #[derive(Clone)]
 pub struct Manager {
@@ -22,7 +50,11 @@ impl Manager {
 		Self { db, vfs_manager }
 	}
 
-	pub fn list_playlists(&self, owner: &str) -> Result<Vec<String>, Error> {
+	pub fn list_playlists(
+		&self,
+		owner: &str,
+		sort: PlaylistSort,
+	) -> Result<Vec<PlaylistHeader>, Error> {
 		let connection = self.db.connect()?;
 
 		let user: User = {
@@ -36,14 +68,28 @@ impl Manager {
 				.ok_or(Error::UserNotFound)?
 		};
 
-		{
-			use self::playlists::dsl::*;
-			let found_playlists: Vec<String> = Playlist::belonging_to(&user)
-				.select(name)
-				.load(&connection)
-				.map_err(anyhow::Error::new)?;
-			Ok(found_playlists)
-		}
+		let order = match sort {
+			PlaylistSort::Name => "p.name",
+			PlaylistSort::Updated => "p.updated_at DESC, p.name",
+		};
+
+		// Not using Diesel because aggregates over a LEFT JOIN on a custom column are not expressible
+		let found_playlists: Vec<PlaylistHeader> = diesel::sql_query(format!(
+			r#"
+			SELECT p.name, p.created_at, p.updated_at, COUNT(ps.id) AS song_count, COALESCE(SUM(s.duration), 0) AS total_duration
+			FROM playlists p
+			LEFT JOIN playlist_songs ps ON ps.playlist = p.id
+			LEFT JOIN songs s ON ps.path = s.path
+			WHERE p.owner = ?
+			GROUP BY p.id
+			ORDER BY {}
+		"#,
+			order
+		))
+		.bind::<sql_types::Integer, _>(user.id)
+		.get_results(&connection)
+		.map_err(anyhow::Error::new)?;
+		Ok(found_playlists)
 	}
 
 	pub fn save_playlist(
@@ -54,6 +100,7 @@ impl Manager {
 	) -> Result<(), Error> {
 		let new_playlist: NewPlaylist;
 		let playlist: Playlist;
+		let new_version: i64;
 		let vfs = self.vfs_manager.get_vfs()?;
 
 		{
@@ -71,10 +118,31 @@ impl Manager {
 					.ok_or(Error::UserNotFound)?
 			};
 
-			// Create playlist
+			// Versions keep increasing even if the playlist is deleted and created again, so clients
+			// never mistake a stale copy for the current content
+			let previous: Option<(i64, i64)> = {
+				use self::playlists::dsl::*;
+				playlists
+					.select((version, created_at))
+					.filter(name.eq(playlist_name).and(owner.eq(user.id)))
+					.get_result(&connection)
+					.optional()
+					.map_err(anyhow::Error::new)?
+			};
+			let now = SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.unwrap_or_default();
+			let previous_version = previous.map(|(v, _)| v);
+			new_version = (now.as_millis() as i64).max(previous_version.unwrap_or_default() + 1);
+
+			// Create playlist. Saving over an existing playlist replaces its row, so the creation
+			// time is carried over.
+			let now = now.as_secs() as i64;
 			new_playlist = NewPlaylist {
 				name: playlist_name.into(),
 				owner: user.id,
+				created_at: previous.map(|(_, c)| c).unwrap_or(now),
+				updated_at: now,
 			};
 
 			diesel::insert_into(playlists::table)
@@ -122,6 +190,11 @@ impl Manager {
 					diesel::insert_into(playlist_songs::table)
 						.values(&new_songs)
 						.execute(&*connection)?; // TODO https://github.com/diesel-rs/diesel/issues/1822
+
+					// Publish new version once content is complete
+					diesel::update(playlists::table.filter(playlists::id.eq(playlist.id)))
+						.set(playlists::version.eq(new_version))
+						.execute(&connection)?;
 					Ok(())
 				})
 				.map_err(anyhow::Error::new)?;
@@ -164,7 +237,7 @@ impl Manager {
 			// Select songs. Not using Diesel because we need to LEFT JOIN using a custom column
 			let query = diesel::sql_query(
 				r#"
-			SELECT s.id, s.path, s.parent, s.track_number, s.disc_number, s.title, s.artist, s.album_artist, s.year, s.album, s.artwork, s.duration, s.lyricist, s.composer, s.genre, s.label
+			SELECT s.id, s.path, s.parent, s.track_number, s.disc_number, s.title, s.artist, s.album_artist, s.year, s.album, s.artwork, s.duration, s.lyricist, s.composer, s.genre, s.label, s.disc_subtitle, s.file_size, s.file_modified, s.replay_gain_track, s.replay_gain_album, s.search_text, s.bitrate
 			FROM playlist_songs ps
 			LEFT JOIN songs s ON ps.path = s.path
 			WHERE ps.playlist = ?
@@ -184,6 +257,69 @@ impl Manager {
 		Ok(virtual_songs)
 	}
 
+	pub fn export_playlist(
+		&self,
+		playlist_name: &str,
+		owner: &str,
+		format: ExportFormat,
+	) -> Result<Vec<u8>, Error> {
+		let songs = self.read_playlist(playlist_name, owner)?;
+		Ok(export(&songs, format))
+	}
+
+	/// Saves a playlist from the content of an M3U file. Entries are matched against the
+	/// collection by virtual path, then by real path, then by artist and title.
+	pub fn import_playlist(
+		&self,
+		playlist_name: &str,
+		owner: &str,
+		content: &[u8],
+	) -> Result<ImportReport, Error> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let mut tracks = Vec::new();
+		let mut report = ImportReport::default();
+
+		{
+			let connection = self.db.connect()?;
+			for entry in parse(content) {
+				match resolve_import_entry(&connection, &vfs, &entry)? {
+					Some(virtual_path) => tracks.push(virtual_path),
+					None => report.unmatched.push(entry.path),
+				}
+			}
+		}
+
+		self.save_playlist(playlist_name, owner, &tracks)?;
+		report.imported = tracks.len();
+		Ok(report)
+	}
+
+	pub fn get_playlist_version(&self, playlist_name: &str, owner: &str) -> Result<i64, Error> {
+		let connection = self.db.connect()?;
+
+		let user: User = {
+			use self::users::dsl::*;
+			users
+				.filter(name.eq(owner))
+				.select((id,))
+				.first(&connection)
+				.optional()
+				.map_err(anyhow::Error::new)?
+				.ok_or(Error::UserNotFound)?
+		};
+
+		{
+			use self::playlists::dsl::*;
+			Playlist::belonging_to(&user)
+				.filter(name.eq(playlist_name))
+				.select(version)
+				.get_result(&connection)
+				.optional()
+				.map_err(anyhow::Error::new)?
+				.ok_or(Error::PlaylistNotFound)
+		}
+	}
+
 	pub fn delete_playlist(&self, playlist_name: &str, owner: &str) -> Result<(), Error> {
 		let connection = self.db.connect()?;
 
@@ -210,6 +346,135 @@ impl Manager {
 			}
 		}
 	}
+
+	// Playlist names are unique per owner and conflicting rows get replaced, so an existing
+	// playlist under the new name must be ruled out before updating.
+	pub fn rename_playlist(
+		&self,
+		playlist_name: &str,
+		new_name: &str,
+		owner: &str,
+	) -> Result<(), Error> {
+		if new_name.trim().is_empty() {
+			return Err(Error::EmptyPlaylistName);
+		}
+
+		let connection = self.db.connect()?;
+
+		let user: User = {
+			use self::users::dsl::*;
+			users
+				.filter(name.eq(owner))
+				.select((id,))
+				.first(&connection)
+				.optional()
+				.map_err(anyhow::Error::new)?
+				.ok_or(Error::UserNotFound)?
+		};
+
+		{
+			// Checking for a collision and renaming happen in a single transaction
+			use self::playlists::dsl::*;
+			connection
+				.transaction::<_, diesel::result::Error, _>(|| {
+					let playlist_id: Option<i32> = Playlist::belonging_to(&user)
+						.filter(name.eq(playlist_name))
+						.select(id)
+						.get_result(&connection)
+						.optional()?;
+					let playlist_id = match playlist_id {
+						Some(playlist_id) => playlist_id,
+						None => return Ok(Err(Error::PlaylistNotFound)),
+					};
+
+					if new_name == playlist_name {
+						return Ok(Ok(()));
+					}
+
+					let collision: Option<i32> = Playlist::belonging_to(&user)
+						.filter(name.eq(new_name))
+						.select(id)
+						.get_result(&connection)
+						.optional()?;
+					if collision.is_some() {
+						return Ok(Err(Error::PlaylistAlreadyExists));
+					}
+
+					diesel::update(playlists.filter(id.eq(playlist_id)))
+						.set(name.eq(new_name))
+						.execute(&connection)?;
+					Ok(Ok(()))
+				})
+				.map_err(anyhow::Error::new)?
+		}
+	}
+
+	/// Deletes every playlist of `owner` and returns how many there were.
+	pub fn delete_playlists(&self, owner: &str) -> Result<usize, Error> {
+		let connection = self.db.connect()?;
+
+		let user: User = {
+			use self::users::dsl::*;
+			users
+				.filter(name.eq(owner))
+				.select((id,))
+				.first(&connection)
+				.optional()
+				.map_err(anyhow::Error::new)?
+				.ok_or(Error::UserNotFound)?
+		};
+
+		let deleted = diesel::delete(Playlist::belonging_to(&user))
+			.execute(&connection)
+			.map_err(anyhow::Error::new)?;
+		Ok(deleted)
+	}
+}
+
+fn resolve_import_entry(
+	connection: &SqliteConnection,
+	vfs: &vfs::VFS,
+	entry: &ImportEntry,
+) -> Result<Option<String>, Error> {
+	use self::songs::dsl::*;
+
+	let candidates = vec![
+		vfs.virtual_to_real(Path::new(&entry.path)).ok(),
+		Some(PathBuf::from(&entry.path)),
+	];
+	let mut real_path: Option<String> = None;
+	for candidate in candidates.into_iter().flatten() {
+		let candidate = match candidate.to_str() {
+			Some(c) => c.to_owned(),
+			None => continue,
+		};
+		real_path = songs
+			.select(path)
+			.filter(path.eq(&candidate))
+			.first(connection)
+			.optional()
+			.map_err(anyhow::Error::new)?;
+		if real_path.is_some() {
+			break;
+		}
+	}
+
+	if real_path.is_none() {
+		if let (Some(song_artist), Some(song_title)) = (&entry.artist, &entry.title) {
+			real_path = songs
+				.select(path)
+				.filter(title.eq(song_title))
+				.filter(artist.eq(song_artist).or(album_artist.eq(song_artist)))
+				.order(path)
+				.first(connection)
+				.optional()
+				.map_err(anyhow::Error::new)?;
+		}
+	}
+
+	Ok(real_path
+		.and_then(|p| vfs.real_to_virtual(Path::new(&p)).ok())
+		.map(|p| p.to_string_lossy().into_owned()))
 }
 
 #[derive(Identifiable, Queryable, Associations)]
@@ -231,6 +496,8 @@ struct PlaylistSong {
 struct NewPlaylist {
 	name: String,
 	owner: i32,
+	created_at: i64,
+	updated_at: i64,
 }
 
 #[derive(Insertable)]
@@ -1,7 +1,11 @@
 mod error;
+mod export;
+mod import;
 mod manager;
 #[cfg(test)]
 mod test;
 
 pub use error::*;
+pub use export::*;
+pub use import::*;
 pub use manager::*;
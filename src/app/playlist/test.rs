@@ -1,6 +1,9 @@
+use diesel::prelude::*;
 use std::path::{Path, PathBuf};
 
-use crate::app::test;
+use super::*;
+use crate::app::{index, test};
+use crate::db::playlists;
 use crate::test_name;
 
 const TEST_USER: &str = "test_user";
@@ -8,6 +11,15 @@ const TEST_PASSWORD: &str = "password";
 const TEST_PLAYLIST_NAME: &str = "Chill & Grill";
 const TEST_MOUNT_NAME: &str = "root";
 
+fn list_playlist_names(ctx: &test::Context, owner: &str) -> Vec<String> {
+	ctx.playlist_manager
+		.list_playlists(owner, PlaylistSort::Name)
+		.unwrap()
+		.into_iter()
+		.map(|h| h.name)
+		.collect()
+}
+
 #[test]
 fn save_playlist_golden_path() {
 	let ctx = test::ContextBuilder::new(test_name!())
@@ -18,7 +30,7 @@ fn save_playlist_golden_path() {
 		.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, &Vec::new())
 		.unwrap();
 
-	let found_playlists = ctx.playlist_manager.list_playlists(TEST_USER).unwrap();
+	let found_playlists = list_playlist_names(&ctx, TEST_USER);
 	assert_eq!(found_playlists.len(), 1);
 	assert_eq!(found_playlists[0], TEST_PLAYLIST_NAME);
 }
@@ -34,7 +46,11 @@ fn save_playlist_is_idempotent() {
 
 	let playlist_content: Vec<String> = ctx
 		.index
-		.flatten(Path::new(TEST_MOUNT_NAME))
+		.flatten(
+			Path::new(TEST_MOUNT_NAME),
+			index::Range::default(),
+			index::Sort::default(),
+		)
 		.unwrap()
 		.into_iter()
 		.map(|s| s.path)
@@ -72,10 +88,207 @@ fn delete_playlist_golden_path() {
 		.delete_playlist(TEST_PLAYLIST_NAME, TEST_USER)
 		.unwrap();
 
-	let found_playlists = ctx.playlist_manager.list_playlists(TEST_USER).unwrap();
+	let found_playlists = list_playlist_names(&ctx, TEST_USER);
 	assert_eq!(found_playlists.len(), 0);
 }
 
+#[test]
+fn rename_playlist_keeps_content_and_version() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let playlist_content: Vec<String> = ctx
+		.index
+		.flatten(
+			Path::new(TEST_MOUNT_NAME),
+			index::Range::default(),
+			index::Sort::default(),
+		)
+		.unwrap()
+		.into_iter()
+		.map(|s| s.path)
+		.collect();
+
+	ctx.playlist_manager
+		.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, &playlist_content)
+		.unwrap();
+	let version = ctx
+		.playlist_manager
+		.get_playlist_version(TEST_PLAYLIST_NAME, TEST_USER)
+		.unwrap();
+
+	ctx.playlist_manager
+		.rename_playlist(TEST_PLAYLIST_NAME, "Renamed", TEST_USER)
+		.unwrap();
+
+	let found_playlists = list_playlist_names(&ctx, TEST_USER);
+	assert_eq!(found_playlists, vec!["Renamed".to_owned()]);
+	let songs = ctx
+		.playlist_manager
+		.read_playlist("Renamed", TEST_USER)
+		.unwrap();
+	assert_eq!(songs.len(), 13);
+	assert_eq!(
+		ctx.playlist_manager
+			.get_playlist_version("Renamed", TEST_USER)
+			.unwrap(),
+		version
+	);
+}
+
+#[test]
+fn rename_playlist_rejects_empty_and_taken_names() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.build();
+
+	ctx.playlist_manager
+		.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, &Vec::new())
+		.unwrap();
+	ctx.playlist_manager
+		.save_playlist("Other", TEST_USER, &Vec::new())
+		.unwrap();
+
+	assert!(matches!(
+		ctx.playlist_manager
+			.rename_playlist(TEST_PLAYLIST_NAME, " ", TEST_USER),
+		Err(Error::EmptyPlaylistName)
+	));
+	assert!(matches!(
+		ctx.playlist_manager
+			.rename_playlist(TEST_PLAYLIST_NAME, "Other", TEST_USER),
+		Err(Error::PlaylistAlreadyExists)
+	));
+	assert!(matches!(
+		ctx.playlist_manager
+			.rename_playlist("Missing", "New", TEST_USER),
+		Err(Error::PlaylistNotFound)
+	));
+
+	let mut found_playlists = list_playlist_names(&ctx, TEST_USER);
+	found_playlists.sort();
+	assert_eq!(
+		found_playlists,
+		vec![TEST_PLAYLIST_NAME.to_owned(), "Other".to_owned()]
+	);
+}
+
+#[test]
+fn delete_playlists_only_affects_owner() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.user("other_user", TEST_PASSWORD, false)
+		.build();
+
+	for name in &[TEST_PLAYLIST_NAME, "Other"] {
+		ctx.playlist_manager
+			.save_playlist(name, TEST_USER, &Vec::new())
+			.unwrap();
+	}
+	ctx.playlist_manager
+		.save_playlist(TEST_PLAYLIST_NAME, "other_user", &Vec::new())
+		.unwrap();
+
+	let deleted = ctx.playlist_manager.delete_playlists(TEST_USER).unwrap();
+	assert_eq!(deleted, 2);
+	assert!(list_playlist_names(&ctx, TEST_USER).is_empty());
+	assert_eq!(
+		list_playlist_names(&ctx, "other_user"),
+		vec![TEST_PLAYLIST_NAME.to_owned()]
+	);
+}
+
+#[test]
+fn saving_playlist_updates_timestamps() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.build();
+
+	for name in &["Zeta", "Alpha"] {
+		ctx.playlist_manager
+			.save_playlist(name, TEST_USER, &Vec::new())
+			.unwrap();
+	}
+
+	{
+		let connection = ctx.db.connect().unwrap();
+		for (name, timestamp) in &[("Zeta", 100), ("Alpha", 200)] {
+			diesel::update(playlists::table.filter(playlists::name.eq(name)))
+				.set((
+					playlists::created_at.eq(timestamp),
+					playlists::updated_at.eq(timestamp),
+				))
+				.execute(&connection)
+				.unwrap();
+		}
+	}
+
+	let list = |sort| -> Vec<PlaylistHeader> {
+		ctx.playlist_manager
+			.list_playlists(TEST_USER, sort)
+			.unwrap()
+	};
+	let names = |headers: &[PlaylistHeader]| -> Vec<String> {
+		headers.iter().map(|h| h.name.clone()).collect()
+	};
+	assert_eq!(names(&list(PlaylistSort::Updated)), vec!["Alpha", "Zeta"]);
+
+	ctx.playlist_manager
+		.save_playlist("Zeta", TEST_USER, &Vec::new())
+		.unwrap();
+
+	assert_eq!(names(&list(PlaylistSort::Name)), vec!["Alpha", "Zeta"]);
+	let headers = list(PlaylistSort::Updated);
+	assert_eq!(names(&headers), vec!["Zeta", "Alpha"]);
+	assert_eq!(headers[0].created_at, 100);
+	assert!(headers[0].updated_at > 200);
+}
+
+#[test]
+fn list_playlists_summarizes_content() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let songs = ctx
+		.index
+		.flatten(
+			Path::new(TEST_MOUNT_NAME),
+			index::Range::default(),
+			index::Sort::default(),
+		)
+		.unwrap();
+	let total_duration: i64 = songs.iter().filter_map(|s| s.duration).map(i64::from).sum();
+	assert!(total_duration > 0);
+	let playlist_content: Vec<String> = songs.into_iter().map(|s| s.path).collect();
+
+	ctx.playlist_manager
+		.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, &playlist_content)
+		.unwrap();
+	ctx.playlist_manager
+		.save_playlist("Empty", TEST_USER, &Vec::new())
+		.unwrap();
+
+	let headers = ctx
+		.playlist_manager
+		.list_playlists(TEST_USER, PlaylistSort::Name)
+		.unwrap();
+	assert_eq!(headers.len(), 2);
+	assert_eq!(headers[0].name, TEST_PLAYLIST_NAME);
+	assert_eq!(headers[0].song_count, 13);
+	assert_eq!(headers[0].total_duration, total_duration);
+	assert_eq!(headers[1].name, "Empty");
+	assert_eq!(headers[1].song_count, 0);
+	assert_eq!(headers[1].total_duration, 0);
+}
+
 #[test]
 fn read_playlist_golden_path() {
 	let ctx = test::ContextBuilder::new(test_name!())
@@ -87,7 +300,11 @@ fn read_playlist_golden_path() {
 
 	let playlist_content: Vec<String> = ctx
 		.index
-		.flatten(Path::new(TEST_MOUNT_NAME))
+		.flatten(
+			Path::new(TEST_MOUNT_NAME),
+			index::Range::default(),
+			index::Sort::default(),
+		)
 		.unwrap()
 		.into_iter()
 		.map(|s| s.path)
@@ -116,3 +333,299 @@ fn read_playlist_golden_path() {
 	.collect();
 	assert_eq!(songs[0].path, first_song_path.to_str().unwrap());
 }
+
+#[test]
+fn read_playlist_includes_replay_gain() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/multi-disc")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let song_path: PathBuf = [TEST_MOUNT_NAME, "Box Set", "1-01 - Above The Water.mp3"]
+		.iter()
+		.collect();
+	ctx.playlist_manager
+		.save_playlist(
+			TEST_PLAYLIST_NAME,
+			TEST_USER,
+			&[song_path.to_string_lossy().into_owned()],
+		)
+		.unwrap();
+
+	let songs = ctx
+		.playlist_manager
+		.read_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+		.unwrap();
+	assert_eq!(songs[0].replay_gain_track, Some(-6.48));
+	assert_eq!(songs[0].replay_gain_album, Some(-7.12));
+}
+
+#[test]
+fn saving_playlist_bumps_version() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.build();
+
+	assert!(matches!(
+		ctx.playlist_manager
+			.get_playlist_version(TEST_PLAYLIST_NAME, TEST_USER),
+		Err(Error::PlaylistNotFound)
+	));
+
+	ctx.playlist_manager
+		.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, &Vec::new())
+		.unwrap();
+	let first_version = ctx
+		.playlist_manager
+		.get_playlist_version(TEST_PLAYLIST_NAME, TEST_USER)
+		.unwrap();
+
+	ctx.playlist_manager
+		.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, &Vec::new())
+		.unwrap();
+	let second_version = ctx
+		.playlist_manager
+		.get_playlist_version(TEST_PLAYLIST_NAME, TEST_USER)
+		.unwrap();
+	assert!(second_version > first_version);
+
+	ctx.playlist_manager
+		.delete_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+		.unwrap();
+	std::thread::sleep(std::time::Duration::from_millis(2)); // Versions of new playlists come from the clock
+	ctx.playlist_manager
+		.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, &Vec::new())
+		.unwrap();
+	let third_version = ctx
+		.playlist_manager
+		.get_playlist_version(TEST_PLAYLIST_NAME, TEST_USER)
+		.unwrap();
+	assert!(third_version > second_version);
+}
+
+#[test]
+fn read_playlist_saved_with_foreign_separators() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let foreign_separator = if cfg!(target_os = "windows") {
+		"/"
+	} else {
+		"\\"
+	};
+	let playlist_content: Vec<String> = ctx
+		.index
+		.flatten(
+			Path::new(TEST_MOUNT_NAME),
+			index::Range::default(),
+			index::Sort::default(),
+		)
+		.unwrap()
+		.into_iter()
+		.map(|s| s.path.replace(std::path::MAIN_SEPARATOR, foreign_separator))
+		.collect();
+	assert_eq!(playlist_content.len(), 13);
+
+	ctx.playlist_manager
+		.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, &playlist_content)
+		.unwrap();
+
+	let songs = ctx
+		.playlist_manager
+		.read_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+		.unwrap();
+	assert_eq!(songs.len(), 13);
+
+	let first_song_path: PathBuf = [
+		TEST_MOUNT_NAME,
+		"Khemmis",
+		"Hunted",
+		"01 - Above The Water.mp3",
+	]
+	.iter()
+	.collect();
+	assert_eq!(songs[0].path, first_song_path.to_str().unwrap());
+}
+
+fn save_test_collection_playlist(ctx: &test::Context) {
+	ctx.index.update().unwrap();
+	let playlist_content: Vec<String> = ctx
+		.index
+		.flatten(
+			Path::new(TEST_MOUNT_NAME),
+			index::Range::default(),
+			index::Sort::default(),
+		)
+		.unwrap()
+		.into_iter()
+		.map(|s| s.path)
+		.collect();
+	ctx.playlist_manager
+		.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, &playlist_content)
+		.unwrap();
+}
+
+#[test]
+fn export_playlist_as_m3u8() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	save_test_collection_playlist(&ctx);
+
+	let content = ctx
+		.playlist_manager
+		.export_playlist(TEST_PLAYLIST_NAME, TEST_USER, ExportFormat::M3u8)
+		.unwrap();
+	let content = String::from_utf8(content).unwrap();
+	let lines: Vec<&str> = content.lines().collect();
+	assert_eq!(lines.len(), 1 + 2 * 13);
+	assert_eq!(lines[0], "#EXTM3U");
+
+	let first_song_path: PathBuf = [
+		TEST_MOUNT_NAME,
+		"Khemmis",
+		"Hunted",
+		"01 - Above The Water.mp3",
+	]
+	.iter()
+	.collect();
+	assert_eq!(lines[1], "#EXTINF:0,Khemmis - Above The Water");
+	assert_eq!(lines[2], first_song_path.to_str().unwrap());
+	assert!(lines.contains(&"#EXTINF:3,Tobokegao - なぜ (Why?)"));
+}
+
+#[test]
+fn export_playlist_as_m3u_replaces_non_latin1_characters() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	save_test_collection_playlist(&ctx);
+
+	let content = ctx
+		.playlist_manager
+		.export_playlist(TEST_PLAYLIST_NAME, TEST_USER, ExportFormat::M3u)
+		.unwrap();
+	let content: String = content.into_iter().map(|b| b as char).collect();
+	let lines: Vec<&str> = content.lines().collect();
+	assert_eq!(lines.len(), 1 + 2 * 13);
+	assert!(lines.contains(&"#EXTINF:3,Tobokegao - ?? (Why?)"));
+}
+
+#[test]
+fn parse_m3u_reads_extinf_labels() {
+	let content = "#EXTM3U\n#EXTINF:123,Miles Davis - So What\r\nJazz/So What.mp3\n\n# comment\nJazz/Blue in Green.mp3\n#EXTINF:-1,Freddie Freeloader\nJazz/Freddie Freeloader.mp3\n";
+	let entries = parse(content.as_bytes());
+	assert_eq!(
+		entries,
+		vec![
+			ImportEntry {
+				path: "Jazz/So What.mp3".to_owned(),
+				artist: Some("Miles Davis".to_owned()),
+				title: Some("So What".to_owned()),
+			},
+			ImportEntry {
+				path: "Jazz/Blue in Green.mp3".to_owned(),
+				artist: None,
+				title: None,
+			},
+			ImportEntry {
+				path: "Jazz/Freddie Freeloader.mp3".to_owned(),
+				artist: None,
+				title: Some("Freddie Freeloader".to_owned()),
+			},
+		]
+	);
+}
+
+#[test]
+fn parse_m3u_falls_back_to_latin1() {
+	let content = b"#EXTINF:60,Bj\xf6rk - J\xf3ga\nBj\xf6rk/J\xf3ga.mp3\n";
+	let entries = parse(&content[..]);
+	assert_eq!(entries.len(), 1);
+	assert_eq!(entries[0].path, "Björk/Jóga.mp3");
+	assert_eq!(entries[0].artist, Some("Björk".to_owned()));
+	assert_eq!(entries[0].title, Some("Jóga".to_owned()));
+}
+
+#[test]
+fn import_playlist_matches_songs() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let hunted_virtual: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let hunted_real = ctx
+		.vfs_manager
+		.get_vfs()
+		.unwrap()
+		.virtual_to_real(&hunted_virtual)
+		.unwrap();
+	let content = format!(
+		"#EXTM3U\n{}\n#EXTINF:0,Khemmis - Candlelight\nsomewhere/else.mp3\n{}\n#EXTINF:0,Nobody - Nothing\nmissing.mp3\n",
+		hunted_virtual.join("05 - Hunted.mp3").to_string_lossy(),
+		hunted_real.join("01 - Above The Water.mp3").to_string_lossy(),
+	);
+
+	let report = ctx
+		.playlist_manager
+		.import_playlist(TEST_PLAYLIST_NAME, TEST_USER, content.as_bytes())
+		.unwrap();
+	assert_eq!(report.imported, 3);
+	assert_eq!(report.unmatched, vec!["missing.mp3".to_owned()]);
+
+	let titles: Vec<Option<String>> = ctx
+		.playlist_manager
+		.read_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+		.unwrap()
+		.into_iter()
+		.map(|s| s.title)
+		.collect();
+	assert_eq!(
+		titles,
+		vec![
+			Some("Hunted".to_owned()),
+			Some("Candlelight".to_owned()),
+			Some("Above The Water".to_owned()),
+		]
+	);
+}
+
+#[test]
+fn import_playlist_round_trips_export() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	save_test_collection_playlist(&ctx);
+
+	let exported = ctx
+		.playlist_manager
+		.export_playlist(TEST_PLAYLIST_NAME, TEST_USER, ExportFormat::M3u8)
+		.unwrap();
+	let report = ctx
+		.playlist_manager
+		.import_playlist("Copy", TEST_USER, &exported)
+		.unwrap();
+	assert_eq!(report.imported, 13);
+	assert!(report.unmatched.is_empty());
+
+	let original = ctx
+		.playlist_manager
+		.read_playlist(TEST_PLAYLIST_NAME, TEST_USER)
+		.unwrap();
+	let copy = ctx
+		.playlist_manager
+		.read_playlist("Copy", TEST_USER)
+		.unwrap();
+	assert_eq!(copy, original);
+}
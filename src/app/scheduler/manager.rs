@@ -0,0 +1,188 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Kinds of requests which do not compete with each other for server resources.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestClass {
+	/// Cheap requests answered from the database (browsing, metadata, settings, etc.).
+	Api,
+	/// Requests streaming file contents (audio, thumbnails, etc.).
+	Transfer,
+}
+
+impl RequestClass {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			RequestClass::Api => "api",
+			RequestClass::Transfer => "transfer",
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PoolStats {
+	pub class: RequestClass,
+	pub limit: Option<usize>,
+	pub in_flight: usize,
+	pub queued: usize,
+}
+
+/// Bounds how many transfers are served concurrently so they cannot starve API requests, which
+/// are never queued. Transfers over the limit wait for a slot in the order they arrived.
+#[derive(Clone)]
+pub struct Manager {
+	api: Arc<Pool>,
+	transfer: Arc<Pool>,
+}
+
+impl Manager {
+	pub fn new(max_concurrent_transfers: Option<usize>) -> Self {
+		Self {
+			api: Arc::new(Pool::new(RequestClass::Api, None)),
+			transfer: Arc::new(Pool::new(
+				RequestClass::Transfer,
+				max_concurrent_transfers.map(|n| n.max(1)),
+			)),
+		}
+	}
+
+	/// Waits for a slot to serve a request of the given class. The slot is released when the
+	/// returned permit is dropped.
+	pub fn acquire(&self, class: RequestClass) -> Acquire {
+		let pool = match class {
+			RequestClass::Api => self.api.clone(),
+			RequestClass::Transfer => self.transfer.clone(),
+		};
+		Acquire { pool, ticket: None }
+	}
+
+	pub fn stats(&self) -> Vec<PoolStats> {
+		vec![self.api.stats(), self.transfer.stats()]
+	}
+}
+
+struct Pool {
+	class: RequestClass,
+	limit: Option<usize>,
+	state: Mutex<PoolState>,
+}
+
+#[derive(Default)]
+struct PoolState {
+	in_flight: usize,
+	next_ticket: u64,
+	waiting: VecDeque<(u64, Waker)>,
+}
+
+impl PoolState {
+	fn wake_next(&self) {
+		if let Some((_, waker)) = self.waiting.front() {
+			waker.wake_by_ref();
+		}
+	}
+}
+
+impl Pool {
+	fn new(class: RequestClass, limit: Option<usize>) -> Self {
+		Self {
+			class,
+			limit,
+			state: Mutex::new(PoolState::default()),
+		}
+	}
+
+	fn stats(&self) -> PoolStats {
+		let state = self.state.lock().unwrap();
+		PoolStats {
+			class: self.class,
+			limit: self.limit,
+			in_flight: state.in_flight,
+			queued: state.waiting.len(),
+		}
+	}
+
+	fn has_room(&self, in_flight: usize) -> bool {
+		match self.limit {
+			Some(limit) => in_flight < limit,
+			None => true,
+		}
+	}
+
+	fn release(&self) {
+		let mut state = self.state.lock().unwrap();
+		state.in_flight -= 1;
+		state.wake_next();
+	}
+}
+
+/// Future resolving to a `Permit` once the request can be served.
+pub struct Acquire {
+	pool: Arc<Pool>,
+	ticket: Option<u64>,
+}
+
+impl Future for Acquire {
+	type Output = Permit;
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let pool = self.pool.clone();
+		let mut state = pool.state.lock().unwrap();
+
+		let has_slot = pool.has_room(state.in_flight);
+		let is_next = match self.ticket {
+			None => state.waiting.is_empty(),
+			Some(t) => state.waiting.front().map(|(f, _)| *f) == Some(t),
+		};
+		if has_slot && is_next {
+			if self.ticket.take().is_some() {
+				state.waiting.pop_front();
+			}
+			state.in_flight += 1;
+			if pool.has_room(state.in_flight) {
+				state.wake_next();
+			}
+			return Poll::Ready(Permit {
+				pool: self.pool.clone(),
+			});
+		}
+
+		match self.ticket {
+			None => {
+				let ticket = state.next_ticket;
+				state.next_ticket += 1;
+				state.waiting.push_back((ticket, cx.waker().clone()));
+				self.ticket = Some(ticket);
+			}
+			Some(t) => {
+				if let Some(entry) = state.waiting.iter_mut().find(|(w, _)| *w == t) {
+					entry.1 = cx.waker().clone();
+				}
+			}
+		}
+		Poll::Pending
+	}
+}
+
+impl Drop for Acquire {
+	fn drop(&mut self) {
+		if let Some(ticket) = self.ticket {
+			let mut state = self.pool.state.lock().unwrap();
+			state.waiting.retain(|(t, _)| *t != ticket);
+			state.wake_next();
+		}
+	}
+}
+
+/// Slot held while a request is being served.
+pub struct Permit {
+	pool: Arc<Pool>,
+}
+
+impl Drop for Permit {
+	fn drop(&mut self) {
+		self.pool.release();
+	}
+}
@@ -0,0 +1,89 @@
+use futures_util::task::noop_waker_ref;
+use futures_util::FutureExt;
+use std::task::{Context, Poll};
+
+use super::*;
+
+fn poll(acquire: &mut Acquire) -> Option<Permit> {
+	let mut context = Context::from_waker(noop_waker_ref());
+	match acquire.poll_unpin(&mut context) {
+		Poll::Ready(permit) => Some(permit),
+		Poll::Pending => None,
+	}
+}
+
+fn stats(manager: &Manager, class: RequestClass) -> PoolStats {
+	manager
+		.stats()
+		.into_iter()
+		.find(|s| s.class == class)
+		.unwrap()
+}
+
+#[test]
+fn api_requests_are_never_queued() {
+	let manager = Manager::new(Some(1));
+	let permits: Vec<Permit> = (0..100)
+		.map(|_| poll(&mut manager.acquire(RequestClass::Api)).unwrap())
+		.collect();
+	let api_stats = stats(&manager, RequestClass::Api);
+	assert_eq!(api_stats.in_flight, 100);
+	assert_eq!(api_stats.queued, 0);
+	drop(permits);
+	assert_eq!(stats(&manager, RequestClass::Api).in_flight, 0);
+}
+
+#[test]
+fn transfers_over_limit_are_queued_in_order() {
+	let manager = Manager::new(Some(2));
+	let first = poll(&mut manager.acquire(RequestClass::Transfer)).unwrap();
+	let _second = poll(&mut manager.acquire(RequestClass::Transfer)).unwrap();
+
+	let mut third = manager.acquire(RequestClass::Transfer);
+	let mut fourth = manager.acquire(RequestClass::Transfer);
+	assert!(poll(&mut third).is_none());
+	assert!(poll(&mut fourth).is_none());
+	assert_eq!(
+		stats(&manager, RequestClass::Transfer),
+		PoolStats {
+			class: RequestClass::Transfer,
+			limit: Some(2),
+			in_flight: 2,
+			queued: 2,
+		}
+	);
+
+	drop(first);
+	assert!(poll(&mut fourth).is_none());
+	let _third = poll(&mut third).unwrap();
+	assert!(poll(&mut fourth).is_none());
+	let transfer_stats = stats(&manager, RequestClass::Transfer);
+	assert_eq!(transfer_stats.in_flight, 2);
+	assert_eq!(transfer_stats.queued, 1);
+}
+
+#[test]
+fn abandoned_transfers_leave_the_queue() {
+	let manager = Manager::new(Some(1));
+	let first = poll(&mut manager.acquire(RequestClass::Transfer)).unwrap();
+	let mut second = manager.acquire(RequestClass::Transfer);
+	let mut third = manager.acquire(RequestClass::Transfer);
+	assert!(poll(&mut second).is_none());
+	assert!(poll(&mut third).is_none());
+
+	drop(second);
+	assert_eq!(stats(&manager, RequestClass::Transfer).queued, 1);
+	drop(first);
+	assert!(poll(&mut third).is_some());
+	assert_eq!(stats(&manager, RequestClass::Transfer).queued, 0);
+}
+
+#[test]
+fn transfers_can_be_unbounded() {
+	let manager = Manager::new(None);
+	let permits: Vec<Permit> = (0..100)
+		.map(|_| poll(&mut manager.acquire(RequestClass::Transfer)).unwrap())
+		.collect();
+	assert_eq!(stats(&manager, RequestClass::Transfer).in_flight, 100);
+	drop(permits);
+}
@@ -6,10 +6,48 @@ pub enum Error {
 	InvalidAuthSecret,
 	#[error("Missing index sleep duration")]
 	IndexSleepDurationNotFound,
+	#[error("Missing index start delay")]
+	IndexStartDelayNotFound,
 	#[error("Missing index album art pattern")]
 	IndexAlbumArtPatternNotFound,
 	#[error("Index album art pattern is not a valid regex")]
 	IndexAlbumArtPatternInvalid,
+	#[error("Missing index trigger policy")]
+	IndexTriggerPolicyNotFound,
+	#[error("Missing index album art names")]
+	IndexAlbumArtNamesNotFound,
+	#[error("Index trigger policy is not recognized")]
+	IndexTriggerPolicyInvalid,
+	#[error("Missing index max tag length")]
+	IndexMaxTagLengthNotFound,
+	#[error("Missing index audio extensions")]
+	IndexAudioExtensionsNotFound,
+	#[error("Missing follow symlinks")]
+	FollowSymlinksNotFound,
+	#[error("Missing auth max failed attempts")]
+	AuthMaxFailedAttemptsNotFound,
+	#[error("Missing auth failure window")]
+	AuthFailureWindowNotFound,
+	#[error("Missing auth realm")]
+	AuthRealmNotFound,
+	#[error("Missing CORS allowed origins")]
+	CorsAllowedOriginsNotFound,
+	#[error("Missing session lifetime")]
+	SessionLifetimeNotFound,
+	#[error("Session lifetime must be a positive number of seconds")]
+	SessionLifetimeInvalid,
+	#[error("Missing thumbnail cache max size")]
+	ThumbnailCacheMaxSizeNotFound,
+	#[error("Missing thumbnail format")]
+	ThumbnailFormatNotFound,
+	#[error("Thumbnail format is not recognized")]
+	ThumbnailFormatInvalid,
+	#[error("Missing thumbnail quality")]
+	ThumbnailQualityNotFound,
+	#[error("Thumbnail quality must be between 1 and 100")]
+	ThumbnailQualityInvalid,
+	#[error("Settings were changed since they were read")]
+	RevisionMismatch,
 	#[error("Unspecified")]
 	Unspecified,
 }
@@ -33,7 +33,8 @@ impl Manager {
 			.map(|key| AuthSecret { key })
 	}
 
-	pub fn get_index_sleep_duration(&self) -> Result<Duration, Error> {
+	// Mounts without their own scan interval are only scanned manually when this is zero
+	pub fn get_index_sleep_duration(&self) -> Result<Option<Duration>, Error> {
 		use self::misc_settings::dsl::*;
 		let connection = self.db.connect()?;
 		misc_settings
@@ -43,7 +44,26 @@ impl Manager {
 				diesel::result::Error::NotFound => Error::IndexSleepDurationNotFound,
 				_ => Error::Unspecified,
 			})
-			.map(|s: i32| Duration::from_secs(s as u64))
+			.map(|s: i32| {
+				if s > 0 {
+					Some(Duration::from_secs(s as u64))
+				} else {
+					None
+				}
+			})
+	}
+
+	pub fn get_index_start_delay(&self) -> Result<Duration, Error> {
+		use self::misc_settings::dsl::*;
+		let connection = self.db.connect()?;
+		misc_settings
+			.select(index_start_delay_seconds)
+			.get_result(&connection)
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::IndexStartDelayNotFound,
+				_ => Error::Unspecified,
+			})
+			.map(|s: i32| Duration::from_secs(s.max(0) as u64))
 	}
 
 	pub fn get_index_album_art_pattern(&self) -> Result<Regex, Error> {
@@ -61,10 +81,210 @@ impl Manager {
 			})
 	}
 
+	/// Lowercase names of the files used as album art, in order of preference.
+	pub fn get_index_album_art_names(&self) -> Result<Vec<String>, Error> {
+		use self::misc_settings::dsl::*;
+		let connection = self.db.connect()?;
+		misc_settings
+			.select(index_album_art_names)
+			.get_result(&connection)
+			.map(|names: String| parse_album_art_names(&names))
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::IndexAlbumArtNamesNotFound,
+				_ => Error::Unspecified,
+			})
+	}
+
+	pub fn get_index_trigger_policy(&self) -> Result<IndexTriggerPolicy, Error> {
+		use self::misc_settings::dsl::*;
+		let connection = self.db.connect()?;
+		misc_settings
+			.select(index_trigger_policy)
+			.get_result(&connection)
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::IndexTriggerPolicyNotFound,
+				_ => Error::Unspecified,
+			})
+			.and_then(|s: String| s.parse())
+	}
+
+	// Zero means tags are never truncated
+	pub fn get_index_max_tag_length(&self) -> Result<Option<usize>, Error> {
+		use self::misc_settings::dsl::*;
+		let connection = self.db.connect()?;
+		misc_settings
+			.select(index_max_tag_length)
+			.get_result(&connection)
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::IndexMaxTagLengthNotFound,
+				_ => Error::Unspecified,
+			})
+			.map(|l: i32| if l > 0 { Some(l as usize) } else { None })
+	}
+
+	/// Lowercase extensions, without a leading dot, of the files the index treats as songs.
+	pub fn get_index_audio_extensions(&self) -> Result<Vec<String>, Error> {
+		use self::misc_settings::dsl::*;
+		let connection = self.db.connect()?;
+		misc_settings
+			.select(index_audio_extensions)
+			.get_result(&connection)
+			.map(|extensions: String| parse_audio_extensions(&extensions))
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::IndexAudioExtensionsNotFound,
+				_ => Error::Unspecified,
+			})
+	}
+
+	/// Whether the index walks into symbolic links, or skips them entirely.
+	pub fn get_follow_symlinks(&self) -> Result<bool, Error> {
+		use self::misc_settings::dsl::*;
+		let connection = self.db.connect()?;
+		misc_settings
+			.select(follow_symlinks)
+			.get_result(&connection)
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::FollowSymlinksNotFound,
+				_ => Error::Unspecified,
+			})
+			.map(|f: i32| f != 0)
+	}
+
+	// Zero means failed login attempts are never throttled
+	pub fn get_auth_max_failed_attempts(&self) -> Result<Option<usize>, Error> {
+		use self::misc_settings::dsl::*;
+		let connection = self.db.connect()?;
+		misc_settings
+			.select(auth_max_failed_attempts)
+			.get_result(&connection)
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::AuthMaxFailedAttemptsNotFound,
+				_ => Error::Unspecified,
+			})
+			.map(|n: i32| if n > 0 { Some(n as usize) } else { None })
+	}
+
+	pub fn get_auth_failure_window(&self) -> Result<Duration, Error> {
+		use self::misc_settings::dsl::*;
+		let connection = self.db.connect()?;
+		misc_settings
+			.select(auth_failure_window_seconds)
+			.get_result(&connection)
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::AuthFailureWindowNotFound,
+				_ => Error::Unspecified,
+			})
+			.map(|s: i32| Duration::from_secs(s.max(0) as u64))
+	}
+
+	pub fn get_auth_realm(&self) -> Result<String, Error> {
+		use self::misc_settings::dsl::*;
+		let connection = self.db.connect()?;
+		misc_settings
+			.select(auth_realm)
+			.get_result(&connection)
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::AuthRealmNotFound,
+				_ => Error::Unspecified,
+			})
+	}
+
+	/// Origins of the browser clients allowed to make cross-origin requests. CORS is disabled when
+	/// this is empty.
+	pub fn get_cors_allowed_origins(&self) -> Result<Vec<String>, Error> {
+		use self::misc_settings::dsl::*;
+		let connection = self.db.connect()?;
+		misc_settings
+			.select(cors_allowed_origins)
+			.get_result(&connection)
+			.map(|origins: String| parse_origins(&origins))
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::CorsAllowedOriginsNotFound,
+				_ => Error::Unspecified,
+			})
+	}
+
+	/// How long login sessions, their cookies and JWTs remain valid.
+	pub fn get_session_lifetime(&self) -> Result<Duration, Error> {
+		use self::misc_settings::dsl::*;
+		let connection = self.db.connect()?;
+		misc_settings
+			.select(session_lifetime_seconds)
+			.get_result(&connection)
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::SessionLifetimeNotFound,
+				_ => Error::Unspecified,
+			})
+			.map(|s: i32| Duration::from_secs(s.max(1) as u64))
+	}
+
+	// Zero means the thumbnail cache is never trimmed
+	pub fn get_thumbnail_cache_max_size(&self) -> Result<Option<u64>, Error> {
+		use self::misc_settings::dsl::*;
+		let connection = self.db.connect()?;
+		misc_settings
+			.select(thumbnail_cache_max_size_bytes)
+			.get_result(&connection)
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::ThumbnailCacheMaxSizeNotFound,
+				_ => Error::Unspecified,
+			})
+			.map(|s: i64| if s > 0 { Some(s as u64) } else { None })
+	}
+
+	pub fn get_thumbnail_format(&self) -> Result<ThumbnailFormat, Error> {
+		use self::misc_settings::dsl::*;
+		let connection = self.db.connect()?;
+		misc_settings
+			.select(thumbnail_format)
+			.get_result(&connection)
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::ThumbnailFormatNotFound,
+				_ => Error::Unspecified,
+			})
+			.and_then(|s: String| s.parse())
+	}
+
+	/// JPEG encoding quality of thumbnails, from 1 to 100. PNG thumbnails are lossless.
+	pub fn get_thumbnail_quality(&self) -> Result<u8, Error> {
+		use self::misc_settings::dsl::*;
+		let connection = self.db.connect()?;
+		misc_settings
+			.select(thumbnail_quality)
+			.get_result(&connection)
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::ThumbnailQualityNotFound,
+				_ => Error::Unspecified,
+			})
+			.map(|q: i32| q.clamp(1, 100) as u8)
+	}
+
 	pub fn read(&self) -> Result<Settings, Error> {
 		let connection = self.db.connect()?;
 
 		let misc: MiscSettings = misc_settings::table
+			.select((
+				misc_settings::auth_secret,
+				misc_settings::index_sleep_duration_seconds,
+				misc_settings::index_album_art_pattern,
+				misc_settings::index_start_delay_seconds,
+				misc_settings::unknown_artist_label,
+				misc_settings::normalize_path_separators,
+				misc_settings::index_trigger_policy,
+				misc_settings::index_max_tag_length,
+				misc_settings::auth_max_failed_attempts,
+				misc_settings::auth_failure_window_seconds,
+				misc_settings::auth_realm,
+				misc_settings::cors_allowed_origins,
+				misc_settings::session_lifetime_seconds,
+				misc_settings::thumbnail_cache_max_size_bytes,
+				misc_settings::index_audio_extensions,
+				misc_settings::follow_symlinks,
+				misc_settings::thumbnail_format,
+				misc_settings::thumbnail_quality,
+				misc_settings::index_album_art_names,
+				misc_settings::settings_revision,
+			))
 			.get_result(&connection)
 			.map_err(|_| Error::Unspecified)?;
 
@@ -72,12 +292,71 @@ impl Manager {
 			auth_secret: misc.auth_secret,
 			album_art_pattern: misc.index_album_art_pattern,
 			reindex_every_n_seconds: misc.index_sleep_duration_seconds,
+			index_start_delay_seconds: misc.index_start_delay_seconds,
+			unknown_artist_label: misc.unknown_artist_label,
+			normalize_path_separators: misc.normalize_path_separators != 0,
+			index_trigger_policy: misc.index_trigger_policy.parse()?,
+			index_max_tag_length: misc.index_max_tag_length,
+			auth_max_failed_attempts: misc.auth_max_failed_attempts,
+			auth_failure_window_seconds: misc.auth_failure_window_seconds,
+			auth_realm: misc.auth_realm,
+			cors_allowed_origins: parse_origins(&misc.cors_allowed_origins),
+			session_lifetime_seconds: misc.session_lifetime_seconds,
+			thumbnail_cache_max_size_bytes: misc.thumbnail_cache_max_size_bytes,
+			index_audio_extensions: parse_audio_extensions(&misc.index_audio_extensions),
+			follow_symlinks: misc.follow_symlinks != 0,
+			thumbnail_format: misc.thumbnail_format.parse()?,
+			thumbnail_quality: misc.thumbnail_quality,
+			album_art_names: parse_album_art_names(&misc.index_album_art_names),
+			revision: misc.settings_revision,
 		})
 	}
 
 	pub fn amend(&self, new_settings: &NewSettings) -> Result<(), Error> {
+		self.amend_revision(new_settings, None)
+	}
+
+	/// Amends settings only if they are still at `revision`, so concurrent edits based on the same
+	/// revision cannot silently overwrite each other.
+	pub fn amend_at_revision(
+		&self,
+		new_settings: &NewSettings,
+		revision: i64,
+	) -> Result<(), Error> {
+		self.amend_revision(new_settings, Some(revision))
+	}
+
+	fn amend_revision(
+		&self,
+		new_settings: &NewSettings,
+		expected_revision: Option<i64>,
+	) -> Result<(), Error> {
+		if matches!(new_settings.session_lifetime_seconds, Some(l) if l <= 0) {
+			return Err(Error::SessionLifetimeInvalid);
+		}
+		if matches!(new_settings.thumbnail_quality, Some(q) if !(1..=100).contains(&q)) {
+			return Err(Error::ThumbnailQualityInvalid);
+		}
+
 		let connection = self.db.connect()?;
 
+		// Checking and bumping the revision in a single statement lets exactly one of several
+		// concurrent amendments at the same revision through
+		{
+			use self::misc_settings::dsl::*;
+			let bump = settings_revision.eq(settings_revision + 1);
+			let bumped = match expected_revision {
+				Some(r) => diesel::update(misc_settings.filter(settings_revision.eq(r)))
+					.set(bump)
+					.execute(&connection),
+				None => diesel::update(misc_settings).set(bump).execute(&connection),
+			}
+			.map_err(|_| Error::Unspecified)?;
+			if bumped == 0 {
+				return Err(Error::RevisionMismatch);
+			}
+		}
+
 		if let Some(sleep_duration) = new_settings.reindex_every_n_seconds {
 			diesel::update(misc_settings::table)
 				.set(misc_settings::index_sleep_duration_seconds.eq(sleep_duration as i32))
@@ -85,6 +364,121 @@ impl Manager {
 				.map_err(|_| Error::Unspecified)?;
 		}
 
+		if let Some(start_delay) = new_settings.index_start_delay_seconds {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::index_start_delay_seconds.eq(start_delay))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(ref label) = new_settings.unknown_artist_label {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::unknown_artist_label.eq(label))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(normalize) = new_settings.normalize_path_separators {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::normalize_path_separators.eq(normalize as i32))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(policy) = new_settings.index_trigger_policy {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::index_trigger_policy.eq(policy.as_str()))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(max_tag_length) = new_settings.index_max_tag_length {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::index_max_tag_length.eq(max_tag_length))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(max_failed_attempts) = new_settings.auth_max_failed_attempts {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::auth_max_failed_attempts.eq(max_failed_attempts))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(failure_window) = new_settings.auth_failure_window_seconds {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::auth_failure_window_seconds.eq(failure_window))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(ref realm) = new_settings.auth_realm {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::auth_realm.eq(realm))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(ref origins) = new_settings.cors_allowed_origins {
+			let origins = parse_origins(&origins.join(","));
+			diesel::update(misc_settings::table)
+				.set(misc_settings::cors_allowed_origins.eq(origins.join(",")))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(lifetime) = new_settings.session_lifetime_seconds {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::session_lifetime_seconds.eq(lifetime))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(max_size) = new_settings.thumbnail_cache_max_size_bytes {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::thumbnail_cache_max_size_bytes.eq(max_size))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(ref extensions) = new_settings.index_audio_extensions {
+			let extensions = parse_audio_extensions(&extensions.join(","));
+			diesel::update(misc_settings::table)
+				.set(misc_settings::index_audio_extensions.eq(extensions.join(",")))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(ref names) = new_settings.album_art_names {
+			let names = parse_album_art_names(&names.join(","));
+			diesel::update(misc_settings::table)
+				.set(misc_settings::index_album_art_names.eq(names.join(",")))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(follow) = new_settings.follow_symlinks {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::follow_symlinks.eq(follow as i32))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(format) = new_settings.thumbnail_format {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::thumbnail_format.eq(format.as_str()))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		if let Some(quality) = new_settings.thumbnail_quality {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::thumbnail_quality.eq(quality))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
 		if let Some(ref album_art_pattern) = new_settings.album_art_pattern {
 			diesel::update(misc_settings::table)
 				.set(misc_settings::index_album_art_pattern.eq(album_art_pattern))
@@ -1,4 +1,5 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 mod error;
 mod manager;
@@ -6,17 +7,135 @@ mod manager;
 pub use error::*;
 pub use manager::*;
 
+pub const DEFAULT_AUTH_REALM: &str = "Polaris";
+
+// Origins are stored in a single column, separated by commas
+fn parse_origins(origins: &str) -> Vec<String> {
+	origins
+		.split(',')
+		.map(|o| o.trim().trim_end_matches('/'))
+		.filter(|o| !o.is_empty())
+		.map(|o| o.to_owned())
+		.collect()
+}
+
+// Audio extensions are stored in a single column, separated by commas
+fn parse_audio_extensions(extensions: &str) -> Vec<String> {
+	let mut parsed: Vec<String> = Vec::new();
+	for extension in extensions.split(',') {
+		let extension = extension.trim().trim_start_matches('.').to_lowercase();
+		if !extension.is_empty() && !parsed.contains(&extension) {
+			parsed.push(extension);
+		}
+	}
+	parsed
+}
+
+// Album art file names are stored in a single column, separated by commas, in order of preference
+fn parse_album_art_names(names: &str) -> Vec<String> {
+	let mut parsed: Vec<String> = Vec::new();
+	for name in names.split(',') {
+		let name = name.trim().to_lowercase();
+		if !name.is_empty() && !parsed.contains(&name) {
+			parsed.push(name);
+		}
+	}
+	parsed
+}
+
 #[derive(Clone, Default)]
 pub struct AuthSecret {
 	pub key: [u8; 32],
 }
 
+/// What happens when a reindex is requested while the index is already being updated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexTriggerPolicy {
+	/// Refuse the request.
+	Reject,
+	/// Drop the request if the running update already covers it, queue it otherwise.
+	Coalesce,
+	/// Run one more update after the current one, no matter how many requests came in.
+	#[default]
+	Queue,
+}
+
+impl IndexTriggerPolicy {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			IndexTriggerPolicy::Reject => "reject",
+			IndexTriggerPolicy::Coalesce => "coalesce",
+			IndexTriggerPolicy::Queue => "queue",
+		}
+	}
+}
+
+impl FromStr for IndexTriggerPolicy {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"reject" => Ok(IndexTriggerPolicy::Reject),
+			"coalesce" => Ok(IndexTriggerPolicy::Coalesce),
+			"queue" => Ok(IndexTriggerPolicy::Queue),
+			_ => Err(Error::IndexTriggerPolicyInvalid),
+		}
+	}
+}
+
+/// Image format thumbnails are encoded in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThumbnailFormat {
+	#[default]
+	Jpeg,
+	Png,
+}
+
+impl ThumbnailFormat {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			ThumbnailFormat::Jpeg => "jpeg",
+			ThumbnailFormat::Png => "png",
+		}
+	}
+}
+
+impl FromStr for ThumbnailFormat {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"jpeg" => Ok(ThumbnailFormat::Jpeg),
+			"png" => Ok(ThumbnailFormat::Png),
+			_ => Err(Error::ThumbnailFormatInvalid),
+		}
+	}
+}
+
 #[derive(Debug, Queryable)]
 struct MiscSettings {
-	id: i32,
 	auth_secret: Vec<u8>,
 	index_sleep_duration_seconds: i32,
 	index_album_art_pattern: String,
+	index_start_delay_seconds: i32,
+	unknown_artist_label: String,
+	normalize_path_separators: i32,
+	index_trigger_policy: String,
+	index_max_tag_length: i32,
+	auth_max_failed_attempts: i32,
+	auth_failure_window_seconds: i32,
+	auth_realm: String,
+	cors_allowed_origins: String,
+	session_lifetime_seconds: i32,
+	thumbnail_cache_max_size_bytes: i64,
+	index_audio_extensions: String,
+	follow_symlinks: i32,
+	thumbnail_format: String,
+	thumbnail_quality: i32,
+	index_album_art_names: String,
+	settings_revision: i64,
 }
 
 #[derive(Debug)]
@@ -24,10 +143,44 @@ pub struct Settings {
 	auth_secret: Vec<u8>,
 	pub reindex_every_n_seconds: i32,
 	pub album_art_pattern: String,
+	pub index_start_delay_seconds: i32,
+	pub unknown_artist_label: String,
+	pub normalize_path_separators: bool,
+	pub index_trigger_policy: IndexTriggerPolicy,
+	pub index_max_tag_length: i32,
+	pub auth_max_failed_attempts: i32,
+	pub auth_failure_window_seconds: i32,
+	pub auth_realm: String,
+	pub cors_allowed_origins: Vec<String>,
+	pub session_lifetime_seconds: i32,
+	pub thumbnail_cache_max_size_bytes: i64,
+	pub index_audio_extensions: Vec<String>,
+	pub follow_symlinks: bool,
+	pub thumbnail_format: ThumbnailFormat,
+	pub thumbnail_quality: i32,
+	pub album_art_names: Vec<String>,
+	// Incremented every time settings are amended
+	pub revision: i64,
 }
 
 #[derive(Debug, Default, Deserialize)]
 pub struct NewSettings {
 	pub reindex_every_n_seconds: Option<i32>,
 	pub album_art_pattern: Option<String>,
+	pub index_start_delay_seconds: Option<i32>,
+	pub unknown_artist_label: Option<String>,
+	pub normalize_path_separators: Option<bool>,
+	pub index_trigger_policy: Option<IndexTriggerPolicy>,
+	pub index_max_tag_length: Option<i32>,
+	pub auth_max_failed_attempts: Option<i32>,
+	pub auth_failure_window_seconds: Option<i32>,
+	pub auth_realm: Option<String>,
+	pub cors_allowed_origins: Option<Vec<String>>,
+	pub session_lifetime_seconds: Option<i32>,
+	pub thumbnail_cache_max_size_bytes: Option<i64>,
+	pub index_audio_extensions: Option<Vec<String>>,
+	pub follow_symlinks: Option<bool>,
+	pub thumbnail_format: Option<ThumbnailFormat>,
+	pub thumbnail_quality: Option<i32>,
+	pub album_art_names: Option<Vec<String>>,
 }
@@ -0,0 +1,216 @@
+use diesel;
+use diesel::prelude::*;
+use diesel::sql_types;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::*;
+use crate::app::index::GROUPING_ARTIST_SQL;
+use crate::app::vfs;
+use crate::db::{play_history, users, DB};
+
+const MAX_TOP_ENTRIES: i64 = 100;
+
+/// Columns of `SongStats` for a `songs` table aliased as `s` joined with a `play_history` table
+/// aliased as `h`, grouped by song.
+const SONG_STATS_COLUMNS_SQL: &str = "s.id, s.path, s.parent, s.track_number, s.disc_number, s.title, s.artist, s.album_artist, s.year, s.album, s.artwork, s.duration, s.lyricist, s.composer, s.genre, s.label, s.disc_subtitle, s.file_size, s.file_modified, s.replay_gain_track, s.replay_gain_album, s.search_text, s.bitrate, COUNT(*) AS play_count, MAX(h.timestamp) AS last_played";
+
+#[derive(Clone)]
+pub struct Manager {
+	pub(super) db: DB,
+	pub(super) vfs_manager: vfs::Manager,
+}
+
+impl Manager {
+	pub fn new(db: DB, vfs_manager: vfs::Manager) -> Self {
+		Self { db, vfs_manager }
+	}
+
+	pub fn record_play(&self, username: &str, track: &Path) -> Result<(), Error> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let real_path = vfs
+			.virtual_to_real(track)
+			.map_err(|_| Error::VFSPathNotFound)?;
+		let timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs() as i64)
+			.unwrap_or_default();
+
+		let connection = self.db.connect()?;
+		let owner = self.get_user_id(&connection, username)?;
+		diesel::insert_into(play_history::table)
+			.values(&NewPlay {
+				owner,
+				path: real_path.to_string_lossy().into_owned(),
+				timestamp,
+			})
+			.execute(&connection)
+			.map_err(anyhow::Error::new)?;
+		Ok(())
+	}
+
+	pub fn get_listening_stats(
+		&self,
+		username: &str,
+		since: i64,
+		count: i64,
+	) -> Result<ListeningStats, Error> {
+		let count = count.clamp(0, MAX_TOP_ENTRIES);
+		let vfs = self.vfs_manager.get_vfs()?;
+		let connection = self.db.connect()?;
+		let owner = self.get_user_id(&connection, username)?;
+
+		// Not using Diesel because aggregates over a LEFT JOIN on a custom column are not expressible
+		let totals: Totals = diesel::sql_query(
+			r#"
+			SELECT COUNT(*) AS total_plays, COALESCE(SUM(s.duration), 0) AS total_listening_time
+			FROM play_history h
+			LEFT JOIN songs s ON h.path = s.path
+			WHERE h.owner = ? AND h.timestamp >= ?
+		"#,
+		)
+		.bind::<sql_types::Integer, _>(owner)
+		.bind::<sql_types::BigInt, _>(since)
+		.get_result(&connection)
+		.map_err(anyhow::Error::new)?;
+
+		let top_artists = diesel::sql_query(format!(
+			r#"
+			SELECT {} AS name, COUNT(*) AS play_count
+			FROM play_history h
+			INNER JOIN songs s ON h.path = s.path
+			WHERE h.owner = ? AND h.timestamp >= ?
+			GROUP BY 1
+			ORDER BY play_count DESC, 1
+			LIMIT ?
+		"#,
+			GROUPING_ARTIST_SQL
+		))
+		.bind::<sql_types::Integer, _>(owner)
+		.bind::<sql_types::BigInt, _>(since)
+		.bind::<sql_types::BigInt, _>(count)
+		.get_results(&connection)
+		.map_err(anyhow::Error::new)?;
+
+		let top_albums = diesel::sql_query(format!(
+			r#"
+			SELECT {} AS artist, s.album AS album, COUNT(*) AS play_count
+			FROM play_history h
+			INNER JOIN songs s ON h.path = s.path
+			WHERE h.owner = ? AND h.timestamp >= ? AND s.album IS NOT NULL
+			GROUP BY 1, 2
+			ORDER BY play_count DESC, 2
+			LIMIT ?
+		"#,
+			GROUPING_ARTIST_SQL
+		))
+		.bind::<sql_types::Integer, _>(owner)
+		.bind::<sql_types::BigInt, _>(since)
+		.bind::<sql_types::BigInt, _>(count)
+		.get_results(&connection)
+		.map_err(anyhow::Error::new)?;
+
+		let top_songs: Vec<SongStats> = diesel::sql_query(format!(
+			r#"
+			SELECT {}
+			FROM play_history h
+			INNER JOIN songs s ON h.path = s.path
+			WHERE h.owner = ? AND h.timestamp >= ?
+			GROUP BY s.path
+			ORDER BY play_count DESC, s.path
+			LIMIT ?
+		"#,
+			SONG_STATS_COLUMNS_SQL
+		))
+		.bind::<sql_types::Integer, _>(owner)
+		.bind::<sql_types::BigInt, _>(since)
+		.bind::<sql_types::BigInt, _>(count)
+		.get_results(&connection)
+		.map_err(anyhow::Error::new)?;
+
+		Ok(ListeningStats {
+			total_plays: totals.total_plays,
+			total_listening_time: totals.total_listening_time,
+			top_artists,
+			top_albums,
+			top_songs: virtualize_song_stats(top_songs, &vfs),
+		})
+	}
+
+	/// Songs the user played the most over their whole history, most played first. Ties go to
+	/// the song played most recently.
+	pub fn get_top_songs(&self, username: &str, count: i64) -> Result<Vec<SongStats>, Error> {
+		let count = count.clamp(0, MAX_TOP_ENTRIES);
+		let vfs = self.vfs_manager.get_vfs()?;
+		let connection = self.db.connect()?;
+		let owner = self.get_user_id(&connection, username)?;
+
+		let top_songs: Vec<SongStats> = diesel::sql_query(format!(
+			r#"
+			SELECT {}
+			FROM play_history h
+			INNER JOIN songs s ON h.path = s.path
+			WHERE h.owner = ?
+			GROUP BY s.path
+			ORDER BY play_count DESC, last_played DESC, s.path
+			LIMIT ?
+		"#,
+			SONG_STATS_COLUMNS_SQL
+		))
+		.bind::<sql_types::Integer, _>(owner)
+		.bind::<sql_types::BigInt, _>(count)
+		.get_results(&connection)
+		.map_err(anyhow::Error::new)?;
+
+		Ok(virtualize_song_stats(top_songs, &vfs))
+	}
+
+	pub(super) fn get_user_id(
+		&self,
+		connection: &SqliteConnection,
+		username: &str,
+	) -> Result<i32, Error> {
+		use self::users::dsl::*;
+		users
+			.filter(name.eq(username))
+			.select(id)
+			.first(connection)
+			.optional()
+			.map_err(anyhow::Error::new)?
+			.ok_or(Error::UserNotFound)
+	}
+}
+
+fn virtualize_song_stats(songs: Vec<SongStats>, vfs: &vfs::VFS) -> Vec<SongStats> {
+	songs
+		.into_iter()
+		.filter_map(|s| {
+			let SongStats {
+				song,
+				play_count,
+				last_played,
+			} = s;
+			song.virtualize(vfs).map(|song| SongStats {
+				song,
+				play_count,
+				last_played,
+			})
+		})
+		.collect()
+}
+
+#[derive(QueryableByName)]
+struct Totals {
+	#[sql_type = "sql_types::BigInt"]
+	total_plays: i64,
+	#[sql_type = "sql_types::BigInt"]
+	total_listening_time: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "play_history"]
+struct NewPlay {
+	owner: i32,
+	path: String,
+	timestamp: i64,
+}
@@ -0,0 +1,166 @@
+use diesel;
+use diesel::prelude::*;
+use diesel::sql_types;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::*;
+use crate::app::index::GROUPING_ARTIST_SQL;
+use crate::db::songs;
+
+const MAX_MIX_SIZE: i64 = 200;
+const MIX_SEED_ARTISTS: i64 = 10;
+const MIX_SEED_GENRES: i64 = 5;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+// Largest prime below 2^31, song ids are scrambled modulo this value to sample discoveries
+const MIX_ORDER_MODULUS: i64 = 2_147_483_647;
+
+#[derive(QueryableByName)]
+struct MixCandidate {
+	#[sql_type = "sql_types::Text"]
+	path: String,
+}
+
+impl Manager {
+	// Mixes songs from the user's most played artists with unplayed songs from their favorite
+	// genres. Only plays from previous days are considered and the selection is seeded with the
+	// current day, so the mix stays the same until the next day.
+	pub fn get_daily_mix(
+		&self,
+		username: &str,
+		count: i64,
+		discovery: f32,
+	) -> Result<Vec<Song>, Error> {
+		let count = count.clamp(0, MAX_MIX_SIZE) as usize;
+		let discovery = discovery.clamp(0.0, 1.0);
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs() as i64)
+			.unwrap_or_default();
+		let day = now / SECONDS_PER_DAY;
+		let day_start = day * SECONDS_PER_DAY;
+
+		let vfs = self.vfs_manager.get_vfs()?;
+		let connection = self.db.connect()?;
+		let owner = self.get_user_id(&connection, username)?;
+
+		let top_artists = format!(
+			r#"
+			SELECT {}
+			FROM play_history h
+			INNER JOIN songs s ON h.path = s.path
+			WHERE h.owner = ? AND h.timestamp < ?
+			GROUP BY 1
+			ORDER BY COUNT(*) DESC, 1
+			LIMIT ?
+		"#,
+			GROUPING_ARTIST_SQL
+		);
+
+		let top_genres = r#"
+			SELECT s.genre
+			FROM play_history h
+			INNER JOIN songs s ON h.path = s.path
+			WHERE h.owner = ? AND h.timestamp < ? AND s.genre IS NOT NULL
+			GROUP BY 1
+			ORDER BY COUNT(*) DESC, 1
+			LIMIT ?
+		"#;
+
+		let familiar: Vec<MixCandidate> = diesel::sql_query(format!(
+			r#"
+			SELECT s.path AS path
+			FROM songs s
+			WHERE {} IN ({})
+			ORDER BY s.path
+		"#,
+			GROUPING_ARTIST_SQL, top_artists
+		))
+		.bind::<sql_types::Integer, _>(owner)
+		.bind::<sql_types::BigInt, _>(day_start)
+		.bind::<sql_types::BigInt, _>(MIX_SEED_ARTISTS)
+		.get_results(&connection)
+		.map_err(anyhow::Error::new)?;
+
+		let mut hasher = DefaultHasher::new();
+		username.hash(&mut hasher);
+		day.hash(&mut hasher);
+		let mut rng = StdRng::seed_from_u64(hasher.finish());
+
+		// When familiar songs run short, discoveries make up for it
+		let num_discoveries = (count as f32 * discovery).round() as usize;
+		let num_discoveries = num_discoveries.max(count.saturating_sub(familiar.len()));
+
+		// Discoveries are sampled by the database, in an order derived from the seed
+		let multiplier = rng.gen_range(1, MIX_ORDER_MODULUS);
+		let offset = rng.gen_range(0, MIX_ORDER_MODULUS);
+
+		// Users without any genre preference discover songs from the whole collection
+		let discoveries: Vec<MixCandidate> = diesel::sql_query(format!(
+			r#"
+			SELECT s.path AS path
+			FROM songs s
+			WHERE {} NOT IN ({})
+			AND NOT EXISTS (
+				SELECT 1 FROM play_history h
+				WHERE h.owner = ? AND h.path = s.path AND h.timestamp < ?
+			)
+			AND (NOT EXISTS ({}) OR s.genre IN ({}))
+			ORDER BY (s.id * ? + ?) % ?, s.path
+			LIMIT ?
+		"#,
+			GROUPING_ARTIST_SQL, top_artists, top_genres, top_genres
+		))
+		.bind::<sql_types::Integer, _>(owner)
+		.bind::<sql_types::BigInt, _>(day_start)
+		.bind::<sql_types::BigInt, _>(MIX_SEED_ARTISTS)
+		.bind::<sql_types::Integer, _>(owner)
+		.bind::<sql_types::BigInt, _>(day_start)
+		.bind::<sql_types::Integer, _>(owner)
+		.bind::<sql_types::BigInt, _>(day_start)
+		.bind::<sql_types::BigInt, _>(MIX_SEED_GENRES)
+		.bind::<sql_types::Integer, _>(owner)
+		.bind::<sql_types::BigInt, _>(day_start)
+		.bind::<sql_types::BigInt, _>(MIX_SEED_GENRES)
+		.bind::<sql_types::BigInt, _>(multiplier)
+		.bind::<sql_types::BigInt, _>(offset)
+		.bind::<sql_types::BigInt, _>(MIX_ORDER_MODULUS)
+		.bind::<sql_types::BigInt, _>(num_discoveries as i64)
+		.get_results(&connection)
+		.map_err(anyhow::Error::new)?;
+
+		// When discoveries run short, familiar songs make up for it
+		let num_familiar = count.saturating_sub(discoveries.len());
+
+		let mut familiar: Vec<String> = familiar.into_iter().map(|c| c.path).collect();
+		familiar.shuffle(&mut rng);
+
+		let mut mix: Vec<String> = familiar
+			.into_iter()
+			.take(num_familiar)
+			.chain(discoveries.into_iter().map(|c| c.path))
+			.collect();
+		mix.shuffle(&mut rng);
+
+		let positions: HashMap<&str, usize> = mix
+			.iter()
+			.enumerate()
+			.map(|(i, p)| (p.as_str(), i))
+			.collect();
+		let mut real_songs: Vec<Song> = songs::table
+			.filter(songs::path.eq_any(&mix))
+			.load(&connection)
+			.map_err(anyhow::Error::new)?;
+		real_songs.sort_by_key(|s| positions.get(s.path.as_str()).copied());
+
+		Ok(real_songs
+			.into_iter()
+			.filter_map(|s| s.virtualize(&vfs))
+			.collect())
+	}
+}
@@ -0,0 +1,51 @@
+use diesel::sql_types;
+use serde::{Deserialize, Serialize};
+
+use crate::app::index::Song;
+
+mod error;
+mod manager;
+mod mix;
+#[cfg(test)]
+mod test;
+
+pub use error::*;
+pub use manager::*;
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ListeningStats {
+	pub total_plays: i64,
+	pub total_listening_time: i64,
+	pub top_artists: Vec<ArtistStats>,
+	pub top_albums: Vec<AlbumStats>,
+	pub top_songs: Vec<SongStats>,
+}
+
+#[derive(Debug, PartialEq, QueryableByName, Serialize, Deserialize)]
+pub struct ArtistStats {
+	#[sql_type = "sql_types::Text"]
+	pub name: String,
+	#[sql_type = "sql_types::BigInt"]
+	pub play_count: i64,
+}
+
+#[derive(Debug, PartialEq, QueryableByName, Serialize, Deserialize)]
+pub struct AlbumStats {
+	#[sql_type = "sql_types::Text"]
+	pub artist: String,
+	#[sql_type = "sql_types::Text"]
+	pub album: String,
+	#[sql_type = "sql_types::BigInt"]
+	pub play_count: i64,
+}
+
+#[derive(Debug, PartialEq, QueryableByName, Serialize, Deserialize)]
+pub struct SongStats {
+	#[diesel(embed)]
+	pub song: Song,
+	#[sql_type = "sql_types::BigInt"]
+	pub play_count: i64,
+	/// Unix timestamp (in seconds) of the most recent play.
+	#[sql_type = "sql_types::BigInt"]
+	pub last_played: i64,
+}
@@ -0,0 +1,316 @@
+use diesel::prelude::*;
+use std::path::PathBuf;
+
+use crate::app::{settings, test, user};
+use crate::test_name;
+
+const TEST_USER: &str = "test_user";
+const TEST_PASSWORD: &str = "password";
+const TEST_MOUNT_NAME: &str = "root";
+
+#[test]
+fn empty_history_returns_zeroed_stats() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.build();
+
+	let stats = ctx
+		.stats_manager
+		.get_listening_stats(TEST_USER, 0, 10)
+		.unwrap();
+	assert_eq!(stats.total_plays, 0);
+	assert_eq!(stats.total_listening_time, 0);
+	assert!(stats.top_artists.is_empty());
+	assert!(stats.top_albums.is_empty());
+	assert!(stats.top_songs.is_empty());
+}
+
+#[test]
+fn deleted_user_plays_are_not_inherited() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	ctx.stats_manager.record_play(TEST_USER, &path).unwrap();
+	ctx.user_manager.delete(TEST_USER).unwrap();
+
+	// The new user may be given the id of the deleted one
+	ctx.user_manager
+		.create(&user::NewUser {
+			name: "other_user".to_owned(),
+			password: TEST_PASSWORD.to_owned(),
+			admin: false,
+		})
+		.unwrap();
+	let stats = ctx
+		.stats_manager
+		.get_listening_stats("other_user", 0, 10)
+		.unwrap();
+	assert_eq!(stats.total_plays, 0);
+	assert!(ctx
+		.stats_manager
+		.get_top_songs("other_user", 10)
+		.unwrap()
+		.is_empty());
+}
+
+#[test]
+fn listening_stats_golden_path() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let candlelight: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let hunted: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "05 - Hunted.mp3"]
+		.iter()
+		.collect();
+	ctx.stats_manager
+		.record_play(TEST_USER, &candlelight)
+		.unwrap();
+	ctx.stats_manager
+		.record_play(TEST_USER, &candlelight)
+		.unwrap();
+	ctx.stats_manager.record_play(TEST_USER, &hunted).unwrap();
+
+	let stats = ctx
+		.stats_manager
+		.get_listening_stats(TEST_USER, 0, 10)
+		.unwrap();
+	assert_eq!(stats.total_plays, 3);
+	assert_eq!(stats.top_artists.len(), 1);
+	assert_eq!(stats.top_artists[0].name, "Khemmis");
+	assert_eq!(stats.top_artists[0].play_count, 3);
+	assert_eq!(stats.top_albums.len(), 1);
+	assert_eq!(stats.top_albums[0].album, "Hunted");
+	assert_eq!(stats.top_songs.len(), 2);
+	assert_eq!(
+		stats.top_songs[0].song.path,
+		candlelight.to_string_lossy().as_ref()
+	);
+	assert_eq!(stats.top_songs[0].play_count, 2);
+
+	let stats = ctx
+		.stats_manager
+		.get_listening_stats(TEST_USER, 0, 1)
+		.unwrap();
+	assert_eq!(stats.top_songs.len(), 1);
+}
+
+#[test]
+fn listening_stats_ignores_plays_before_cutoff() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	ctx.stats_manager.record_play(TEST_USER, &path).unwrap();
+
+	let stats = ctx
+		.stats_manager
+		.get_listening_stats(TEST_USER, i64::MAX, 10)
+		.unwrap();
+	assert_eq!(stats.total_plays, 0);
+	assert!(stats.top_songs.is_empty());
+}
+
+#[test]
+fn listening_stats_group_songs_without_artist_under_unknown_label() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	let real_path: PathBuf = ["test-data", "small-collection", "untagged.mp3"]
+		.iter()
+		.collect();
+	let real_path = real_path.to_string_lossy().into_owned();
+	diesel::sql_query("INSERT INTO songs (path, parent, album) VALUES (?, ?, 'Untitled')")
+		.bind::<diesel::sql_types::Text, _>(&real_path)
+		.bind::<diesel::sql_types::Text, _>("test-data/small-collection")
+		.execute(&ctx.db.connect().unwrap())
+		.unwrap();
+
+	let virtual_path: PathBuf = [TEST_MOUNT_NAME, "untagged.mp3"].iter().collect();
+	ctx.stats_manager
+		.record_play(TEST_USER, &virtual_path)
+		.unwrap();
+
+	let stats = ctx
+		.stats_manager
+		.get_listening_stats(TEST_USER, 0, 10)
+		.unwrap();
+	assert_eq!(stats.top_artists[0].name, "Unknown Artist");
+	assert_eq!(stats.top_albums[0].artist, "Unknown Artist");
+
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			unknown_artist_label: Some("Anonymous".to_owned()),
+			..Default::default()
+		})
+		.unwrap();
+
+	let stats = ctx
+		.stats_manager
+		.get_listening_stats(TEST_USER, 0, 10)
+		.unwrap();
+	assert_eq!(stats.top_artists[0].name, "Anonymous");
+	assert_eq!(stats.top_albums[0].artist, "Anonymous");
+}
+
+#[test]
+fn daily_mix_is_stable_within_a_day() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let mix = ctx.stats_manager.get_daily_mix(TEST_USER, 8, 0.3).unwrap();
+	assert_eq!(mix.len(), 8);
+	let mut paths: Vec<&str> = mix.iter().map(|s| s.path.as_str()).collect();
+	paths.sort();
+	paths.dedup();
+	assert_eq!(paths.len(), 8);
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	ctx.stats_manager.record_play(TEST_USER, &path).unwrap();
+
+	let same_mix = ctx.stats_manager.get_daily_mix(TEST_USER, 8, 0.3).unwrap();
+	assert_eq!(mix, same_mix);
+}
+
+#[test]
+fn daily_mix_favors_most_played_artists() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	// Plays from previous days are the only ones taken into account
+	let real_path: PathBuf = [
+		"test-data",
+		"small-collection",
+		"Khemmis",
+		"Hunted",
+		"02 - Candlelight.mp3",
+	]
+	.iter()
+	.collect();
+	diesel::sql_query(
+		"INSERT INTO play_history (owner, path, timestamp) SELECT id, ?, 0 FROM users WHERE name = ?",
+	)
+	.bind::<diesel::sql_types::Text, _>(real_path.to_string_lossy().as_ref())
+	.bind::<diesel::sql_types::Text, _>(TEST_USER)
+	.execute(&ctx.db.connect().unwrap())
+	.unwrap();
+
+	let mix = ctx.stats_manager.get_daily_mix(TEST_USER, 5, 0.0).unwrap();
+	assert_eq!(mix.len(), 5);
+	assert!(mix.iter().all(|s| s.artist.as_deref() == Some("Khemmis")));
+
+	// No unplayed song shares a genre with Khemmis, so familiar songs make up for discoveries
+	let mix = ctx.stats_manager.get_daily_mix(TEST_USER, 5, 1.0).unwrap();
+	assert_eq!(mix.len(), 5);
+	assert!(mix.iter().all(|s| s.artist.as_deref() == Some("Khemmis")));
+}
+
+#[test]
+fn top_songs_are_ordered_by_play_count() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.user("other_user", TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let candlelight: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let hunted: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "05 - Hunted.mp3"]
+		.iter()
+		.collect();
+	ctx.stats_manager.record_play(TEST_USER, &hunted).unwrap();
+	ctx.stats_manager
+		.record_play(TEST_USER, &candlelight)
+		.unwrap();
+	ctx.stats_manager
+		.record_play(TEST_USER, &candlelight)
+		.unwrap();
+	for _ in 0..3 {
+		ctx.stats_manager
+			.record_play("other_user", &hunted)
+			.unwrap();
+	}
+
+	let top_songs = ctx.stats_manager.get_top_songs(TEST_USER, 10).unwrap();
+	assert_eq!(top_songs.len(), 2);
+	assert_eq!(
+		top_songs[0].song.path,
+		candlelight.to_string_lossy().as_ref()
+	);
+	assert_eq!(top_songs[0].play_count, 2);
+	assert!(top_songs[0].last_played > 0);
+	assert_eq!(top_songs[1].song.path, hunted.to_string_lossy().as_ref());
+	assert_eq!(top_songs[1].play_count, 1);
+
+	let top_songs = ctx.stats_manager.get_top_songs(TEST_USER, 1).unwrap();
+	assert_eq!(top_songs.len(), 1);
+}
+
+#[test]
+fn top_songs_break_ties_by_most_recent_play() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USER, TEST_PASSWORD, false)
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let candlelight: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let hunted: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "05 - Hunted.mp3"]
+		.iter()
+		.collect();
+	ctx.stats_manager.record_play(TEST_USER, &hunted).unwrap();
+	ctx.stats_manager
+		.record_play(TEST_USER, &candlelight)
+		.unwrap();
+	{
+		use crate::db::play_history::dsl::*;
+		let connection = ctx.db.connect().unwrap();
+		diesel::update(play_history.filter(path.like("%Hunted.mp3")))
+			.set(timestamp.eq(0))
+			.execute(&connection)
+			.unwrap();
+	}
+
+	let top_songs = ctx.stats_manager.get_top_songs(TEST_USER, 10).unwrap();
+	assert_eq!(top_songs.len(), 2);
+	assert_eq!(
+		top_songs[0].song.path,
+		candlelight.to_string_lossy().as_ref()
+	);
+	assert_eq!(top_songs[1].last_played, 0);
+}
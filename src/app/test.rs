@@ -1,17 +1,26 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
-use crate::app::{config, ddns, index::Index, lastfm, playlist, settings, thumbnail, user, vfs};
-use crate::db::DB;
+use crate::app::{
+	artist, config, ddns,
+	index::{self, Index},
+	lastfm, listenbrainz, maintenance, metrics, playlist, settings, stats, thumbnail, user, vfs,
+};
+use crate::db::{self, DB};
 use crate::test::*;
 
 pub struct Context {
 	pub db: DB,
 	pub index: Index,
+	pub artist_manager: artist::Manager,
 	pub config_manager: config::Manager,
 	pub ddns_manager: ddns::Manager,
 	pub lastfm_manager: lastfm::Manager,
+	pub listenbrainz_manager: listenbrainz::Manager,
+	pub maintenance_manager: maintenance::Manager,
 	pub playlist_manager: playlist::Manager,
 	pub settings_manager: settings::Manager,
+	pub stats_manager: stats::Manager,
 	pub thumbnail_manager: thumbnail::Manager,
 	pub user_manager: user::Manager,
 	pub vfs_manager: vfs::Manager,
@@ -20,6 +29,8 @@ pub struct Context {
 
 pub struct ContextBuilder {
 	config: config::Config,
+	insert_buffer_size: usize,
+	db_busy_timeout: Duration,
 	pub test_directory: PathBuf,
 }
 
@@ -28,9 +39,37 @@ impl ContextBuilder {
 		Self {
 			test_directory: prepare_test_directory(&test_name),
 			config: config::Config::default(),
+			insert_buffer_size: index::DEFAULT_INSERT_BUFFER_SIZE,
+			db_busy_timeout: db::DEFAULT_BUSY_TIMEOUT,
 		}
 	}
 
+	pub fn insert_buffer_size(mut self, size: usize) -> Self {
+		self.insert_buffer_size = size;
+		self
+	}
+
+	pub fn db_busy_timeout(mut self, timeout: Duration) -> Self {
+		self.db_busy_timeout = timeout;
+		self
+	}
+
+	pub fn thumbnail_cache_max_size(mut self, max_size: i64) -> Self {
+		self.config
+			.settings
+			.get_or_insert_with(settings::NewSettings::default)
+			.thumbnail_cache_max_size_bytes = Some(max_size);
+		self
+	}
+
+	pub fn reindex_every_n_seconds(mut self, seconds: i32) -> Self {
+		self.config
+			.settings
+			.get_or_insert_with(settings::NewSettings::default)
+			.reindex_every_n_seconds = Some(seconds);
+		self
+	}
+
 	pub fn user(mut self, name: &str, password: &str, is_admin: bool) -> Self {
 		self.config
 			.users
@@ -50,6 +89,7 @@ impl ContextBuilder {
 			.push(vfs::MountDir {
 				name: name.to_owned(),
 				source: source.to_owned(),
+				scan_interval_seconds: None,
 			});
 		self
 	}
@@ -58,10 +98,10 @@ impl ContextBuilder {
 		let cache_output_dir = self.test_directory.join("cache");
 		let db_path = self.test_directory.join("db.sqlite");
 
-		let db = DB::new(&db_path).unwrap();
+		let db = DB::new(&db_path, self.db_busy_timeout).unwrap();
 		let settings_manager = settings::Manager::new(db.clone());
 		let auth_secret = settings_manager.get_auth_secret().unwrap();
-		let user_manager = user::Manager::new(db.clone(), auth_secret);
+		let user_manager = user::Manager::new(db.clone(), auth_secret, settings_manager.clone());
 		let vfs_manager = vfs::Manager::new(db.clone());
 		let ddns_manager = ddns::Manager::new(db.clone());
 		let config_manager = config::Manager::new(
@@ -70,21 +110,35 @@ impl ContextBuilder {
 			vfs_manager.clone(),
 			ddns_manager.clone(),
 		);
-		let index = Index::new(db.clone(), vfs_manager.clone(), settings_manager.clone());
+		let index = Index::new(
+			db.clone(),
+			vfs_manager.clone(),
+			settings_manager.clone(),
+			metrics::Manager::new(Vec::new()),
+			self.insert_buffer_size,
+		);
 		let playlist_manager = playlist::Manager::new(db.clone(), vfs_manager.clone());
-		let thumbnail_manager = thumbnail::Manager::new(cache_output_dir);
+		let artist_manager = artist::Manager::new(db.clone(), cache_output_dir.join("artists"));
+		let stats_manager = stats::Manager::new(db.clone(), vfs_manager.clone());
+		let thumbnail_manager = thumbnail::Manager::new(cache_output_dir, settings_manager.clone());
 		let lastfm_manager = lastfm::Manager::new(index.clone(), user_manager.clone());
+		let listenbrainz_manager = listenbrainz::Manager::new(index.clone(), user_manager.clone());
+		let maintenance_manager = maintenance::Manager::new(db.clone());
 
 		config_manager.apply(&self.config).unwrap();
 
 		Context {
 			db,
 			index,
+			artist_manager,
 			config_manager,
 			ddns_manager,
 			lastfm_manager,
+			listenbrainz_manager,
+			maintenance_manager,
 			playlist_manager,
 			settings_manager,
+			stats_manager,
 			thumbnail_manager,
 			user_manager,
 			vfs_manager,
@@ -0,0 +1,11 @@
+use std::time::Duration;
+
+use crate::app::settings;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("Too many failed attempts, retry in {} seconds", .0.as_secs())]
+	TooManyAttempts(Duration),
+	#[error(transparent)]
+	Settings(#[from] settings::Error),
+}
@@ -0,0 +1,76 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::*;
+use crate::app::settings;
+
+/// Identifies who failed an attempt. Client IPs and usernames are tracked separately so that
+/// an attacker can neither hammer one account from many addresses nor many accounts from one
+/// address.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Key {
+	ClientIp(String),
+	Username(String),
+}
+
+/// Counts failed login attempts over a sliding window. Once a key reaches the configured number
+/// of failures, further attempts are refused until the oldest failure leaves the window.
+#[derive(Clone)]
+pub struct Manager {
+	settings_manager: settings::Manager,
+	failures: Arc<Mutex<HashMap<Key, VecDeque<Instant>>>>,
+}
+
+impl Manager {
+	pub fn new(settings_manager: settings::Manager) -> Self {
+		Self {
+			settings_manager,
+			failures: Arc::new(Mutex::new(HashMap::new())),
+		}
+	}
+
+	/// Fails with the time left before the next attempt is allowed if `key` is throttled.
+	pub fn check(&self, key: &Key) -> Result<(), Error> {
+		let max_failed_attempts = self.settings_manager.get_auth_max_failed_attempts()?;
+		let window = self.settings_manager.get_auth_failure_window()?;
+		let now = Instant::now();
+
+		let mut failures = self.failures.lock().unwrap();
+		failures.retain(|_, attempts| {
+			while let Some(oldest) = attempts.front() {
+				if now.duration_since(*oldest) < window {
+					break;
+				}
+				attempts.pop_front();
+			}
+			!attempts.is_empty()
+		});
+
+		let max_failed_attempts = match max_failed_attempts {
+			Some(n) => n,
+			None => return Ok(()),
+		};
+		match failures.get(key) {
+			Some(attempts) if attempts.len() >= max_failed_attempts => {
+				let oldest = attempts[attempts.len() - max_failed_attempts];
+				let remaining = window - now.duration_since(oldest);
+				let retry_after = Duration::from_secs(remaining.as_secs() + 1);
+				Err(Error::TooManyAttempts(retry_after))
+			}
+			_ => Ok(()),
+		}
+	}
+
+	pub fn record_failure(&self, key: &Key) {
+		let mut failures = self.failures.lock().unwrap();
+		failures
+			.entry(key.clone())
+			.or_default()
+			.push_back(Instant::now());
+	}
+
+	pub fn reset(&self, key: &Key) {
+		self.failures.lock().unwrap().remove(key);
+	}
+}
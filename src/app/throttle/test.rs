@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use super::*;
+use crate::app::{settings, test};
+use crate::test_name;
+
+fn make_manager(ctx: &test::Context, max_failed_attempts: i32, window_seconds: i32) -> Manager {
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			auth_max_failed_attempts: Some(max_failed_attempts),
+			auth_failure_window_seconds: Some(window_seconds),
+			..Default::default()
+		})
+		.unwrap();
+	Manager::new(ctx.settings_manager.clone())
+}
+
+#[test]
+fn throttles_after_too_many_failures() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	let manager = make_manager(&ctx, 3, 600);
+	let key = Key::ClientIp("127.0.0.1".to_owned());
+
+	for _ in 0..3 {
+		assert!(manager.check(&key).is_ok());
+		manager.record_failure(&key);
+	}
+
+	match manager.check(&key) {
+		Err(Error::TooManyAttempts(retry_after)) => {
+			assert!(retry_after > Duration::from_secs(0));
+			assert!(retry_after <= Duration::from_secs(600));
+		}
+		_ => panic!("Expected throttling"),
+	}
+}
+
+#[test]
+fn keys_are_throttled_independently() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	let manager = make_manager(&ctx, 1, 600);
+	let ip = Key::ClientIp("127.0.0.1".to_owned());
+	let other_ip = Key::ClientIp("127.0.0.2".to_owned());
+	let username = Key::Username("127.0.0.1".to_owned());
+
+	manager.record_failure(&ip);
+	assert!(manager.check(&ip).is_err());
+	assert!(manager.check(&other_ip).is_ok());
+	assert!(manager.check(&username).is_ok());
+}
+
+#[test]
+fn reset_clears_failures() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	let manager = make_manager(&ctx, 1, 600);
+	let key = Key::Username("walter".to_owned());
+
+	manager.record_failure(&key);
+	assert!(manager.check(&key).is_err());
+	manager.reset(&key);
+	assert!(manager.check(&key).is_ok());
+}
+
+#[test]
+fn failures_expire_after_window() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	let manager = make_manager(&ctx, 1, 1);
+	let key = Key::Username("walter".to_owned());
+
+	manager.record_failure(&key);
+	assert!(manager.check(&key).is_err());
+	std::thread::sleep(Duration::from_millis(1100));
+	assert!(manager.check(&key).is_ok());
+}
+
+#[test]
+fn zero_max_attempts_disables_throttling() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	let manager = make_manager(&ctx, 0, 600);
+	let key = Key::Username("walter".to_owned());
+
+	for _ in 0..10 {
+		manager.record_failure(&key);
+	}
+	assert!(manager.check(&key).is_ok());
+}
@@ -18,7 +18,18 @@ pub fn generate_thumbnail(image_path: &Path, options: &Options) -> Result<Dynami
 	let is_almost_square = source_aspect_ratio > 0.8 && source_aspect_ratio < 1.2;
 
 	let mut final_image;
-	if is_almost_square && options.resize_if_almost_square {
+	if options.crop_to_square {
+		let side = cmp::min(source_width, source_height);
+		let out_dimension = cmp::min(out_dimension, side);
+		final_image = source_image
+			.crop_imm(
+				(source_width - side) / 2,
+				(source_height - side) / 2,
+				side,
+				side,
+			)
+			.thumbnail_exact(out_dimension, out_dimension);
+	} else if is_almost_square && options.resize_if_almost_square {
 		final_image = source_image.thumbnail_exact(out_dimension, out_dimension);
 	} else if options.pad_to_square {
 		let scaled_image = source_image.thumbnail(out_dimension, out_dimension);
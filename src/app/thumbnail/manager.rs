@@ -1,40 +1,103 @@
 use anyhow::*;
 use image::ImageOutputFormat;
+use log::error;
 use std::collections::hash_map::DefaultHasher;
 use std::fs::{self, File};
 use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
+use crate::app::settings::{self, ThumbnailFormat};
 use crate::app::thumbnail::*;
 
+// Encoder settings are part of the cache key, so that changing them never serves stale thumbnails
+#[derive(Hash)]
+struct Encoding {
+	format: ThumbnailFormat,
+	quality: u8,
+}
+
+impl Encoding {
+	fn extension(&self) -> &'static str {
+		match self.format {
+			ThumbnailFormat::Jpeg => "jpg",
+			ThumbnailFormat::Png => "png",
+		}
+	}
+
+	fn output_format(&self) -> ImageOutputFormat {
+		match self.format {
+			ThumbnailFormat::Jpeg => ImageOutputFormat::Jpeg(self.quality),
+			ThumbnailFormat::Png => ImageOutputFormat::Png,
+		}
+	}
+}
+
+const THUMBNAIL_EXTENSIONS: [&str; 2] = ["jpg", "png"];
+
+struct CachedThumbnail {
+	path: PathBuf,
+	size: u64,
+	last_used: SystemTime,
+}
+
 #[derive(Clone)]
 pub struct Manager {
 	thumbnails_dir_path: PathBuf,
+	settings_manager: settings::Manager,
+	// Total size of the thumbnails on disk, measured on the first write
+	cache_size: Arc<Mutex<Option<u64>>>,
 }
 
 impl Manager {
-	pub fn new(thumbnails_dir_path: PathBuf) -> Self {
+	pub fn new(thumbnails_dir_path: PathBuf, settings_manager: settings::Manager) -> Self {
 		Self {
 			thumbnails_dir_path,
+			settings_manager,
+			cache_size: Arc::new(Mutex::new(None)),
 		}
 	}
 
 	pub fn get_thumbnail(&self, image_path: &Path, thumbnailoptions: &Options) -> Result<PathBuf> {
-		match self.retrieve_thumbnail(image_path, thumbnailoptions) {
-			Some(path) => Ok(path),
-			None => self.create_thumbnail(image_path, thumbnailoptions),
+		let encoding = Encoding {
+			format: self.settings_manager.get_thumbnail_format()?,
+			quality: self.settings_manager.get_thumbnail_quality()?,
+		};
+		match self.retrieve_thumbnail(image_path, thumbnailoptions, &encoding) {
+			Some(path) => {
+				Manager::mark_used(&path);
+				Ok(path)
+			}
+			None => {
+				let path = self.create_thumbnail(image_path, thumbnailoptions, &encoding)?;
+				if let Err(e) = self.enforce_cache_limit(&path) {
+					error!("Could not trim thumbnail cache: {}", e);
+				}
+				Ok(path)
+			}
 		}
 	}
 
-	fn get_thumbnail_path(&self, image_path: &Path, thumbnailoptions: &Options) -> PathBuf {
-		let hash = Manager::hash(image_path, thumbnailoptions);
+	fn get_thumbnail_path(
+		&self,
+		image_path: &Path,
+		thumbnailoptions: &Options,
+		encoding: &Encoding,
+	) -> PathBuf {
+		let hash = Manager::hash(image_path, thumbnailoptions, encoding);
 		let mut thumbnail_path = self.thumbnails_dir_path.clone();
-		thumbnail_path.push(format!("{}.jpg", hash.to_string()));
+		thumbnail_path.push(format!("{}.{}", hash, encoding.extension()));
 		thumbnail_path
 	}
 
-	fn retrieve_thumbnail(&self, image_path: &Path, thumbnailoptions: &Options) -> Option<PathBuf> {
-		let path = self.get_thumbnail_path(image_path, thumbnailoptions);
+	fn retrieve_thumbnail(
+		&self,
+		image_path: &Path,
+		thumbnailoptions: &Options,
+		encoding: &Encoding,
+	) -> Option<PathBuf> {
+		let path = self.get_thumbnail_path(image_path, thumbnailoptions, encoding);
 		if path.exists() {
 			Some(path)
 		} else {
@@ -42,21 +105,109 @@ impl Manager {
 		}
 	}
 
-	fn create_thumbnail(&self, image_path: &Path, thumbnailoptions: &Options) -> Result<PathBuf> {
+	fn create_thumbnail(
+		&self,
+		image_path: &Path,
+		thumbnailoptions: &Options,
+		encoding: &Encoding,
+	) -> Result<PathBuf> {
 		let thumbnail = generate_thumbnail(image_path, thumbnailoptions)?;
-		let quality = 80;
 
 		fs::create_dir_all(&self.thumbnails_dir_path)?;
-		let path = self.get_thumbnail_path(image_path, thumbnailoptions);
+		let path = self.get_thumbnail_path(image_path, thumbnailoptions, encoding);
 		let mut out_file = File::create(&path)?;
-		thumbnail.write_to(&mut out_file, ImageOutputFormat::Jpeg(quality))?;
+		thumbnail.write_to(&mut out_file, encoding.output_format())?;
 		Ok(path)
 	}
 
-	fn hash(path: &Path, thumbnailoptions: &Options) -> u64 {
+	// Access times are often not recorded, so cache hits bump the modification time instead
+	fn mark_used(path: &Path) {
+		let bumped = File::options()
+			.write(true)
+			.open(path)
+			.and_then(|f| f.set_modified(SystemTime::now()));
+		if let Err(e) = bumped {
+			error!("Could not update last use of {:?}: {}", path, e);
+		}
+	}
+
+	fn enforce_cache_limit(&self, new_thumbnail: &Path) -> Result<()> {
+		let max_size = match self.settings_manager.get_thumbnail_cache_max_size()? {
+			Some(s) => s,
+			None => return Ok(()),
+		};
+
+		let mut cache_size = self
+			.cache_size
+			.lock()
+			.map_err(|_| anyhow!("Thumbnail cache size lock is poisoned"))?;
+		let size = match *cache_size {
+			Some(size) => size + fs::metadata(new_thumbnail)?.len(),
+			None => self.list_thumbnails()?.iter().map(|t| t.size).sum(),
+		};
+		*cache_size = Some(if size > max_size {
+			self.evict(max_size, new_thumbnail)?
+		} else {
+			size
+		});
+		Ok(())
+	}
+
+	// Deletes the least recently used thumbnails until the cache fits within max_size, and
+	// returns the resulting cache size
+	fn evict(&self, max_size: u64, keep: &Path) -> Result<u64> {
+		let mut thumbnails = self.list_thumbnails()?;
+		let mut size: u64 = thumbnails.iter().map(|t| t.size).sum();
+		thumbnails.sort_by_key(|t| t.last_used);
+		for thumbnail in thumbnails {
+			if size <= max_size {
+				break;
+			}
+			if thumbnail.path == keep {
+				continue;
+			}
+			match fs::remove_file(&thumbnail.path) {
+				Ok(()) => size -= thumbnail.size,
+				Err(e) => error!("Could not evict thumbnail {:?}: {}", thumbnail.path, e),
+			}
+		}
+		Ok(size)
+	}
+
+	fn list_thumbnails(&self) -> Result<Vec<CachedThumbnail>> {
+		let mut thumbnails = Vec::new();
+		for entry in fs::read_dir(&self.thumbnails_dir_path)? {
+			let entry = entry?;
+			let path = entry.path();
+			if path
+				.extension()
+				.is_none_or(|e| !THUMBNAIL_EXTENSIONS.iter().any(|t| e == *t))
+			{
+				continue;
+			}
+			let metadata = entry.metadata()?;
+			if !metadata.is_file() {
+				continue;
+			}
+			thumbnails.push(CachedThumbnail {
+				path,
+				size: metadata.len(),
+				last_used: metadata.modified()?,
+			});
+		}
+		Ok(thumbnails)
+	}
+
+	fn hash(path: &Path, thumbnailoptions: &Options, encoding: &Encoding) -> u64 {
 		let mut hasher = DefaultHasher::new();
 		path.hash(&mut hasher);
+		// Replacing an image invalidates its thumbnails
+		if let Ok(metadata) = fs::metadata(path) {
+			metadata.len().hash(&mut hasher);
+			metadata.modified().ok().hash(&mut hasher);
+		}
 		thumbnailoptions.hash(&mut hasher);
+		encoding.hash(&mut hasher);
 		hasher.finish()
 	}
 }
@@ -2,6 +2,8 @@ mod generate;
 mod manager;
 mod options;
 mod read;
+#[cfg(test)]
+mod test;
 
 pub use generate::*;
 pub use manager::*;
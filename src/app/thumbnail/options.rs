@@ -3,6 +3,7 @@ pub struct Options {
 	pub max_dimension: Option<u32>,
 	pub resize_if_almost_square: bool,
 	pub pad_to_square: bool,
+	pub crop_to_square: bool,
 }
 
 impl Default for Options {
@@ -11,6 +12,7 @@ impl Default for Options {
 			max_dimension: Some(400),
 			resize_if_almost_square: true,
 			pad_to_square: true,
+			crop_to_square: false,
 		}
 	}
 }
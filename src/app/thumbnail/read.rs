@@ -1,5 +1,5 @@
 use anyhow::*;
-use image::DynamicImage;
+use image::{DynamicImage, ImageFormat};
 use std::path::Path;
 
 use crate::utils;
@@ -7,30 +7,55 @@ use crate::utils::AudioFormat;
 
 pub fn read(image_path: &Path) -> Result<DynamicImage> {
 	match utils::get_audio_format(image_path) {
-		Some(AudioFormat::AIFF) => read_aiff(image_path),
-		Some(AudioFormat::APE) => read_ape(image_path),
-		Some(AudioFormat::FLAC) => read_flac(image_path),
-		Some(AudioFormat::MP3) => read_mp3(image_path),
-		Some(AudioFormat::MP4) => read_mp4(image_path),
-		Some(AudioFormat::MPC) => read_ape(image_path),
-		Some(AudioFormat::OGG) => read_vorbis(image_path),
-		Some(AudioFormat::OPUS) => read_opus(image_path),
-		Some(AudioFormat::WAVE) => read_wave(image_path),
+		Some(_) => Ok(image::load_from_memory(&read_embedded(image_path)?)?),
 		None => Ok(image::open(image_path)?),
 	}
 }
 
-fn read_ape(_: &Path) -> Result<DynamicImage> {
+/// Returns the encoded bytes of the artwork embedded in an audio file, as they appear in its tags.
+pub fn read_embedded(path: &Path) -> Result<Vec<u8>> {
+	match utils::get_audio_format(path) {
+		Some(AudioFormat::AIFF) => read_aiff(path),
+		Some(AudioFormat::APE) => read_ape(path),
+		Some(AudioFormat::FLAC) => read_flac(path),
+		Some(AudioFormat::MP3) => read_mp3(path),
+		Some(AudioFormat::MP4) => read_mp4(path),
+		Some(AudioFormat::MPC) => read_ape(path),
+		Some(AudioFormat::OGG) => read_vorbis(path),
+		Some(AudioFormat::OPUS) => read_opus(path),
+		Some(AudioFormat::WAVE) => read_wave(path),
+		None => Err(crate::Error::msg(format!(
+			"Not an audio file: {}",
+			path.display()
+		))),
+	}
+}
+
+/// Guesses the content type of encoded image data from its first bytes.
+pub fn mime_type(data: &[u8]) -> Option<&'static str> {
+	match image::guess_format(data).ok()? {
+		ImageFormat::Png => Some("image/png"),
+		ImageFormat::Jpeg => Some("image/jpeg"),
+		ImageFormat::Gif => Some("image/gif"),
+		ImageFormat::WebP => Some("image/webp"),
+		ImageFormat::Tiff => Some("image/tiff"),
+		ImageFormat::Bmp => Some("image/bmp"),
+		ImageFormat::Ico => Some("image/x-icon"),
+		_ => None,
+	}
+}
+
+fn read_ape(_: &Path) -> Result<Vec<u8>> {
 	Err(crate::Error::msg(
 		"Embedded images are not supported in APE files",
 	))
 }
 
-fn read_flac(path: &Path) -> Result<DynamicImage> {
+fn read_flac(path: &Path) -> Result<Vec<u8>> {
 	let tag = metaflac::Tag::read_from_path(path)?;
 
 	if let Some(p) = tag.pictures().next() {
-		return Ok(image::load_from_memory(&p.data)?);
+		return Ok(p.data.clone());
 	}
 
 	Err(crate::Error::msg(format!(
@@ -39,27 +64,27 @@ fn read_flac(path: &Path) -> Result<DynamicImage> {
 	)))
 }
 
-fn read_mp3(path: &Path) -> Result<DynamicImage> {
+fn read_mp3(path: &Path) -> Result<Vec<u8>> {
 	let tag = id3::Tag::read_from_path(path)?;
 
 	read_id3(&path, &tag)
 }
 
-fn read_aiff(path: &Path) -> Result<DynamicImage> {
+fn read_aiff(path: &Path) -> Result<Vec<u8>> {
 	let tag = id3::Tag::read_from_aiff(path)?;
 
 	read_id3(&path, &tag)
 }
 
-fn read_wave(path: &Path) -> Result<DynamicImage> {
+fn read_wave(path: &Path) -> Result<Vec<u8>> {
 	let tag = id3::Tag::read_from_wav(path)?;
 
 	read_id3(&path, &tag)
 }
 
-fn read_id3(path: &Path, tag: &id3::Tag) -> Result<DynamicImage> {
+fn read_id3(path: &Path, tag: &id3::Tag) -> Result<Vec<u8>> {
 	if let Some(p) = tag.pictures().next() {
-		return Ok(image::load_from_memory(&p.data)?);
+		return Ok(p.data.clone());
 	}
 
 	Err(crate::Error::msg(format!(
@@ -68,11 +93,11 @@ fn read_id3(path: &Path, tag: &id3::Tag) -> Result<DynamicImage> {
 	)))
 }
 
-fn read_mp4(path: &Path) -> Result<DynamicImage> {
+fn read_mp4(path: &Path) -> Result<Vec<u8>> {
 	let tag = mp4ameta::Tag::read_from_path(path)?;
 
 	match tag.artwork().and_then(|d| d.image_data()) {
-		Some(v) => Ok(image::load_from_memory(v)?),
+		Some(v) => Ok(v.to_vec()),
 		_ => Err(crate::Error::msg(format!(
 			"Embedded mp4 artwork not found for file: {}",
 			path.display()
@@ -80,18 +105,17 @@ fn read_mp4(path: &Path) -> Result<DynamicImage> {
 	}
 }
 
-fn read_vorbis(_: &Path) -> Result<DynamicImage> {
+fn read_vorbis(_: &Path) -> Result<Vec<u8>> {
 	Err(crate::Error::msg(
 		"Embedded images are not supported in Vorbis files",
 	))
 }
 
-fn read_opus(_: &Path) -> Result<DynamicImage> {
+fn read_opus(_: &Path) -> Result<Vec<u8>> {
 	Err(crate::Error::msg(
 		"Embedded images are not supported in Opus files",
 	))
 }
-
 #[test]
 fn can_read_artwork_data() {
 	let ext_img = image::open("test-data/artwork/Folder.png")
@@ -146,3 +170,29 @@ fn can_read_artwork_data() {
 		.to_rgb8();
 	assert_eq!(wave_img, embedded_img);
 }
+
+#[test]
+fn can_read_embedded_artwork_bytes() {
+	let embedded_img = image::open("test-data/artwork/Embedded.png")
+		.unwrap()
+		.to_rgb8();
+
+	let data = read_embedded(Path::new("test-data/artwork/sample.mp3")).unwrap();
+	assert!(mime_type(&data).is_some());
+	assert_eq!(
+		image::load_from_memory(&data).unwrap().to_rgb8(),
+		embedded_img
+	);
+
+	assert!(read_embedded(Path::new("test-data/artwork/sample.ogg")).is_err());
+	assert!(read_embedded(Path::new("test-data/artwork/Folder.png")).is_err());
+}
+
+#[test]
+fn guesses_image_mime_type() {
+	let png = std::fs::read("test-data/artwork/Folder.png").unwrap();
+	assert_eq!(mime_type(&png), Some("image/png"));
+	let jpeg = std::fs::read("test-data/small-collection/Khemmis/Hunted/Folder.jpg").unwrap();
+	assert_eq!(mime_type(&jpeg), Some("image/jpeg"));
+	assert_eq!(mime_type(b"not an image"), None);
+}
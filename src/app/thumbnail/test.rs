@@ -0,0 +1,191 @@
+use image::{DynamicImage, GenericImageView, ImageBuffer};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use super::*;
+use crate::app::{settings, test};
+use crate::test::prepare_test_directory;
+use crate::test_name;
+
+fn wide_image(directory: &Path) -> PathBuf {
+	let path = directory.join("wide.png");
+	let image = ImageBuffer::from_pixel(300, 100, image::Rgb([0u8, 0, 0]));
+	DynamicImage::ImageRgb8(image).save(&path).unwrap();
+	path
+}
+
+#[test]
+fn can_pad_to_square() {
+	let path = wide_image(&prepare_test_directory(test_name!()));
+	let options = Options {
+		pad_to_square: true,
+		..Default::default()
+	};
+	let thumbnail = generate_thumbnail(&path, &options).unwrap();
+	assert_eq!(thumbnail.dimensions(), (300, 300));
+}
+
+#[test]
+fn can_crop_to_square() {
+	let path = wide_image(&prepare_test_directory(test_name!()));
+	let options = Options {
+		crop_to_square: true,
+		..Default::default()
+	};
+	let thumbnail = generate_thumbnail(&path, &options).unwrap();
+	assert_eq!(thumbnail.dimensions(), (100, 100));
+}
+
+#[test]
+fn preserves_aspect_ratio_without_padding_or_cropping() {
+	let path = wide_image(&prepare_test_directory(test_name!()));
+	let options = Options {
+		pad_to_square: false,
+		..Default::default()
+	};
+	let thumbnail = generate_thumbnail(&path, &options).unwrap();
+	assert_eq!(thumbnail.dimensions(), (300, 100));
+}
+
+#[test]
+fn sizes_are_cached_separately() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	let path = wide_image(&ctx.test_directory);
+	let manager = &ctx.thumbnail_manager;
+	let small = Options {
+		max_dimension: Some(100),
+		..Default::default()
+	};
+	let large = Options {
+		max_dimension: Some(200),
+		..Default::default()
+	};
+	let small_path = manager.get_thumbnail(&path, &small).unwrap();
+	let large_path = manager.get_thumbnail(&path, &large).unwrap();
+	assert_ne!(small_path, large_path);
+	assert_eq!(image::image_dimensions(small_path).unwrap(), (100, 100));
+	assert_eq!(image::image_dimensions(large_path).unwrap(), (200, 200));
+}
+
+#[test]
+fn cache_evicts_least_recently_used_thumbnails() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	let path = wide_image(&ctx.test_directory);
+	let manager = &ctx.thumbnail_manager;
+	let options = |max_dimension| Options {
+		max_dimension: Some(max_dimension),
+		..Default::default()
+	};
+
+	let a = manager.get_thumbnail(&path, &options(100)).unwrap();
+	let b = manager.get_thumbnail(&path, &options(200)).unwrap();
+	let c = manager.get_thumbnail(&path, &options(300)).unwrap();
+	let size = |p: &Path| fs::metadata(p).unwrap().len() as i64;
+	let max_size = size(&a) + size(&c);
+	fs::remove_file(&c).unwrap();
+
+	// Reading a thumbnail makes it the most recently used one
+	let age = |p: &Path, seconds| {
+		let time = SystemTime::now() - Duration::from_secs(seconds);
+		let file = File::options().write(true).open(p).unwrap();
+		file.set_modified(time).unwrap();
+	};
+	age(&a, 2 * 60 * 60);
+	age(&b, 60 * 60);
+	manager.get_thumbnail(&path, &options(100)).unwrap();
+
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			thumbnail_cache_max_size_bytes: Some(max_size),
+			..Default::default()
+		})
+		.unwrap();
+	manager.get_thumbnail(&path, &options(300)).unwrap();
+
+	assert!(a.exists());
+	assert!(!b.exists());
+	assert!(c.exists());
+}
+
+#[test]
+fn cache_keeps_new_thumbnail_when_over_limit() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.thumbnail_cache_max_size(1)
+		.build();
+	let path = wide_image(&ctx.test_directory);
+	let options = Options::default();
+
+	let thumbnail = ctx
+		.thumbnail_manager
+		.get_thumbnail(&path, &options)
+		.unwrap();
+	assert!(thumbnail.exists());
+}
+
+#[test]
+fn encoding_settings_are_cached_separately() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	let path = wide_image(&ctx.test_directory);
+	let manager = &ctx.thumbnail_manager;
+	let options = Options::default();
+
+	let jpeg = manager.get_thumbnail(&path, &options).unwrap();
+	assert_eq!(jpeg.extension().unwrap(), "jpg");
+
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			thumbnail_quality: Some(50),
+			..Default::default()
+		})
+		.unwrap();
+	let low_quality = manager.get_thumbnail(&path, &options).unwrap();
+	assert_ne!(jpeg, low_quality);
+
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			thumbnail_format: Some(settings::ThumbnailFormat::Png),
+			..Default::default()
+		})
+		.unwrap();
+	let png = manager.get_thumbnail(&path, &options).unwrap();
+	assert_eq!(png.extension().unwrap(), "png");
+	assert_eq!(
+		image::ImageFormat::from_path(&png).unwrap(),
+		image::ImageFormat::Png
+	);
+}
+
+#[test]
+fn rejects_out_of_range_quality() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	for quality in [0, 101] {
+		let result = ctx.settings_manager.amend(&settings::NewSettings {
+			thumbnail_quality: Some(quality),
+			..Default::default()
+		});
+		assert!(matches!(
+			result,
+			Err(settings::Error::ThumbnailQualityInvalid)
+		));
+	}
+}
+
+#[test]
+fn replaced_images_get_new_thumbnails() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	let path = wide_image(&ctx.test_directory);
+	let manager = &ctx.thumbnail_manager;
+	let options = Options {
+		pad_to_square: false,
+		..Default::default()
+	};
+
+	let before = manager.get_thumbnail(&path, &options).unwrap();
+	let image = ImageBuffer::from_pixel(200, 100, image::Rgb([0u8, 0, 0]));
+	DynamicImage::ImageRgb8(image).save(&path).unwrap();
+	let after = manager.get_thumbnail(&path, &options).unwrap();
+
+	assert_ne!(before, after);
+	assert_eq!(image::image_dimensions(after).unwrap(), (200, 100));
+}
@@ -0,0 +1,65 @@
+use anyhow::*;
+use rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+use rustls::{NoClientAuth, PrivateKey, ServerConfig};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+#[cfg(test)]
+mod test;
+
+/// Builds the TLS configuration for the server, or returns `None` when serving plain HTTP.
+/// The certificate and key must be provided together.
+pub fn load_config(
+	cert_file_path: Option<&Path>,
+	key_file_path: Option<&Path>,
+) -> Result<Option<ServerConfig>> {
+	match (cert_file_path, key_file_path) {
+		(None, None) => Ok(None),
+		(Some(_), None) => bail!("A TLS certificate was provided without a private key"),
+		(None, Some(_)) => bail!("A TLS private key was provided without a certificate"),
+		(Some(cert), Some(key)) => make_config(cert, key).map(Some),
+	}
+}
+
+fn make_config(cert_file_path: &Path, key_file_path: &Path) -> Result<ServerConfig> {
+	let cert_chain = read_certificates(cert_file_path).with_context(|| {
+		format!(
+			"Could not load TLS certificate from `{}`",
+			cert_file_path.display()
+		)
+	})?;
+	let key = read_private_key(key_file_path).with_context(|| {
+		format!(
+			"Could not load TLS private key from `{}`",
+			key_file_path.display()
+		)
+	})?;
+	let mut config = ServerConfig::new(NoClientAuth::new());
+	config
+		.set_single_cert(cert_chain, key)
+		.context("TLS certificate and private key cannot be used together")?;
+	Ok(config)
+}
+
+fn read_certificates(path: &Path) -> Result<Vec<rustls::Certificate>> {
+	let mut reader = BufReader::new(File::open(path)?);
+	let cert_chain = certs(&mut reader).map_err(|_| anyhow!("Invalid PEM data"))?;
+	if cert_chain.is_empty() {
+		bail!("No certificate found");
+	}
+	Ok(cert_chain)
+}
+
+// Keys can be stored in PKCS#8 or in the older RSA-specific format
+fn read_private_key(path: &Path) -> Result<PrivateKey> {
+	let mut reader = BufReader::new(File::open(path)?);
+	let mut keys = pkcs8_private_keys(&mut reader).map_err(|_| anyhow!("Invalid PEM data"))?;
+	if keys.is_empty() {
+		let mut reader = BufReader::new(File::open(path)?);
+		keys = rsa_private_keys(&mut reader).map_err(|_| anyhow!("Invalid PEM data"))?;
+	}
+	keys.into_iter()
+		.next()
+		.ok_or_else(|| anyhow!("No private key found"))
+}
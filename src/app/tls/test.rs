@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use super::*;
+
+fn test_file(name: &str) -> PathBuf {
+	["test-data", "tls", name].iter().collect()
+}
+
+fn load_error(cert: Option<&Path>, key: Option<&Path>) -> String {
+	format!("{:#}", load_config(cert, key).err().unwrap())
+}
+
+#[test]
+fn serves_plain_http_without_tls_files() {
+	assert!(load_config(None, None).unwrap().is_none());
+}
+
+#[test]
+fn loads_certificate_and_key() {
+	let cert = test_file("cert.pem");
+	let key = test_file("key.pem");
+	assert!(load_config(Some(&cert), Some(&key)).unwrap().is_some());
+}
+
+#[test]
+fn requires_certificate_and_key_together() {
+	let cert = test_file("cert.pem");
+	let key = test_file("key.pem");
+	assert!(load_error(Some(&cert), None).contains("without a private key"));
+	assert!(load_error(None, Some(&key)).contains("without a certificate"));
+}
+
+#[test]
+fn rejects_missing_files() {
+	let cert = test_file("cert.pem");
+	let key = test_file("key.pem");
+	let missing = test_file("missing.pem");
+	let error = load_error(Some(&missing), Some(&key));
+	assert!(error.contains("Could not load TLS certificate"));
+	assert!(error.contains("missing.pem"));
+	let error = load_error(Some(&cert), Some(&missing));
+	assert!(error.contains("Could not load TLS private key"));
+	assert!(error.contains("missing.pem"));
+}
+
+#[test]
+fn rejects_files_without_pem_data() {
+	let cert = test_file("cert.pem");
+	let key = test_file("key.pem");
+	let not_pem: PathBuf = ["test-data", "config.toml"].iter().collect();
+	assert!(load_error(Some(&not_pem), Some(&key)).contains("No certificate found"));
+	assert!(load_error(Some(&cert), Some(&not_pem)).contains("No private key found"));
+}
+
+#[test]
+fn rejects_swapped_certificate_and_key() {
+	let cert = test_file("cert.pem");
+	let key = test_file("key.pem");
+	assert!(load_error(Some(&key), Some(&cert)).contains("No certificate found"));
+}
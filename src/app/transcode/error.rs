@@ -0,0 +1,15 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error(
+		"Bitrate must be between {} and {} kbps",
+		super::MIN_BITRATE,
+		super::MAX_BITRATE
+	)]
+	UnsupportedBitrate,
+	#[error("Could not start the audio encoder: {0}")]
+	EncoderUnavailable(#[source] std::io::Error),
+	#[error("Audio encoder exited with an error")]
+	EncodingFailed,
+	#[error(transparent)]
+	Io(#[from] std::io::Error),
+}
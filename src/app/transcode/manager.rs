@@ -0,0 +1,125 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::app::transcode::*;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Clone)]
+pub struct Manager {
+	encoder_path: PathBuf,
+	active_sessions: Arc<AtomicUsize>,
+}
+
+impl Manager {
+	pub fn new(encoder_path: PathBuf) -> Self {
+		Self {
+			encoder_path,
+			active_sessions: Arc::new(AtomicUsize::new(0)),
+		}
+	}
+
+	/// Number of transcoded streams currently being served.
+	pub fn active_sessions(&self) -> usize {
+		self.active_sessions.load(Ordering::SeqCst)
+	}
+
+	pub fn transcode(&self, audio_path: &Path, options: &Options) -> Result<Transcoder, Error> {
+		if options.bitrate < MIN_BITRATE || options.bitrate > MAX_BITRATE {
+			return Err(Error::UnsupportedBitrate);
+		}
+		self.spawn(&[audio_path], &options.encoder_arguments())
+	}
+
+	/// Transcodes several audio files, in order, into a single continuous stream.
+	pub fn transcode_sequence(
+		&self,
+		audio_paths: &[PathBuf],
+		options: &Options,
+	) -> Result<Transcoder, Error> {
+		if options.bitrate < MIN_BITRATE || options.bitrate > MAX_BITRATE {
+			return Err(Error::UnsupportedBitrate);
+		}
+		let audio_paths: Vec<&Path> = audio_paths.iter().map(PathBuf::as_path).collect();
+		let mut arguments = concatenation_arguments(audio_paths.len());
+		arguments.extend(options.encoder_arguments());
+		self.spawn(&audio_paths, &arguments)
+	}
+
+	/// Decodes audio into raw mono samples, as signed 16-bit little-endian integers.
+	pub fn decode(&self, audio_path: &Path) -> Result<Transcoder, Error> {
+		self.spawn(&[audio_path], &decoder_arguments())
+	}
+
+	fn spawn(
+		&self,
+		audio_paths: &[&Path],
+		output_arguments: &[String],
+	) -> Result<Transcoder, Error> {
+		let mut command = Command::new(&self.encoder_path);
+		command.args(["-hide_banner", "-loglevel", "error", "-nostdin"]);
+		for audio_path in audio_paths {
+			command.arg("-i").arg(audio_path);
+		}
+		let mut child = command
+			.args(output_arguments)
+			.stdin(Stdio::null())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::null())
+			.spawn()
+			.map_err(Error::EncoderUnavailable)?;
+
+		let stdout = match child.stdout.take() {
+			Some(stdout) => stdout,
+			None => {
+				let _ = child.kill();
+				let _ = child.wait();
+				return Err(Error::EncodingFailed);
+			}
+		};
+
+		self.active_sessions.fetch_add(1, Ordering::SeqCst);
+		Ok(Transcoder {
+			child,
+			stdout,
+			active_sessions: self.active_sessions.clone(),
+		})
+	}
+}
+
+// Encoder process producing a transcoded stream. The process is killed when this is dropped, which
+// happens as soon as the client stops reading the response.
+pub struct Transcoder {
+	child: Child,
+	stdout: ChildStdout,
+	active_sessions: Arc<AtomicUsize>,
+}
+
+impl Transcoder {
+	// Returns the next chunk of encoded audio, or None once the encoder has finished
+	pub fn read_chunk(&mut self) -> Result<Option<Vec<u8>>, Error> {
+		let mut chunk = vec![0; CHUNK_SIZE];
+		let length = self.stdout.read(&mut chunk)?;
+		if length == 0 {
+			if !self.child.wait()?.success() {
+				return Err(Error::EncodingFailed);
+			}
+			return Ok(None);
+		}
+		chunk.truncate(length);
+		Ok(Some(chunk))
+	}
+}
+
+impl Drop for Transcoder {
+	fn drop(&mut self) {
+		if let Ok(None) = self.child.try_wait() {
+			let _ = self.child.kill();
+			let _ = self.child.wait();
+		}
+		self.active_sessions.fetch_sub(1, Ordering::SeqCst);
+	}
+}
@@ -0,0 +1,9 @@
+mod error;
+mod manager;
+mod options;
+#[cfg(test)]
+mod test;
+
+pub use error::*;
+pub use manager::*;
+pub use options::*;
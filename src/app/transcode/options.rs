@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+
+pub const MIN_BITRATE: u32 = 32;
+pub const MAX_BITRATE: u32 = 320;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+	Mp3,
+	Opus,
+}
+
+impl Format {
+	pub fn content_type(self) -> &'static str {
+		match self {
+			Format::Mp3 => "audio/mpeg",
+			Format::Opus => "audio/ogg",
+		}
+	}
+
+	fn codec(self) -> &'static str {
+		match self {
+			Format::Mp3 => "libmp3lame",
+			Format::Opus => "libopus",
+		}
+	}
+
+	fn container(self) -> &'static str {
+		match self {
+			Format::Mp3 => "mp3",
+			Format::Opus => "ogg",
+		}
+	}
+
+	fn default_bitrate(self) -> u32 {
+		match self {
+			Format::Mp3 => 192,
+			Format::Opus => 128,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Options {
+	pub format: Format,
+	pub bitrate: u32, // kbps
+}
+
+impl Options {
+	pub fn new(format: Option<Format>, bitrate: Option<u32>) -> Self {
+		let format = format.unwrap_or(Format::Mp3);
+		Self {
+			format,
+			bitrate: bitrate.unwrap_or_else(|| format.default_bitrate()),
+		}
+	}
+
+	pub(super) fn encoder_arguments(&self) -> Vec<String> {
+		vec![
+			"-vn".to_owned(),
+			"-map_metadata".to_owned(),
+			"-1".to_owned(),
+			"-c:a".to_owned(),
+			self.format.codec().to_owned(),
+			"-b:a".to_owned(),
+			format!("{}k", self.bitrate),
+			"-f".to_owned(),
+			self.format.container().to_owned(),
+			"pipe:1".to_owned(),
+		]
+	}
+}
+
+pub(super) fn decoder_arguments() -> Vec<String> {
+	vec![
+		"-vn".to_owned(),
+		"-ac".to_owned(),
+		"1".to_owned(),
+		"-c:a".to_owned(),
+		"pcm_s16le".to_owned(),
+		"-f".to_owned(),
+		"s16le".to_owned(),
+		"pipe:1".to_owned(),
+	]
+}
+
+// Joins the first audio stream of every input, in order. Inputs with different sample rates or
+// channel layouts are converted to a common format by the encoder.
+pub(super) fn concatenation_arguments(input_count: usize) -> Vec<String> {
+	let inputs: String = (0..input_count).map(|i| format!("[{}:a:0]", i)).collect();
+	vec![
+		"-filter_complex".to_owned(),
+		format!("{}concat=n={}:v=0:a=1[audio]", inputs, input_count),
+		"-map".to_owned(),
+		"[audio]".to_owned(),
+	]
+}
@@ -0,0 +1,140 @@
+use std::path::{Path, PathBuf};
+
+use super::*;
+use crate::test::prepare_test_directory;
+use crate::test_name;
+
+#[cfg(unix)]
+fn fake_encoder(test_directory: &Path, script: &str) -> PathBuf {
+	use std::os::unix::fs::PermissionsExt;
+	let path = test_directory.join("encoder.sh");
+	std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+	std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+	path
+}
+
+#[cfg(unix)]
+fn read_all(transcoder: &mut Transcoder) -> Result<Vec<u8>, Error> {
+	let mut output = Vec::new();
+	while let Some(chunk) = transcoder.read_chunk()? {
+		output.extend(chunk);
+	}
+	Ok(output)
+}
+
+#[test]
+fn options_use_format_defaults() {
+	assert_eq!(
+		Options::new(None, None),
+		Options {
+			format: Format::Mp3,
+			bitrate: 192
+		}
+	);
+	assert_eq!(
+		Options::new(Some(Format::Opus), None),
+		Options {
+			format: Format::Opus,
+			bitrate: 128
+		}
+	);
+	assert_eq!(
+		Options::new(None, Some(96)),
+		Options {
+			format: Format::Mp3,
+			bitrate: 96
+		}
+	);
+}
+
+#[test]
+fn encoder_arguments_match_options() {
+	let arguments = Options::new(Some(Format::Opus), Some(64)).encoder_arguments();
+	let arguments = arguments.join(" ");
+	assert!(arguments.contains("-c:a libopus"));
+	assert!(arguments.contains("-b:a 64k"));
+	assert!(arguments.contains("-f ogg"));
+	assert!(arguments.ends_with("pipe:1"));
+}
+
+#[test]
+fn decoder_arguments_output_raw_mono_samples() {
+	let arguments = decoder_arguments().join(" ");
+	assert!(arguments.contains("-ac 1"));
+	assert!(arguments.contains("-c:a pcm_s16le"));
+	assert!(arguments.contains("-f s16le"));
+	assert!(arguments.ends_with("pipe:1"));
+}
+
+#[test]
+fn concatenation_arguments_join_all_inputs() {
+	let arguments = concatenation_arguments(3).join(" ");
+	assert!(arguments.contains("[0:a:0][1:a:0][2:a:0]concat=n=3:v=0:a=1[audio]"));
+	assert!(arguments.ends_with("-map [audio]"));
+}
+
+#[test]
+fn rejects_unsupported_bitrate() {
+	let manager = Manager::new(PathBuf::from("ffmpeg"));
+	for bitrate in [0, MIN_BITRATE - 1, MAX_BITRATE + 1].iter() {
+		let options = Options::new(None, Some(*bitrate));
+		assert!(matches!(
+			manager.transcode(Path::new("song.flac"), &options),
+			Err(Error::UnsupportedBitrate)
+		));
+	}
+}
+
+#[test]
+fn reports_missing_encoder() {
+	let test_directory = prepare_test_directory(test_name!());
+	let manager = Manager::new(test_directory.join("missing-encoder"));
+	let options = Options::new(None, None);
+	assert!(matches!(
+		manager.transcode(Path::new("song.flac"), &options),
+		Err(Error::EncoderUnavailable(_))
+	));
+}
+
+#[cfg(unix)]
+#[test]
+fn streams_encoder_output() {
+	let test_directory = prepare_test_directory(test_name!());
+	let encoder = fake_encoder(&test_directory, "printf 'encoded audio'");
+	let manager = Manager::new(encoder);
+	let mut transcoder = manager
+		.transcode(Path::new("song.flac"), &Options::new(None, None))
+		.unwrap();
+	assert_eq!(read_all(&mut transcoder).unwrap(), b"encoded audio");
+}
+
+#[cfg(unix)]
+#[test]
+fn reports_encoder_failure() {
+	let test_directory = prepare_test_directory(test_name!());
+	let encoder = fake_encoder(&test_directory, "exit 1");
+	let manager = Manager::new(encoder);
+	let mut transcoder = manager
+		.transcode(Path::new("song.flac"), &Options::new(None, None))
+		.unwrap();
+	assert!(matches!(
+		read_all(&mut transcoder),
+		Err(Error::EncodingFailed)
+	));
+}
+
+#[cfg(unix)]
+#[test]
+fn passes_sequence_inputs_in_order() {
+	let test_directory = prepare_test_directory(test_name!());
+	let encoder = fake_encoder(
+		&test_directory,
+		"while [ $# -gt 0 ]; do [ \"$1\" = -i ] && printf '%s;' \"$2\"; shift; done",
+	);
+	let manager = Manager::new(encoder);
+	let audio_paths = vec![PathBuf::from("01.flac"), PathBuf::from("02.flac")];
+	let mut transcoder = manager
+		.transcode_sequence(&audio_paths, &Options::new(Some(Format::Opus), None))
+		.unwrap();
+	assert_eq!(read_all(&mut transcoder).unwrap(), b"01.flac;02.flac;");
+}
@@ -12,6 +12,8 @@ pub enum Error {
 	InvalidAuthToken,
 	#[error("Incorrect authorization scope")]
 	IncorrectAuthorizationScope,
+	#[error("Session does not exist")]
+	SessionNotFound,
 	#[error("Unspecified")]
 	Unspecified,
 }
@@ -0,0 +1,97 @@
+use hmac::{Hmac, Mac, NewMac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::*;
+use crate::app::user::sessions::now;
+
+const JWT_HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Claims carried by stateless tokens. Unlike session tokens, these cannot be revoked and are only
+// invalidated by their expiry or by a change of auth secret.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claims {
+	pub sub: String,
+	pub admin: bool,
+	pub iat: i64,
+	pub exp: i64,
+}
+
+impl AuthToken {
+	// Session tokens never contain dots, JWTs are made of three dot-separated segments
+	pub fn is_jwt(&self) -> bool {
+		self.0.matches('.').count() == 2
+	}
+}
+
+impl Manager {
+	pub fn generate_jwt(&self, username: &str) -> Result<(AuthToken, Claims), Error> {
+		let issued_at = now()?;
+		let claims = Claims {
+			sub: username.to_owned(),
+			admin: self.is_admin(username)?,
+			iat: issued_at,
+			exp: issued_at + self.session_lifetime()?.as_secs() as i64,
+		};
+		let auth_token = self.encode_jwt(&claims)?;
+		Ok((auth_token, claims))
+	}
+
+	pub(super) fn encode_jwt(&self, claims: &Claims) -> Result<AuthToken, Error> {
+		let serialized_claims = serde_json::to_vec(claims).map_err(|_| Error::Unspecified)?;
+		let signing_input = format!(
+			"{}.{}",
+			encode_segment(JWT_HEADER.as_bytes()),
+			encode_segment(&serialized_claims)
+		);
+		let signature = self.jwt_mac(&signing_input)?.finalize().into_bytes();
+		let token = format!("{}.{}", signing_input, encode_segment(&signature));
+		Ok(AuthToken(token))
+	}
+
+	pub fn authenticate_jwt(&self, auth_token: &AuthToken) -> Result<Claims, Error> {
+		let AuthToken(data) = auth_token;
+		let (signing_input, signature) = data.rsplit_once('.').ok_or(Error::InvalidAuthToken)?;
+		let (header, claims) = signing_input
+			.split_once('.')
+			.ok_or(Error::InvalidAuthToken)?;
+
+		let signature = decode_segment(signature)?;
+		self.jwt_mac(signing_input)?
+			.verify(&signature)
+			.map_err(|_| Error::InvalidAuthToken)?;
+
+		let header: serde_json::Value = serde_json::from_slice(&decode_segment(header)?)
+			.map_err(|_| Error::InvalidAuthToken)?;
+		if header["alg"] != "HS256" {
+			return Err(Error::InvalidAuthToken);
+		}
+
+		let claims: Claims = serde_json::from_slice(&decode_segment(claims)?)
+			.map_err(|_| Error::InvalidAuthToken)?;
+		if claims.exp <= now()? {
+			return Err(Error::InvalidAuthToken);
+		}
+		if !self.exists(&claims.sub)? {
+			return Err(Error::IncorrectUsername);
+		}
+		Ok(claims)
+	}
+
+	fn jwt_mac(&self, signing_input: &str) -> Result<HmacSha256, Error> {
+		let mut mac =
+			HmacSha256::new_varkey(&self.auth_secret.key).map_err(|_| Error::Unspecified)?;
+		mac.update(signing_input.as_bytes());
+		Ok(mac)
+	}
+}
+
+fn encode_segment(data: &[u8]) -> String {
+	base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+fn decode_segment(segment: &str) -> Result<Vec<u8>, Error> {
+	base64::decode_config(segment, base64::URL_SAFE_NO_PAD).map_err(|_| Error::InvalidAuthToken)
+}
@@ -1,10 +1,12 @@
 use anyhow::anyhow;
 use diesel;
 use diesel::prelude::*;
-use std::time::{SystemTime, UNIX_EPOCH};
+use log::error;
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use super::*;
-use crate::app::settings::AuthSecret;
+use crate::app::settings::{self, AuthSecret};
 use crate::db::DB;
 
 const HASH_ITERATIONS: u32 = 10000;
@@ -13,12 +15,24 @@ const HASH_ITERATIONS: u32 = 10000;
 pub struct Manager {
 	// TODO make this private and move preferences methods in this file
 	pub db: DB,
-	auth_secret: AuthSecret,
+	pub(super) auth_secret: AuthSecret,
+	settings_manager: settings::Manager,
 }
 
 impl Manager {
-	pub fn new(db: DB, auth_secret: AuthSecret) -> Self {
-		Self { db, auth_secret }
+	pub fn new(db: DB, auth_secret: AuthSecret, settings_manager: settings::Manager) -> Self {
+		Self {
+			db,
+			auth_secret,
+			settings_manager,
+		}
+	}
+
+	// Applies to sessions, the cookies handed out with them and JWTs
+	pub fn session_lifetime(&self) -> Result<Duration, Error> {
+		self.settings_manager
+			.get_session_lifetime()
+			.map_err(|_| Error::Unspecified)
 	}
 
 	pub fn create(&self, new_user: &NewUser) -> Result<(), Error> {
@@ -41,16 +55,48 @@ impl Manager {
 		Ok(())
 	}
 
+	// Returns whether the user was created, which only happens when there are no users yet
+	pub fn create_initial_admin(&self, username: &str, password: &str) -> Result<bool, Error> {
+		if self.count()? > 0 {
+			return Ok(false);
+		}
+		self.create(&NewUser {
+			name: username.to_owned(),
+			password: password.to_owned(),
+			admin: true,
+		})?;
+		Ok(true)
+	}
+
+	// Sessions and play history are deleted explicitly rather than through foreign key cascades,
+	// which SQLite only applies when enabled. User ids can be reused by users created later.
 	pub fn delete(&self, username: &str) -> Result<(), Error> {
-		use crate::db::users::dsl::*;
+		use crate::db::{play_history, sessions, users};
 		let connection = self.db.connect()?;
-		diesel::delete(users.filter(name.eq(username)))
-			.execute(&connection)
+		connection
+			.transaction::<_, diesel::result::Error, _>(|| {
+				let owner_id = users::table
+					.filter(users::name.eq(username))
+					.select(users::id);
+				diesel::delete(sessions::table.filter(sessions::owner.eq_any(owner_id)))
+					.execute(&connection)?;
+				diesel::delete(play_history::table.filter(play_history::owner.eq_any(owner_id)))
+					.execute(&connection)?;
+				diesel::delete(users::table.filter(users::name.eq(username)))
+					.execute(&connection)?;
+				Ok(())
+			})
 			.map_err(|_| Error::Unspecified)?;
 		Ok(())
 	}
 
+	// Sessions of the user are revoked, in case the account was compromised
 	pub fn set_password(&self, username: &str, password: &str) -> Result<(), Error> {
+		self.write_password_hash(username, password)?;
+		self.revoke_sessions(username, None)
+	}
+
+	fn write_password_hash(&self, username: &str, password: &str) -> Result<(), Error> {
 		let hash = hash_password(password)?;
 		let connection = self.db.connect()?;
 		use crate::db::users::dsl::*;
@@ -61,6 +107,20 @@ impl Manager {
 		Ok(())
 	}
 
+	// Other sessions of the user are revoked, in case they were opened with the old password by
+	// someone else. The session making the change, if any, stays valid.
+	pub fn change_password(
+		&self,
+		username: &str,
+		old_password: &str,
+		new_password: &str,
+		current_session: Option<i32>,
+	) -> Result<(), Error> {
+		self.check_credentials(username, old_password)?;
+		self.write_password_hash(username, new_password)?;
+		self.revoke_sessions(username, current_session)
+	}
+
 	pub fn set_is_admin(&self, username: &str, is_admin: bool) -> Result<(), Error> {
 		use crate::db::users::dsl::*;
 		let connection = self.db.connect()?;
@@ -71,7 +131,7 @@ impl Manager {
 		Ok(())
 	}
 
-	pub fn login(&self, username: &str, password: &str) -> Result<AuthToken, Error> {
+	pub fn check_credentials(&self, username: &str, password: &str) -> Result<(), Error> {
 		use crate::db::users::dsl::*;
 		let connection = self.db.connect()?;
 		match users
@@ -83,11 +143,16 @@ impl Manager {
 			Ok(hash) => {
 				let hash: String = hash;
 				if verify_password(&hash, password) {
-					let authorization = Authorization {
-						username: username.to_owned(),
-						scope: AuthorizationScope::PolarisAuth,
-					};
-					self.generate_auth_token(&authorization)
+					// Brings hashes made with older parameters up to date while the password is at hand
+					if is_hash_outdated(&hash) {
+						if let Err(e) = self.write_password_hash(username, password) {
+							error!(
+								"Could not upgrade password hash for `{}`: {:?}",
+								username, e
+							);
+						}
+					}
+					Ok(())
 				} else {
 					Err(Error::IncorrectPassword)
 				}
@@ -96,12 +161,29 @@ impl Manager {
 		}
 	}
 
+	pub fn login(
+		&self,
+		username: &str,
+		password: &str,
+		label: Option<&str>,
+	) -> Result<AuthToken, Error> {
+		self.check_credentials(username, password)?;
+		self.open_session(username, label)
+	}
+
 	pub fn authenticate(
 		&self,
 		auth_token: &AuthToken,
 		scope: AuthorizationScope,
 	) -> Result<Authorization, Error> {
 		let authorization = self.decode_auth_token(auth_token, scope)?;
+		if let AuthorizationScope::PolarisAuth = authorization.scope {
+			// Login tokens are only valid for as long as their session has not been revoked
+			let session = authorization.session.ok_or(Error::InvalidAuthToken)?;
+			if self.authenticate_session(session)? != authorization.username {
+				return Err(Error::InvalidAuthToken);
+			}
+		}
 		if self.exists(&authorization.username)? {
 			Ok(authorization)
 		} else {
@@ -129,7 +211,10 @@ impl Manager {
 		Ok(authorization)
 	}
 
-	fn generate_auth_token(&self, authorization: &Authorization) -> Result<AuthToken, Error> {
+	pub(super) fn generate_auth_token(
+		&self,
+		authorization: &Authorization,
+	) -> Result<AuthToken, Error> {
 		let serialized_authorization =
 			serde_json::to_string(&authorization).map_err(|_| Error::Unspecified)?;
 		branca::encode(
@@ -182,6 +267,18 @@ impl Manager {
 		Ok(is_admin != 0)
 	}
 
+	/// Returns whether this user is the only one with admin rights.
+	pub fn is_last_admin(&self, username: &str) -> Result<bool, Error> {
+		use crate::db::users::dsl::*;
+		let connection = self.db.connect()?;
+		let admins: Vec<String> = users
+			.select(name)
+			.filter(admin.ne(0))
+			.get_results(&connection)
+			.map_err(|_| Error::Unspecified)?;
+		Ok(admins.len() == 1 && admins[0] == username)
+	}
+
 	pub fn lastfm_link(
 		&self,
 		username: &str,
@@ -204,6 +301,7 @@ impl Manager {
 		self.generate_auth_token(&Authorization {
 			username: username.to_owned(),
 			scope: AuthorizationScope::LastFMLink,
+			session: None,
 		})
 	}
 
@@ -233,6 +331,43 @@ impl Manager {
 			.execute(&connection)?;
 		Ok(())
 	}
+
+	pub fn listenbrainz_link(&self, username: &str, token: &str) -> Result<(), Error> {
+		use crate::db::users::dsl::*;
+		let connection = self.db.connect()?;
+		diesel::update(users.filter(name.eq(username)))
+			.set(listenbrainz_token.eq(token))
+			.execute(&connection)
+			.map_err(|_| Error::Unspecified)?;
+		Ok(())
+	}
+
+	pub fn get_listenbrainz_token(&self, username: &str) -> anyhow::Result<String> {
+		use crate::db::users::dsl::*;
+		let connection = self.db.connect()?;
+		let token = users
+			.filter(name.eq(username))
+			.select(listenbrainz_token)
+			.get_result(&connection)?;
+		match token {
+			Some(t) => Ok(t),
+			_ => Err(anyhow!("Missing ListenBrainz credentials")),
+		}
+	}
+
+	pub fn is_listenbrainz_linked(&self, username: &str) -> bool {
+		self.get_listenbrainz_token(username).is_ok()
+	}
+
+	pub fn listenbrainz_unlink(&self, username: &str) -> anyhow::Result<()> {
+		use crate::db::users::dsl::*;
+		let connection = self.db.connect()?;
+		let null: Option<String> = None;
+		diesel::update(users.filter(name.eq(username)))
+			.set(listenbrainz_token.eq(&null))
+			.execute(&connection)?;
+		Ok(())
+	}
 }
 
 fn hash_password(password: &str) -> Result<String, Error> {
@@ -245,3 +380,14 @@ fn hash_password(password: &str) -> Result<String, Error> {
 fn verify_password(password_hash: &str, attempted_password: &str) -> bool {
 	pbkdf2::pbkdf2_check(attempted_password, password_hash).is_ok()
 }
+
+// Hashes are stored as $rpbkdf2$0$<base64(iterations)>$<base64(salt)>$<base64(hash)>$
+fn is_hash_outdated(password_hash: &str) -> bool {
+	let iterations = password_hash
+		.split('$')
+		.nth(3)
+		.and_then(|c| base64::decode(c).ok())
+		.and_then(|c| <[u8; 4]>::try_from(c.as_slice()).ok())
+		.map(u32::from_be_bytes);
+	iterations != Some(HASH_ITERATIONS)
+}
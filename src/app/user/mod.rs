@@ -3,14 +3,17 @@ use serde::{Deserialize, Serialize};
 use crate::db::users;
 
 mod error;
+mod jwt;
 mod manager;
 mod preferences;
+mod sessions;
 #[cfg(test)]
 mod test;
 
 pub use error::*;
 pub use manager::*;
 pub use preferences::*;
+pub use sessions::*;
 
 #[derive(Debug, Insertable, Queryable)]
 #[table_name = "users"]
@@ -46,4 +49,6 @@ pub enum AuthorizationScope {
 pub struct Authorization {
 	pub username: String,
 	pub scope: AuthorizationScope,
+	#[serde(default)]
+	pub session: Option<i32>,
 }
@@ -10,20 +10,28 @@ pub struct Preferences {
 	pub lastfm_username: Option<String>,
 	pub web_theme_base: Option<String>,
 	pub web_theme_accent: Option<String>,
+	// Seed used for random albums when requests do not specify one
+	pub random_seed: Option<u64>,
 }
 
 impl Manager {
 	pub fn read_preferences(&self, username: &str) -> Result<Preferences> {
 		use self::users::dsl::*;
 		let connection = self.db.connect()?;
-		let (theme_base, theme_accent, read_lastfm_username) = users
-			.select((web_theme_base, web_theme_accent, lastfm_username))
+		let (theme_base, theme_accent, read_lastfm_username, seed): (_, _, _, Option<i64>) = users
+			.select((
+				web_theme_base,
+				web_theme_accent,
+				lastfm_username,
+				random_seed,
+			))
 			.filter(name.eq(username))
 			.get_result(&connection)?;
 		Ok(Preferences {
 			web_theme_base: theme_base,
 			web_theme_accent: theme_accent,
 			lastfm_username: read_lastfm_username,
+			random_seed: seed.map(|s| s as u64),
 		})
 	}
 
@@ -34,6 +42,7 @@ impl Manager {
 			.set((
 				web_theme_base.eq(&preferences.web_theme_base),
 				web_theme_accent.eq(&preferences.web_theme_accent),
+				random_seed.eq(preferences.random_seed.map(|s| s as i64)),
 			))
 			.execute(&connection)?;
 		Ok(())
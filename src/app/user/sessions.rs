@@ -0,0 +1,200 @@
+use diesel;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::*;
+use crate::db::sessions;
+
+// Avoids writing to the database on every authenticated request
+const LAST_SEEN_UPDATE_INTERVAL: i64 = 60; // Seconds
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Queryable)]
+pub struct Session {
+	pub id: i32,
+	pub username: String,
+	pub label: Option<String>,
+	pub created: i64,
+	pub last_seen: i64,
+}
+
+impl Manager {
+	pub fn open_session(&self, username: &str, label: Option<&str>) -> Result<AuthToken, Error> {
+		self.delete_expired_sessions()?;
+		let session = self.create_session(username, label)?;
+		self.session_token(username, session)
+	}
+
+	// Clients sending credentials with every request (HTTP basic auth) would otherwise open a new
+	// session each time. They share the latest valid session with the same label instead.
+	pub fn resume_session(&self, username: &str, label: Option<&str>) -> Result<AuthToken, Error> {
+		let connection = self.db.connect()?;
+		let oldest_valid = now()? - self.session_lifetime()?.as_secs() as i64;
+		let query = sessions::table
+			.inner_join(users::table)
+			.filter(users::name.eq(username))
+			.filter(sessions::created.gt(oldest_valid))
+			.select(sessions::id)
+			.order(sessions::id.desc())
+			.into_boxed();
+		let query = match label {
+			Some(label) => query.filter(sessions::label.eq(label)),
+			None => query.filter(sessions::label.is_null()),
+		};
+		let session: Option<i32> = query
+			.first(&connection)
+			.optional()
+			.map_err(|_| Error::Unspecified)?;
+		match session {
+			Some(session) => self.session_token(username, session),
+			None => self.open_session(username, label),
+		}
+	}
+
+	fn session_token(&self, username: &str, session: i32) -> Result<AuthToken, Error> {
+		self.generate_auth_token(&Authorization {
+			username: username.to_owned(),
+			scope: AuthorizationScope::PolarisAuth,
+			session: Some(session),
+		})
+	}
+
+	// Revokes every session of a user, except the one it may be using to make this request
+	pub(super) fn revoke_sessions(&self, username: &str, except: Option<i32>) -> Result<(), Error> {
+		let connection = self.db.connect()?;
+		let owner_id = users::table
+			.filter(users::name.eq(username))
+			.select(users::id);
+		diesel::delete(
+			sessions::table
+				.filter(sessions::owner.eq_any(owner_id))
+				.filter(sessions::id.ne_all(except)),
+		)
+		.execute(&connection)
+		.map_err(|_| Error::Unspecified)?;
+		Ok(())
+	}
+
+	fn delete_expired_sessions(&self) -> Result<(), Error> {
+		let connection = self.db.connect()?;
+		let oldest_valid = now()? - self.session_lifetime()?.as_secs() as i64;
+		diesel::delete(sessions::table.filter(sessions::created.le(oldest_valid)))
+			.execute(&connection)
+			.map_err(|_| Error::Unspecified)?;
+		Ok(())
+	}
+
+	fn create_session(&self, username: &str, label: Option<&str>) -> Result<i32, Error> {
+		let connection = self.db.connect()?;
+		let now = now()?;
+		connection
+			.transaction(|| {
+				let user_id: i32 = users::table
+					.filter(users::name.eq(username))
+					.select(users::id)
+					.get_result(&connection)?;
+				diesel::insert_into(sessions::table)
+					.values((
+						sessions::owner.eq(user_id),
+						sessions::label.eq(label),
+						sessions::created.eq(now),
+						sessions::last_seen.eq(now),
+					))
+					.execute(&connection)?;
+				sessions::table
+					.filter(sessions::owner.eq(user_id))
+					.select(sessions::id)
+					.order(sessions::id.desc())
+					.first(&connection)
+			})
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::IncorrectUsername,
+				_ => Error::Unspecified,
+			})
+	}
+
+	pub fn list_sessions(&self, username: &str) -> Result<Vec<Session>, Error> {
+		let connection = self.db.connect()?;
+		sessions::table
+			.inner_join(users::table)
+			.filter(users::name.eq(username))
+			.select((
+				sessions::id,
+				users::name,
+				sessions::label,
+				sessions::created,
+				sessions::last_seen,
+			))
+			.order(sessions::created.desc())
+			.load(&connection)
+			.map_err(|_| Error::Unspecified)
+	}
+
+	pub fn list_all_sessions(&self) -> Result<Vec<Session>, Error> {
+		let connection = self.db.connect()?;
+		sessions::table
+			.inner_join(users::table)
+			.select((
+				sessions::id,
+				users::name,
+				sessions::label,
+				sessions::created,
+				sessions::last_seen,
+			))
+			.order((users::name, sessions::created.desc()))
+			.load(&connection)
+			.map_err(|_| Error::Unspecified)
+	}
+
+	pub fn revoke_session(&self, username: &str, session_id: i32) -> Result<(), Error> {
+		let connection = self.db.connect()?;
+		let owner_id = users::table
+			.filter(users::name.eq(username))
+			.select(users::id);
+		let deleted = diesel::delete(
+			sessions::table
+				.filter(sessions::id.eq(session_id))
+				.filter(sessions::owner.eq_any(owner_id)),
+		)
+		.execute(&connection)
+		.map_err(|_| Error::Unspecified)?;
+		match deleted {
+			0 => Err(Error::SessionNotFound),
+			_ => Ok(()),
+		}
+	}
+
+	// Returns the name of the user owning a session, and records that it was just used
+	pub fn authenticate_session(&self, session_id: i32) -> Result<String, Error> {
+		let connection = self.db.connect()?;
+		let (username, created, last_seen): (String, i64, i64) = sessions::table
+			.inner_join(users::table)
+			.filter(sessions::id.eq(session_id))
+			.select((users::name, sessions::created, sessions::last_seen))
+			.get_result(&connection)
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::InvalidAuthToken,
+				_ => Error::Unspecified,
+			})?;
+
+		let now = now()?;
+		if now - created >= self.session_lifetime()?.as_secs() as i64 {
+			return Err(Error::InvalidAuthToken);
+		}
+		if now - last_seen >= LAST_SEEN_UPDATE_INTERVAL {
+			diesel::update(sessions::table.filter(sessions::id.eq(session_id)))
+				.set(sessions::last_seen.eq(now))
+				.execute(&connection)
+				.map_err(|_| Error::Unspecified)?;
+		}
+
+		Ok(username)
+	}
+}
+
+pub(super) fn now() -> Result<i64, Error> {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|d| d.as_secs() as i64)
+		.map_err(|_| Error::Unspecified)
+}
@@ -1,5 +1,9 @@
+use diesel::prelude::*;
+
+use super::jwt::Claims;
 use super::*;
-use crate::app::test;
+use crate::app::{settings, test};
+use crate::db::sessions;
 use crate::test_name;
 
 const TEST_USERNAME: &str = "Walter";
@@ -22,6 +26,194 @@ fn create_delete_user_golden_path() {
 	assert_eq!(ctx.user_manager.list().unwrap().len(), 0);
 }
 
+#[test]
+fn can_detect_last_admin() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+
+	let admin = NewUser {
+		name: TEST_USERNAME.to_owned(),
+		password: TEST_PASSWORD.to_owned(),
+		admin: true,
+	};
+	ctx.user_manager.create(&admin).unwrap();
+	assert!(ctx.user_manager.is_last_admin(TEST_USERNAME).unwrap());
+
+	let other_admin = NewUser {
+		name: "Skyler".to_owned(),
+		password: TEST_PASSWORD.to_owned(),
+		admin: true,
+	};
+	ctx.user_manager.create(&other_admin).unwrap();
+	assert!(!ctx.user_manager.is_last_admin(TEST_USERNAME).unwrap());
+
+	ctx.user_manager.set_is_admin("Skyler", false).unwrap();
+	assert!(ctx.user_manager.is_last_admin(TEST_USERNAME).unwrap());
+	assert!(!ctx.user_manager.is_last_admin("Skyler").unwrap());
+}
+
+#[test]
+fn creates_initial_admin_only_without_users() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+
+	assert!(ctx
+		.user_manager
+		.create_initial_admin(TEST_USERNAME, TEST_PASSWORD)
+		.unwrap());
+	assert!(ctx.user_manager.is_admin(TEST_USERNAME).unwrap());
+	assert!(ctx
+		.user_manager
+		.check_credentials(TEST_USERNAME, TEST_PASSWORD)
+		.is_ok());
+
+	assert!(!ctx
+		.user_manager
+		.create_initial_admin("Skyler", TEST_PASSWORD)
+		.unwrap());
+	assert!(!ctx.user_manager.exists("Skyler").unwrap());
+}
+
+#[test]
+fn change_password_requires_current_password() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USERNAME, TEST_PASSWORD, false)
+		.build();
+
+	assert_eq!(
+		ctx.user_manager
+			.change_password(TEST_USERNAME, "wrong_password", "new_password", None)
+			.unwrap_err(),
+		Error::IncorrectPassword
+	);
+	ctx.user_manager
+		.check_credentials(TEST_USERNAME, TEST_PASSWORD)
+		.unwrap();
+
+	ctx.user_manager
+		.change_password(TEST_USERNAME, TEST_PASSWORD, "new_password", None)
+		.unwrap();
+	ctx.user_manager
+		.check_credentials(TEST_USERNAME, "new_password")
+		.unwrap();
+}
+
+#[test]
+fn change_password_revokes_other_sessions() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USERNAME, TEST_PASSWORD, false)
+		.build();
+
+	let current_token = ctx
+		.user_manager
+		.login(TEST_USERNAME, TEST_PASSWORD, None)
+		.unwrap();
+	let other_token = ctx
+		.user_manager
+		.login(TEST_USERNAME, TEST_PASSWORD, None)
+		.unwrap();
+	let current_session = ctx
+		.user_manager
+		.authenticate(&current_token, AuthorizationScope::PolarisAuth)
+		.unwrap()
+		.session;
+	let other_session = ctx
+		.user_manager
+		.authenticate(&other_token, AuthorizationScope::PolarisAuth)
+		.unwrap()
+		.session;
+
+	ctx.user_manager
+		.change_password(
+			TEST_USERNAME,
+			TEST_PASSWORD,
+			"new_password",
+			current_session,
+		)
+		.unwrap();
+
+	assert!(ctx
+		.user_manager
+		.authenticate_session(current_session.unwrap())
+		.is_ok());
+	assert_eq!(
+		ctx.user_manager
+			.authenticate_session(other_session.unwrap())
+			.unwrap_err(),
+		Error::InvalidAuthToken
+	);
+}
+
+#[test]
+fn set_password_revokes_sessions() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USERNAME, TEST_PASSWORD, false)
+		.build();
+
+	let token = ctx
+		.user_manager
+		.login(TEST_USERNAME, TEST_PASSWORD, None)
+		.unwrap();
+	let session = ctx
+		.user_manager
+		.authenticate(&token, AuthorizationScope::PolarisAuth)
+		.unwrap()
+		.session;
+
+	ctx.user_manager
+		.set_password(TEST_USERNAME, "new_password")
+		.unwrap();
+
+	assert_eq!(
+		ctx.user_manager
+			.authenticate_session(session.unwrap())
+			.unwrap_err(),
+		Error::InvalidAuthToken
+	);
+	assert_eq!(
+		ctx.user_manager
+			.authenticate(&token, AuthorizationScope::PolarisAuth)
+			.unwrap_err(),
+		Error::InvalidAuthToken
+	);
+}
+
+#[test]
+fn login_upgrades_outdated_password_hash() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USERNAME, TEST_PASSWORD, false)
+		.build();
+
+	let read_hash = || -> String {
+		use crate::db::users::dsl::*;
+		let connection = ctx.db.connect().unwrap();
+		users
+			.select(password_hash)
+			.filter(name.eq(TEST_USERNAME))
+			.get_result(&connection)
+			.unwrap()
+	};
+
+	let old_hash = pbkdf2::pbkdf2_simple(TEST_PASSWORD, 1000).unwrap();
+	{
+		use crate::db::users::dsl::*;
+		let connection = ctx.db.connect().unwrap();
+		diesel::update(users.filter(name.eq(TEST_USERNAME)))
+			.set(password_hash.eq(&old_hash))
+			.execute(&connection)
+			.unwrap();
+	}
+
+	ctx.user_manager
+		.login(TEST_USERNAME, TEST_PASSWORD, None)
+		.unwrap();
+	let upgraded_hash = read_hash();
+	assert_ne!(upgraded_hash, old_hash);
+
+	ctx.user_manager
+		.login(TEST_USERNAME, TEST_PASSWORD, None)
+		.unwrap();
+	assert_eq!(read_hash(), upgraded_hash);
+}
+
 #[test]
 fn cannot_create_user_with_blank_username() {
 	let ctx = test::ContextBuilder::new(test_name!()).build();
@@ -70,6 +262,7 @@ fn can_read_write_preferences() {
 		web_theme_base: Some("very-dark-theme".to_owned()),
 		web_theme_accent: Some("#FF0000".to_owned()),
 		lastfm_username: None,
+		random_seed: Some(u64::MAX),
 	};
 
 	let new_user = NewUser {
@@ -100,7 +293,7 @@ fn login_rejects_bad_password() {
 	ctx.user_manager.create(&new_user).unwrap();
 	assert_eq!(
 		ctx.user_manager
-			.login(TEST_USERNAME, "not the password")
+			.login(TEST_USERNAME, "not the password", None)
 			.unwrap_err(),
 		Error::IncorrectPassword
 	)
@@ -115,7 +308,10 @@ fn login_golden_path() {
 		admin: false,
 	};
 	ctx.user_manager.create(&new_user).unwrap();
-	assert!(ctx.user_manager.login(TEST_USERNAME, TEST_PASSWORD).is_ok())
+	assert!(ctx
+		.user_manager
+		.login(TEST_USERNAME, TEST_PASSWORD, None)
+		.is_ok())
 }
 
 #[test]
@@ -149,17 +345,19 @@ fn authenticate_golden_path() {
 	ctx.user_manager.create(&new_user).unwrap();
 	let token = ctx
 		.user_manager
-		.login(TEST_USERNAME, TEST_PASSWORD)
+		.login(TEST_USERNAME, TEST_PASSWORD, None)
 		.unwrap();
 	let authorization = ctx
 		.user_manager
 		.authenticate(&token, AuthorizationScope::PolarisAuth)
 		.unwrap();
+	let sessions = ctx.user_manager.list_sessions(TEST_USERNAME).unwrap();
 	assert_eq!(
 		authorization,
 		Authorization {
 			username: TEST_USERNAME.to_owned(),
 			scope: AuthorizationScope::PolarisAuth,
+			session: Some(sessions[0].id),
 		}
 	)
 }
@@ -187,3 +385,313 @@ fn authenticate_validates_scope() {
 		Error::IncorrectAuthorizationScope
 	)
 }
+
+#[test]
+fn login_opens_session() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	let new_user = NewUser {
+		name: TEST_USERNAME.to_owned(),
+		password: TEST_PASSWORD.to_owned(),
+		admin: false,
+	};
+	ctx.user_manager.create(&new_user).unwrap();
+	ctx.user_manager
+		.login(TEST_USERNAME, TEST_PASSWORD, Some("Test Client"))
+		.unwrap();
+
+	let sessions = ctx.user_manager.list_sessions(TEST_USERNAME).unwrap();
+	assert_eq!(sessions.len(), 1);
+	assert_eq!(sessions[0].username, TEST_USERNAME);
+	assert_eq!(sessions[0].label.as_deref(), Some("Test Client"));
+	assert_eq!(sessions[0].created, sessions[0].last_seen);
+}
+
+#[test]
+fn revoking_session_invalidates_token() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	let new_user = NewUser {
+		name: TEST_USERNAME.to_owned(),
+		password: TEST_PASSWORD.to_owned(),
+		admin: false,
+	};
+	ctx.user_manager.create(&new_user).unwrap();
+	let token = ctx
+		.user_manager
+		.login(TEST_USERNAME, TEST_PASSWORD, None)
+		.unwrap();
+	let other_token = ctx
+		.user_manager
+		.login(TEST_USERNAME, TEST_PASSWORD, None)
+		.unwrap();
+
+	let authorization = ctx
+		.user_manager
+		.authenticate(&token, AuthorizationScope::PolarisAuth)
+		.unwrap();
+	ctx.user_manager
+		.revoke_session(TEST_USERNAME, authorization.session.unwrap())
+		.unwrap();
+
+	assert_eq!(
+		ctx.user_manager
+			.authenticate(&token, AuthorizationScope::PolarisAuth)
+			.unwrap_err(),
+		Error::InvalidAuthToken
+	);
+	assert!(ctx
+		.user_manager
+		.authenticate(&other_token, AuthorizationScope::PolarisAuth)
+		.is_ok());
+	assert_eq!(
+		ctx.user_manager.list_sessions(TEST_USERNAME).unwrap().len(),
+		1
+	);
+}
+
+#[test]
+fn cannot_revoke_session_of_other_user() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USERNAME, TEST_PASSWORD, false)
+		.user("Lisa", "another_secret!", false)
+		.build();
+	ctx.user_manager
+		.login(TEST_USERNAME, TEST_PASSWORD, None)
+		.unwrap();
+	let session = ctx.user_manager.list_sessions(TEST_USERNAME).unwrap()[0].id;
+
+	assert_eq!(
+		ctx.user_manager
+			.revoke_session("Lisa", session)
+			.unwrap_err(),
+		Error::SessionNotFound
+	);
+	assert_eq!(ctx.user_manager.list_all_sessions().unwrap().len(), 1);
+}
+
+#[test]
+fn session_expires_after_lifetime() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USERNAME, TEST_PASSWORD, false)
+		.build();
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			session_lifetime_seconds: Some(60 * 60),
+			..Default::default()
+		})
+		.unwrap();
+	let token = ctx
+		.user_manager
+		.login(TEST_USERNAME, TEST_PASSWORD, None)
+		.unwrap();
+	assert!(ctx
+		.user_manager
+		.authenticate(&token, AuthorizationScope::PolarisAuth)
+		.is_ok());
+
+	diesel::update(sessions::table)
+		.set(sessions::created.eq(sessions::created - 60 * 60))
+		.execute(&ctx.db.connect().unwrap())
+		.unwrap();
+	assert_eq!(
+		ctx.user_manager
+			.authenticate(&token, AuthorizationScope::PolarisAuth)
+			.unwrap_err(),
+		Error::InvalidAuthToken
+	);
+}
+
+#[test]
+fn resuming_session_reuses_session_with_same_label() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USERNAME, TEST_PASSWORD, false)
+		.build();
+
+	let token = ctx
+		.user_manager
+		.resume_session(TEST_USERNAME, Some("curl"))
+		.unwrap();
+	let same_token = ctx
+		.user_manager
+		.resume_session(TEST_USERNAME, Some("curl"))
+		.unwrap();
+	ctx.user_manager
+		.resume_session(TEST_USERNAME, Some("Firefox"))
+		.unwrap();
+	ctx.user_manager
+		.resume_session(TEST_USERNAME, None)
+		.unwrap();
+	ctx.user_manager
+		.resume_session(TEST_USERNAME, None)
+		.unwrap();
+
+	let session = |token| {
+		ctx.user_manager
+			.authenticate(token, AuthorizationScope::PolarisAuth)
+			.unwrap()
+			.session
+	};
+	assert_eq!(session(&token), session(&same_token));
+	assert_eq!(
+		ctx.user_manager.list_sessions(TEST_USERNAME).unwrap().len(),
+		3
+	);
+}
+
+#[test]
+fn opening_session_deletes_expired_sessions() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USERNAME, TEST_PASSWORD, false)
+		.build();
+	ctx.user_manager
+		.login(TEST_USERNAME, TEST_PASSWORD, None)
+		.unwrap();
+
+	let lifetime = ctx.user_manager.session_lifetime().unwrap().as_secs() as i64;
+	diesel::update(sessions::table)
+		.set(sessions::created.eq(sessions::created - lifetime))
+		.execute(&ctx.db.connect().unwrap())
+		.unwrap();
+	ctx.user_manager
+		.resume_session(TEST_USERNAME, None)
+		.unwrap();
+
+	let sessions = ctx.user_manager.list_sessions(TEST_USERNAME).unwrap();
+	assert_eq!(sessions.len(), 1);
+}
+
+#[test]
+fn deleting_user_deletes_sessions() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	let new_user = NewUser {
+		name: TEST_USERNAME.to_owned(),
+		password: TEST_PASSWORD.to_owned(),
+		admin: false,
+	};
+	ctx.user_manager.create(&new_user).unwrap();
+	ctx.user_manager
+		.login(TEST_USERNAME, TEST_PASSWORD, None)
+		.unwrap();
+
+	ctx.user_manager.delete(TEST_USERNAME).unwrap();
+
+	// The new user may be given the id of the deleted one
+	let other_user = NewUser {
+		name: "Jesse".to_owned(),
+		password: TEST_PASSWORD.to_owned(),
+		admin: false,
+	};
+	ctx.user_manager.create(&other_user).unwrap();
+	assert!(ctx.user_manager.list_sessions("Jesse").unwrap().is_empty());
+	assert!(ctx.user_manager.list_all_sessions().unwrap().is_empty());
+}
+
+#[test]
+fn can_link_and_unlink_listenbrainz() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+
+	let new_user = NewUser {
+		name: TEST_USERNAME.to_owned(),
+		password: TEST_PASSWORD.to_owned(),
+		admin: false,
+	};
+	ctx.user_manager.create(&new_user).unwrap();
+	assert!(!ctx.user_manager.is_listenbrainz_linked(TEST_USERNAME));
+
+	ctx.user_manager
+		.listenbrainz_link(TEST_USERNAME, "some_token")
+		.unwrap();
+	assert!(ctx.user_manager.is_listenbrainz_linked(TEST_USERNAME));
+	assert_eq!(
+		ctx.user_manager
+			.get_listenbrainz_token(TEST_USERNAME)
+			.unwrap(),
+		"some_token"
+	);
+
+	ctx.user_manager.listenbrainz_unlink(TEST_USERNAME).unwrap();
+	assert!(!ctx.user_manager.is_listenbrainz_linked(TEST_USERNAME));
+}
+
+#[test]
+fn jwt_golden_path() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USERNAME, TEST_PASSWORD, true)
+		.build();
+
+	let (token, claims) = ctx.user_manager.generate_jwt(TEST_USERNAME).unwrap();
+	assert!(token.is_jwt());
+	assert_eq!(claims.sub, TEST_USERNAME);
+	assert!(claims.admin);
+	assert!(claims.exp > claims.iat);
+
+	let authenticated_claims = ctx.user_manager.authenticate_jwt(&token).unwrap();
+	assert_eq!(authenticated_claims, claims);
+}
+
+#[test]
+fn jwt_expiry_follows_session_lifetime() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USERNAME, TEST_PASSWORD, false)
+		.build();
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			session_lifetime_seconds: Some(7 * 24 * 60 * 60),
+			..Default::default()
+		})
+		.unwrap();
+
+	let (_, claims) = ctx.user_manager.generate_jwt(TEST_USERNAME).unwrap();
+	assert_eq!(claims.exp - claims.iat, 7 * 24 * 60 * 60);
+}
+
+#[test]
+fn jwt_rejects_tampered_claims() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USERNAME, TEST_PASSWORD, false)
+		.user("Other", TEST_PASSWORD, false)
+		.build();
+
+	let (AuthToken(token), _) = ctx.user_manager.generate_jwt(TEST_USERNAME).unwrap();
+	let (other_token, _) = ctx.user_manager.generate_jwt("Other").unwrap();
+	let other_claims = other_token.0.split('.').nth(1).unwrap();
+
+	let segments: Vec<&str> = token.split('.').collect();
+	let tampered = AuthToken(format!("{}.{}.{}", segments[0], other_claims, segments[2]));
+	assert_eq!(
+		ctx.user_manager.authenticate_jwt(&tampered).unwrap_err(),
+		Error::InvalidAuthToken
+	);
+}
+
+#[test]
+fn jwt_rejects_expired_token() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USERNAME, TEST_PASSWORD, false)
+		.build();
+
+	let claims = Claims {
+		sub: TEST_USERNAME.to_owned(),
+		admin: false,
+		iat: 0,
+		exp: 60,
+	};
+	let token = ctx.user_manager.encode_jwt(&claims).unwrap();
+	assert_eq!(
+		ctx.user_manager.authenticate_jwt(&token).unwrap_err(),
+		Error::InvalidAuthToken
+	);
+}
+
+#[test]
+fn jwt_rejects_deleted_user() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.user(TEST_USERNAME, TEST_PASSWORD, false)
+		.build();
+
+	let (token, _) = ctx.user_manager.generate_jwt(TEST_USERNAME).unwrap();
+	ctx.user_manager.delete(TEST_USERNAME).unwrap();
+	assert_eq!(
+		ctx.user_manager.authenticate_jwt(&token).unwrap_err(),
+		Error::IncorrectUsername
+	);
+}
@@ -2,8 +2,7 @@ use anyhow::*;
 use diesel::prelude::*;
 
 use super::*;
-use crate::db::mount_points;
-use crate::db::DB;
+use crate::db::{misc_settings, mount_points, DB};
 
 #[derive(Clone)]
 pub struct Manager {
@@ -18,14 +17,23 @@ impl Manager {
 	pub fn get_vfs(&self) -> Result<VFS> {
 		let mount_dirs = self.mount_dirs()?;
 		let mounts = mount_dirs.into_iter().map(|p| p.into()).collect();
-		Ok(VFS::new(mounts))
+		Ok(VFS::new(mounts, self.normalizes_separators()?))
+	}
+
+	fn normalizes_separators(&self) -> Result<bool> {
+		let connection = self.db.connect()?;
+		let normalize: Option<i32> = misc_settings::table
+			.select(misc_settings::normalize_path_separators)
+			.get_result(&connection)
+			.optional()?;
+		Ok(normalize.map(|n| n != 0).unwrap_or(true))
 	}
 
 	pub fn mount_dirs(&self) -> Result<Vec<MountDir>> {
 		use self::mount_points::dsl::*;
 		let connection = self.db.connect()?;
 		let mount_dirs: Vec<MountDir> = mount_points
-			.select((source, name))
+			.select((source, name, scan_interval_seconds))
 			.get_results(&connection)?;
 		Ok(mount_dirs)
 	}
@@ -1,8 +1,7 @@
 use anyhow::*;
-use core::ops::Deref;
-use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::path::{self, Path, PathBuf};
+use std::borrow::Cow;
+use std::path::{self, Component, Path, PathBuf};
 
 use crate::db::mount_points;
 
@@ -17,6 +16,10 @@ pub use manager::*;
 pub struct MountDir {
 	pub source: String,
 	pub name: String,
+	// How often this mount is scanned automatically, zero for manual scans only. Mounts without
+	// their own interval follow the index sleep duration setting.
+	#[serde(default)]
+	pub scan_interval_seconds: Option<i32>,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
@@ -27,11 +30,7 @@ pub struct Mount {
 
 impl From<MountDir> for Mount {
 	fn from(m: MountDir) -> Self {
-		let separator_regex = Regex::new(r"\\|/").unwrap();
-		let mut correct_separator = String::new();
-		correct_separator.push(path::MAIN_SEPARATOR);
-		let path_string = separator_regex.replace_all(&m.source, correct_separator.as_str());
-		let source = PathBuf::from(path_string.deref());
+		let source = PathBuf::from(normalize_separators(&m.source));
 		Self {
 			name: m.name,
 			source: source,
@@ -39,18 +38,54 @@ impl From<MountDir> for Mount {
 	}
 }
 
+// Rewrites both Windows and Unix separators into the separator of the current platform
+fn normalize_separators(path: &str) -> String {
+	path.chars()
+		.map(|c| match c {
+			'\\' | '/' => path::MAIN_SEPARATOR,
+			_ => c,
+		})
+		.collect()
+}
+
+fn is_foreign_separator(c: char) -> bool {
+	(c == '\\' || c == '/') && c != path::MAIN_SEPARATOR
+}
+
 pub struct VFS {
 	mounts: Vec<Mount>,
+	normalize_separators: bool,
 }
 
 impl VFS {
-	pub fn new(mounts: Vec<Mount>) -> VFS {
-		VFS { mounts }
+	pub fn new(mounts: Vec<Mount>, normalize_separators: bool) -> VFS {
+		VFS {
+			mounts,
+			normalize_separators,
+		}
+	}
+
+	// Paths written on another OS (eg. playlists or libraries migrated from Windows to Linux) may use
+	// a separator that this platform does not recognize
+	fn canonicalize<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+		if !self.normalize_separators {
+			return Cow::Borrowed(path);
+		}
+		match path.to_str() {
+			Some(s) if s.contains(is_foreign_separator) => {
+				Cow::Owned(PathBuf::from(normalize_separators(s)))
+			}
+			_ => Cow::Borrowed(path),
+		}
 	}
 
 	pub fn real_to_virtual<P: AsRef<Path>>(&self, real_path: P) -> Result<PathBuf> {
+		let real_path = self.canonicalize(real_path.as_ref());
 		for mount in &self.mounts {
-			if let Ok(p) = real_path.as_ref().strip_prefix(&mount.source) {
+			if let Ok(p) = real_path.strip_prefix(&mount.source) {
+				if p.components().any(|c| !matches!(c, Component::Normal(_))) {
+					bail!("Real path must not leave its mount");
+				}
 				let mount_path = Path::new(&mount.name);
 				return if p.components().count() == 0 {
 					Ok(mount_path.to_path_buf())
@@ -63,19 +98,43 @@ impl VFS {
 	}
 
 	pub fn virtual_to_real<P: AsRef<Path>>(&self, virtual_path: P) -> Result<PathBuf> {
+		let virtual_path = self.canonicalize(virtual_path.as_ref());
 		for mount in &self.mounts {
 			let mount_path = Path::new(&mount.name);
-			if let Ok(p) = virtual_path.as_ref().strip_prefix(mount_path) {
-				return if p.components().count() == 0 {
-					Ok(mount.source.clone())
+			if let Ok(p) = virtual_path.strip_prefix(mount_path) {
+				// Anything but plain names could resolve outside of the mount
+				if p.components().any(|c| !matches!(c, Component::Normal(_))) {
+					bail!("Virtual path must not leave its mount");
+				}
+				let real_path = if p.components().count() == 0 {
+					mount.source.clone()
 				} else {
-					Ok(mount.source.join(p))
+					mount.source.join(p)
 				};
+				if real_path.exists() && !self.contains(&real_path) {
+					bail!("Virtual path resolves outside of the VFS mounts");
+				}
+				return Ok(real_path);
 			}
 		}
 		bail!("Virtual path has no match in VFS")
 	}
 
+	// Symbolic links are resolved, so a link pointing outside of every mount is not contained.
+	// Paths which do not exist cannot be resolved and are never contained.
+	pub fn contains<P: AsRef<Path>>(&self, real_path: P) -> bool {
+		let real_path = match real_path.as_ref().canonicalize() {
+			Ok(p) => p,
+			Err(_) => return false,
+		};
+		self.mounts.iter().any(|m| {
+			m.source
+				.canonicalize()
+				.map(|source| real_path.starts_with(source))
+				.unwrap_or(false)
+		})
+	}
+
 	pub fn mounts(&self) -> &Vec<Mount> {
 		&self.mounts
 	}
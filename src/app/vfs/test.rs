@@ -1,13 +1,18 @@
 use std::path::{Path, PathBuf};
 
 use super::*;
+use crate::test::prepare_test_directory;
+use crate::test_name;
 
 #[test]
 fn converts_virtual_to_real() {
-	let vfs = VFS::new(vec![Mount {
-		name: "root".to_owned(),
-		source: Path::new("test_dir").to_owned(),
-	}]);
+	let vfs = VFS::new(
+		vec![Mount {
+			name: "root".to_owned(),
+			source: Path::new("test_dir").to_owned(),
+		}],
+		true,
+	);
 	let real_path: PathBuf = ["test_dir", "somewhere", "something.png"].iter().collect();
 	let virtual_path: PathBuf = ["root", "somewhere", "something.png"].iter().collect();
 	let converted_path = vfs.virtual_to_real(virtual_path.as_path()).unwrap();
@@ -16,27 +21,143 @@ fn converts_virtual_to_real() {
 
 #[test]
 fn converts_virtual_to_real_top_level() {
-	let vfs = VFS::new(vec![Mount {
-		name: "root".to_owned(),
-		source: Path::new("test_dir").to_owned(),
-	}]);
+	let vfs = VFS::new(
+		vec![Mount {
+			name: "root".to_owned(),
+			source: Path::new("test_dir").to_owned(),
+		}],
+		true,
+	);
 	let real_path = Path::new("test_dir");
 	let converted_path = vfs.virtual_to_real(Path::new("root")).unwrap();
 	assert_eq!(converted_path, real_path);
 }
 
+#[test]
+fn rejects_virtual_paths_leaving_mount() {
+	let vfs = VFS::new(
+		vec![Mount {
+			name: "root".to_owned(),
+			source: Path::new("test_dir").to_owned(),
+		}],
+		true,
+	);
+	let escaping: PathBuf = ["root", "..", "secret.txt"].iter().collect();
+	assert!(vfs.virtual_to_real(&escaping).is_err());
+	let escaping: PathBuf = ["root", "somewhere", "..", "..", "secret.txt"]
+		.iter()
+		.collect();
+	assert!(vfs.virtual_to_real(&escaping).is_err());
+	assert!(vfs
+		.virtual_to_real(Path::new("root/../secret.txt"))
+		.is_err());
+	assert!(vfs
+		.virtual_to_real(Path::new("root\\..\\secret.txt"))
+		.is_err());
+}
+
+#[test]
+fn virtual_to_real_ignores_absolute_path_injection() {
+	let vfs = VFS::new(
+		vec![Mount {
+			name: "root".to_owned(),
+			source: Path::new("test_dir").to_owned(),
+		}],
+		true,
+	);
+	assert!(vfs.virtual_to_real(Path::new("/etc/passwd")).is_err());
+	let real_path = vfs.virtual_to_real(Path::new("root//etc/passwd")).unwrap();
+	assert_eq!(
+		real_path,
+		["test_dir", "etc", "passwd"].iter().collect::<PathBuf>()
+	);
+}
+
+#[cfg(unix)]
+#[test]
+fn virtual_to_real_rejects_symlinks_leaving_mounts() {
+	let test_directory = prepare_test_directory(test_name!());
+	let mount_directory = test_directory.join("mount");
+	std::fs::create_dir_all(&mount_directory).unwrap();
+	std::fs::write(mount_directory.join("inside.mp3"), b"").unwrap();
+	std::fs::write(test_directory.join("outside.mp3"), b"").unwrap();
+	let outside = test_directory.join("outside.mp3").canonicalize().unwrap();
+	std::os::unix::fs::symlink(&outside, mount_directory.join("escape.mp3")).unwrap();
+	std::os::unix::fs::symlink("inside.mp3", mount_directory.join("alias.mp3")).unwrap();
+
+	let vfs = VFS::new(
+		vec![Mount {
+			name: "root".to_owned(),
+			source: mount_directory.clone(),
+		}],
+		true,
+	);
+	assert!(vfs.virtual_to_real(Path::new("root/escape.mp3")).is_err());
+	assert!(!vfs.contains(mount_directory.join("escape.mp3")));
+	assert!(!vfs.contains(&outside));
+
+	let alias = vfs.virtual_to_real(Path::new("root/alias.mp3")).unwrap();
+	assert_eq!(alias, mount_directory.join("alias.mp3"));
+	assert!(vfs.contains(&alias));
+}
+
+#[test]
+fn converts_virtual_to_real_across_mounts() {
+	let vfs = VFS::new(
+		vec![
+			Mount {
+				name: "music".to_owned(),
+				source: Path::new("flac_drive").to_owned(),
+			},
+			Mount {
+				name: "music_podcasts".to_owned(),
+				source: Path::new("podcast_drive").to_owned(),
+			},
+		],
+		true,
+	);
+	let real_path: PathBuf = ["podcast_drive", "episode.mp3"].iter().collect();
+	let virtual_path: PathBuf = ["music_podcasts", "episode.mp3"].iter().collect();
+	assert_eq!(vfs.virtual_to_real(&virtual_path).unwrap(), real_path);
+	assert_eq!(vfs.real_to_virtual(&real_path).unwrap(), virtual_path);
+
+	let real_path: PathBuf = ["flac_drive", "song.flac"].iter().collect();
+	let virtual_path: PathBuf = ["music", "song.flac"].iter().collect();
+	assert_eq!(vfs.virtual_to_real(&virtual_path).unwrap(), real_path);
+	assert_eq!(vfs.real_to_virtual(&real_path).unwrap(), virtual_path);
+
+	assert!(vfs.virtual_to_real(Path::new("other")).is_err());
+}
+
 #[test]
 fn converts_real_to_virtual() {
-	let vfs = VFS::new(vec![Mount {
-		name: "root".to_owned(),
-		source: Path::new("test_dir").to_owned(),
-	}]);
+	let vfs = VFS::new(
+		vec![Mount {
+			name: "root".to_owned(),
+			source: Path::new("test_dir").to_owned(),
+		}],
+		true,
+	);
 	let virtual_path: PathBuf = ["root", "somewhere", "something.png"].iter().collect();
 	let real_path: PathBuf = ["test_dir", "somewhere", "something.png"].iter().collect();
 	let converted_path = vfs.real_to_virtual(real_path.as_path()).unwrap();
 	assert_eq!(converted_path, virtual_path);
 }
 
+#[test]
+fn rejects_real_paths_leaving_mount() {
+	let vfs = VFS::new(
+		vec![Mount {
+			name: "root".to_owned(),
+			source: Path::new("test_dir").to_owned(),
+		}],
+		true,
+	);
+	let escaping: PathBuf = ["test_dir", "..", "secret.txt"].iter().collect();
+	assert!(vfs.real_to_virtual(&escaping).is_err());
+	assert!(vfs.real_to_virtual(Path::new("other_dir")).is_err());
+}
+
 #[test]
 fn cleans_path_string() {
 	let mut correct_path = path::PathBuf::new();
@@ -70,8 +191,67 @@ fn cleans_path_string() {
 		let mount_dir = MountDir {
 			source: test.to_owned(),
 			name: "name".to_owned(),
+			scan_interval_seconds: None,
 		};
 		let mount: Mount = mount_dir.into();
 		assert_eq!(mount.source, correct_path);
 	}
 }
+
+#[test]
+fn converts_virtual_to_real_with_foreign_separators() {
+	let vfs = VFS::new(
+		vec![Mount {
+			name: "root".to_owned(),
+			source: Path::new("test_dir").to_owned(),
+		}],
+		true,
+	);
+	let real_path: PathBuf = ["test_dir", "somewhere", "something.png"].iter().collect();
+	for virtual_path in &[
+		r#"root\somewhere\something.png"#,
+		r#"root/somewhere/something.png"#,
+		r#"root\somewhere/something.png"#,
+	] {
+		let converted_path = vfs.virtual_to_real(Path::new(virtual_path)).unwrap();
+		assert_eq!(converted_path, real_path);
+	}
+}
+
+#[test]
+fn converts_real_to_virtual_with_foreign_separators() {
+	let vfs = VFS::new(
+		vec![Mount {
+			name: "root".to_owned(),
+			source: Path::new("test_dir").to_owned(),
+		}],
+		true,
+	);
+	let virtual_path: PathBuf = ["root", "somewhere", "something.png"].iter().collect();
+	for real_path in &[
+		r#"test_dir\somewhere\something.png"#,
+		r#"test_dir/somewhere/something.png"#,
+		r#"test_dir/somewhere\something.png"#,
+	] {
+		let converted_path = vfs.real_to_virtual(Path::new(real_path)).unwrap();
+		assert_eq!(converted_path, virtual_path);
+	}
+}
+
+#[test]
+fn can_disable_separator_normalization() {
+	let vfs = VFS::new(
+		vec![Mount {
+			name: "root".to_owned(),
+			source: Path::new("test_dir").to_owned(),
+		}],
+		false,
+	);
+	let foreign_separator = if cfg!(target_os = "windows") {
+		"/"
+	} else {
+		"\\"
+	};
+	let virtual_path = ["root", "somewhere", "something.png"].join(foreign_separator);
+	assert!(vfs.virtual_to_real(Path::new(&virtual_path)).is_err());
+}
@@ -0,0 +1,13 @@
+use crate::app::transcode;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("Number of bins must be between 1 and {}", super::MAX_BINS)]
+	UnsupportedBinCount,
+	#[error(transparent)]
+	Decoding(#[from] transcode::Error),
+	#[error(transparent)]
+	Io(#[from] std::io::Error),
+	#[error(transparent)]
+	Serialization(#[from] serde_json::Error),
+}
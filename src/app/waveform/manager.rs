@@ -0,0 +1,68 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::app::transcode;
+use crate::app::waveform::*;
+
+#[derive(Clone)]
+pub struct Manager {
+	waveforms_dir_path: PathBuf,
+	transcode_manager: transcode::Manager,
+}
+
+impl Manager {
+	pub fn new(waveforms_dir_path: PathBuf, transcode_manager: transcode::Manager) -> Self {
+		Self {
+			waveforms_dir_path,
+			transcode_manager,
+		}
+	}
+
+	/// Returns the path of a JSON file listing the peaks of an audio file. Waveforms are cached
+	/// until the audio file is modified.
+	pub fn get_waveform(&self, audio_path: &Path, bins: u32) -> Result<PathBuf, Error> {
+		if bins == 0 || bins > MAX_BINS {
+			return Err(Error::UnsupportedBinCount);
+		}
+
+		let modified = fs::metadata(audio_path)?.modified()?;
+		let waveform_path = self.get_waveform_path(audio_path, bins, modified);
+		if waveform_path.exists() {
+			return Ok(waveform_path);
+		}
+
+		let peaks = self.compute_peaks(audio_path, bins)?;
+
+		// Written under a temporary name first, so that concurrent requests never read a
+		// partial file
+		fs::create_dir_all(&self.waveforms_dir_path)?;
+		let temporary_path = waveform_path.with_extension(format!("{}.tmp", rand::random::<u64>()));
+		fs::write(&temporary_path, serde_json::to_vec(&peaks)?)?;
+		if let Err(e) = fs::rename(&temporary_path, &waveform_path) {
+			let _ = fs::remove_file(&temporary_path);
+			return Err(e.into());
+		}
+		Ok(waveform_path)
+	}
+
+	fn compute_peaks(&self, audio_path: &Path, bins: u32) -> Result<Vec<Peak>, Error> {
+		let mut decoder = self.transcode_manager.decode(audio_path)?;
+		let mut reader = PeakReader::default();
+		while let Some(chunk) = decoder.read_chunk()? {
+			reader.push(&chunk);
+		}
+		Ok(reader.finish(bins))
+	}
+
+	fn get_waveform_path(&self, audio_path: &Path, bins: u32, modified: SystemTime) -> PathBuf {
+		let mut hasher = DefaultHasher::new();
+		audio_path.hash(&mut hasher);
+		bins.hash(&mut hasher);
+		modified.hash(&mut hasher);
+		self.waveforms_dir_path
+			.join(format!("{}.json", hasher.finish()))
+	}
+}
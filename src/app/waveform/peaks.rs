@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_BINS: u32 = 1000;
+pub const MAX_BINS: u32 = 10_000;
+
+// Samples are reduced into blocks as they are decoded, so that memory use does not grow with the
+// full sample count
+const BLOCK_SIZE: usize = 256;
+
+/// Lowest and highest amplitude within a bin, scaled to -128..=127. Serialized as a pair.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Peak(pub i8, pub i8);
+
+/// Accumulates raw samples, as signed 16-bit little-endian integers, into peaks.
+#[derive(Default)]
+pub struct PeakReader {
+	blocks: Vec<(i16, i16)>,
+	current_block: Option<(i16, i16)>,
+	samples_in_block: usize,
+	pending_byte: Option<u8>,
+}
+
+impl PeakReader {
+	// Chunks may end in the middle of a sample, in which case its first byte is kept for the next
+	// chunk
+	pub fn push(&mut self, bytes: &[u8]) {
+		let mut bytes = bytes.iter().copied();
+		while let Some(low) = self.pending_byte.take().or_else(|| bytes.next()) {
+			match bytes.next() {
+				Some(high) => self.push_sample(i16::from_le_bytes([low, high])),
+				None => {
+					self.pending_byte = Some(low);
+					break;
+				}
+			}
+		}
+	}
+
+	fn push_sample(&mut self, sample: i16) {
+		let (min, max) = self.current_block.unwrap_or((sample, sample));
+		self.current_block = Some((min.min(sample), max.max(sample)));
+		self.samples_in_block += 1;
+		if self.samples_in_block == BLOCK_SIZE {
+			self.blocks.extend(self.current_block.take());
+			self.samples_in_block = 0;
+		}
+	}
+
+	/// Splits the samples read so far into evenly sized bins. Audio shorter than the number of
+	/// bins repeats its blocks across neighbouring bins, and empty audio has no peaks.
+	pub fn finish(mut self, bins: u32) -> Vec<Peak> {
+		self.blocks.extend(self.current_block.take());
+		let num_blocks = self.blocks.len();
+		if num_blocks == 0 {
+			return Vec::new();
+		}
+		let bins = bins as usize;
+		(0..bins)
+			.map(|bin| {
+				let start = bin * num_blocks / bins;
+				let end = ((bin + 1) * num_blocks / bins).max(start + 1);
+				let (min, max) = self.blocks[start..end]
+					.iter()
+					.fold((i16::MAX, i16::MIN), |(low, high), &(min, max)| {
+						(low.min(min), high.max(max))
+					});
+				Peak(scale(min), scale(max))
+			})
+			.collect()
+	}
+}
+
+fn scale(sample: i16) -> i8 {
+	(sample >> 8) as i8
+}
@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+
+use super::*;
+use crate::app::transcode;
+use crate::test::prepare_test_directory;
+use crate::test_name;
+
+fn samples_to_bytes(samples: &[i16]) -> Vec<u8> {
+	samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+}
+
+#[cfg(unix)]
+fn fake_encoder(test_directory: &Path, script: &str) -> PathBuf {
+	use std::os::unix::fs::PermissionsExt;
+	let path = test_directory.join("encoder.sh");
+	std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+	std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+	path
+}
+
+#[test]
+fn reads_samples_split_across_chunks() {
+	let bytes = samples_to_bytes(&[-32768, 256, 32767]);
+	let mut reader = PeakReader::default();
+	reader.push(&bytes[..1]);
+	reader.push(&bytes[1..4]);
+	reader.push(&bytes[4..]);
+	assert_eq!(reader.finish(1), vec![Peak(-128, 127)]);
+}
+
+#[test]
+fn splits_samples_into_bins() {
+	let mut samples = vec![0; 512];
+	samples[10] = -512;
+	samples[300] = 1024;
+	let mut reader = PeakReader::default();
+	reader.push(&samples_to_bytes(&samples));
+	assert_eq!(reader.finish(2), vec![Peak(-2, 0), Peak(0, 4)]);
+}
+
+#[test]
+fn short_audio_fills_every_bin() {
+	let mut reader = PeakReader::default();
+	reader.push(&samples_to_bytes(&[-256, 512]));
+	assert_eq!(reader.finish(3), vec![Peak(-1, 2); 3]);
+}
+
+#[test]
+fn empty_audio_has_no_peaks() {
+	let reader = PeakReader::default();
+	assert_eq!(reader.finish(DEFAULT_BINS), Vec::new());
+}
+
+#[test]
+fn rejects_unsupported_bin_count() {
+	let test_directory = prepare_test_directory(test_name!());
+	let manager = Manager::new(
+		test_directory.join("waveforms"),
+		transcode::Manager::new(PathBuf::from("ffmpeg")),
+	);
+	for bins in [0, MAX_BINS + 1].iter() {
+		assert!(matches!(
+			manager.get_waveform(Path::new("test-data/formats/sample.mp3"), *bins),
+			Err(Error::UnsupportedBinCount)
+		));
+	}
+}
+
+#[cfg(unix)]
+#[test]
+fn caches_waveform_until_audio_changes() {
+	let test_directory = prepare_test_directory(test_name!());
+	let audio_path = test_directory.join("song.flac");
+	std::fs::write(&audio_path, b"").unwrap();
+
+	let encoder = fake_encoder(&test_directory, r"printf '\000\200\377\177'");
+	let manager = Manager::new(
+		test_directory.join("waveforms"),
+		transcode::Manager::new(encoder.clone()),
+	);
+
+	let waveform_path = manager.get_waveform(&audio_path, 2).unwrap();
+	let waveform: Vec<Peak> =
+		serde_json::from_slice(&std::fs::read(&waveform_path).unwrap()).unwrap();
+	assert_eq!(waveform, vec![Peak(-128, 127); 2]);
+	assert_eq!(
+		std::fs::read_to_string(&waveform_path).unwrap(),
+		"[[-128,127],[-128,127]]"
+	);
+
+	// Cached waveforms do not run the decoder again
+	fake_encoder(&test_directory, "exit 1");
+	assert_eq!(manager.get_waveform(&audio_path, 2).unwrap(), waveform_path);
+
+	let modified = std::fs::metadata(&audio_path).unwrap().modified().unwrap();
+	std::fs::File::options()
+		.write(true)
+		.open(&audio_path)
+		.unwrap()
+		.set_modified(modified - std::time::Duration::from_secs(60))
+		.unwrap();
+	assert!(matches!(
+		manager.get_waveform(&audio_path, 2),
+		Err(Error::Decoding(transcode::Error::EncodingFailed))
+	));
+}
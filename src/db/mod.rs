@@ -1,9 +1,10 @@
 use anyhow::*;
+use diesel::connection::SimpleConnection;
 use diesel::r2d2::{self, ConnectionManager, PooledConnection};
 use diesel::sqlite::SqliteConnection;
-use diesel::RunQueryDsl;
 use diesel_migrations;
 use std::path::Path;
+use std::time::Duration;
 
 mod schema;
 
@@ -13,38 +14,47 @@ pub use self::schema::*;
 const DB_MIGRATIONS_PATH: &str = "migrations";
 embed_migrations!("migrations");
 
+pub const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Health checks answer quickly instead of waiting for a connection when the pool is exhausted
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(1);
+
 #[derive(Clone)]
 pub struct DB {
 	pool: r2d2::Pool<ConnectionManager<SqliteConnection>>,
 }
 
 #[derive(Debug)]
-struct ConnectionCustomizer {}
+struct ConnectionCustomizer {
+	// How long a connection waits for another one to release its lock before giving up
+	busy_timeout: Duration,
+}
 impl diesel::r2d2::CustomizeConnection<SqliteConnection, diesel::r2d2::Error>
 	for ConnectionCustomizer
 {
 	fn on_acquire(&self, connection: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
-		let query = diesel::sql_query(
-			r#"
-			PRAGMA busy_timeout = 60000;
-			PRAGMA journal_mode = WAL;
-			PRAGMA synchronous = NORMAL;
-			PRAGMA foreign_keys = ON;
-		"#,
-		);
-		query
-			.execute(connection)
-			.map_err(|e| diesel::r2d2::Error::QueryError(e))?;
+		// Unlike `sql_query`, `batch_execute` runs every statement rather than only the first one
+		connection
+			.batch_execute(&format!(
+				r#"
+				PRAGMA busy_timeout = {};
+				PRAGMA journal_mode = WAL;
+				PRAGMA synchronous = NORMAL;
+				PRAGMA foreign_keys = ON;
+			"#,
+				self.busy_timeout.as_millis()
+			))
+			.map_err(diesel::r2d2::Error::QueryError)?;
 		Ok(())
 	}
 }
 
 impl DB {
-	pub fn new(path: &Path) -> Result<DB> {
+	pub fn new(path: &Path, busy_timeout: Duration) -> Result<DB> {
 		std::fs::create_dir_all(&path.parent().unwrap())?;
 		let manager = ConnectionManager::<SqliteConnection>::new(path.to_string_lossy());
 		let pool = diesel::r2d2::Pool::builder()
-			.connection_customizer(Box::new(ConnectionCustomizer {}))
+			.connection_customizer(Box::new(ConnectionCustomizer { busy_timeout }))
 			.build(manager)?;
 		let db = DB { pool: pool };
 		db.migrate_up()?;
@@ -55,6 +65,13 @@ impl DB {
 		self.pool.get().map_err(Error::new)
 	}
 
+	/// Runs a trivial query to check that the database can be read.
+	pub fn check_health(&self) -> Result<()> {
+		let connection = self.pool.get_timeout(HEALTH_CHECK_TIMEOUT)?;
+		connection.batch_execute("SELECT 1 FROM misc_settings LIMIT 1")?;
+		Ok(())
+	}
+
 	#[allow(dead_code)]
 	fn migrate_down(&self) -> Result<()> {
 		let connection = self.connect().unwrap();
@@ -86,8 +103,62 @@ fn run_migrations() {
 	use crate::test_name;
 	let output_dir = prepare_test_directory(test_name!());
 	let db_path = output_dir.join("db.sqlite");
-	let db = DB::new(&db_path).unwrap();
+	let db = DB::new(&db_path, DEFAULT_BUSY_TIMEOUT).unwrap();
 
 	db.migrate_down().unwrap();
 	db.migrate_up().unwrap();
 }
+
+#[test]
+fn connections_use_wal_and_busy_timeout() {
+	use crate::test_name;
+	use diesel::sql_types;
+	use diesel::RunQueryDsl;
+
+	#[derive(QueryableByName)]
+	struct BusyTimeout {
+		#[sql_type = "sql_types::Integer"]
+		timeout: i32,
+	}
+
+	#[derive(QueryableByName)]
+	struct JournalMode {
+		#[sql_type = "sql_types::Text"]
+		journal_mode: String,
+	}
+
+	let ctx = crate::app::test::ContextBuilder::new(test_name!())
+		.db_busy_timeout(Duration::from_millis(1234))
+		.build();
+	let connection = ctx.db.connect().unwrap();
+
+	let busy_timeout: BusyTimeout = diesel::sql_query("PRAGMA busy_timeout")
+		.get_result(&connection)
+		.unwrap();
+	assert_eq!(busy_timeout.timeout, 1234);
+
+	let journal_mode: JournalMode = diesel::sql_query("PRAGMA journal_mode")
+		.get_result(&connection)
+		.unwrap();
+	assert_eq!(journal_mode.journal_mode, "wal");
+}
+
+#[test]
+fn deleting_user_cascades_to_playlists() {
+	use crate::db::playlists;
+	use crate::test_name;
+	use diesel::prelude::*;
+
+	let ctx = crate::app::test::ContextBuilder::new(test_name!())
+		.user("test_user", "password", false)
+		.build();
+	ctx.playlist_manager
+		.save_playlist("Chill & Grill", "test_user", &Vec::new())
+		.unwrap();
+
+	ctx.user_manager.delete("test_user").unwrap();
+
+	let connection = ctx.db.connect().unwrap();
+	let count: i64 = playlists::table.count().get_result(&connection).unwrap();
+	assert_eq!(count, 0);
+}
@@ -1,9 +1,21 @@
+table! {
+	artist_images (id) {
+		id -> Integer,
+		name -> Text,
+		path -> Text,
+	}
+}
+
 table! {
 	ddns_config (id) {
 		id -> Integer,
 		host -> Text,
 		username -> Text,
 		password -> Text,
+		provider -> Text,
+		update_interval_seconds -> Integer,
+		last_update_time -> Nullable<Integer>,
+		last_update_error -> Nullable<Text>,
 	}
 }
 
@@ -17,6 +29,7 @@ table! {
 		album -> Nullable<Text>,
 		artwork -> Nullable<Text>,
 		date_added -> Integer,
+		search_text -> Nullable<Text>,
 	}
 }
 
@@ -26,6 +39,24 @@ table! {
 		auth_secret -> Binary,
 		index_sleep_duration_seconds -> Integer,
 		index_album_art_pattern -> Text,
+		index_start_delay_seconds -> Integer,
+		unknown_artist_label -> Text,
+		normalize_path_separators -> Integer,
+		index_trigger_policy -> Text,
+		index_max_tag_length -> Integer,
+		index_last_completed -> Nullable<BigInt>,
+		auth_max_failed_attempts -> Integer,
+		auth_failure_window_seconds -> Integer,
+		auth_realm -> Text,
+		cors_allowed_origins -> Text,
+		session_lifetime_seconds -> Integer,
+		thumbnail_cache_max_size_bytes -> BigInt,
+		index_audio_extensions -> Text,
+		follow_symlinks -> Integer,
+		thumbnail_format -> Text,
+		thumbnail_quality -> Integer,
+		index_album_art_names -> Text,
+		settings_revision -> BigInt,
 	}
 }
 
@@ -34,6 +65,16 @@ table! {
 		id -> Integer,
 		source -> Text,
 		name -> Text,
+		scan_interval_seconds -> Nullable<Integer>,
+	}
+}
+
+table! {
+	play_history (id) {
+		id -> Integer,
+		owner -> Integer,
+		path -> Text,
+		timestamp -> BigInt,
 	}
 }
 
@@ -51,6 +92,35 @@ table! {
 		id -> Integer,
 		owner -> Integer,
 		name -> Text,
+		version -> BigInt,
+		created_at -> BigInt,
+		updated_at -> BigInt,
+	}
+}
+
+table! {
+	sessions (id) {
+		id -> Integer,
+		owner -> Integer,
+		label -> Nullable<Text>,
+		created -> BigInt,
+		last_seen -> BigInt,
+	}
+}
+
+table! {
+	song_artists (id) {
+		id -> Integer,
+		path -> Text,
+		artist -> Text,
+	}
+}
+
+table! {
+	song_genres (id) {
+		id -> Integer,
+		path -> Text,
+		genre -> Text,
 	}
 }
 
@@ -72,6 +142,13 @@ table! {
 		composer -> Nullable<Text>,
 		genre -> Nullable<Text>,
 		label -> Nullable<Text>,
+		disc_subtitle -> Nullable<Text>,
+		file_size -> Nullable<BigInt>,
+		file_modified -> Nullable<BigInt>,
+		replay_gain_track -> Nullable<Float>,
+		replay_gain_album -> Nullable<Float>,
+		search_text -> Nullable<Text>,
+		bitrate -> Nullable<Integer>,
 	}
 }
 
@@ -85,19 +162,28 @@ table! {
 		lastfm_session_key -> Nullable<Text>,
 		web_theme_base -> Nullable<Text>,
 		web_theme_accent -> Nullable<Text>,
+		listenbrainz_token -> Nullable<Text>,
+		random_seed -> Nullable<BigInt>,
 	}
 }
 
+joinable!(play_history -> users (owner));
 joinable!(playlist_songs -> playlists (playlist));
 joinable!(playlists -> users (owner));
+joinable!(sessions -> users (owner));
 
 allow_tables_to_appear_in_same_query!(
+	artist_images,
 	ddns_config,
 	directories,
 	misc_settings,
 	mount_points,
+	play_history,
 	playlist_songs,
 	playlists,
+	sessions,
+	song_artists,
+	song_genres,
 	songs,
 	users,
 );
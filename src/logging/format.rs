@@ -0,0 +1,19 @@
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+	Text,
+	Json,
+}
+
+impl FromStr for Format {
+	type Err = anyhow::Error;
+
+	fn from_str(input: &str) -> Result<Self, Self::Err> {
+		match input {
+			"text" => Ok(Format::Text),
+			"json" => Ok(Format::Json),
+			_ => Err(anyhow::anyhow!("Unknown log format `{}`", input)),
+		}
+	}
+}
@@ -0,0 +1,73 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+use simplelog::{Config, SharedLogger};
+use std::io::Write;
+use std::sync::Mutex;
+
+#[derive(Serialize)]
+struct Entry<'a> {
+	timestamp: String,
+	level: String,
+	target: &'a str,
+	message: String,
+}
+
+/// Logger writing one JSON object per line, for consumption by log processing tools.
+pub struct JsonLogger<W: Write + Send + 'static> {
+	level: LevelFilter,
+	writable: Mutex<W>,
+}
+
+impl<W: Write + Send + 'static> JsonLogger<W> {
+	pub fn new(level: LevelFilter, writable: W) -> Box<Self> {
+		Box::new(Self {
+			level,
+			writable: Mutex::new(writable),
+		})
+	}
+}
+
+impl<W: Write + Send + 'static> Log for JsonLogger<W> {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		metadata.level() <= self.level
+	}
+
+	fn log(&self, record: &Record) {
+		if !self.enabled(record.metadata()) {
+			return;
+		}
+		let entry = Entry {
+			timestamp: time::OffsetDateTime::now_utc().format(time::Format::Rfc3339),
+			level: record.level().to_string(),
+			target: record.target(),
+			message: record.args().to_string(),
+		};
+		if let Ok(mut line) = serde_json::to_vec(&entry) {
+			line.push(b'\n');
+			// Written at once so that rotating files never split an entry
+			if let Ok(mut writable) = self.writable.lock() {
+				let _ = writable.write_all(&line);
+			}
+		}
+	}
+
+	fn flush(&self) {
+		if let Ok(mut writable) = self.writable.lock() {
+			let _ = writable.flush();
+		}
+	}
+}
+
+impl<W: Write + Send + 'static> SharedLogger for JsonLogger<W> {
+	fn level(&self) -> LevelFilter {
+		self.level
+	}
+
+	fn config(&self) -> Option<&Config> {
+		None
+	}
+
+	fn as_log(self: Box<Self>) -> Box<dyn Log> {
+		Box::new(*self)
+	}
+}
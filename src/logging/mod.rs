@@ -0,0 +1,9 @@
+mod format;
+mod json;
+mod rotation;
+#[cfg(test)]
+mod test;
+
+pub use format::*;
+pub use json::*;
+pub use rotation::*;
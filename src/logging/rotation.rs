@@ -0,0 +1,126 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+pub const DEFAULT_MAX_FILES: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rotation {
+	/// Size above which the log file is rotated, in bytes. 0 lets the file grow without limit.
+	pub max_size: u64,
+	/// Number of rotated files kept next to the log file (`polaris.log.1`, `polaris.log.2`, etc.).
+	pub max_files: usize,
+}
+
+impl Default for Rotation {
+	fn default() -> Self {
+		Self {
+			max_size: DEFAULT_MAX_SIZE,
+			max_files: DEFAULT_MAX_FILES,
+		}
+	}
+}
+
+// Log file which is moved aside once it grows past a maximum size. Existing content is kept when
+// the file is opened, so logs from previous runs survive restarts.
+pub struct RotatingFile {
+	path: PathBuf,
+	rotation: Rotation,
+	file: File,
+	size: u64,
+	at_line_start: bool,
+	// Whether standard output and error of the process are redirected to the log file, and
+	// should keep following it when it is rotated
+	#[cfg(unix)]
+	std_streams: bool,
+}
+
+impl RotatingFile {
+	pub fn open(path: &Path, rotation: Rotation) -> io::Result<Self> {
+		let file = OpenOptions::new().create(true).append(true).open(path)?;
+		let size = file.metadata()?.len();
+		Ok(Self {
+			path: path.to_owned(),
+			rotation,
+			file,
+			size,
+			at_line_start: true,
+			#[cfg(unix)]
+			std_streams: false,
+		})
+	}
+
+	#[cfg(unix)]
+	pub fn with_std_streams(mut self) -> Self {
+		self.std_streams = true;
+		self
+	}
+
+	fn rotated_path(&self, index: usize) -> PathBuf {
+		let mut path = self.path.clone().into_os_string();
+		path.push(format!(".{}", index));
+		PathBuf::from(path)
+	}
+
+	fn rotate(&mut self) -> io::Result<()> {
+		self.file.flush()?;
+		if self.rotation.max_files > 0 {
+			let _ = fs::remove_file(self.rotated_path(self.rotation.max_files));
+			for index in (1..self.rotation.max_files).rev() {
+				let from = self.rotated_path(index);
+				if from.exists() {
+					fs::rename(&from, self.rotated_path(index + 1))?;
+				}
+			}
+			fs::rename(&self.path, self.rotated_path(1))?;
+		}
+		self.file = OpenOptions::new()
+			.create(true)
+			.write(true)
+			.truncate(true)
+			.open(&self.path)?;
+		self.size = 0;
+		#[cfg(unix)]
+		if self.std_streams {
+			redirect_std_streams(&self.file)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(unix)]
+fn redirect_std_streams(file: &File) -> io::Result<()> {
+	use std::os::unix::io::AsRawFd;
+	for stream in &[libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+		if unsafe { libc::dup2(file.as_raw_fd(), *stream) } < 0 {
+			return Err(io::Error::last_os_error());
+		}
+	}
+	Ok(())
+}
+
+impl Write for RotatingFile {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		// Log records are written in several pieces, only rotate between lines so that records are
+		// never split across files.
+		let max_size = self.rotation.max_size;
+		if max_size > 0
+			&& self.at_line_start
+			&& self.size > 0
+			&& self.size + buf.len() as u64 > max_size
+		{
+			self.rotate()?;
+		}
+		let written = self.file.write(buf)?;
+		self.size += written as u64;
+		if written > 0 {
+			self.at_line_start = buf[written - 1] == b'\n';
+		}
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.file.flush()
+	}
+}
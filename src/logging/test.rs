@@ -0,0 +1,123 @@
+use std::fs;
+use std::io::Write;
+
+use super::*;
+use crate::test::prepare_test_directory;
+use crate::test_name;
+
+#[test]
+fn keeps_existing_content() {
+	let test_directory = prepare_test_directory(test_name!());
+	let path = test_directory.join("polaris.log");
+	fs::write(&path, "previous run\n").unwrap();
+
+	let mut file = RotatingFile::open(&path, Rotation::default()).unwrap();
+	file.write_all(b"current run\n").unwrap();
+
+	assert_eq!(
+		fs::read_to_string(&path).unwrap(),
+		"previous run\ncurrent run\n"
+	);
+}
+
+#[test]
+fn rotates_when_full() {
+	let test_directory = prepare_test_directory(test_name!());
+	let path = test_directory.join("polaris.log");
+	let rotation = Rotation {
+		max_size: 10,
+		max_files: 2,
+	};
+
+	let mut file = RotatingFile::open(&path, rotation).unwrap();
+	for line in ["first\n", "second\n", "third\n", "fourth\n"].iter() {
+		file.write_all(line.as_bytes()).unwrap();
+	}
+
+	assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+	assert_eq!(
+		fs::read_to_string(test_directory.join("polaris.log.1")).unwrap(),
+		"third\n"
+	);
+	assert_eq!(
+		fs::read_to_string(test_directory.join("polaris.log.2")).unwrap(),
+		"second\n"
+	);
+	assert!(!test_directory.join("polaris.log.3").exists());
+}
+
+#[test]
+fn does_not_split_lines() {
+	let test_directory = prepare_test_directory(test_name!());
+	let path = test_directory.join("polaris.log");
+	let rotation = Rotation {
+		max_size: 8,
+		max_files: 1,
+	};
+
+	let mut file = RotatingFile::open(&path, rotation).unwrap();
+	file.write_all(b"[INFO] ").unwrap();
+	file.write_all(b"message\n").unwrap();
+
+	assert_eq!(fs::read_to_string(&path).unwrap(), "[INFO] message\n");
+	assert!(!test_directory.join("polaris.log.1").exists());
+}
+
+#[test]
+fn truncates_without_rotated_files() {
+	let test_directory = prepare_test_directory(test_name!());
+	let path = test_directory.join("polaris.log");
+	let rotation = Rotation {
+		max_size: 10,
+		max_files: 0,
+	};
+
+	let mut file = RotatingFile::open(&path, rotation).unwrap();
+	file.write_all(b"first line\n").unwrap();
+	file.write_all(b"second line\n").unwrap();
+
+	assert_eq!(fs::read_to_string(&path).unwrap(), "second line\n");
+	assert!(!test_directory.join("polaris.log.1").exists());
+}
+
+#[test]
+fn parses_formats() {
+	assert_eq!("text".parse::<Format>().unwrap(), Format::Text);
+	assert_eq!("json".parse::<Format>().unwrap(), Format::Json);
+	assert!("xml".parse::<Format>().is_err());
+}
+
+#[test]
+fn json_logger_writes_one_object_per_line() {
+	use log::{Level, LevelFilter, Log, Record};
+
+	let test_directory = prepare_test_directory(test_name!());
+	let path = test_directory.join("polaris.log");
+	let file = RotatingFile::open(&path, Rotation::default()).unwrap();
+	let logger = JsonLogger::new(LevelFilter::Info, file);
+
+	logger.log(
+		&Record::builder()
+			.level(Level::Warn)
+			.target("polaris::app")
+			.args(format_args!("Disk is \"{}\"", "full"))
+			.build(),
+	);
+	logger.log(
+		&Record::builder()
+			.level(Level::Debug)
+			.args(format_args!("Filtered out"))
+			.build(),
+	);
+	logger.flush();
+
+	let content = fs::read_to_string(&path).unwrap();
+	let lines: Vec<&str> = content.lines().collect();
+	assert_eq!(lines.len(), 1);
+	let entry: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+	assert_eq!(entry["level"], "WARN");
+	assert_eq!(entry["target"], "polaris::app");
+	assert_eq!(entry["message"], "Disk is \"full\"");
+	let timestamp = entry["timestamp"].as_str().unwrap();
+	assert!(time::OffsetDateTime::parse(timestamp, time::Format::Rfc3339).is_ok());
+}
@@ -7,13 +7,17 @@ extern crate diesel;
 extern crate diesel_migrations;
 
 use anyhow::*;
-use log::info;
+use log::{error, info};
 use simplelog::{CombinedLogger, LevelFilter, SharedLogger, TermLogger, TerminalMode, WriteLogger};
 use std::fs;
+use std::io::{self, Write};
+use std::net::SocketAddr;
 use std::path::Path;
+use std::time::Duration;
 
 mod app;
 mod db;
+mod logging;
 mod options;
 mod paths;
 mod service;
@@ -22,21 +26,39 @@ mod test;
 mod ui;
 mod utils;
 
+// Output which does not go through the logger, like panic messages, is written to the log file
 #[cfg(unix)]
-fn daemonize<T: AsRef<Path>>(foreground: bool, pid_file_path: T) -> Result<()> {
+fn daemonize(foreground: bool, pid_file_path: &Path, log_file_path: Option<&Path>) -> Result<()> {
 	if foreground {
 		return Ok(());
 	}
-	if let Some(parent) = pid_file_path.as_ref().parent() {
+	if let Some(parent) = pid_file_path.parent() {
 		fs::create_dir_all(parent)?;
 	}
-	let daemonize = daemonize::Daemonize::new()
-		.pid_file(pid_file_path.as_ref())
+	let mut daemonize = daemonize::Daemonize::new()
+		.pid_file(pid_file_path)
 		.working_directory(".");
+	if let Some(path) = log_file_path {
+		let log_file = fs::OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(path)?;
+		daemonize = daemonize.stdout(log_file.try_clone()?).stderr(log_file);
+	}
 	daemonize.start()?;
 	Ok(())
 }
 
+// Once daemonized, there is no terminal to write to
+fn log_to_terminal(foreground: bool) -> bool {
+	cfg!(not(unix)) || foreground
+}
+
+// Whether standard output and error of the process end up in the log file
+fn redirects_std_streams<T>(foreground: bool, log_file_path: &Option<T>) -> bool {
+	cfg!(unix) && !foreground && log_file_path.is_some()
+}
+
 #[cfg(unix)]
 fn notify_ready() -> Result<()> {
 	if let Ok(true) = sd_notify::booted() {
@@ -45,26 +67,61 @@ fn notify_ready() -> Result<()> {
 	Ok(())
 }
 
-fn init_logging<T: AsRef<Path>>(log_level: LevelFilter, log_file_path: &Option<T>) -> Result<()> {
+// Blocks until the process is asked to terminate (Ctrl-C or SIGTERM, console close events on Windows)
+fn wait_for_shutdown_signal() -> Result<()> {
+	let mut runtime = tokio::runtime::Builder::new()
+		.basic_scheduler()
+		.enable_all()
+		.build()?;
+	runtime.block_on(async {
+		#[cfg(unix)]
+		{
+			use tokio::signal::unix::{signal, SignalKind};
+			let mut terminate = signal(SignalKind::terminate())?;
+			let ctrl_c = Box::pin(tokio::signal::ctrl_c());
+			let terminate = Box::pin(terminate.recv());
+			futures_util::future::select(ctrl_c, terminate).await;
+		}
+		#[cfg(not(unix))]
+		tokio::signal::ctrl_c().await?;
+		Ok(())
+	})
+}
+
+fn init_logging<T: AsRef<Path>>(
+	log_level: LevelFilter,
+	log_file_path: &Option<T>,
+	log_rotation: logging::Rotation,
+	log_format: logging::Format,
+	log_to_terminal: bool,
+	log_std_streams: bool,
+) -> Result<()> {
 	let log_config = simplelog::ConfigBuilder::new()
 		.set_location_level(LevelFilter::Error)
 		.build();
 
-	let mut loggers: Vec<Box<dyn SharedLogger>> = vec![TermLogger::new(
-		log_level,
-		log_config.clone(),
-		TerminalMode::Mixed,
-	)];
+	let mut loggers: Vec<Box<dyn SharedLogger>> = Vec::new();
+	if log_to_terminal {
+		loggers.push(TermLogger::new(
+			log_level,
+			log_config.clone(),
+			TerminalMode::Mixed,
+		));
+	}
 
 	if let Some(path) = log_file_path {
 		if let Some(parent) = path.as_ref().parent() {
 			fs::create_dir_all(parent)?;
 		}
-		loggers.push(WriteLogger::new(
-			log_level,
-			log_config.clone(),
-			fs::File::create(path)?,
-		));
+		let mut log_file = logging::RotatingFile::open(path.as_ref(), log_rotation)?;
+		#[cfg(unix)]
+		if log_std_streams {
+			log_file = log_file.with_std_streams();
+		}
+		loggers.push(match log_format {
+			logging::Format::Text => WriteLogger::new(log_level, log_config.clone(), log_file),
+			logging::Format::Json => logging::JsonLogger::new(log_level, log_file),
+		});
 	}
 
 	CombinedLogger::init(loggers)?;
@@ -72,6 +129,43 @@ fn init_logging<T: AsRef<Path>>(log_level: LevelFilter, log_file_path: &Option<T
 	Ok(())
 }
 
+// Sets the password of an existing user directly in the database, so that it works while the server
+// is not running.
+fn reset_password(
+	db_file_path: &Path,
+	database_busy_timeout: Duration,
+	username: &str,
+	new_password: Option<String>,
+) -> Result<()> {
+	if !db_file_path.is_file() {
+		bail!("Database file {:#?} does not exist", db_file_path);
+	}
+	let db = db::DB::new(db_file_path, database_busy_timeout)?;
+	let settings_manager = app::settings::Manager::new(db.clone());
+	let auth_secret = settings_manager.get_auth_secret()?;
+	let user_manager = app::user::Manager::new(db, auth_secret, settings_manager);
+	if !user_manager.exists(username)? {
+		bail!("User `{}` does not exist", username);
+	}
+
+	let new_password = match new_password {
+		Some(password) => password,
+		None => {
+			print!("New password for `{}`: ", username);
+			io::stdout().flush()?;
+			let mut password = String::new();
+			io::stdin().read_line(&mut password)?;
+			password.trim_end_matches(&['\r', '\n'][..]).to_owned()
+		}
+	};
+	// Also revokes sessions, in case the account was taken over
+	user_manager.set_password(username, &new_password)?;
+	// Written to the terminal rather than logged, this runs as a one-off command before logging
+	// is set up
+	println!("Password of `{}` has been reset", username);
+	Ok(())
+}
+
 fn main() -> Result<()> {
 	// Parse CLI options
 	let args: Vec<String> = std::env::args().collect();
@@ -86,14 +180,47 @@ fn main() -> Result<()> {
 	}
 
 	let paths = paths::Paths::new(&cli_options);
+	let database_busy_timeout = cli_options
+		.database_busy_timeout
+		.map(Duration::from_millis)
+		.unwrap_or(db::DEFAULT_BUSY_TIMEOUT);
+
+	if let Some(username) = &cli_options.reset_password_username {
+		return reset_password(
+			&paths.db_file_path,
+			database_busy_timeout,
+			username,
+			cli_options.new_password.clone(),
+		);
+	}
 
 	// Logging
 	let log_level = cli_options.log_level.unwrap_or(LevelFilter::Info);
-	init_logging(log_level, &paths.log_file_path)?;
+	let log_rotation = logging::Rotation {
+		max_size: cli_options
+			.log_max_size
+			.unwrap_or(logging::DEFAULT_MAX_SIZE),
+		max_files: cli_options
+			.log_max_files
+			.unwrap_or(logging::DEFAULT_MAX_FILES),
+	};
+	let log_format = cli_options.log_format.unwrap_or(logging::Format::Text);
+	init_logging(
+		log_level,
+		&paths.log_file_path,
+		log_rotation,
+		log_format,
+		log_to_terminal(cli_options.foreground),
+		redirects_std_streams(cli_options.foreground, &paths.log_file_path),
+	)?;
 
 	// Fork
 	#[cfg(unix)]
-	daemonize(cli_options.foreground, &paths.pid_file_path)?;
+	daemonize(
+		cli_options.foreground,
+		&paths.pid_file_path,
+		paths.log_file_path.as_deref(),
+	)?;
 
 	info!("Cache files location is {:#?}", paths.cache_dir_path);
 	info!("Config files location is {:#?}", paths.config_file_path);
@@ -103,27 +230,161 @@ fn main() -> Result<()> {
 	if !cli_options.foreground {
 		info!("Pid file location is {:#?}", paths.pid_file_path);
 	}
-	info!("Swagger files location is {:#?}", paths.swagger_dir_path);
+	match &paths.swagger_dir_path {
+		Some(path) => info!("Swagger files location is {:#?}", path),
+		None => info!("Swagger UI is disabled"),
+	}
+	if let Some(path) = &paths.tls_cert_file_path {
+		info!("TLS certificate location is {:#?}", path);
+	}
+	if let Some(path) = &paths.tls_key_file_path {
+		info!("TLS private key location is {:#?}", path);
+	}
 	info!("Web client files location is {:#?}", paths.web_dir_path);
 
 	// Create and run app
-	let app = app::App::new(cli_options.port.unwrap_or(5050), paths)?;
+	let port = cli_options.port.unwrap_or(5050);
+	let bind_address = cli_options
+		.bind_address
+		.unwrap_or(app::DEFAULT_BIND_ADDRESS);
+	let address = SocketAddr::new(bind_address, port);
+	let collection_cache_max_age = Duration::from_secs(cli_options.cache_max_age.unwrap_or(60));
+	let max_concurrent_transfers = match cli_options.max_concurrent_transfers {
+		Some(0) => None,
+		Some(n) => Some(n),
+		None => Some(num_cpus::get() * 2),
+	};
+	let index_insert_buffer_size = cli_options
+		.index_insert_buffer_size
+		.unwrap_or(app::index::DEFAULT_INSERT_BUFFER_SIZE);
+	let max_json_body_size = cli_options
+		.max_json_body_size
+		.unwrap_or(app::DEFAULT_MAX_JSON_BODY_SIZE);
+	let metrics_allowlist = cli_options
+		.metrics_allowlist
+		.unwrap_or_else(|| app::metrics::DEFAULT_ALLOWLIST.to_vec());
+	let options = app::Options {
+		address,
+		collection_cache_max_age,
+		max_concurrent_transfers,
+		index_insert_buffer_size,
+		db_busy_timeout: database_busy_timeout,
+		max_json_body_size,
+		metrics_allowlist,
+	};
+	let app = app::App::new(options, paths)?;
+	if let Some(mount_dirs) = &cli_options.mount_dirs {
+		app.vfs_manager.set_mount_dirs(mount_dirs)?;
+	}
+	match (&cli_options.admin_username, &cli_options.admin_password) {
+		(Some(username), Some(password)) => {
+			if app.user_manager.create_initial_admin(username, password)? {
+				info!("Created admin user `{}`", username);
+			}
+		}
+		(None, None) => (),
+		_ => bail!("--admin-username and --admin-password must be used together"),
+	}
+
 	app.index.begin_periodic_updates();
 	app.ddns_manager.begin_periodic_updates();
 
+	let index = app.index.clone();
+
 	// Start server
-	info!("Starting up server");
-	std::thread::spawn(move || {
-		let _ = service::run(app);
+	let location = address.to_string();
+	#[cfg(unix)]
+	let location = app
+		.socket_file_path
+		.as_ref()
+		.map(|p| p.display().to_string())
+		.unwrap_or(location);
+	info!("Starting up server on {}", location);
+	let (server_sender, server_receiver) = crossbeam_channel::bounded(1);
+	let server_thread = std::thread::spawn(move || {
+		if let Err(e) = service::run(app, server_sender) {
+			error!("Error running server: {}", e);
+		}
 	});
+	let server = server_receiver
+		.recv()
+		.map_err(|_| anyhow!("Could not start server on {}", location))?;
 
 	// Send readiness notification
 	#[cfg(unix)]
 	notify_ready()?;
 
+	// Listen for shutdown signals
+	let (shutdown_sender, shutdown_receiver) = crossbeam_channel::bounded(1);
+	std::thread::spawn(move || match wait_for_shutdown_signal() {
+		Ok(()) => {
+			let _ = shutdown_sender.send(());
+		}
+		Err(e) => error!("Could not listen for shutdown signals: {}", e),
+	});
+
 	// Run UI
-	ui::run();
+	ui::run(shutdown_receiver);
 
+	// Let the index update in progress save what it found before stopping the server
 	info!("Shutting down server");
+	index.shutdown();
+	// The stop command is sent immediately, the server thread exits once it completes
+	drop(server.stop(true));
+	if let Err(e) = server_thread.join() {
+		error!("Error joining on server thread: {:?}", e);
+	}
+	info!("Server has shut down");
 	Ok(())
 }
+
+#[test]
+fn reset_password_revokes_sessions() {
+	use app::user::AuthorizationScope;
+
+	let ctx = app::test::ContextBuilder::new(test_name!())
+		.user("Walter", "super_secret!", false)
+		.build();
+	let token = ctx
+		.user_manager
+		.login("Walter", "super_secret!", None)
+		.unwrap();
+
+	reset_password(
+		&ctx.test_directory.join("db.sqlite"),
+		db::DEFAULT_BUSY_TIMEOUT,
+		"Walter",
+		Some("new_password".to_owned()),
+	)
+	.unwrap();
+
+	ctx.user_manager
+		.check_credentials("Walter", "new_password")
+		.unwrap();
+	assert!(ctx
+		.user_manager
+		.authenticate(&token, AuthorizationScope::PolarisAuth)
+		.is_err());
+}
+
+#[test]
+fn reset_password_rejects_unknown_user() {
+	let ctx = app::test::ContextBuilder::new(test_name!()).build();
+	assert!(reset_password(
+		&ctx.test_directory.join("db.sqlite"),
+		db::DEFAULT_BUSY_TIMEOUT,
+		"Walter",
+		Some("new_password".to_owned()),
+	)
+	.is_err());
+}
+
+#[test]
+fn daemon_output_goes_to_log_file() {
+	let log_file_path = Some(Path::new("polaris.log"));
+	assert!(log_to_terminal(true));
+	assert!(!redirects_std_streams(true, &log_file_path));
+	assert_eq!(log_to_terminal(false), cfg!(not(unix)));
+	assert_eq!(redirects_std_streams(false, &log_file_path), cfg!(unix));
+	assert!(!redirects_std_streams::<&Path>(false, &None));
+}
@@ -1,20 +1,44 @@
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use simplelog::LevelFilter;
+use std::net::IpAddr;
 use std::path::PathBuf;
 
+use crate::app::vfs::MountDir;
+use crate::logging;
+
 pub struct CLIOptions {
 	pub show_help: bool,
 	pub foreground: bool,
 	pub log_file_path: Option<PathBuf>,
+	pub log_max_size: Option<u64>,
+	pub log_max_files: Option<usize>,
+	pub log_format: Option<logging::Format>,
 	#[cfg(unix)]
 	pub pid_file_path: Option<PathBuf>,
+	#[cfg(unix)]
+	pub socket_file_path: Option<PathBuf>,
 	pub config_file_path: Option<PathBuf>,
 	pub database_file_path: Option<PathBuf>,
 	pub cache_dir_path: Option<PathBuf>,
 	pub web_dir_path: Option<PathBuf>,
 	pub swagger_dir_path: Option<PathBuf>,
+	pub disable_swagger: bool,
+	pub tls_cert_file_path: Option<PathBuf>,
+	pub tls_key_file_path: Option<PathBuf>,
 	pub port: Option<u16>,
+	pub bind_address: Option<IpAddr>,
+	pub cache_max_age: Option<u64>,
+	pub max_concurrent_transfers: Option<usize>,
+	pub index_insert_buffer_size: Option<usize>,
+	pub database_busy_timeout: Option<u64>,
+	pub max_json_body_size: Option<usize>,
+	pub metrics_allowlist: Option<Vec<IpAddr>>,
 	pub log_level: Option<LevelFilter>,
+	pub reset_password_username: Option<String>,
+	pub new_password: Option<String>,
+	pub admin_username: Option<String>,
+	pub admin_password: Option<String>,
+	pub mount_dirs: Option<Vec<MountDir>>,
 }
 
 pub struct Manager {
@@ -29,24 +53,82 @@ impl Manager {
 	}
 
 	pub fn parse(&self, input: &[String]) -> Result<CLIOptions> {
-		let matches = self.protocol.parse(input)?;
+		self.parse_with_environment(input, |name| std::env::var(name).ok())
+	}
+
+	fn parse_with_environment<E>(&self, input: &[String], environment: E) -> Result<CLIOptions>
+	where
+		E: Fn(&str) -> Option<String>,
+	{
+		let matches = Overlay {
+			matches: self.protocol.parse(input)?,
+			environment,
+		};
 
 		Ok(CLIOptions {
-			show_help: matches.opt_present("h"),
+			show_help: matches.matches.opt_present("help"),
 			#[cfg(unix)]
-			foreground: matches.opt_present("f"),
+			foreground: matches.opt_present("foreground"),
 			#[cfg(windows)]
 			foreground: !cfg!(feature = "ui"),
 			log_file_path: matches.opt_str("log").map(PathBuf::from),
+			log_max_size: matches.opt_str("log-max-size").and_then(|s| s.parse().ok()),
+			log_max_files: matches
+				.opt_str("log-max-files")
+				.and_then(|s| s.parse().ok()),
+			log_format: matches
+				.opt_str("log-format")
+				.map(|f| f.parse())
+				.transpose()?,
 			#[cfg(unix)]
 			pid_file_path: matches.opt_str("pid").map(PathBuf::from),
-			config_file_path: matches.opt_str("c").map(PathBuf::from),
-			database_file_path: matches.opt_str("d").map(PathBuf::from),
+			#[cfg(unix)]
+			socket_file_path: matches.opt_str("socket").map(PathBuf::from),
+			config_file_path: matches.opt_str("config").map(PathBuf::from),
+			database_file_path: matches.opt_str("database").map(PathBuf::from),
 			cache_dir_path: matches.opt_str("cache").map(PathBuf::from),
-			web_dir_path: matches.opt_str("w").map(PathBuf::from),
-			swagger_dir_path: matches.opt_str("s").map(PathBuf::from),
-			port: matches.opt_str("p").and_then(|p| p.parse().ok()),
+			web_dir_path: matches.opt_str("web").map(PathBuf::from),
+			swagger_dir_path: matches.opt_str("swagger").map(PathBuf::from),
+			disable_swagger: matches.opt_present("no-swagger"),
+			tls_cert_file_path: matches.opt_str("tls-cert").map(PathBuf::from),
+			tls_key_file_path: matches.opt_str("tls-key").map(PathBuf::from),
+			port: matches.opt_str("port").and_then(|p| p.parse().ok()),
+			bind_address: matches
+				.opt_str("bind-address")
+				.map(|a| {
+					a.parse()
+						.with_context(|| format!("Invalid bind address `{}`", a))
+				})
+				.transpose()?,
+			cache_max_age: matches
+				.opt_str("cache-max-age")
+				.and_then(|s| s.parse().ok()),
+			max_concurrent_transfers: matches
+				.opt_str("max-concurrent-transfers")
+				.and_then(|s| s.parse().ok()),
+			index_insert_buffer_size: matches
+				.opt_str("index-insert-buffer-size")
+				.and_then(|s| s.parse().ok()),
+			database_busy_timeout: matches
+				.opt_str("database-busy-timeout")
+				.and_then(|s| s.parse().ok()),
+			max_json_body_size: matches
+				.opt_str("max-json-body-size")
+				.and_then(|s| s.parse().ok()),
+			metrics_allowlist: matches
+				.opt_str("metrics-allow")
+				.map(|s| parse_addresses(&s))
+				.transpose()?,
 			log_level: matches.opt_str("log-level").and_then(|l| l.parse().ok()),
+			// Not read from the environment, where it could stay set and prevent startup
+			reset_password_username: matches.matches.opt_str("reset-password"),
+			new_password: matches.opt_str("new-password"),
+			admin_username: matches.opt_str("admin-username"),
+			admin_password: matches.opt_str("admin-password"),
+			mount_dirs: Some(matches.opt_strs("mount"))
+				.filter(|m| !m.is_empty())
+				.map(|m| m.iter().map(|m| parse_mount_dir(m)).collect())
+				.transpose()?,
 		})
 	}
 
@@ -55,21 +137,171 @@ impl Manager {
 	}
 }
 
+// Command line arguments, completed by environment variables named after the long name of each
+// option (`POLARIS_BIND_ADDRESS` for `--bind-address`). Arguments take precedence over environment
+// variables, which take precedence over default values. Empty variables count as unset.
+struct Overlay<E> {
+	matches: getopts::Matches,
+	environment: E,
+}
+
+impl<E: Fn(&str) -> Option<String>> Overlay<E> {
+	fn env(&self, name: &str) -> Option<String> {
+		(self.environment)(&environment_variable(name)).filter(|v| !v.is_empty())
+	}
+
+	fn opt_str(&self, name: &str) -> Option<String> {
+		self.matches.opt_str(name).or_else(|| self.env(name))
+	}
+
+	// Repeated options are separated by semicolons in environment variables
+	fn opt_strs(&self, name: &str) -> Vec<String> {
+		let values = self.matches.opt_strs(name);
+		if !values.is_empty() {
+			return values;
+		}
+		self.env(name)
+			.map(|v| {
+				v.split(';')
+					.map(str::trim)
+					.filter(|v| !v.is_empty())
+					.map(str::to_owned)
+					.collect()
+			})
+			.unwrap_or_default()
+	}
+
+	fn opt_present(&self, name: &str) -> bool {
+		self.matches.opt_present(name) || matches!(self.env(name).as_deref(), Some("1" | "true"))
+	}
+}
+
+fn environment_variable(option_name: &str) -> String {
+	format!("POLARIS_{}", option_name.to_uppercase().replace('-', "_"))
+}
+
+fn parse_mount_dir(input: &str) -> Result<MountDir> {
+	let (name, source) = input
+		.split_once('=')
+		.filter(|(name, source)| !name.is_empty() && !source.is_empty())
+		.ok_or_else(|| anyhow!("Invalid mount `{}`, expected NAME=DIRECTORY", input))?;
+	Ok(MountDir {
+		name: name.to_owned(),
+		source: source.to_owned(),
+		scan_interval_seconds: None,
+	})
+}
+
+fn parse_addresses(input: &str) -> Result<Vec<IpAddr>> {
+	input
+		.split(',')
+		.map(str::trim)
+		.filter(|a| !a.is_empty())
+		.map(|a| Ok(a.parse()?))
+		.collect()
+}
+
 fn get_options() -> getopts::Options {
 	let mut options = getopts::Options::new();
 	options.optopt("c", "config", "set the configuration file", "FILE");
 	options.optopt("p", "port", "set polaris to run on a custom port", "PORT");
+	options.optopt(
+		"",
+		"bind-address",
+		"set the network address polaris listens on, 0.0.0.0 (all IPv4 interfaces) by default",
+		"ADDRESS",
+	);
 	options.optopt("d", "database", "set the path to index database", "FILE");
 	options.optopt("w", "web", "set the path to web client files", "DIRECTORY");
 	options.optopt("s", "swagger", "set the path to swagger files", "DIRECTORY");
+	options.optflag("", "no-swagger", "do not serve the API documentation");
+	options.optopt(
+		"",
+		"tls-cert",
+		"serve HTTPS using this PEM certificate chain, requires --tls-key",
+		"FILE",
+	);
+	options.optopt(
+		"",
+		"tls-key",
+		"serve HTTPS using this PEM private key, requires --tls-cert",
+		"FILE",
+	);
 	options.optopt(
 		"",
 		"cache",
 		"set the directory to use as cache",
 		"DIRECTORY",
 	);
+	options.optopt(
+		"",
+		"cache-max-age",
+		"set how long clients may cache browsing results, in seconds",
+		"SECONDS",
+	);
+	options.optopt(
+		"",
+		"max-concurrent-transfers",
+		"set how many files (audio, thumbnails, etc.) can be served at once, 0 for no limit",
+		"COUNT",
+	);
+	options.optopt(
+		"",
+		"index-insert-buffer-size",
+		"set how many songs or directories are written to the database at once while indexing",
+		"COUNT",
+	);
+	options.optopt(
+		"",
+		"database-busy-timeout",
+		"set how long database queries wait for a locked database, in milliseconds",
+		"MILLISECONDS",
+	);
+	options.optopt(
+		"",
+		"max-json-body-size",
+		"set the largest JSON request body (settings, playlists, etc.) accepted, in bytes",
+		"BYTES",
+	);
+	options.optopt(
+		"",
+		"metrics-allow",
+		"set which client addresses can read /metrics without logging in, separated by commas",
+		"ADDRESSES",
+	);
+	options.optmulti(
+		"",
+		"mount",
+		"replace the mount directories with this one on startup, can be repeated",
+		"NAME=DIRECTORY",
+	);
 	options.optopt("", "log", "set the path to the log file", "FILE");
+	options.optopt(
+		"",
+		"log-max-size",
+		"set the size above which the log file is rotated, in bytes, 0 for no limit",
+		"BYTES",
+	);
+	options.optopt(
+		"",
+		"log-max-files",
+		"set how many rotated log files are kept",
+		"COUNT",
+	);
+	options.optopt(
+		"",
+		"log-format",
+		"set the format of the log file, text (default) or json",
+		"FORMAT",
+	);
 	options.optopt("", "pid", "set the path to the pid file", "FILE");
+	#[cfg(unix)]
+	options.optopt(
+		"",
+		"socket",
+		"listen on a unix domain socket at this path instead of a TCP port",
+		"FILE",
+	);
 	options.optopt(
 		"",
 		"log-level",
@@ -77,6 +309,31 @@ fn get_options() -> getopts::Options {
 		"LEVEL",
 	);
 
+	options.optopt(
+		"",
+		"reset-password",
+		"set the password of this user and exit, prompts for the password unless --new-password is set",
+		"USERNAME",
+	);
+	options.optopt(
+		"",
+		"new-password",
+		"set the password used by --reset-password",
+		"PASSWORD",
+	);
+	options.optopt(
+		"",
+		"admin-username",
+		"create an admin user with this name on startup if there are no users yet, requires --admin-password",
+		"USERNAME",
+	);
+	options.optopt(
+		"",
+		"admin-password",
+		"set the password of the user created by --admin-username",
+		"PASSWORD",
+	);
+
 	#[cfg(unix)]
 	options.optflag(
 		"f",
@@ -87,3 +344,108 @@ fn get_options() -> getopts::Options {
 	options.optflag("h", "help", "print this help menu");
 	options
 }
+
+#[cfg(test)]
+fn parse_test_options(arguments: &[&str], environment: &[(&str, &str)]) -> CLIOptions {
+	let arguments: Vec<String> = arguments.iter().map(|a| a.to_string()).collect();
+	let environment: std::collections::HashMap<String, String> = environment
+		.iter()
+		.map(|(k, v)| (k.to_string(), v.to_string()))
+		.collect();
+	Manager::new()
+		.parse_with_environment(&arguments, |name| environment.get(name).cloned())
+		.unwrap()
+}
+
+#[test]
+fn environment_sets_options() {
+	let options = parse_test_options(
+		&[],
+		&[
+			("POLARIS_PORT", "5051"),
+			("POLARIS_DATABASE", "/data/db.sqlite"),
+		],
+	);
+	assert_eq!(options.port, Some(5051));
+	assert_eq!(
+		options.database_file_path,
+		Some(PathBuf::from("/data/db.sqlite"))
+	);
+}
+
+#[test]
+fn arguments_take_precedence_over_environment() {
+	let options = parse_test_options(
+		&["--port", "5052", "--mount", "cli=/cli"],
+		&[("POLARIS_PORT", "5051"), ("POLARIS_MOUNT", "env=/env")],
+	);
+	assert_eq!(options.port, Some(5052));
+	assert_eq!(
+		options.mount_dirs.unwrap(),
+		vec![MountDir {
+			name: "cli".to_owned(),
+			source: "/cli".to_owned(),
+			scan_interval_seconds: None,
+		}]
+	);
+}
+
+#[test]
+fn environment_enables_flags() {
+	for value in &["1", "true"] {
+		let options = parse_test_options(&[], &[("POLARIS_NO_SWAGGER", value)]);
+		assert!(options.disable_swagger);
+	}
+	for value in &["0", "false", "yes"] {
+		let options = parse_test_options(&[], &[("POLARIS_NO_SWAGGER", value)]);
+		assert!(!options.disable_swagger);
+	}
+}
+
+#[test]
+fn unset_and_empty_environment_variables_are_ignored() {
+	let options = parse_test_options(&[], &[]);
+	assert_eq!(options.port, None);
+	assert_eq!(options.database_file_path, None);
+	assert!(!options.disable_swagger);
+	assert!(options.mount_dirs.is_none());
+
+	let options = parse_test_options(
+		&[],
+		&[
+			("POLARIS_PORT", ""),
+			("POLARIS_DATABASE", ""),
+			("POLARIS_NO_SWAGGER", ""),
+			("POLARIS_MOUNT", ""),
+		],
+	);
+	assert_eq!(options.port, None);
+	assert_eq!(options.database_file_path, None);
+	assert!(!options.disable_swagger);
+	assert!(options.mount_dirs.is_none());
+}
+
+#[test]
+fn environment_sets_mount_dirs() {
+	let options = parse_test_options(
+		&[],
+		&[("POLARIS_MOUNT", "music=/srv/music; podcasts=/srv/podcasts")],
+	);
+	let mount_dirs = options.mount_dirs.unwrap();
+	assert_eq!(mount_dirs.len(), 2);
+	assert_eq!(mount_dirs[0].name, "music");
+	assert_eq!(mount_dirs[0].source, "/srv/music");
+	assert_eq!(mount_dirs[1].name, "podcasts");
+	assert_eq!(mount_dirs[1].source, "/srv/podcasts");
+}
+
+#[test]
+fn invalid_mount_dirs_are_rejected() {
+	let manager = Manager::new();
+	for mount in &["music", "=/srv/music", "music="] {
+		let arguments = vec!["--mount".to_owned(), mount.to_string()];
+		assert!(manager
+			.parse_with_environment(&arguments, |_| None)
+			.is_err());
+	}
+}
@@ -1,4 +1,5 @@
-use std::path::PathBuf;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 
 use crate::options::CLIOptions;
 
@@ -9,44 +10,131 @@ pub struct Paths {
 	pub log_file_path: Option<PathBuf>,
 	#[cfg(unix)]
 	pub pid_file_path: PathBuf,
-	pub swagger_dir_path: PathBuf,
+	#[cfg(unix)]
+	pub socket_file_path: Option<PathBuf>,
+	// Absent when the API documentation is not served
+	pub swagger_dir_path: Option<PathBuf>,
+	pub tls_cert_file_path: Option<PathBuf>,
+	pub tls_key_file_path: Option<PathBuf>,
 	pub web_dir_path: PathBuf,
 }
 
-// TODO Make this the only implementation when we can expand %LOCALAPPDATA% correctly on Windows
-// And fix the installer accordingly (`release_script.ps1`)
-#[cfg(not(windows))]
 impl Default for Paths {
 	fn default() -> Self {
+		Self::from_environment(|v| std::env::var_os(v), Path::new("."))
+	}
+}
+
+// Data files follow the XDG Base Directory Specification, like `make install-xdg`. Client files
+// are looked up in the working directory so that `cargo run` works from a clone of the repository.
+#[cfg(not(windows))]
+impl Paths {
+	fn from_environment<E>(env: E, working_directory: &Path) -> Self
+	where
+		E: Fn(&str) -> Option<OsString>,
+	{
+		let working_directory = working_directory.to_owned();
+		let data_dir = xdg_directory(&env, "XDG_DATA_HOME", &[".local", "share"])
+			.unwrap_or_else(|| working_directory.clone());
+		let cache_dir = xdg_directory(&env, "XDG_CACHE_HOME", &[".cache"])
+			.unwrap_or_else(|| working_directory.clone());
+		let state_dir = xdg_directory(&env, "XDG_STATE_HOME", &[".local", "state"])
+			.unwrap_or_else(|| working_directory.clone());
+		let runtime_dir = env("XDG_RUNTIME_DIR")
+			.map(|d| PathBuf::from(d).join("polaris"))
+			.unwrap_or_else(|| working_directory.clone());
+
+		// Databases created by earlier versions in the working directory keep being used
+		let legacy_db_file_path = working_directory.join("db.sqlite");
+		let db_file_path = if legacy_db_file_path.is_file() {
+			legacy_db_file_path
+		} else {
+			data_dir.join("db.sqlite")
+		};
+
 		Self {
-			cache_dir_path: ["."].iter().collect(),
-			config_file_path: None,
-			db_file_path: [".", "db.sqlite"].iter().collect(),
-			log_file_path: Some([".", "polaris.log"].iter().collect()),
-			pid_file_path: [".", "polaris.pid"].iter().collect(),
-			swagger_dir_path: [".", "docs", "swagger"].iter().collect(),
-			web_dir_path: [".", "web"].iter().collect(),
+			cache_dir_path: cache_dir,
+			config_file_path: xdg_directory(&env, "XDG_CONFIG_HOME", &[".config"])
+				.map(|d| d.join("polaris.toml"))
+				.filter(|p| p.is_file()),
+			db_file_path,
+			log_file_path: Some(state_dir.join("polaris.log")),
+			pid_file_path: runtime_dir.join("polaris.pid"),
+			socket_file_path: None,
+			swagger_dir_path: Some(working_directory.join("docs").join("swagger")),
+			tls_cert_file_path: None,
+			tls_key_file_path: None,
+			web_dir_path: working_directory.join("web"),
 		}
 	}
 }
 
+// Polaris directory within a base directory from the XDG specification. Relative values are
+// ignored, as required by the specification.
+#[cfg(not(windows))]
+fn xdg_directory<E>(env: &E, variable: &str, default_in_home: &[&str]) -> Option<PathBuf>
+where
+	E: Fn(&str) -> Option<OsString>,
+{
+	let base = env(variable)
+		.map(PathBuf::from)
+		.filter(|p| p.is_absolute())
+		.or_else(|| {
+			let home = PathBuf::from(env("HOME")?);
+			Some(default_in_home.iter().fold(home, |path, c| path.join(c)))
+		})?;
+	Some(base.join("polaris"))
+}
+
+// Data files are stored in %APPDATA%\Polaris, the log file in %LOCALAPPDATA%\Polaris and cache
+// files in %LOCALAPPDATA%\Polaris\cache. Client files are looked up in the directory where the
+// installer puts them.
 #[cfg(windows)]
-impl Default for Paths {
-	fn default() -> Self {
-		let local_app_data = std::env::var("LOCALAPPDATA").map(PathBuf::from).unwrap();
-		let install_directory: PathBuf =
-			local_app_data.join(["Permafrost", "Polaris"].iter().collect::<PathBuf>());
+impl Paths {
+	fn from_environment<E>(env: E, working_directory: &Path) -> Self
+	where
+		E: Fn(&str) -> Option<OsString>,
+	{
+		let working_directory = working_directory.to_owned();
+		let install_directory = windows_directory(&env, "LOCALAPPDATA")
+			.map(|d| d.join("Permafrost").join("Polaris"))
+			.unwrap_or_else(|| working_directory.clone());
+		let data_dir = windows_directory(&env, "APPDATA")
+			.map(|d| d.join("Polaris"))
+			.unwrap_or_else(|| working_directory.clone());
+		let local_data_dir = windows_directory(&env, "LOCALAPPDATA")
+			.map(|d| d.join("Polaris"))
+			.unwrap_or_else(|| working_directory.clone());
+
+		// Databases created by earlier versions in the installation directory keep being used
+		let legacy_db_file_path = install_directory.join("db.sqlite");
+		let db_file_path = if legacy_db_file_path.is_file() {
+			legacy_db_file_path
+		} else {
+			data_dir.join("db.sqlite")
+		};
+
 		Self {
-			cache_dir_path: install_directory.clone(),
-			config_file_path: None,
-			db_file_path: install_directory.join("db.sqlite"),
-			log_file_path: Some(install_directory.join("polaris.log")),
-			swagger_dir_path: install_directory.join("swagger"),
+			cache_dir_path: local_data_dir.join("cache"),
+			config_file_path: Some(data_dir.join("polaris.toml")).filter(|p| p.is_file()),
+			db_file_path,
+			log_file_path: Some(local_data_dir.join("polaris.log")),
+			swagger_dir_path: Some(install_directory.join("swagger")),
+			tls_cert_file_path: None,
+			tls_key_file_path: None,
 			web_dir_path: install_directory.join("web"),
 		}
 	}
 }
 
+#[cfg(windows)]
+fn windows_directory<E>(env: &E, variable: &str) -> Option<PathBuf>
+where
+	E: Fn(&str) -> Option<OsString>,
+{
+	env(variable).map(PathBuf::from).filter(|p| p.is_absolute())
+}
+
 impl Paths {
 	fn from_build() -> Self {
 		let defaults = Self::default();
@@ -55,7 +143,7 @@ impl Paths {
 				.map(PathBuf::from)
 				.map(|p| p.join("db.sqlite"))
 				.unwrap_or(defaults.db_file_path),
-			config_file_path: None,
+			config_file_path: defaults.config_file_path,
 			cache_dir_path: option_env!("POLARIS_CACHE_DIR")
 				.map(PathBuf::from)
 				.unwrap_or(defaults.cache_dir_path),
@@ -68,9 +156,13 @@ impl Paths {
 				.map(PathBuf::from)
 				.map(|p| p.join("polaris.pid"))
 				.unwrap_or(defaults.pid_file_path),
+			#[cfg(unix)]
+			socket_file_path: defaults.socket_file_path,
 			swagger_dir_path: option_env!("POLARIS_SWAGGER_DIR")
 				.map(PathBuf::from)
-				.unwrap_or(defaults.swagger_dir_path),
+				.or(defaults.swagger_dir_path),
+			tls_cert_file_path: defaults.tls_cert_file_path,
+			tls_key_file_path: defaults.tls_key_file_path,
 			web_dir_path: option_env!("POLARIS_WEB_DIR")
 				.map(PathBuf::from)
 				.unwrap_or(defaults.web_dir_path),
@@ -92,8 +184,21 @@ impl Paths {
 		if let Some(path) = &cli_options.pid_file_path {
 			paths.pid_file_path = path.clone();
 		}
+		#[cfg(unix)]
+		if let Some(path) = &cli_options.socket_file_path {
+			paths.socket_file_path = Some(path.clone());
+		}
 		if let Some(path) = &cli_options.swagger_dir_path {
-			paths.swagger_dir_path = path.clone();
+			paths.swagger_dir_path = Some(path.clone());
+		}
+		if cli_options.disable_swagger {
+			paths.swagger_dir_path = None;
+		}
+		if let Some(path) = &cli_options.tls_cert_file_path {
+			paths.tls_cert_file_path = Some(path.clone());
+		}
+		if let Some(path) = &cli_options.tls_key_file_path {
+			paths.tls_key_file_path = Some(path.clone());
 		}
 		if let Some(path) = &cli_options.web_dir_path {
 			paths.web_dir_path = path.clone();
@@ -109,3 +214,99 @@ impl Paths {
 		return paths;
 	}
 }
+
+#[cfg(test)]
+fn test_environment(variables: &[(&str, &str)]) -> impl Fn(&str) -> Option<OsString> {
+	let variables: std::collections::HashMap<String, OsString> = variables
+		.iter()
+		.map(|(k, v)| (k.to_string(), OsString::from(v)))
+		.collect();
+	move |name| variables.get(name).cloned()
+}
+
+#[cfg(not(windows))]
+#[test]
+fn paths_follow_xdg_variables() {
+	let env = test_environment(&[
+		("HOME", "/home/user"),
+		("XDG_DATA_HOME", "/xdg/data"),
+		("XDG_CACHE_HOME", "/xdg/cache"),
+		("XDG_STATE_HOME", "/xdg/state"),
+		("XDG_RUNTIME_DIR", "/xdg/runtime"),
+	]);
+	let paths = Paths::from_environment(env, Path::new("not-a-directory"));
+	assert_eq!(paths.db_file_path, Path::new("/xdg/data/polaris/db.sqlite"));
+	assert_eq!(paths.cache_dir_path, Path::new("/xdg/cache/polaris"));
+	assert_eq!(
+		paths.log_file_path.unwrap(),
+		Path::new("/xdg/state/polaris/polaris.log")
+	);
+	assert_eq!(
+		paths.pid_file_path,
+		Path::new("/xdg/runtime/polaris/polaris.pid")
+	);
+}
+
+#[cfg(not(windows))]
+#[test]
+fn paths_default_to_home_directory() {
+	let env = test_environment(&[("HOME", "/home/user"), ("XDG_DATA_HOME", "relative")]);
+	let paths = Paths::from_environment(env, Path::new("not-a-directory"));
+	assert_eq!(
+		paths.db_file_path,
+		Path::new("/home/user/.local/share/polaris/db.sqlite")
+	);
+	assert_eq!(paths.cache_dir_path, Path::new("/home/user/.cache/polaris"));
+	assert_eq!(
+		paths.log_file_path.unwrap(),
+		Path::new("/home/user/.local/state/polaris/polaris.log")
+	);
+}
+
+#[cfg(not(windows))]
+#[test]
+fn paths_keep_legacy_database() {
+	let working_directory = crate::test::prepare_test_directory(crate::test_name!());
+	std::fs::write(working_directory.join("db.sqlite"), b"").unwrap();
+	let env = test_environment(&[("HOME", "/home/user")]);
+	let paths = Paths::from_environment(env, &working_directory);
+	assert_eq!(paths.db_file_path, working_directory.join("db.sqlite"));
+}
+
+#[cfg(windows)]
+#[test]
+fn paths_follow_appdata_variables() {
+	let env = test_environment(&[("APPDATA", "C:\\Roaming"), ("LOCALAPPDATA", "C:\\Local")]);
+	let paths = Paths::from_environment(env, Path::new("not-a-directory"));
+	assert_eq!(
+		paths.db_file_path,
+		Path::new("C:\\Roaming\\Polaris\\db.sqlite")
+	);
+	assert_eq!(paths.cache_dir_path, Path::new("C:\\Local\\Polaris\\cache"));
+	assert_eq!(
+		paths.log_file_path.unwrap(),
+		Path::new("C:\\Local\\Polaris\\polaris.log")
+	);
+	assert_eq!(
+		paths.web_dir_path,
+		Path::new("C:\\Local\\Permafrost\\Polaris\\web")
+	);
+}
+
+#[cfg(windows)]
+#[test]
+fn paths_keep_legacy_database() {
+	let local_app_data = crate::test::prepare_test_directory(crate::test_name!())
+		.canonicalize()
+		.unwrap();
+	let install_directory = local_app_data.join("Permafrost").join("Polaris");
+	std::fs::create_dir_all(&install_directory).unwrap();
+	std::fs::write(install_directory.join("db.sqlite"), b"").unwrap();
+	let local_app_data_variable = local_app_data.to_string_lossy().into_owned();
+	let env = test_environment(&[
+		("APPDATA", "C:\\Roaming"),
+		("LOCALAPPDATA", local_app_data_variable.as_str()),
+	]);
+	let paths = Paths::from_environment(env, Path::new("not-a-directory"));
+	assert_eq!(paths.db_file_path, install_directory.join("db.sqlite"));
+}
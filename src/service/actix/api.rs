@@ -2,37 +2,66 @@ use actix_files::NamedFile;
 use actix_web::{
 	client::HttpError,
 	delete,
-	dev::{MessageBody, Payload, Service, ServiceRequest, ServiceResponse},
-	error::{BlockingError, ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized},
+	dev::{
+		Body, BodyEncoding, BodySize, MessageBody, Payload, ResponseBody, Service, ServiceRequest,
+		ServiceResponse,
+	},
+	error::{BlockingError, ErrorGone, JsonPayloadError},
 	get,
-	http::StatusCode,
-	post, put,
-	web::{self, Data, Json, JsonConfig, ServiceConfig},
+	http::{
+		header::{
+			self, ETag, EntityTag, Header, HeaderName, HttpDate, IfMatch, IfModifiedSince,
+			IfNoneMatch, LastModified,
+		},
+		ContentEncoding, HeaderMap, HeaderValue, Method, StatusCode,
+	},
+	post, put, route, rt,
+	web::{self, Bytes, Data, Json, JsonConfig, PayloadConfig, ServiceConfig},
 	FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError,
 };
-use actix_web_httpauth::extractors::{basic::BasicAuth, bearer::BearerAuth};
+use actix_web_httpauth::extractors::{basic::BasicAuth, bearer::BearerAuth, AuthenticationError};
+use actix_web_httpauth::headers::www_authenticate::basic::Basic;
 use cookie::{self, *};
-use futures_util::future::{err, ok};
+use futures_util::future::{self, err, ok};
+use futures_util::stream::{self, StreamExt};
+use mime::Mime;
 use percent_encoding::percent_decode_str;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
 use std::future::Future;
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::str;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
 
 use crate::app::{
-	config, ddns,
+	artist, config, ddns,
 	index::{self, Index},
-	lastfm, playlist, settings, thumbnail, user, vfs,
+	lastfm, listenbrainz, maintenance, metrics, now_playing, operation, playlist, scheduler,
+	settings, stats, throttle, thumbnail, transcode, user, vfs, waveform,
 };
 use crate::service::{dto, error::*};
+use crate::utils;
 
-pub fn make_config() -> impl FnOnce(&mut ServiceConfig) + Clone {
+pub fn make_config(max_json_body_size: usize) -> impl FnOnce(&mut ServiceConfig) + Clone {
 	move |cfg: &mut ServiceConfig| {
 		let megabyte = 1024 * 1024;
-		cfg.app_data(JsonConfig::default().limit(4 * megabyte)) // 4MB
+		let json_config = JsonConfig::default()
+			.limit(max_json_body_size)
+			.error_handler(|e, _| match e {
+				JsonPayloadError::Overflow => APIError::RequestBodyTooLarge.into(),
+				e => e.into(),
+			});
+		cfg.app_data(json_config)
+			.app_data(PayloadConfig::default().limit(16 * megabyte)) // 16MB
 			.service(version)
+			.service(health)
 			.service(initial_setup)
+			.service(get_status)
 			.service(apply_config)
 			.service(get_settings)
 			.service(put_settings)
@@ -40,33 +69,74 @@ pub fn make_config() -> impl FnOnce(&mut ServiceConfig) + Clone {
 			.service(put_mount_dirs)
 			.service(get_ddns_config)
 			.service(put_ddns_config)
+			.service(test_ddns_config)
+			.service(get_ddns_status)
 			.service(list_users)
 			.service(create_user)
 			.service(update_user)
+			.service(update_user_password)
 			.service(delete_user)
 			.service(get_preferences)
 			.service(put_preferences)
+			.service(put_preferences_password)
 			.service(trigger_index)
+			.service(trigger_index_path)
+			.service(preview_index)
+			.service(get_index_progress)
+			.service(resolve)
+			.service(events)
+			.service(find_orphans)
+			.service(get_metrics)
+			.service(clean_orphans)
 			.service(login)
+			.service(login_jwt)
+			.service(logout)
 			.service(browse_root)
 			.service(browse)
 			.service(flatten_root)
 			.service(flatten)
+			.service(get_songs)
+			.service(get_album)
 			.service(random)
 			.service(recent)
+			.service(artists)
+			.service(artist_albums)
+			.service(genres)
+			.service(albums_by_genre)
 			.service(search_root)
 			.service(search)
 			.service(get_audio)
+			.service(get_album_stream)
+			.service(get_stream)
+			.service(get_waveform)
+			.service(cancel_operation)
 			.service(get_thumbnail)
+			.service(get_artwork)
+			.service(put_artist_image)
+			.service(get_artist_image)
 			.service(list_playlists)
 			.service(save_playlist)
 			.service(read_playlist)
+			.service(export_playlist)
+			.service(import_playlist)
+			.service(rename_playlist)
 			.service(delete_playlist)
+			.service(delete_playlists)
+			.service(get_listening_stats)
+			.service(get_top_songs)
+			.service(scrobble_local)
+			.service(get_daily_mix)
+			.service(list_sessions)
+			.service(revoke_session)
+			.service(list_all_sessions)
+			.service(get_now_playing)
 			.service(lastfm_now_playing)
 			.service(lastfm_scrobble)
 			.service(lastfm_link_token)
 			.service(lastfm_link)
-			.service(lastfm_unlink);
+			.service(lastfm_unlink)
+			.service(listenbrainz_link)
+			.service(listenbrainz_unlink);
 	}
 }
 
@@ -74,21 +144,63 @@ impl ResponseError for APIError {
 	fn status_code(&self) -> StatusCode {
 		match self {
 			APIError::IncorrectCredentials => StatusCode::UNAUTHORIZED,
+			APIError::TooManyLoginAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
 			APIError::EmptyUsername => StatusCode::BAD_REQUEST,
 			APIError::EmptyPassword => StatusCode::BAD_REQUEST,
 			APIError::DeletingOwnAccount => StatusCode::CONFLICT,
 			APIError::OwnAdminPrivilegeRemoval => StatusCode::CONFLICT,
+			APIError::LastAdminRemoval => StatusCode::CONFLICT,
+			APIError::AdminPrivilegeRequired => StatusCode::FORBIDDEN,
+			APIError::MetricsAccessDenied => StatusCode::FORBIDDEN,
+			APIError::ArtistImageNotFound => StatusCode::NOT_FOUND,
+			APIError::ArtworkNotFound => StatusCode::NOT_FOUND,
+			APIError::InvalidImage => StatusCode::BAD_REQUEST,
 			APIError::AudioFileIOError => StatusCode::NOT_FOUND,
+			APIError::UnplayableAudioFormat => StatusCode::NOT_ACCEPTABLE,
+			APIError::UnsupportedBitrate => StatusCode::BAD_REQUEST,
+			APIError::TranscodingFailed => StatusCode::INTERNAL_SERVER_ERROR,
+			APIError::UnsupportedWaveformBins => StatusCode::BAD_REQUEST,
 			APIError::ThumbnailFileIOError => StatusCode::NOT_FOUND,
 			APIError::LastFMAccountNotLinked => StatusCode::NO_CONTENT,
 			APIError::LastFMLinkContentBase64DecodeError => StatusCode::BAD_REQUEST,
 			APIError::LastFMLinkContentEncodingError => StatusCode::BAD_REQUEST,
+			APIError::OperationNotFound => StatusCode::NOT_FOUND,
 			APIError::UserNotFound => StatusCode::NOT_FOUND,
 			APIError::PlaylistNotFound => StatusCode::NOT_FOUND,
+			APIError::EmptyPlaylistName => StatusCode::BAD_REQUEST,
+			APIError::PlaylistAlreadyExists => StatusCode::CONFLICT,
+			APIError::SessionNotFound => StatusCode::NOT_FOUND,
+			APIError::IndexAlreadyRunning => StatusCode::CONFLICT,
+			APIError::RequestBodyTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
 			APIError::VFSPathNotFound => StatusCode::NOT_FOUND,
+			APIError::VFSPathForbidden => StatusCode::FORBIDDEN,
+			APIError::InvalidSessionLifetime => StatusCode::BAD_REQUEST,
+			APIError::InvalidThumbnailQuality => StatusCode::BAD_REQUEST,
+			APIError::SettingsRevisionMismatch => StatusCode::CONFLICT,
+			APIError::InvalidPercentEncoding => StatusCode::BAD_REQUEST,
+			APIError::PathTraversal => StatusCode::BAD_REQUEST,
+			APIError::UnsupportedBrowseDepth => StatusCode::BAD_REQUEST,
+			APIError::TooManySongPaths => StatusCode::BAD_REQUEST,
+			APIError::DatabaseUnavailable => StatusCode::SERVICE_UNAVAILABLE,
 			APIError::Unspecified => StatusCode::INTERNAL_SERVER_ERROR,
 		}
 	}
+
+	fn error_response(&self) -> HttpResponse {
+		let status = self.status_code();
+		let mut response = HttpResponse::build(status);
+		if let APIError::TooManyLoginAttempts(retry_after) = self {
+			response.set_header(header::RETRY_AFTER, retry_after.as_secs().to_string());
+		}
+		// Responses with this status cannot have a body
+		if status == StatusCode::NO_CONTENT {
+			return response.finish();
+		}
+		response.json(dto::ErrorResponse {
+			error: self.code().to_owned(),
+			message: self.to_string(),
+		})
+	}
 }
 
 #[derive(Clone)]
@@ -115,6 +227,10 @@ impl Cookies {
 		self.jar.signed(&self.key).add(cookie);
 	}
 
+	fn remove(&mut self, cookie: Cookie<'static>) {
+		self.jar.remove(cookie);
+	}
+
 	#[allow(dead_code)]
 	fn get(&self, name: &str) -> Option<&Cookie> {
 		self.jar.get(name)
@@ -133,12 +249,12 @@ impl FromRequest for Cookies {
 	fn from_request(request: &HttpRequest, _payload: &mut Payload) -> Self::Future {
 		let request_cookies = match request.cookies() {
 			Ok(c) => c,
-			Err(_) => return Box::pin(err(ErrorInternalServerError(APIError::Unspecified))),
+			Err(_) => return Box::pin(err(APIError::Unspecified.into())),
 		};
 
 		let key = match request.app_data::<Data<Key>>() {
 			Some(k) => k.as_ref(),
-			None => return Box::pin(err(ErrorInternalServerError(APIError::Unspecified))),
+			None => return Box::pin(err(APIError::Unspecified.into())),
 		};
 
 		let mut cookies = Cookies::new(key.clone());
@@ -150,7 +266,7 @@ impl FromRequest for Cookies {
 	}
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 enum AuthSource {
 	AuthorizationBasic,
 	AuthorizationBearer,
@@ -158,10 +274,12 @@ enum AuthSource {
 	QueryParameter,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct Auth {
 	username: String,
 	source: AuthSource,
+	// Absent when authenticating without a session, like with a JWT or HTTP basic auth
+	session: Option<i32>,
 }
 
 impl FromRequest for Auth {
@@ -172,28 +290,42 @@ impl FromRequest for Auth {
 	fn from_request(request: &HttpRequest, payload: &mut Payload) -> Self::Future {
 		let user_manager = match request.app_data::<Data<user::Manager>>() {
 			Some(m) => m.clone(),
-			None => return Box::pin(err(ErrorInternalServerError(APIError::Unspecified))),
+			None => return Box::pin(err(APIError::Unspecified.into())),
+		};
+
+		let settings_manager = match request.app_data::<Data<settings::Manager>>() {
+			Some(m) => m.clone(),
+			None => return Box::pin(err(APIError::Unspecified.into())),
 		};
 
+		let throttle_manager = match request.app_data::<Data<throttle::Manager>>() {
+			Some(m) => m.clone(),
+			None => return Box::pin(err(APIError::Unspecified.into())),
+		};
+		let client_ip = request.peer_addr().map(|a| a.ip().to_string());
+
 		let cookies_future = Cookies::from_request(request, payload);
 		let basic_auth_future = BasicAuth::from_request(request, payload);
 		let bearer_auth_future = BearerAuth::from_request(request, payload);
 		let query_params_future =
 			web::Query::<dto::AuthQueryParameters>::from_request(request, payload);
+		let request = request.clone();
 
 		Box::pin(async move {
 			// Auth via session cookie
 			{
 				let mut cookies = cookies_future.await?;
 				if let Some(session_cookie) = cookies.get_signed(dto::COOKIE_SESSION) {
-					let username = session_cookie.value().to_string();
-					let exists = block(move || user_manager.exists(&username)).await?;
-					if !exists {
-						return Err(ErrorUnauthorized(APIError::Unspecified));
-					}
+					let auth_token = user::AuthToken(session_cookie.value().to_owned());
+					let authorization = block(move || {
+						user_manager
+							.authenticate(&auth_token, user::AuthorizationScope::PolarisAuth)
+					})
+					.await?;
 					return Ok(Auth {
-						username: session_cookie.value().to_string(),
+						username: authorization.username,
 						source: AuthSource::Cookie,
+						session: authorization.session,
 					});
 				}
 			}
@@ -208,45 +340,79 @@ impl FromRequest for Auth {
 				return Ok(Auth {
 					username: authorization.username.to_owned(),
 					source: AuthSource::QueryParameter,
+					session: authorization.session,
 				});
 			}
 
 			// Auth via bearer token in authorization header
 			if let Ok(bearer_auth) = bearer_auth_future.await {
 				let auth_token = user::AuthToken(bearer_auth.token().to_owned());
-				let authorization = block(move || {
-					user_manager.authenticate(&auth_token, user::AuthorizationScope::PolarisAuth)
+				let (username, session) = block(move || -> Result<_, user::Error> {
+					if auth_token.is_jwt() {
+						return Ok((user_manager.authenticate_jwt(&auth_token)?.sub, None));
+					}
+					let authorization = user_manager
+						.authenticate(&auth_token, user::AuthorizationScope::PolarisAuth)?;
+					Ok((authorization.username, authorization.session))
 				})
 				.await?;
 				return Ok(Auth {
-					username: authorization.username.to_owned(),
+					username,
 					source: AuthSource::AuthorizationBearer,
+					session,
 				});
 			}
 
 			// Auth via basic authorization header
 			{
-				let basic_auth = basic_auth_future.await?;
+				let basic_auth = match basic_auth_future.await {
+					Ok(b) => b,
+					Err(_) => return Err(basic_auth_challenge(settings_manager).await),
+				};
 				let username = basic_auth.user_id().to_string();
 				let password = basic_auth
 					.password()
 					.map(|s| s.as_ref())
 					.unwrap_or("")
 					.to_string();
-				let auth_result = block(move || user_manager.login(&username, &password)).await;
-				if auth_result.is_ok() {
-					Ok(Auth {
-						username: basic_auth.user_id().to_string(),
-						source: AuthSource::AuthorizationBasic,
+				// Credentials are checked on every request, which must not allow more guesses
+				// than the login endpoints
+				let mut keys = vec![throttle::Key::Username(username.clone())];
+				keys.extend(client_ip.map(throttle::Key::ClientIp));
+				let auth_result = block(move || {
+					throttled_login(&throttle_manager, &keys, || {
+						user_manager.check_credentials(&username, &password)
 					})
-				} else {
-					Err(ErrorUnauthorized(APIError::Unspecified))
+				})
+				.await;
+				match auth_result {
+					Ok(()) => {
+						let auth = Auth {
+							username: basic_auth.user_id().to_string(),
+							source: AuthSource::AuthorizationBasic,
+							session: None,
+						};
+						// Lets http_auth_middleware hand out a session without checking the
+						// credentials a second time
+						request.extensions_mut().insert(auth.clone());
+						Ok(auth)
+					}
+					Err(e @ APIError::TooManyLoginAttempts(_)) => Err(e.into()),
+					Err(_) => Err(basic_auth_challenge(settings_manager).await),
 				}
 			}
 		})
 	}
 }
 
+// Lets browsers and command line clients prompt for credentials
+async fn basic_auth_challenge(settings_manager: Data<settings::Manager>) -> actix_web::Error {
+	let realm = block(move || settings_manager.get_auth_realm())
+		.await
+		.unwrap_or_else(|_| settings::DEFAULT_AUTH_REALM.to_owned());
+	AuthenticationError::new(Basic::with_realm(realm)).into()
+}
+
 #[derive(Debug)]
 struct AdminRights {
 	auth: Option<Auth>,
@@ -260,7 +426,7 @@ impl FromRequest for AdminRights {
 	fn from_request(request: &HttpRequest, payload: &mut Payload) -> Self::Future {
 		let user_manager = match request.app_data::<Data<user::Manager>>() {
 			Some(m) => m.clone(),
-			None => return Box::pin(err(ErrorInternalServerError(APIError::Unspecified))),
+			None => return Box::pin(err(APIError::Unspecified.into())),
 		};
 
 		let auth_future = Auth::from_request(request, payload);
@@ -269,7 +435,7 @@ impl FromRequest for AdminRights {
 			let user_manager_count = user_manager.clone();
 			let user_count = block(move || user_manager_count.count()).await;
 			match user_count {
-				Err(_) => return Err(ErrorInternalServerError(APIError::Unspecified)),
+				Err(_) => return Err(APIError::Unspecified.into()),
 				Ok(0) => return Ok(AdminRights { auth: None }),
 				_ => (),
 			};
@@ -280,7 +446,7 @@ impl FromRequest for AdminRights {
 			if is_admin {
 				Ok(AdminRights { auth: Some(auth) })
 			} else {
-				Err(ErrorForbidden(APIError::Unspecified))
+				Err(APIError::AdminPrivilegeRequired.into())
 			}
 		})
 	}
@@ -296,21 +462,23 @@ pub fn http_auth_middleware<
 ) -> Pin<Box<dyn Future<Output = Result<ServiceResponse<B>, actix_web::Error>>>> {
 	let user_manager = match request.app_data::<Data<user::Manager>>() {
 		Some(m) => m.clone(),
-		None => return Box::pin(err(ErrorInternalServerError(APIError::Unspecified))),
+		None => return Box::pin(err(APIError::Unspecified.into())),
 	};
 
+	let label = session_label(request.headers());
 	let (request, mut payload) = request.into_parts();
-	let auth_future = Auth::from_request(&request, &mut payload);
 	let cookies_future = Cookies::from_request(&request, &mut payload);
 	let request = match ServiceRequest::from_parts(request, payload) {
 		Ok(s) => s,
-		Err(_) => return Box::pin(err(ErrorInternalServerError(APIError::Unspecified))),
+		Err(_) => return Box::pin(err(APIError::Unspecified.into())),
 	};
 
 	let response_future = service.call(request);
 	Box::pin(async move {
 		let mut response = response_future.await?;
-		if let Ok(auth) = auth_future.await {
+		// Set by the Auth extractor of the endpoint which handled the request
+		let auth = response.request().extensions().get::<Auth>().cloned();
+		if let Some(auth) = auth {
 			let set_cookies = match auth.source {
 				AuthSource::AuthorizationBasic => true,
 				AuthSource::AuthorizationBearer => false,
@@ -320,14 +488,168 @@ pub fn http_auth_middleware<
 			if set_cookies {
 				let cookies = cookies_future.await?;
 				let username = auth.username.clone();
-				let is_admin = block(move || {
-					user_manager
-						.is_admin(&auth.username)
-						.map_err(|_| APIError::Unspecified)
+				let (auth_token, is_admin, lifetime) = block(move || -> Result<_, APIError> {
+					let auth_token =
+						user_manager.resume_session(&auth.username, label.as_deref())?;
+					let is_admin = user_manager.is_admin(&auth.username)?;
+					let lifetime = user_manager.session_lifetime()?;
+					Ok((auth_token, is_admin, lifetime))
 				})
 				.await?;
-				add_auth_cookies(response.response_mut(), &cookies, &username, is_admin)?;
+				add_auth_cookies(
+					response.response_mut(),
+					&cookies,
+					&username,
+					&auth_token,
+					is_admin,
+					lifetime,
+				)?;
+			}
+		}
+		Ok(response)
+	})
+}
+
+fn is_login_request(request: &ServiceRequest) -> bool {
+	request.method() == Method::POST
+		&& matches!(
+			request
+				.match_pattern()
+				.as_deref()
+				.map(|p| p.trim_start_matches("/api")),
+			Some("/auth") | Some("/auth/jwt")
+		)
+}
+
+// Methods offered to cross-origin clients, which covers every endpoint of the API
+const CORS_ALLOWED_METHODS: &str = "GET, POST, PUT, DELETE";
+// How long browsers may cache the answer to a preflight request, in seconds
+const CORS_MAX_AGE: &str = "86400";
+
+pub fn cors_middleware<
+	S: Service<
+			Response = ServiceResponse<Body>,
+			Request = ServiceRequest,
+			Error = actix_web::Error,
+		> + 'static,
+>(
+	request: ServiceRequest,
+	service: &mut S,
+) -> Pin<Box<dyn Future<Output = Result<ServiceResponse<Body>, actix_web::Error>>>> {
+	let origin = request
+		.headers()
+		.get(header::ORIGIN)
+		.and_then(|o| o.to_str().ok())
+		.map(|o| o.to_owned());
+	let settings_manager = request.app_data::<Data<settings::Manager>>().cloned();
+	let (origin, settings_manager) = match (origin, settings_manager) {
+		(Some(o), Some(m)) => (o, m),
+		_ => return Box::pin(service.call(request)),
+	};
+
+	let is_preflight = request.method() == Method::OPTIONS
+		&& request
+			.headers()
+			.contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+	let requested_headers = request
+		.headers()
+		.get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+		.cloned();
+
+	// Preflight requests are answered here, other requests go through the API as usual
+	let response_future = match is_preflight {
+		true => Err(request),
+		false => Ok(service.call(request)),
+	};
+
+	Box::pin(async move {
+		let allowed_origins = block(move || settings_manager.get_cors_allowed_origins()).await?;
+		let is_allowed = allowed_origins.iter().any(|o| o == &origin);
+		let mut response = match response_future {
+			Ok(response_future) => response_future.await?,
+			Err(request) if is_allowed => {
+				let mut preflight = HttpResponse::NoContent();
+				preflight
+					.header(header::ACCESS_CONTROL_ALLOW_METHODS, CORS_ALLOWED_METHODS)
+					.header(header::ACCESS_CONTROL_MAX_AGE, CORS_MAX_AGE);
+				if let Some(requested_headers) = requested_headers {
+					preflight.header(header::ACCESS_CONTROL_ALLOW_HEADERS, requested_headers);
+				}
+				request.into_response(preflight.finish())
 			}
+			Err(request) => request.into_response(HttpResponse::Forbidden().finish()),
+		};
+		if is_allowed {
+			let headers = response.headers_mut();
+			headers.insert(
+				header::ACCESS_CONTROL_ALLOW_ORIGIN,
+				HeaderValue::from_str(&origin).map_err(|_| APIError::Unspecified)?,
+			);
+			headers.insert(
+				header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+				HeaderValue::from_static("true"),
+			);
+			headers.append(header::VARY, HeaderValue::from_static("Origin"));
+		}
+		Ok(response)
+	})
+}
+
+pub fn metrics_middleware<
+	B: MessageBody + 'static,
+	S: Service<Response = ServiceResponse<B>, Request = ServiceRequest, Error = actix_web::Error>
+		+ 'static,
+>(
+	request: ServiceRequest,
+	service: &mut S,
+) -> Pin<Box<dyn Future<Output = Result<ServiceResponse<B>, actix_web::Error>>>> {
+	let metrics_manager = match request.app_data::<Data<metrics::Manager>>() {
+		Some(m) => m.clone(),
+		None => return Box::pin(service.call(request)),
+	};
+	let is_login = is_login_request(&request);
+
+	let response_future = service.call(request);
+	Box::pin(async move {
+		let response = response_future.await;
+		let status = match &response {
+			Ok(r) => r.status(),
+			Err(e) => e.as_response_error().status_code(),
+		};
+		metrics_manager.record_response(status.as_u16());
+		if is_login && (status.is_success() || status == StatusCode::UNAUTHORIZED) {
+			metrics_manager.record_login(status.is_success());
+		}
+		response
+	})
+}
+
+pub fn auth_throttle_middleware<
+	B: MessageBody + 'static,
+	S: Service<Response = ServiceResponse<B>, Request = ServiceRequest, Error = actix_web::Error>
+		+ 'static,
+>(
+	request: ServiceRequest,
+	service: &mut S,
+) -> Pin<Box<dyn Future<Output = Result<ServiceResponse<B>, actix_web::Error>>>> {
+	let is_login = is_login_request(&request);
+	let throttle_manager = request.app_data::<Data<throttle::Manager>>().cloned();
+	let client_ip = request.peer_addr().map(|a| a.ip().to_string());
+	let (throttle_manager, key) = match (is_login, throttle_manager, client_ip) {
+		(true, Some(m), Some(ip)) => (m, throttle::Key::ClientIp(ip)),
+		_ => return Box::pin(service.call(request)),
+	};
+
+	let response_future = service.call(request);
+	Box::pin(async move {
+		let check_key = key.clone();
+		let check_manager = throttle_manager.clone();
+		block(move || check_manager.check(&check_key)).await?;
+		let response = response_future.await?;
+		if response.status().is_success() {
+			throttle_manager.reset(&key);
+		} else if response.status() == StatusCode::UNAUTHORIZED {
+			throttle_manager.record_failure(&key);
 		}
 		Ok(response)
 	})
@@ -337,15 +659,18 @@ fn add_auth_cookies<T>(
 	response: &mut HttpResponse<T>,
 	cookies: &Cookies,
 	username: &str,
+	auth_token: &user::AuthToken,
 	is_admin: bool,
+	lifetime: Duration,
 ) -> Result<(), HttpError> {
 	let mut cookies = cookies.clone();
+	let max_age = time::Duration::seconds(lifetime.as_secs() as i64);
 
 	cookies.add_signed(
-		Cookie::build(dto::COOKIE_SESSION, username.to_owned())
+		Cookie::build(dto::COOKIE_SESSION, auth_token.0.clone())
 			.same_site(cookie::SameSite::Lax)
 			.http_only(true)
-			.permanent()
+			.max_age(max_age)
 			.finish(),
 	);
 
@@ -353,7 +678,7 @@ fn add_auth_cookies<T>(
 		Cookie::build(dto::COOKIE_USERNAME, username.to_owned())
 			.same_site(cookie::SameSite::Lax)
 			.http_only(false)
-			.permanent()
+			.max_age(max_age)
 			.path("/")
 			.finish(),
 	);
@@ -362,21 +687,303 @@ fn add_auth_cookies<T>(
 		Cookie::build(dto::COOKIE_ADMIN, format!("{}", is_admin))
 			.same_site(cookie::SameSite::Lax)
 			.http_only(false)
-			.permanent()
+			.max_age(max_age)
 			.path("/")
 			.finish(),
 	);
 
+	set_cookie_headers(response, &cookies)
+}
+
+// Only cookies the client actually sent produce removal headers
+fn remove_auth_cookies<T>(
+	response: &mut HttpResponse<T>,
+	cookies: &Cookies,
+) -> Result<(), HttpError> {
+	let mut cookies = cookies.clone();
+	cookies.remove(Cookie::named(dto::COOKIE_SESSION));
+	cookies.remove(Cookie::build(dto::COOKIE_USERNAME, "").path("/").finish());
+	cookies.remove(Cookie::build(dto::COOKIE_ADMIN, "").path("/").finish());
+	set_cookie_headers(response, &cookies)
+}
+
+fn set_cookie_headers<T>(
+	response: &mut HttpResponse<T>,
+	cookies: &Cookies,
+) -> Result<(), HttpError> {
 	let headers = response.headers_mut();
 	for cookie in cookies.jar.delta() {
 		http::HeaderValue::from_str(&cookie.to_string()).map(|c| {
 			headers.append(http::header::SET_COOKIE, c);
 		})?;
 	}
-
 	Ok(())
 }
 
+// Describes the client a session was opened from, so users can tell their sessions apart
+fn session_label(headers: &HeaderMap) -> Option<String> {
+	headers
+		.get(header::USER_AGENT)
+		.and_then(|h| h.to_str().ok())
+		.map(|s| s.to_owned())
+}
+
+#[derive(Clone, Copy)]
+pub struct CachePolicy {
+	pub collection_max_age: Duration,
+}
+
+enum CacheClass {
+	NoStore,
+	Static,
+	Collection,
+	Media,
+	Revalidate,
+}
+
+fn cache_class(method: &Method, pattern: Option<&str>) -> CacheClass {
+	if method != Method::GET && method != Method::HEAD {
+		return CacheClass::NoStore;
+	}
+	match pattern.map(|p| p.trim_start_matches("/api")) {
+		Some("/version") => CacheClass::Static,
+		Some("/browse")
+		| Some("/browse/{path:.*}")
+		| Some("/flatten")
+		| Some("/flatten/{path:.*}")
+		| Some("/album/{path:.*}")
+		| Some("/recent")
+		| Some("/artists")
+		| Some("/artist/{name}")
+		| Some("/genres")
+		| Some("/genre/{name}")
+		| Some("/search")
+		| Some("/search/{query:.*}") => CacheClass::Collection,
+		Some("/audio/{path:.*}")
+		| Some("/album_stream/{path:.*}")
+		| Some("/stream/{path:.*}")
+		| Some("/thumbnail/{path:.*}")
+		| Some("/artwork/{path:.*}")
+		| Some("/artist/{name}/image") => CacheClass::Media,
+		Some("/playlist/{name}") => CacheClass::Revalidate,
+		_ => CacheClass::NoStore,
+	}
+}
+
+// Sets Cache-Control on every API response according to the kind of endpoint that produced it.
+// Responses carrying credentials (cookies) or errors are never cached.
+pub fn cache_control_middleware<
+	B: MessageBody + 'static,
+	S: Service<Response = ServiceResponse<B>, Request = ServiceRequest, Error = actix_web::Error>
+		+ 'static,
+>(
+	request: ServiceRequest,
+	service: &mut S,
+) -> Pin<Box<dyn Future<Output = Result<ServiceResponse<B>, actix_web::Error>>>> {
+	let policy = request
+		.app_data::<Data<CachePolicy>>()
+		.map(|p| *p.get_ref());
+	let response_future = service.call(request);
+	Box::pin(async move {
+		let mut response = response_future.await?;
+		let status = response.status();
+		let cacheable = (status.is_success() || status == StatusCode::NOT_MODIFIED)
+			&& !response.headers().contains_key(header::SET_COOKIE);
+		let class = match cacheable {
+			true => cache_class(
+				response.request().method(),
+				response.request().match_pattern().as_deref(),
+			),
+			false => CacheClass::NoStore,
+		};
+		let value = match (class, policy) {
+			(CacheClass::Static, _) => "public, max-age=3600".to_owned(),
+			(CacheClass::Collection, Some(policy)) => {
+				format!("private, max-age={}", policy.collection_max_age.as_secs())
+			}
+			(CacheClass::Media, _) => "private, max-age=86400".to_owned(),
+			(CacheClass::Revalidate, _) => "private, no-cache".to_owned(),
+			_ => "no-store".to_owned(),
+		};
+		response.headers_mut().insert(
+			header::CACHE_CONTROL,
+			HeaderValue::from_str(&value).map_err(|_| APIError::Unspecified)?,
+		);
+		Ok(response)
+	})
+}
+
+// Answering multiple ranges would require a `multipart/byteranges` response, which is not
+// supported. The Range header of these requests is dropped so that they receive the full content,
+// instead of only the first range.
+pub fn multi_range_middleware<S>(mut request: ServiceRequest, service: &mut S) -> S::Future
+where
+	S: Service<Request = ServiceRequest>,
+{
+	let num_ranges = request
+		.headers()
+		.get(header::RANGE)
+		.and_then(|r| r.to_str().ok())
+		.map(|r| r.split(',').filter(|r| !r.trim().is_empty()).count())
+		.unwrap_or(0);
+	if num_ranges > 1 {
+		request.headers_mut().remove(header::RANGE);
+	}
+	service.call(request)
+}
+
+fn request_class(method: &Method, pattern: Option<&str>) -> scheduler::RequestClass {
+	if method != Method::GET {
+		return scheduler::RequestClass::Api;
+	}
+	match pattern.map(|p| p.trim_start_matches("/api")) {
+		Some("/audio/{path:.*}")
+		| Some("/album_stream/{path:.*}")
+		| Some("/stream/{path:.*}")
+		| Some("/thumbnail/{path:.*}")
+		| Some("/artwork/{path:.*}")
+		| Some("/artist/{name}/image") => scheduler::RequestClass::Transfer,
+		_ => scheduler::RequestClass::Api,
+	}
+}
+
+// Response body which holds on to a scheduling slot until it has been fully sent
+struct ScheduledBody<B> {
+	body: ResponseBody<B>,
+	_permit: scheduler::Permit,
+}
+
+impl<B: MessageBody + Unpin> MessageBody for ScheduledBody<B> {
+	fn size(&self) -> BodySize {
+		self.body.size()
+	}
+
+	fn poll_next(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Option<Result<Bytes, actix_web::Error>>> {
+		Pin::new(&mut self.body).poll_next(cx)
+	}
+}
+
+// Waits for a slot in the pool matching the kind of request before serving it, so that file
+// transfers cannot starve browsing requests.
+pub fn scheduling_middleware<
+	B: MessageBody + Unpin + 'static,
+	S: Service<Response = ServiceResponse<B>, Request = ServiceRequest, Error = actix_web::Error>
+		+ 'static,
+>(
+	request: ServiceRequest,
+	service: &mut S,
+) -> Pin<Box<dyn Future<Output = Result<ServiceResponse<Body>, actix_web::Error>>>> {
+	let class = request_class(request.method(), request.match_pattern().as_deref());
+	let acquire = request
+		.app_data::<Data<scheduler::Manager>>()
+		.map(|s| s.acquire(class));
+	let response_future = service.call(request);
+	Box::pin(async move {
+		let permit = match acquire {
+			Some(acquire) => Some(acquire.await),
+			None => None,
+		};
+		let response = response_future.await?;
+		Ok(match permit {
+			Some(permit) => response.map_body(|_, body| {
+				ResponseBody::Body(Body::from_message(ScheduledBody {
+					body,
+					_permit: permit,
+				}))
+			}),
+			None => response.map_body(|_, body| ResponseBody::Body(Body::from_message(body))),
+		})
+	})
+}
+
+// Response body which stops producing data when its operation gets cancelled
+struct CancellableBody {
+	body: ResponseBody<Body>,
+	operation: operation::Operation,
+}
+
+impl MessageBody for CancellableBody {
+	fn size(&self) -> BodySize {
+		self.body.size()
+	}
+
+	fn poll_next(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Option<Result<Bytes, actix_web::Error>>> {
+		if self.operation.poll_cancelled(cx) {
+			return Poll::Ready(Some(Err(ErrorGone("Operation was cancelled"))));
+		}
+		Pin::new(&mut self.body).poll_next(cx)
+	}
+}
+
+fn make_cancellable(response: HttpResponse, operation: operation::Operation) -> HttpResponse {
+	let id = operation.id();
+	let mut response = response.map_body(|_, body| {
+		ResponseBody::Body(Body::from_message(CancellableBody { body, operation }))
+	});
+	response.headers_mut().insert(
+		HeaderName::from_static("x-operation-id"),
+		HeaderValue::from(id),
+	);
+	response
+}
+
+// Parameters extracted by actix are already percent-decoded (except for a few reserved
+// characters), which makes a second decoding pass ambiguous for names containing '%'. This reads
+// the parameter from the raw request URI instead and decodes it exactly once.
+fn decode_path_parameter(request: &HttpRequest, name: &str) -> Result<String, APIError> {
+	let pattern = request.match_pattern().ok_or(APIError::Unspecified)?;
+	let start = pattern
+		.find(&format!("{{{}", name))
+		.ok_or(APIError::Unspecified)?;
+	let end = start + pattern[start..].find('}').ok_or(APIError::Unspecified)?;
+	let is_tail = pattern[start..end].ends_with(":.*");
+	let depth = pattern[..start].matches('/').count();
+
+	let mut segments = request.uri().path().split('/').skip(depth);
+	let raw = if is_tail {
+		segments.collect::<Vec<_>>().join("/")
+	} else {
+		segments.next().unwrap_or_default().to_owned()
+	};
+	utils::percent_decode_strict(&raw).ok_or(APIError::InvalidPercentEncoding)
+}
+
+// Virtual paths must stay within their mount, so `..` segments are refused before they reach the
+// VFS. Encoded slashes (%2F) and nested route segments decode to the same path.
+fn decode_virtual_path(request: &HttpRequest) -> Result<String, APIError> {
+	check_virtual_path(decode_path_parameter(request, "path")?)
+}
+
+fn check_virtual_path(path: String) -> Result<String, APIError> {
+	if path.split(['/', '\\']).any(|s| s == "..") {
+		return Err(APIError::PathTraversal);
+	}
+	Ok(path)
+}
+
+// Files are served from their canonical location, after checking that it lies within a mount.
+// This catches symbolic links leading out of the collection.
+fn resolve_served_file(
+	vfs_manager: &vfs::Manager,
+	virtual_path: &str,
+) -> Result<PathBuf, APIError> {
+	let vfs = vfs_manager.get_vfs()?;
+	let real_path = vfs
+		.virtual_to_real(Path::new(virtual_path))
+		.and_then(|p| Ok(p.canonicalize()?))
+		.map_err(|_| APIError::VFSPathNotFound)?;
+	if !vfs.contains(&real_path) {
+		return Err(APIError::VFSPathForbidden);
+	}
+	Ok(real_path)
+}
+
 async fn block<F, I, E>(f: F) -> Result<I, APIError>
 where
 	F: FnOnce() -> Result<I, E> + Send + 'static,
@@ -398,6 +1005,15 @@ async fn version() -> Json<dto::Version> {
 	Json(current_version)
 }
 
+// Readiness probe for container orchestrators, which do not log in
+#[get("/health")]
+async fn health(maintenance_manager: Data<maintenance::Manager>) -> Result<HttpResponse, APIError> {
+	block(move || maintenance_manager.check_database())
+		.await
+		.map_err(|_| APIError::DatabaseUnavailable)?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
 #[get("/initial_setup")]
 async fn initial_setup(
 	user_manager: Data<user::Manager>,
@@ -413,6 +1029,12 @@ async fn initial_setup(
 	Ok(Json(initial_setup))
 }
 
+#[get("/status")]
+async fn get_status(index: Data<Index>) -> Result<Json<index::Status>, APIError> {
+	let status = block(move || index.get_status()).await?;
+	Ok(Json(status))
+}
+
 #[put("/config")]
 async fn apply_config(
 	_admin_rights: AdminRights,
@@ -427,18 +1049,39 @@ async fn apply_config(
 async fn get_settings(
 	settings_manager: Data<settings::Manager>,
 	_admin_rights: AdminRights,
-) -> Result<Json<dto::Settings>, APIError> {
+) -> Result<HttpResponse, APIError> {
 	let settings = block(move || settings_manager.read()).await?;
-	Ok(Json(settings.into()))
+	let etag = EntityTag::strong(settings.revision.to_string());
+	Ok(HttpResponse::Ok()
+		.set(ETag(etag))
+		.json(dto::Settings::from(settings)))
 }
 
+// Updates carrying an If-Match header are rejected if settings changed since the client read them
 #[put("/settings")]
 async fn put_settings(
+	request: HttpRequest,
 	_admin_rights: AdminRights,
 	settings_manager: Data<settings::Manager>,
 	new_settings: Json<dto::NewSettings>,
 ) -> Result<HttpResponse, APIError> {
-	block(move || settings_manager.amend(&new_settings.to_owned().into())).await?;
+	let expected_revision = match IfMatch::parse(&request) {
+		Ok(IfMatch::Items(tags)) if !tags.is_empty() => {
+			let revision = tags
+				.iter()
+				.filter(|t| !t.weak)
+				.find_map(|t| t.tag().parse::<i64>().ok())
+				.ok_or(APIError::SettingsRevisionMismatch)?;
+			Some(revision)
+		}
+		_ => None,
+	};
+	let new_settings: settings::NewSettings = new_settings.to_owned().into();
+	block(move || match expected_revision {
+		Some(revision) => settings_manager.amend_at_revision(&new_settings, revision),
+		None => settings_manager.amend(&new_settings),
+	})
+	.await?;
 	Ok(HttpResponse::new(StatusCode::OK))
 }
 
@@ -486,6 +1129,24 @@ async fn put_ddns_config(
 	Ok(HttpResponse::new(StatusCode::OK))
 }
 
+#[get("/ddns/status")]
+async fn get_ddns_status(
+	ddns_manager: Data<ddns::Manager>,
+	_admin_rights: AdminRights,
+) -> Result<Json<dto::DDNSStatus>, APIError> {
+	let status = block(move || ddns_manager.status()).await?;
+	Ok(Json(status.into()))
+}
+
+#[post("/ddns/test")]
+async fn test_ddns_config(
+	_admin_rights: AdminRights,
+	ddns_manager: Data<ddns::Manager>,
+) -> Result<Json<dto::DDNSUpdateReport>, APIError> {
+	let report = block(move || ddns_manager.test_update()).await?;
+	Ok(Json(report.into()))
+}
+
 #[get("/users")]
 async fn list_users(
 	user_manager: Data<user::Manager>,
@@ -523,6 +1184,9 @@ async fn update_user(
 	}
 
 	block(move || -> Result<(), APIError> {
+		if user_update.new_is_admin == Some(false) && user_manager.is_last_admin(&name)? {
+			return Err(APIError::LastAdminRemoval);
+		}
 		if let Some(password) = &user_update.new_password {
 			user_manager.set_password(&name, password)?;
 		}
@@ -535,6 +1199,24 @@ async fn update_user(
 	Ok(HttpResponse::new(StatusCode::OK))
 }
 
+#[put("/user/{name}/password")]
+async fn update_user_password(
+	user_manager: Data<user::Manager>,
+	_admin_rights: AdminRights,
+	name: web::Path<String>,
+	password_update: Json<dto::PasswordUpdate>,
+) -> Result<HttpResponse, APIError> {
+	block(move || -> Result<(), APIError> {
+		if !user_manager.exists(&name)? {
+			return Err(APIError::UserNotFound);
+		}
+		user_manager.set_password(&name, &password_update.new_password)?;
+		Ok(())
+	})
+	.await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
 #[delete("/user/{name}")]
 async fn delete_user(
 	user_manager: Data<user::Manager>,
@@ -546,7 +1228,14 @@ async fn delete_user(
 			return Err(APIError::DeletingOwnAccount);
 		}
 	}
-	block(move || user_manager.delete(&name)).await?;
+	block(move || -> Result<(), APIError> {
+		if user_manager.is_last_admin(&name)? {
+			return Err(APIError::LastAdminRemoval);
+		}
+		user_manager.delete(&name)?;
+		Ok(())
+	})
+	.await?;
 	Ok(HttpResponse::new(StatusCode::OK))
 }
 
@@ -569,102 +1258,536 @@ async fn put_preferences(
 	Ok(HttpResponse::new(StatusCode::OK))
 }
 
-#[post("/trigger_index")]
-async fn trigger_index(
-	index: Data<Index>,
-	_admin_rights: AdminRights,
+#[put("/preferences/password")]
+async fn put_preferences_password(
+	user_manager: Data<user::Manager>,
+	auth: Auth,
+	password_change: Json<dto::PasswordChange>,
 ) -> Result<HttpResponse, APIError> {
-	index.trigger_reindex();
+	block(move || {
+		user_manager.change_password(
+			&auth.username,
+			&password_change.old_password,
+			&password_change.new_password,
+			auth.session,
+		)
+	})
+	.await?;
 	Ok(HttpResponse::new(StatusCode::OK))
 }
 
-#[post("/auth")]
-async fn login(
-	user_manager: Data<user::Manager>,
-	credentials: Json<dto::Credentials>,
-	cookies: Cookies,
+// The `path` query parameter is deprecated in favor of the /trigger_index/{path} route
+#[post("/trigger_index")]
+async fn trigger_index(
+	index: Data<Index>,
+	_admin_rights: AdminRights,
+	options: web::Query<dto::TriggerIndexOptions>,
 ) -> Result<HttpResponse, APIError> {
-	let username = credentials.username.clone();
-	let (user::AuthToken(token), is_admin) =
-		block(move || -> Result<(user::AuthToken, bool), APIError> {
-			let auth_token = user_manager.login(&credentials.username, &credentials.password)?;
-			let is_admin = user_manager.is_admin(&credentials.username)?;
-			Ok((auth_token, is_admin))
-		})
-		.await?;
-	let authorization = dto::Authorization {
-		username: username.clone(),
-		token,
-		is_admin,
-	};
-	let mut response = HttpResponse::Ok().json(authorization);
-	add_auth_cookies(&mut response, &cookies, &username, is_admin)
-		.map_err(|_| APIError::Unspecified)?;
-	Ok(response)
+	let options = options.into_inner();
+	request_reindex(index, options.path, options.mode).await
 }
 
-#[get("/browse")]
-async fn browse_root(
+#[post("/trigger_index/{path:.*}")]
+async fn trigger_index_path(
+	request: HttpRequest,
 	index: Data<Index>,
-	_auth: Auth,
-) -> Result<Json<Vec<index::CollectionFile>>, APIError> {
-	let result = block(move || index.browse(Path::new(""))).await?;
-	Ok(Json(result))
+	_admin_rights: AdminRights,
+	options: web::Query<dto::TriggerIndexOptions>,
+) -> Result<HttpResponse, APIError> {
+	let path = decode_path_parameter(&request, "path")?;
+	request_reindex(index, Some(path), options.mode).await
 }
 
-#[get("/browse/{path:.*}")]
-async fn browse(
+async fn request_reindex(
 	index: Data<Index>,
-	_auth: Auth,
-	path: web::Path<String>,
-) -> Result<Json<Vec<index::CollectionFile>>, APIError> {
-	let result = block(move || {
-		let path = percent_decode_str(&(path.0)).decode_utf8_lossy();
-		index.browse(Path::new(path.as_ref()))
+	path: Option<String>,
+	mode: Option<index::ScanMode>,
+) -> Result<HttpResponse, APIError> {
+	let path = path.map(check_virtual_path).transpose()?.map(PathBuf::from);
+	let mode = mode.unwrap_or(index::ScanMode::Full);
+	block(move || match (path, mode) {
+		(Some(path), index::ScanMode::Full) => index.trigger_reindex_path(&path),
+		(Some(path), index::ScanMode::Incremental) => index.trigger_incremental_reindex_path(&path),
+		(None, index::ScanMode::Full) => index.trigger_reindex(),
+		(None, index::ScanMode::Incremental) => index.trigger_incremental_reindex(),
 	})
 	.await?;
-	Ok(Json(result))
+	Ok(HttpResponse::new(StatusCode::OK))
 }
 
-#[get("/flatten")]
-async fn flatten_root(index: Data<Index>, _auth: Auth) -> Result<Json<Vec<index::Song>>, APIError> {
-	let songs = block(move || index.flatten(Path::new(""))).await?;
-	Ok(Json(songs))
+#[get("/index/preview")]
+async fn preview_index(
+	index: Data<Index>,
+	_admin_rights: AdminRights,
+) -> Result<Json<index::UpdatePreview>, APIError> {
+	let preview = block(move || index.dry_run()).await?;
+	Ok(Json(preview))
 }
 
-#[get("/flatten/{path:.*}")]
-async fn flatten(
+#[get("/index/progress")]
+async fn get_index_progress(
 	index: Data<Index>,
 	_auth: Auth,
-	path: web::Path<String>,
-) -> Result<Json<Vec<index::Song>>, APIError> {
-	let songs = block(move || {
-		let path = percent_decode_str(&(path.0)).decode_utf8_lossy();
-		index.flatten(Path::new(path.as_ref()))
-	})
-	.await?;
-	Ok(Json(songs))
+) -> Result<Json<index::Progress>, APIError> {
+	let progress = block(move || index.get_progress()).await?;
+	Ok(Json(progress))
 }
 
-#[get("/random")]
-async fn random(index: Data<Index>, _auth: Auth) -> Result<Json<Vec<index::Directory>>, APIError> {
-	let result = block(move || index.get_random_albums(20)).await?;
-	Ok(Json(result))
-}
-
-#[get("/recent")]
-async fn recent(index: Data<Index>, _auth: Auth) -> Result<Json<Vec<index::Directory>>, APIError> {
-	let result = block(move || index.get_recent_albums(20)).await?;
-	Ok(Json(result))
+#[get("/resolve")]
+async fn resolve(
+	vfs_manager: Data<vfs::Manager>,
+	_admin_rights: AdminRights,
+	options: web::Query<dto::ResolveOptions>,
+) -> Result<Json<dto::ResolvedPath>, APIError> {
+	let options = options.into_inner();
+	let vfs = block(move || vfs_manager.get_vfs()).await?;
+	let virtual_path = vfs
+		.real_to_virtual(Path::new(&options.real))
+		.map_err(|_| APIError::VFSPathNotFound)?;
+	Ok(Json(dto::ResolvedPath {
+		path: virtual_path.to_string_lossy().into_owned(),
+	}))
+}
+
+// Comments sent periodically on event streams so that idle connections are not closed by proxies
+const EVENTS_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+#[get("/events")]
+async fn events(index: Data<Index>, _auth: Auth) -> HttpResponse {
+	let events = stream::unfold(index.subscribe(), |mut receiver| async move {
+		loop {
+			match receiver.recv().await {
+				Ok(event) => return Some((event, receiver)),
+				Err(broadcast::RecvError::Lagged(_)) => continue,
+				Err(broadcast::RecvError::Closed) => return None,
+			}
+		}
+	})
+	.map(|event| {
+		let data = serde_json::to_string(&event).unwrap_or_default();
+		Bytes::from(format!("event: {}\ndata: {}\n\n", event.name(), data))
+	});
+	let heartbeats = rt::time::interval(EVENTS_HEARTBEAT_INTERVAL)
+		.map(|_| Bytes::from_static(b": heartbeat\n\n"));
+	let messages = stream::select(events, heartbeats).map(Ok::<_, actix_web::Error>);
+
+	// Compressing the stream would hold events back until enough of them are buffered
+	HttpResponse::Ok()
+		.content_type("text/event-stream")
+		.encoding(ContentEncoding::Identity)
+		.streaming(Box::pin(messages))
+}
+
+#[get("/maintenance/orphans")]
+async fn find_orphans(
+	maintenance_manager: Data<maintenance::Manager>,
+	_admin_rights: AdminRights,
+) -> Result<Json<maintenance::OrphanReport>, APIError> {
+	let report = block(move || maintenance_manager.find_orphans()).await?;
+	Ok(Json(report))
+}
+
+#[post("/maintenance/orphans/cleanup")]
+async fn clean_orphans(
+	maintenance_manager: Data<maintenance::Manager>,
+	_admin_rights: AdminRights,
+) -> Result<Json<maintenance::OrphanCleanup>, APIError> {
+	let cleanup = block(move || maintenance_manager.clean_orphans()).await?;
+	Ok(Json(cleanup))
+}
+
+#[get("/metrics")]
+async fn get_metrics(
+	index: Data<Index>,
+	metrics_manager: Data<metrics::Manager>,
+	scheduler_manager: Data<scheduler::Manager>,
+	transcode_manager: Data<transcode::Manager>,
+	_admin_rights: AdminRights,
+) -> Result<HttpResponse, APIError> {
+	render_metrics(index, metrics_manager, scheduler_manager, transcode_manager).await
+}
+
+// Counterpart of `metrics` for Prometheus scrapers, which are identified by their address instead
+// of logging in
+#[get("/metrics")]
+pub async fn public_metrics(
+	request: HttpRequest,
+	index: Data<Index>,
+	metrics_manager: Data<metrics::Manager>,
+	scheduler_manager: Data<scheduler::Manager>,
+	transcode_manager: Data<transcode::Manager>,
+) -> Result<HttpResponse, APIError> {
+	let allowed = request
+		.peer_addr()
+		.map(|a| metrics_manager.is_allowed(a.ip()))
+		.unwrap_or(false);
+	if !allowed {
+		return Err(APIError::MetricsAccessDenied);
+	}
+	render_metrics(index, metrics_manager, scheduler_manager, transcode_manager).await
+}
+
+async fn render_metrics(
+	index: Data<Index>,
+	metrics_manager: Data<metrics::Manager>,
+	scheduler_manager: Data<scheduler::Manager>,
+	transcode_manager: Data<transcode::Manager>,
+) -> Result<HttpResponse, APIError> {
+	let status = block(move || index.get_status()).await?;
+	let pools = scheduler_manager.stats();
+	let mut exposition = metrics::Exposition::new();
+
+	let name = "polaris_requests_in_flight";
+	exposition.family(name, "gauge", "Requests currently being served.");
+	for pool in &pools {
+		exposition.sample(name, &[("class", pool.class.as_str())], pool.in_flight);
+	}
+	let name = "polaris_requests_queued";
+	exposition.family(name, "gauge", "Requests waiting for a slot to be served.");
+	for pool in &pools {
+		exposition.sample(name, &[("class", pool.class.as_str())], pool.queued);
+	}
+
+	let name = "polaris_songs";
+	exposition.family(name, "gauge", "Songs in the index.");
+	exposition.sample(name, &[], status.song_count);
+	let name = "polaris_directories";
+	exposition.family(name, "gauge", "Directories in the index.");
+	exposition.sample(name, &[], status.directory_count);
+
+	let name = "polaris_transcoding_sessions";
+	exposition.family(name, "gauge", "Transcoded streams currently being served.");
+	exposition.sample(name, &[], transcode_manager.active_sessions());
+
+	metrics_manager.write(&mut exposition);
+
+	Ok(HttpResponse::Ok()
+		.content_type("text/plain; version=0.0.4")
+		.body(exposition.into_string()))
+}
+
+// Per-username counterpart of auth_throttle_middleware, which only sees client IPs of requests
+// to the login endpoints
+fn throttled_login<T>(
+	throttle_manager: &throttle::Manager,
+	keys: &[throttle::Key],
+	attempt: impl FnOnce() -> Result<T, user::Error>,
+) -> Result<T, APIError> {
+	for key in keys {
+		throttle_manager.check(key)?;
+	}
+	let result = attempt();
+	for key in keys {
+		match result {
+			Ok(_) => throttle_manager.reset(key),
+			Err(user::Error::IncorrectUsername) | Err(user::Error::IncorrectPassword) => {
+				throttle_manager.record_failure(key)
+			}
+			Err(_) => (),
+		}
+	}
+	Ok(result?)
+}
+
+#[post("/auth")]
+async fn login(
+	request: HttpRequest,
+	user_manager: Data<user::Manager>,
+	throttle_manager: Data<throttle::Manager>,
+	credentials: Json<dto::Credentials>,
+	cookies: Cookies,
+) -> Result<HttpResponse, APIError> {
+	let username = credentials.username.clone();
+	let label = session_label(request.headers());
+	let (auth_token, is_admin, lifetime) = block(move || -> Result<_, APIError> {
+		let keys = [throttle::Key::Username(credentials.username.clone())];
+		let auth_token = throttled_login(&throttle_manager, &keys, || {
+			user_manager.login(
+				&credentials.username,
+				&credentials.password,
+				label.as_deref(),
+			)
+		})?;
+		let is_admin = user_manager.is_admin(&credentials.username)?;
+		let lifetime = user_manager.session_lifetime()?;
+		Ok((auth_token, is_admin, lifetime))
+	})
+	.await?;
+	let authorization = dto::Authorization {
+		username: username.clone(),
+		token: auth_token.0.clone(),
+		is_admin,
+	};
+	let mut response = HttpResponse::Ok().json(authorization);
+	add_auth_cookies(
+		&mut response,
+		&cookies,
+		&username,
+		&auth_token,
+		is_admin,
+		lifetime,
+	)
+	.map_err(|_| APIError::Unspecified)?;
+	Ok(response)
+}
+
+#[post("/auth/jwt")]
+async fn login_jwt(
+	user_manager: Data<user::Manager>,
+	throttle_manager: Data<throttle::Manager>,
+	credentials: Json<dto::Credentials>,
+) -> Result<Json<dto::JwtAuthorization>, APIError> {
+	let (auth_token, claims) = block(move || -> Result<_, APIError> {
+		let keys = [throttle::Key::Username(credentials.username.clone())];
+		throttled_login(&throttle_manager, &keys, || {
+			user_manager.check_credentials(&credentials.username, &credentials.password)
+		})?;
+		Ok(user_manager.generate_jwt(&credentials.username)?)
+	})
+	.await?;
+	Ok(Json(dto::JwtAuthorization {
+		username: claims.sub,
+		token: auth_token.0,
+		is_admin: claims.admin,
+		expires_at: claims.exp,
+	}))
+}
+
+#[post("/logout")]
+async fn logout(
+	user_manager: Data<user::Manager>,
+	mut cookies: Cookies,
+) -> Result<HttpResponse, APIError> {
+	// Revoke the session so its token stops working, not just the cookies carrying it
+	if let Some(session_cookie) = cookies.get_signed(dto::COOKIE_SESSION) {
+		let auth_token = user::AuthToken(session_cookie.value().to_owned());
+		// Logging out of a session that is already gone is not an error
+		block(move || -> Result<(), user::Error> {
+			let authorization =
+				user_manager.authenticate(&auth_token, user::AuthorizationScope::PolarisAuth)?;
+			let session = authorization.session.ok_or(user::Error::InvalidAuthToken)?;
+			user_manager.revoke_session(&authorization.username, session)
+		})
+		.await
+		.ok();
+	}
+	let mut response = HttpResponse::Ok().finish();
+	remove_auth_cookies(&mut response, &cookies).map_err(|_| APIError::Unspecified)?;
+	Ok(response)
+}
+
+#[get("/browse")]
+async fn browse_root(
+	index: Data<Index>,
+	operation_manager: Data<operation::Manager>,
+	auth: Auth,
+	options: web::Query<dto::BrowseOptions>,
+) -> Result<HttpResponse, APIError> {
+	let sort = index::Sort::from(&*options);
+	let depth = options.depth();
+	if options.stream.unwrap_or(false) {
+		let operation = operation_manager.begin(&auth.username);
+		return stream_browse(index, PathBuf::new(), sort, depth, operation).await;
+	}
+	let result = block(move || index.browse_tree(Path::new(""), sort, depth)).await?;
+	Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/browse/{path:.*}")]
+async fn browse(
+	request: HttpRequest,
+	index: Data<Index>,
+	operation_manager: Data<operation::Manager>,
+	auth: Auth,
+	options: web::Query<dto::BrowseOptions>,
+) -> Result<HttpResponse, APIError> {
+	let path = PathBuf::from(decode_virtual_path(&request)?);
+	let sort = index::Sort::from(&*options);
+	let depth = options.depth();
+	if options.stream.unwrap_or(false) {
+		let operation = operation_manager.begin(&auth.username);
+		return stream_browse(index, path, sort, depth, operation).await;
+	}
+	let result = block(move || index.browse_tree(&path, sort, depth)).await?;
+	Ok(HttpResponse::Ok().json(result))
+}
+
+// Sends browse results as newline-delimited JSON, one batch of database rows at a time. Batches
+// cannot nest the content of sub-directories, so only a depth of 1 is supported.
+async fn stream_browse(
+	index: Data<Index>,
+	path: PathBuf,
+	sort: index::Sort,
+	depth: u32,
+	operation: operation::Operation,
+) -> Result<HttpResponse, APIError> {
+	const BATCH_SIZE: i64 = 200;
+
+	if depth != 1 {
+		return Err(APIError::UnsupportedBrowseDepth);
+	}
+
+	let fetch_batch = move |cursor: index::BrowseCursor| {
+		let index = index.clone();
+		let path = path.clone();
+		async move {
+			let (files, next) =
+				block(move || index.browse_batch(&path, cursor, BATCH_SIZE, sort)).await?;
+			let mut body = Vec::new();
+			for file in files {
+				serde_json::to_writer(&mut body, &file).map_err(|_| APIError::Unspecified)?;
+				body.push(b'\n');
+			}
+			Ok::<_, APIError>((Bytes::from(body), next))
+		}
+	};
+
+	// The first batch is fetched upfront so that errors like invalid paths get a proper status code
+	let (first_batch, next) = fetch_batch(index::BrowseCursor::default()).await?;
+	let remaining_batches = stream::unfold(next, move |cursor| {
+		let fetch_batch = fetch_batch.clone();
+		async move {
+			match fetch_batch(cursor?).await {
+				Ok((batch, next)) => Some((Ok(batch), next)),
+				Err(e) => Some((Err(e), None)),
+			}
+		}
+	});
+	let batches = stream::once(future::ready(Ok(first_batch))).chain(remaining_batches);
+
+	let response = HttpResponse::Ok()
+		.content_type("application/x-ndjson")
+		.streaming(Box::pin(batches));
+	Ok(make_cancellable(response, operation))
+}
+
+#[get("/flatten")]
+async fn flatten_root(
+	index: Data<Index>,
+	_auth: Auth,
+	options: web::Query<dto::FlattenOptions>,
+) -> Result<Json<Vec<index::SequencedSong>>, APIError> {
+	let range = index::Range::from(&*options);
+	let sort = index::Sort::from(&*options);
+	let songs = block(move || index.flatten_sequenced(Path::new(""), range, sort)).await?;
+	Ok(Json(songs))
+}
+
+#[get("/flatten/{path:.*}")]
+async fn flatten(
+	request: HttpRequest,
+	index: Data<Index>,
+	_auth: Auth,
+	options: web::Query<dto::FlattenOptions>,
+) -> Result<Json<Vec<index::SequencedSong>>, APIError> {
+	let path = decode_virtual_path(&request)?;
+	let range = index::Range::from(&*options);
+	let sort = index::Sort::from(&*options);
+	let songs = block(move || index.flatten_sequenced(Path::new(&path), range, sort)).await?;
+	Ok(Json(songs))
+}
+
+#[post("/songs")]
+async fn get_songs(
+	index: Data<Index>,
+	_auth: Auth,
+	paths: Json<Vec<String>>,
+) -> Result<Json<Vec<Option<index::Song>>>, APIError> {
+	let paths = paths.into_inner();
+	if paths
+		.iter()
+		.any(|p| p.split(['/', '\\']).any(|s| s == ".."))
+	{
+		return Err(APIError::PathTraversal);
+	}
+	let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+	let songs = block(move || index.get_songs(&paths)).await?;
+	Ok(Json(songs))
+}
+
+#[get("/album/{path:.*}")]
+async fn get_album(
+	request: HttpRequest,
+	index: Data<Index>,
+	_auth: Auth,
+) -> Result<Json<index::Album>, APIError> {
+	let path = decode_virtual_path(&request)?;
+	let album = block(move || index.get_album(Path::new(&path))).await?;
+	Ok(Json(album))
+}
+
+#[get("/random")]
+async fn random(
+	index: Data<Index>,
+	user_manager: Data<user::Manager>,
+	auth: Auth,
+	options: web::Query<dto::RandomOptions>,
+) -> Result<Json<Vec<index::Directory>>, APIError> {
+	let count = options.count.unwrap_or(20);
+	let seed = options.seed;
+	let result = block(move || -> anyhow::Result<_> {
+		let seed = match seed {
+			Some(seed) => Some(seed),
+			None => user_manager.read_preferences(&auth.username)?.random_seed,
+		};
+		index.get_random_albums(count, seed)
+	})
+	.await?;
+	Ok(Json(result))
+}
+
+#[get("/recent")]
+async fn recent(
+	index: Data<Index>,
+	_auth: Auth,
+	options: web::Query<dto::RecentOptions>,
+) -> Result<Json<Vec<index::Directory>>, APIError> {
+	let count = options.count.unwrap_or(20);
+	let result = block(move || index.get_recent_albums(count)).await?;
+	Ok(Json(result))
+}
+
+#[get("/artists")]
+async fn artists(index: Data<Index>, _auth: Auth) -> Result<Json<Vec<index::Artist>>, APIError> {
+	let result = block(move || index.get_artists()).await?;
+	Ok(Json(result))
+}
+
+#[get("/artist/{name}")]
+async fn artist_albums(
+	request: HttpRequest,
+	index: Data<Index>,
+	_auth: Auth,
+) -> Result<Json<Vec<index::Directory>>, APIError> {
+	let name = decode_path_parameter(&request, "name")?;
+	let result = block(move || index.get_artist_albums(&name)).await?;
+	Ok(Json(result))
+}
+
+#[get("/genres")]
+async fn genres(index: Data<Index>, _auth: Auth) -> Result<Json<Vec<index::Genre>>, APIError> {
+	let result = block(move || index.get_genres()).await?;
+	Ok(Json(result))
+}
+
+#[get("/genre/{name}")]
+async fn albums_by_genre(
+	request: HttpRequest,
+	index: Data<Index>,
+	_auth: Auth,
+) -> Result<Json<Vec<index::Directory>>, APIError> {
+	let name = decode_path_parameter(&request, "name")?;
+	let result = block(move || index.get_albums_by_genre(&name)).await?;
+	Ok(Json(result))
 }
 
 #[get("/search")]
 async fn search_root(
 	index: Data<Index>,
 	_auth: Auth,
-) -> Result<Json<Vec<index::CollectionFile>>, APIError> {
-	let result = block(move || index.search("")).await?;
-	Ok(Json(result))
+	options: web::Query<dto::SearchOptions>,
+) -> Result<HttpResponse, APIError> {
+	search_collection(index, String::new(), options.into_inner()).await
 }
 
 #[get("/search/{query:.*}")]
@@ -672,67 +1795,487 @@ async fn search(
 	index: Data<Index>,
 	_auth: Auth,
 	query: web::Path<String>,
-) -> Result<Json<Vec<index::CollectionFile>>, APIError> {
-	let result = block(move || index.search(&query)).await?;
-	Ok(Json(result))
+	options: web::Query<dto::SearchOptions>,
+) -> Result<HttpResponse, APIError> {
+	search_collection(index, query.into_inner(), options.into_inner()).await
+}
+
+async fn search_collection(
+	index: Data<Index>,
+	query: String,
+	options: dto::SearchOptions,
+) -> Result<HttpResponse, APIError> {
+	if !options.summary.unwrap_or(false) {
+		let result = block(move || index.search(&query)).await?;
+		return Ok(HttpResponse::Ok().json(result));
+	}
+	let result = block(move || -> anyhow::Result<dto::SearchResults> {
+		let summary = index.search_summary(&query)?;
+		Ok(dto::SearchResults {
+			results: index.search(&query)?,
+			directory_count: summary.directory_count,
+			song_count: summary.song_count,
+		})
+	})
+	.await?;
+	Ok(HttpResponse::Ok().json(result))
 }
 
-#[get("/audio/{path:.*}")]
+#[route("/audio/{path:.*}", method = "GET", method = "HEAD")]
 async fn get_audio(
+	request: HttpRequest,
+	vfs_manager: Data<vfs::Manager>,
+	operation_manager: Data<operation::Manager>,
+	transcode_manager: Data<transcode::Manager>,
+	auth: Auth,
+	options: web::Query<dto::AudioOptions>,
+) -> Result<HttpResponse, APIError> {
+	let path = decode_virtual_path(&request)?;
+	let audio_path = block(move || resolve_served_file(&vfs_manager, &path)).await?;
+
+	if let Some(options) = options.into_inner().into() {
+		let operation = operation_manager.begin(&auth.username);
+		let transcode = move || transcode_manager.transcode(&audio_path, &options);
+		return stream_transcoded(transcode, options.format, operation).await;
+	}
+
+	let response = serve_audio_file(&request, &audio_path)?;
+	let operation = operation_manager.begin(&auth.username);
+	Ok(make_cancellable(response, operation))
+}
+
+// Range requests covering the whole file are answered like other range requests, with a
+// `206 Partial Content` status. Multi-range requests never get here, see `multi_range_middleware`.
+fn serve_audio_file(request: &HttpRequest, audio_path: &Path) -> Result<HttpResponse, APIError> {
+	let validators = CacheValidators::new(audio_path, audio_path);
+	if let Some(response) = validators.as_ref().and_then(|v| v.not_modified(request)) {
+		return Ok(response);
+	}
+	let named_file = NamedFile::open(audio_path).map_err(|_| APIError::AudioFileIOError)?;
+	let mut response = named_file
+		.use_etag(false)
+		.use_last_modified(false)
+		.into_response(request)
+		.map_err(|_| APIError::AudioFileIOError)?;
+	if response.status() == StatusCode::OK && response.headers().contains_key(header::CONTENT_RANGE)
+	{
+		*response.status_mut() = StatusCode::PARTIAL_CONTENT;
+	}
+	if let Some(validators) = validators {
+		validators.apply(&mut response)?;
+	}
+	Ok(response)
+}
+
+// Cache validators of a served file. They are derived from the file its content was produced
+// from, because served files may be cached thumbnails whose modification time changes whenever
+// they are used.
+struct CacheValidators {
+	etag: EntityTag,
+	last_modified: SystemTime,
+}
+
+impl CacheValidators {
+	fn new(served_path: &Path, source_path: &Path) -> Option<Self> {
+		let metadata = fs::metadata(source_path).ok()?;
+		let last_modified = metadata.modified().ok()?;
+		let mut hasher = DefaultHasher::new();
+		served_path.hash(&mut hasher);
+		metadata.len().hash(&mut hasher);
+		last_modified.hash(&mut hasher);
+		Some(Self {
+			etag: EntityTag::strong(format!("{:x}", hasher.finish())),
+			last_modified,
+		})
+	}
+
+	// If-Modified-Since is only considered when the request has no If-None-Match header
+	fn not_modified(&self, request: &HttpRequest) -> Option<HttpResponse> {
+		let is_unchanged = if request.headers().contains_key(header::IF_NONE_MATCH) {
+			match IfNoneMatch::parse(request) {
+				Ok(IfNoneMatch::Any) => true,
+				Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|t| t.weak_eq(&self.etag)),
+				Err(_) => false,
+			}
+		} else {
+			match IfModifiedSince::parse(request) {
+				Ok(IfModifiedSince(since)) => {
+					// HTTP dates have a precision of one second
+					let seconds =
+						|t: SystemTime| t.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs());
+					seconds(self.last_modified) <= seconds(SystemTime::from(since))
+				}
+				Err(_) => false,
+			}
+		};
+		if !is_unchanged {
+			return None;
+		}
+		Some(
+			HttpResponse::NotModified()
+				.set(ETag(self.etag.clone()))
+				.set(LastModified(HttpDate::from(self.last_modified)))
+				.finish(),
+		)
+	}
+
+	fn apply(self, response: &mut HttpResponse) -> Result<(), APIError> {
+		let headers = response.headers_mut();
+		let etag =
+			HeaderValue::from_str(&self.etag.to_string()).map_err(|_| APIError::Unspecified)?;
+		let last_modified = HeaderValue::from_str(&HttpDate::from(self.last_modified).to_string())
+			.map_err(|_| APIError::Unspecified)?;
+		headers.insert(header::ETAG, etag);
+		headers.insert(header::LAST_MODIFIED, last_modified);
+		Ok(())
+	}
+}
+
+// Streams the output of an encoder process. Transcoded streams have no known length, so they
+// cannot serve range requests.
+async fn stream_transcoded<F>(
+	transcode: F,
+	format: transcode::Format,
+	operation: operation::Operation,
+) -> Result<HttpResponse, APIError>
+where
+	F: FnOnce() -> Result<transcode::Transcoder, transcode::Error> + Send + 'static,
+{
+	let read_chunk = |mut transcoder: transcode::Transcoder| async move {
+		block(move || transcoder.read_chunk().map(|chunk| (chunk, transcoder))).await
+	};
+
+	// The first chunk is read upfront so that encoder failures get a proper status code
+	let transcoder = block(transcode).await?;
+	let (first_chunk, transcoder) = read_chunk(transcoder).await?;
+	let transcoder = first_chunk.as_ref().map(|_| transcoder);
+	let remaining_chunks = stream::unfold(transcoder, move |transcoder| async move {
+		match read_chunk(transcoder?).await {
+			Ok((Some(chunk), transcoder)) => Some((Ok(Bytes::from(chunk)), Some(transcoder))),
+			Ok((None, _)) => None,
+			Err(e) => Some((Err(e), None)),
+		}
+	});
+	let chunks =
+		stream::iter(first_chunk.map(|chunk| Ok(Bytes::from(chunk)))).chain(remaining_chunks);
+
+	let response = HttpResponse::Ok()
+		.content_type(format.content_type())
+		.header(header::ACCEPT_RANGES, "none")
+		.streaming(Box::pin(chunks));
+	Ok(make_cancellable(response, operation))
+}
+
+// All songs of the album are transcoded into one continuous stream, ordered by disc and track
+// number. Like other transcoded streams, it cannot be seeked into.
+#[route("/album_stream/{path:.*}", method = "GET", method = "HEAD")]
+async fn get_album_stream(
+	request: HttpRequest,
+	index: Data<Index>,
+	vfs_manager: Data<vfs::Manager>,
+	operation_manager: Data<operation::Manager>,
+	transcode_manager: Data<transcode::Manager>,
+	auth: Auth,
+	options: web::Query<dto::AudioOptions>,
+) -> Result<HttpResponse, APIError> {
+	let path = decode_virtual_path(&request)?;
+	let audio_paths = block(move || -> Result<Vec<PathBuf>, APIError> {
+		let album = index.get_album(Path::new(&path))?;
+		album
+			.discs
+			.iter()
+			.flat_map(|d| d.songs.iter())
+			.map(|s| resolve_served_file(&vfs_manager, &s.path))
+			.collect()
+	})
+	.await?;
+	if audio_paths.is_empty() {
+		return Err(APIError::VFSPathNotFound);
+	}
+
+	let options = transcode::Options::new(options.format, options.bitrate);
+	let operation = operation_manager.begin(&auth.username);
+	let transcode = move || transcode_manager.transcode_sequence(&audio_paths, &options);
+	stream_transcoded(transcode, options.format, operation).await
+}
+
+#[route("/stream/{path:.*}", method = "GET", method = "HEAD")]
+async fn get_stream(
+	request: HttpRequest,
+	vfs_manager: Data<vfs::Manager>,
+	operation_manager: Data<operation::Manager>,
+	auth: Auth,
+) -> Result<HttpResponse, APIError> {
+	let path = decode_virtual_path(&request)?;
+	let audio_path = block(move || resolve_served_file(&vfs_manager, &path)).await?;
+
+	let extension = audio_path
+		.extension()
+		.map(|e| e.to_string_lossy())
+		.unwrap_or_default();
+	let content_type = actix_files::file_extension_to_mime(&extension);
+	if !client_can_play(&request, &content_type) {
+		return Err(APIError::UnplayableAudioFormat);
+	}
+
+	let mut response = serve_audio_file(&request, &audio_path)?;
+	response
+		.headers_mut()
+		.append(header::VARY, HeaderValue::from_static("accept"));
+	let operation = operation_manager.begin(&auth.username);
+	Ok(make_cancellable(response, operation))
+}
+
+// Decides whether the original file can be streamed to a client, based on the formats listed in
+// its Accept header. Clients which do not advertise their capabilities get the original file.
+fn client_can_play(request: &HttpRequest, content_type: &Mime) -> bool {
+	let accept = match header::Accept::parse(request) {
+		Ok(accept) => accept,
+		Err(_) => return true,
+	};
+	if accept.is_empty() {
+		return true;
+	}
+	accept.iter().any(|accepted| {
+		let format = &accepted.item;
+		accepted.quality > header::q(0)
+			&& (format.type_() == mime::STAR || format.type_() == content_type.type_())
+			&& (format.subtype() == mime::STAR || format.subtype() == content_type.subtype())
+	})
+}
+
+#[post("/operation/{id}/cancel")]
+async fn cancel_operation(
+	operation_manager: Data<operation::Manager>,
+	auth: Auth,
+	id: web::Path<u64>,
+) -> Result<HttpResponse, APIError> {
+	operation_manager.cancel(id.0, &auth.username)?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[get("/waveform/{path:.*}")]
+async fn get_waveform(
+	request: HttpRequest,
 	vfs_manager: Data<vfs::Manager>,
+	waveform_manager: Data<waveform::Manager>,
 	_auth: Auth,
-	path: web::Path<String>,
-) -> Result<NamedFile, APIError> {
-	let audio_path = block(move || {
-		let vfs = vfs_manager.get_vfs()?;
-		let path = percent_decode_str(&(path.0)).decode_utf8_lossy();
-		vfs.virtual_to_real(Path::new(path.as_ref()))
-			.map_err(|_| APIError::VFSPathNotFound)
+	options: web::Query<dto::WaveformOptions>,
+) -> Result<HttpResponse, APIError> {
+	let path = decode_virtual_path(&request)?;
+	let bins = options.bins();
+	let waveform_path = block(move || -> Result<_, APIError> {
+		let audio_path = resolve_served_file(&vfs_manager, &path)?;
+		Ok(waveform_manager.get_waveform(&audio_path, bins)?)
 	})
 	.await?;
 
-	let named_file = NamedFile::open(&audio_path).map_err(|_| APIError::AudioFileIOError)?;
-	Ok(named_file)
+	let named_file = NamedFile::open(&waveform_path).map_err(|_| APIError::Unspecified)?;
+	named_file
+		.into_response(&request)
+		.map_err(|_| APIError::Unspecified)
 }
 
 #[get("/thumbnail/{path:.*}")]
 async fn get_thumbnail(
+	request: HttpRequest,
 	vfs_manager: Data<vfs::Manager>,
 	thumbnails_manager: Data<thumbnail::Manager>,
 	_auth: Auth,
-	path: web::Path<String>,
 	options_input: web::Query<dto::ThumbnailOptions>,
-) -> Result<NamedFile, APIError> {
-	let options = thumbnail::Options::from(options_input.0);
-
-	let thumbnail_path = block(move || {
-		let vfs = vfs_manager.get_vfs()?;
-		let path = percent_decode_str(&(path.0)).decode_utf8_lossy();
-		let image_path = vfs
-			.virtual_to_real(Path::new(path.as_ref()))
-			.map_err(|_| APIError::VFSPathNotFound)?;
+) -> Result<HttpResponse, APIError> {
+	let path = decode_virtual_path(&request)?;
+	let original = options_input.original.unwrap_or(false);
+	let mut options = thumbnail::Options::from(options_input.0);
+
+	let (thumbnail_path, image_path) = block(move || {
+		let image_path = resolve_served_file(&vfs_manager, &path)?;
+		if original {
+			// Image files are served as-is, embedded artwork is extracted at full resolution
+			if utils::get_audio_format(&image_path).is_none() {
+				return Ok((image_path.clone(), image_path));
+			}
+			options = thumbnail::Options {
+				max_dimension: None,
+				resize_if_almost_square: false,
+				pad_to_square: false,
+				crop_to_square: false,
+			};
+		}
+		thumbnails_manager
+			.get_thumbnail(&image_path, &options)
+			.map(|thumbnail_path| (thumbnail_path, image_path))
+			.map_err(|_| APIError::Unspecified)
+	})
+	.await?;
+
+	serve_image(&request, thumbnail_path, image_path).await
+}
+
+enum Artwork {
+	File(PathBuf),
+	Embedded { data: Vec<u8>, source: PathBuf },
+}
+
+#[get("/artwork/{path:.*}")]
+async fn get_artwork(
+	request: HttpRequest,
+	index: Data<Index>,
+	vfs_manager: Data<vfs::Manager>,
+	_auth: Auth,
+) -> Result<HttpResponse, APIError> {
+	let path = decode_virtual_path(&request)?;
+
+	let artwork = block(move || -> Result<_, APIError> {
+		let artwork_path = index
+			.get_artwork(Path::new(&path))?
+			.ok_or(APIError::ArtworkNotFound)?;
+		let artwork_path = vfs_manager
+			.get_vfs()?
+			.real_to_virtual(&artwork_path)
+			.map_err(|_| APIError::ArtworkNotFound)?;
+		let artwork_path = resolve_served_file(&vfs_manager, &artwork_path.to_string_lossy())?;
+		if utils::get_audio_format(&artwork_path).is_none() {
+			return Ok(Artwork::File(artwork_path));
+		}
+		let data =
+			thumbnail::read_embedded(&artwork_path).map_err(|_| APIError::ArtworkNotFound)?;
+		Ok(Artwork::Embedded {
+			data,
+			source: artwork_path,
+		})
+	})
+	.await?;
+
+	match artwork {
+		Artwork::File(path) => serve_image(&request, path.clone(), path).await,
+		Artwork::Embedded { data, source } => {
+			let validators = CacheValidators::new(&source, &source);
+			if let Some(response) = validators.as_ref().and_then(|v| v.not_modified(&request)) {
+				return Ok(response);
+			}
+			let content_type = thumbnail::mime_type(&data).unwrap_or("application/octet-stream");
+			let mut response = HttpResponse::Ok();
+			response.content_type(content_type);
+			let dimensions = image::io::Reader::new(std::io::Cursor::new(&data))
+				.with_guessed_format()
+				.ok()
+				.and_then(|r| r.into_dimensions().ok());
+			if let Some((width, height)) = dimensions {
+				response.header(
+					HeaderName::from_static("x-image-width"),
+					HeaderValue::from(width),
+				);
+				response.header(
+					HeaderName::from_static("x-image-height"),
+					HeaderValue::from(height),
+				);
+			}
+			if let Some(validators) = validators {
+				response.set(ETag(validators.etag));
+				response.set(LastModified(HttpDate::from(validators.last_modified)));
+			}
+			Ok(response.body(data))
+		}
+	}
+}
+
+#[put("/artist/{name}/image")]
+async fn put_artist_image(
+	request: HttpRequest,
+	artist_manager: Data<artist::Manager>,
+	_admin_rights: AdminRights,
+	content: Bytes,
+) -> Result<HttpResponse, APIError> {
+	let name = decode_path_parameter(&request, "name")?;
+	block(move || artist_manager.set_image(&name, &content)).await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[get("/artist/{name}/image")]
+async fn get_artist_image(
+	request: HttpRequest,
+	artist_manager: Data<artist::Manager>,
+	thumbnails_manager: Data<thumbnail::Manager>,
+	_auth: Auth,
+	options_input: web::Query<dto::ThumbnailOptions>,
+) -> Result<HttpResponse, APIError> {
+	let name = decode_path_parameter(&request, "name")?;
+	let original = options_input.original.unwrap_or(false);
+	let mut options = thumbnail::Options::from(options_input.0);
+
+	let (served_path, image_path) = block(move || -> Result<_, APIError> {
+		let image_path = artist_manager.get_image(&name)?;
+		if original {
+			// Album artwork may be embedded in an audio file
+			if utils::get_audio_format(&image_path).is_none() {
+				return Ok((image_path.clone(), image_path));
+			}
+			options = thumbnail::Options {
+				max_dimension: None,
+				resize_if_almost_square: false,
+				pad_to_square: false,
+				crop_to_square: false,
+			};
+		}
 		thumbnails_manager
 			.get_thumbnail(&image_path, &options)
+			.map(|thumbnail_path| (thumbnail_path, image_path))
 			.map_err(|_| APIError::Unspecified)
 	})
 	.await?;
 
-	let named_file =
-		NamedFile::open(&thumbnail_path).map_err(|_| APIError::ThumbnailFileIOError)?;
+	serve_image(&request, served_path, image_path).await
+}
+
+// Sends an image file along with its dimensions, so clients know what they received without
+// decoding it first. `source_path` is the file the image was produced from.
+async fn serve_image(
+	request: &HttpRequest,
+	image_path: PathBuf,
+	source_path: PathBuf,
+) -> Result<HttpResponse, APIError> {
+	let validators = CacheValidators::new(&image_path, &source_path);
+	if let Some(response) = validators.as_ref().and_then(|v| v.not_modified(request)) {
+		return Ok(response);
+	}
 
-	Ok(named_file)
+	let named_file = NamedFile::open(&image_path).map_err(|_| APIError::ThumbnailFileIOError)?;
+	let dimensions =
+		block(move || -> Result<_, APIError> { Ok(image::image_dimensions(&image_path).ok()) })
+			.await?;
+
+	let mut response = named_file
+		.use_etag(false)
+		.use_last_modified(false)
+		.into_response(request)
+		.map_err(|_| APIError::ThumbnailFileIOError)?;
+	if let Some(validators) = validators {
+		validators.apply(&mut response)?;
+	}
+	if let Some((width, height)) = dimensions {
+		let headers = response.headers_mut();
+		headers.insert(
+			HeaderName::from_static("x-image-width"),
+			HeaderValue::from(width),
+		);
+		headers.insert(
+			HeaderName::from_static("x-image-height"),
+			HeaderValue::from(height),
+		);
+	}
+	Ok(response)
 }
 
 #[get("/playlists")]
 async fn list_playlists(
 	playlist_manager: Data<playlist::Manager>,
 	auth: Auth,
+	options: web::Query<dto::ListPlaylistsOptions>,
 ) -> Result<Json<Vec<dto::ListPlaylistsEntry>>, APIError> {
-	let playlist_names = block(move || playlist_manager.list_playlists(&auth.username)).await?;
-	let playlists: Vec<dto::ListPlaylistsEntry> = playlist_names
-		.into_iter()
-		.map(|p| dto::ListPlaylistsEntry { name: p })
-		.collect();
+	let sort = options.sort.unwrap_or_default();
+	let headers = block(move || playlist_manager.list_playlists(&auth.username, sort)).await?;
+	let playlists: Vec<dto::ListPlaylistsEntry> = headers.into_iter().map(|h| h.into()).collect();
 
 	Ok(Json(playlists))
 }
@@ -750,12 +2293,99 @@ async fn save_playlist(
 
 #[get("/playlist/{name}")]
 async fn read_playlist(
+	request: HttpRequest,
 	playlist_manager: Data<playlist::Manager>,
 	auth: Auth,
 	name: web::Path<String>,
-) -> Result<Json<Vec<index::Song>>, APIError> {
+) -> Result<HttpResponse, APIError> {
+	let manager = playlist_manager.clone();
+	let playlist_name = name.clone();
+	let username = auth.username.clone();
+	let playlist_version =
+		block(move || manager.get_playlist_version(&playlist_name, &username)).await?;
+
+	// The version is read before the content, so a concurrent save can only make the ETag older
+	// than the content (causing an extra download later), never newer.
+	let etag = EntityTag::strong(playlist_version.to_string());
+	let is_unchanged = match IfNoneMatch::parse(&request) {
+		Ok(IfNoneMatch::Any) => true,
+		Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|t| t.weak_eq(&etag)),
+		Err(_) => false,
+	};
+	if is_unchanged {
+		return Ok(HttpResponse::NotModified().set(ETag(etag)).finish());
+	}
+
 	let songs = block(move || playlist_manager.read_playlist(&name, &auth.username)).await?;
-	Ok(Json(songs))
+	Ok(HttpResponse::Ok().set(ETag(etag)).json(songs))
+}
+
+#[get("/playlist/{name}/export")]
+async fn export_playlist(
+	playlist_manager: Data<playlist::Manager>,
+	auth: Auth,
+	name: web::Path<String>,
+	options: web::Query<dto::PlaylistExportOptions>,
+) -> Result<HttpResponse, APIError> {
+	let format = options.format.unwrap_or_default();
+	let playlist_name = name.clone();
+	let content =
+		block(move || playlist_manager.export_playlist(&playlist_name, &auth.username, format))
+			.await?;
+
+	let filename = format!("{}.{}", name.as_str(), format.extension());
+	let ascii_filename: String = filename
+		.chars()
+		.map(|c| {
+			if c.is_ascii() && !c.is_ascii_control() {
+				c
+			} else {
+				'_'
+			}
+		})
+		.collect();
+	let mut parameters = vec![header::DispositionParam::Filename(ascii_filename)];
+	if !filename.is_ascii() {
+		parameters.push(header::DispositionParam::FilenameExt(
+			header::ExtendedValue {
+				charset: header::Charset::Ext("UTF-8".to_owned()),
+				language_tag: None,
+				value: filename.into_bytes(),
+			},
+		));
+	}
+	let disposition = header::ContentDisposition {
+		disposition: header::DispositionType::Attachment,
+		parameters,
+	};
+
+	Ok(HttpResponse::Ok()
+		.content_type(format.content_type())
+		.set(disposition)
+		.body(content))
+}
+
+#[post("/playlist/{name}/import")]
+async fn import_playlist(
+	playlist_manager: Data<playlist::Manager>,
+	auth: Auth,
+	name: web::Path<String>,
+	content: Bytes,
+) -> Result<Json<dto::PlaylistImportReport>, APIError> {
+	let report =
+		block(move || playlist_manager.import_playlist(&name, &auth.username, &content)).await?;
+	Ok(Json(report.into()))
+}
+
+#[post("/playlist/{name}/rename")]
+async fn rename_playlist(
+	playlist_manager: Data<playlist::Manager>,
+	auth: Auth,
+	name: web::Path<String>,
+	rename: Json<dto::PlaylistRename>,
+) -> Result<HttpResponse, APIError> {
+	block(move || playlist_manager.rename_playlist(&name, &rename.name, &auth.username)).await?;
+	Ok(HttpResponse::new(StatusCode::OK))
 }
 
 #[delete("/playlist/{name}")]
@@ -768,19 +2398,124 @@ async fn delete_playlist(
 	Ok(HttpResponse::new(StatusCode::OK))
 }
 
+#[delete("/playlists")]
+async fn delete_playlists(
+	playlist_manager: Data<playlist::Manager>,
+	auth: Auth,
+) -> Result<HttpResponse, APIError> {
+	block(move || playlist_manager.delete_playlists(&auth.username)).await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[get("/me/stats")]
+async fn get_listening_stats(
+	stats_manager: Data<stats::Manager>,
+	auth: Auth,
+	options: web::Query<dto::ListeningStatsOptions>,
+) -> Result<Json<stats::ListeningStats>, APIError> {
+	let since = options.since.unwrap_or(0);
+	let count = options.count.unwrap_or(10);
+	let stats =
+		block(move || stats_manager.get_listening_stats(&auth.username, since, count)).await?;
+	Ok(Json(stats))
+}
+
+#[get("/top_songs")]
+async fn get_top_songs(
+	stats_manager: Data<stats::Manager>,
+	auth: Auth,
+	options: web::Query<dto::TopSongsOptions>,
+) -> Result<Json<Vec<stats::SongStats>>, APIError> {
+	let count = options.count.unwrap_or(50);
+	let songs = block(move || stats_manager.get_top_songs(&auth.username, count)).await?;
+	Ok(Json(songs))
+}
+
+#[post("/scrobble_local/{path:.*}")]
+async fn scrobble_local(
+	request: HttpRequest,
+	stats_manager: Data<stats::Manager>,
+	auth: Auth,
+) -> Result<HttpResponse, APIError> {
+	let path = decode_virtual_path(&request)?;
+	block(move || stats_manager.record_play(&auth.username, Path::new(&path))).await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[get("/me/daily_mix")]
+async fn get_daily_mix(
+	stats_manager: Data<stats::Manager>,
+	auth: Auth,
+	options: web::Query<dto::DailyMixOptions>,
+) -> Result<Json<Vec<index::Song>>, APIError> {
+	let count = options.count.unwrap_or(50);
+	let discovery = options.discovery.unwrap_or(0.3);
+	let songs =
+		block(move || stats_manager.get_daily_mix(&auth.username, count, discovery)).await?;
+	Ok(Json(songs))
+}
+
+#[get("/me/sessions")]
+async fn list_sessions(
+	user_manager: Data<user::Manager>,
+	auth: Auth,
+) -> Result<Json<Vec<user::Session>>, APIError> {
+	let sessions = block(move || user_manager.list_sessions(&auth.username)).await?;
+	Ok(Json(sessions))
+}
+
+#[delete("/me/sessions/{id}")]
+async fn revoke_session(
+	user_manager: Data<user::Manager>,
+	auth: Auth,
+	id: web::Path<i32>,
+) -> Result<HttpResponse, APIError> {
+	let id = id.into_inner();
+	block(move || user_manager.revoke_session(&auth.username, id)).await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[get("/sessions")]
+async fn list_all_sessions(
+	user_manager: Data<user::Manager>,
+	_admin_rights: AdminRights,
+) -> Result<Json<Vec<user::Session>>, APIError> {
+	let sessions = block(move || user_manager.list_all_sessions()).await?;
+	Ok(Json(sessions))
+}
+
+#[get("/now_playing")]
+async fn get_now_playing(
+	now_playing_manager: Data<now_playing::Manager>,
+	_auth: Auth,
+) -> Json<Vec<now_playing::NowPlaying>> {
+	Json(now_playing_manager.list())
+}
+
 #[put("/lastfm/now_playing/{path:.*}")]
 async fn lastfm_now_playing(
+	request: HttpRequest,
 	lastfm_manager: Data<lastfm::Manager>,
+	listenbrainz_manager: Data<listenbrainz::Manager>,
+	now_playing_manager: Data<now_playing::Manager>,
 	user_manager: Data<user::Manager>,
 	auth: Auth,
-	path: web::Path<String>,
 ) -> Result<HttpResponse, APIError> {
+	let path = decode_virtual_path(&request)?;
 	block(move || -> Result<(), APIError> {
-		if !user_manager.is_lastfm_linked(&auth.username) {
+		// Shared with other users whether or not a scrobbling service is linked
+		now_playing_manager.record(&auth.username, Path::new(&path))?;
+		let lastfm_linked = user_manager.is_lastfm_linked(&auth.username);
+		let listenbrainz_linked = user_manager.is_listenbrainz_linked(&auth.username);
+		if !lastfm_linked && !listenbrainz_linked {
 			return Err(APIError::LastFMAccountNotLinked);
 		}
-		let path = percent_decode_str(&(path.0)).decode_utf8_lossy();
-		lastfm_manager.now_playing(&auth.username, Path::new(path.as_ref()))?;
+		if lastfm_linked {
+			lastfm_manager.now_playing(&auth.username, Path::new(&path))?;
+		}
+		if listenbrainz_linked {
+			listenbrainz_manager.now_playing(&auth.username, Path::new(&path))?;
+		}
 		Ok(())
 	})
 	.await?;
@@ -789,17 +2524,27 @@ async fn lastfm_now_playing(
 
 #[post("/lastfm/scrobble/{path:.*}")]
 async fn lastfm_scrobble(
+	request: HttpRequest,
 	lastfm_manager: Data<lastfm::Manager>,
+	listenbrainz_manager: Data<listenbrainz::Manager>,
 	user_manager: Data<user::Manager>,
 	auth: Auth,
-	path: web::Path<String>,
 ) -> Result<HttpResponse, APIError> {
+	let path = decode_virtual_path(&request)?;
+	// Plays are only counted locally by /scrobble_local, so that clients calling both endpoints
+	// don't count them twice
 	block(move || -> Result<(), APIError> {
-		if !user_manager.is_lastfm_linked(&auth.username) {
+		let lastfm_linked = user_manager.is_lastfm_linked(&auth.username);
+		let listenbrainz_linked = user_manager.is_listenbrainz_linked(&auth.username);
+		if !lastfm_linked && !listenbrainz_linked {
 			return Err(APIError::LastFMAccountNotLinked);
 		}
-		let path = percent_decode_str(&(path.0)).decode_utf8_lossy();
-		lastfm_manager.scrobble(&auth.username, Path::new(path.as_ref()))?;
+		if lastfm_linked {
+			lastfm_manager.scrobble(&auth.username, Path::new(&path))?;
+		}
+		if listenbrainz_linked {
+			listenbrainz_manager.scrobble(&auth.username, Path::new(&path))?;
+		}
 		Ok(())
 	})
 	.await?;
@@ -856,3 +2601,22 @@ async fn lastfm_unlink(
 	block(move || lastfm_manager.unlink(&auth.username)).await?;
 	Ok(HttpResponse::new(StatusCode::OK))
 }
+
+#[put("/listenbrainz/link")]
+async fn listenbrainz_link(
+	listenbrainz_manager: Data<listenbrainz::Manager>,
+	auth: Auth,
+	payload: Json<dto::ListenBrainzLink>,
+) -> Result<HttpResponse, APIError> {
+	block(move || listenbrainz_manager.link(&auth.username, &payload.token)).await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[delete("/listenbrainz/link")]
+async fn listenbrainz_unlink(
+	listenbrainz_manager: Data<listenbrainz::Manager>,
+	auth: Auth,
+) -> Result<HttpResponse, APIError> {
+	block(move || listenbrainz_manager.unlink(&auth.username)).await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
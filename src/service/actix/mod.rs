@@ -1,15 +1,34 @@
 use actix_web::{
+	dev::Server,
 	middleware::{normalize::TrailingSlash, Compress, Logger, NormalizePath},
 	rt::System,
 	web::{self, ServiceConfig},
 	App as ActixApp, HttpServer,
 };
 use anyhow::*;
+use crossbeam_channel::Sender;
 use log::error;
+#[cfg(unix)]
+use std::fs;
+#[cfg(unix)]
+use std::io;
+#[cfg(unix)]
+use std::path::Path;
 
 use crate::app::App;
 
 mod api;
+mod precompressed;
+
+// Readable and writable by the owner and group of the socket file
+#[cfg(unix)]
+const SOCKET_FILE_MODE: u32 = 0o660;
+
+// Mount points of the services. The web client is served from the root as a fallback, so it must
+// be registered after the other services. Prefixes are checked when the server starts.
+const API_PREFIX: &str = "/api";
+const SWAGGER_PREFIX: &str = "/swagger";
+const WEB_PREFIX: &str = "/";
 
 #[cfg(test)]
 pub mod test;
@@ -17,49 +36,185 @@ pub mod test;
 pub fn make_config(app: App) -> impl FnOnce(&mut ServiceConfig) + Clone {
 	move |cfg: &mut ServiceConfig| {
 		let encryption_key = cookie::Key::derive_from(&app.auth_secret.key[..]);
+		let cache_policy = api::CachePolicy {
+			collection_max_age: app.collection_cache_max_age,
+		};
 		cfg.app_data(web::Data::new(app.index))
+			.app_data(web::Data::new(app.artist_manager))
 			.app_data(web::Data::new(app.config_manager))
 			.app_data(web::Data::new(app.ddns_manager))
 			.app_data(web::Data::new(app.lastfm_manager))
+			.app_data(web::Data::new(app.listenbrainz_manager))
+			.app_data(web::Data::new(app.maintenance_manager))
+			.app_data(web::Data::new(app.metrics_manager))
+			.app_data(web::Data::new(app.now_playing_manager))
+			.app_data(web::Data::new(app.operation_manager))
 			.app_data(web::Data::new(app.playlist_manager))
+			.app_data(web::Data::new(app.scheduler_manager))
 			.app_data(web::Data::new(app.settings_manager))
+			.app_data(web::Data::new(app.stats_manager))
 			.app_data(web::Data::new(app.thumbnail_manager))
+			.app_data(web::Data::new(app.throttle_manager))
+			.app_data(web::Data::new(app.transcode_manager))
 			.app_data(web::Data::new(app.user_manager))
 			.app_data(web::Data::new(app.vfs_manager))
+			.app_data(web::Data::new(app.waveform_manager))
 			.app_data(web::Data::new(encryption_key))
+			.app_data(web::Data::new(cache_policy))
 			.service(
-				web::scope("/api")
-					.configure(api::make_config())
+				web::scope(API_PREFIX)
+					.configure(api::make_config(app.max_json_body_size))
 					.wrap_fn(api::http_auth_middleware)
+					.wrap_fn(api::auth_throttle_middleware)
+					.wrap_fn(api::cache_control_middleware)
+					.wrap_fn(api::scheduling_middleware)
+					.wrap_fn(api::metrics_middleware)
+					.wrap_fn(api::cors_middleware)
+					.wrap_fn(api::multi_range_middleware)
 					.wrap(NormalizePath::new(TrailingSlash::Trim)),
 			)
-			.service(
-				actix_files::Files::new("/swagger", app.swagger_dir_path)
+			.service(api::public_metrics);
+		if let Some(swagger_dir_path) = app.swagger_dir_path {
+			cfg.service(
+				actix_files::Files::new(SWAGGER_PREFIX, swagger_dir_path)
 					.redirect_to_slash_directory()
 					.index_file("index.html"),
-			)
+			);
+		}
+		cfg.service(precompressed::service(app.web_dir_path.clone()))
 			.service(
-				actix_files::Files::new("/", app.web_dir_path)
+				actix_files::Files::new(WEB_PREFIX, app.web_dir_path)
 					.redirect_to_slash_directory()
 					.index_file("index.html"),
 			);
 	}
 }
 
-pub fn run(app: App) -> Result<()> {
+/// Runs the HTTP server until it is stopped. Its handle is sent through `server_sender` once it is listening.
+pub fn run(app: App, server_sender: Sender<Server>) -> Result<()> {
+	check_prefixes(&[API_PREFIX, SWAGGER_PREFIX], WEB_PREFIX)?;
+
+	#[cfg(unix)]
+	let socket_file_path = app.socket_file_path.clone();
+	#[cfg(unix)]
+	if let Some(path) = &socket_file_path {
+		remove_stale_socket(path)?;
+	}
+
 	System::run(move || {
-		let address = format!("0.0.0.0:{}", app.port);
-		HttpServer::new(move || {
+		let address = app.address;
+		let tls_config = app.tls_config.clone();
+		#[cfg(unix)]
+		let socket_file_path = app.socket_file_path.clone();
+		let server = HttpServer::new(move || {
 			ActixApp::new()
 				.wrap(Logger::default())
 				.wrap(Compress::default())
 				.configure(make_config(app.clone()))
 		})
 		.disable_signals()
-		.bind(address)
-		.map(|server| server.run())
-		.map_err(|e| error!("Error starting HTTP server: {:?}", e))
-		.ok();
+		.system_exit();
+
+		// HTTP/2 is negotiated with clients over TLS, plain HTTP connections use HTTP/1.1
+		let bind_tcp = move |server: HttpServer<_, _, _, _>| match tls_config {
+			Some(config) => server.bind_rustls(address, config),
+			None => server.bind(address),
+		};
+
+		#[cfg(unix)]
+		let server = match &socket_file_path {
+			Some(path) => server
+				.bind_uds(path)
+				.and_then(|server| set_socket_permissions(path).map(|_| server)),
+			None => bind_tcp(server),
+		};
+		#[cfg(not(unix))]
+		let server = bind_tcp(server);
+
+		server
+			.map(|server| {
+				let _ = server_sender.send(server.run());
+			})
+			.map_err(|e| error!("Error starting HTTP server: {:?}", e))
+			.ok();
 	})?;
+
+	#[cfg(unix)]
+	if let Some(path) = &socket_file_path {
+		remove_stale_socket(path)?;
+	}
+
 	Ok(())
 }
+
+// Prefixes start with a slash and have no trailing slash. A service mounted within the prefix of
+// another would not receive its requests, except within the prefix of the web client which is
+// registered last.
+fn check_prefixes(service_prefixes: &[&str], web_prefix: &str) -> Result<()> {
+	for prefix in service_prefixes.iter().chain(std::iter::once(&web_prefix)) {
+		if !prefix.starts_with('/') || (*prefix != "/" && prefix.ends_with('/')) {
+			bail!(
+				"URL prefix `{}` must start with a slash and have no trailing slash",
+				prefix
+			);
+		}
+	}
+	for (i, prefix) in service_prefixes.iter().enumerate() {
+		if *prefix == web_prefix {
+			bail!("URL prefix `{}` is also used by the web client", prefix);
+		}
+		for other in &service_prefixes[i + 1..] {
+			if is_within_prefix(prefix, other) || is_within_prefix(other, prefix) {
+				bail!("URL prefixes `{}` and `{}` overlap", prefix, other);
+			}
+		}
+	}
+	Ok(())
+}
+
+fn is_within_prefix(path: &str, prefix: &str) -> bool {
+	prefix == "/" || path == prefix || path.starts_with(&format!("{}/", prefix))
+}
+
+// Sockets left behind by a previous run would prevent binding. Other kinds of files are never
+// deleted, in case the path was mistyped.
+#[cfg(unix)]
+fn remove_stale_socket(path: &Path) -> Result<()> {
+	use std::os::unix::fs::FileTypeExt;
+	match fs::symlink_metadata(path) {
+		Ok(metadata) if metadata.file_type().is_socket() => Ok(fs::remove_file(path)?),
+		Ok(_) => bail!("`{}` already exists and is not a socket", path.display()),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+		Err(e) => Err(e.into()),
+	}
+}
+
+// Lets a reverse proxy running as another user of the same group connect
+#[cfg(unix)]
+fn set_socket_permissions(path: &Path) -> io::Result<()> {
+	use std::os::unix::fs::PermissionsExt;
+	fs::set_permissions(path, fs::Permissions::from_mode(SOCKET_FILE_MODE))
+}
+
+#[test]
+fn default_prefixes_are_valid() {
+	check_prefixes(&[API_PREFIX, SWAGGER_PREFIX], WEB_PREFIX).unwrap();
+}
+
+#[test]
+fn malformed_prefixes_are_rejected() {
+	assert!(check_prefixes(&["api", "/swagger"], "/").is_err());
+	assert!(check_prefixes(&["/api/", "/swagger"], "/").is_err());
+	assert!(check_prefixes(&["/api", "/swagger"], "").is_err());
+	assert!(check_prefixes(&["/api", "/swagger"], "/web/").is_err());
+}
+
+#[test]
+fn overlapping_prefixes_are_rejected() {
+	assert!(check_prefixes(&["/api", "/api"], "/").is_err());
+	assert!(check_prefixes(&["/api", "/api/swagger"], "/").is_err());
+	assert!(check_prefixes(&["/", "/swagger"], "/web").is_err());
+	assert!(check_prefixes(&["/api", "/swagger"], "/api").is_err());
+	assert!(check_prefixes(&["/api", "/apidocs"], "/").is_ok());
+	assert!(check_prefixes(&["/web/api", "/swagger"], "/web").is_ok());
+}
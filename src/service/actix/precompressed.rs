@@ -0,0 +1,157 @@
+use actix_files::{file_extension_to_mime, NamedFile};
+use actix_web::{
+	dev::RequestHead,
+	error::ErrorNotFound,
+	guard::Guard,
+	http::{header, HeaderValue, Method},
+	web::{self, Data},
+	HttpRequest, HttpResponse, Resource,
+};
+use percent_encoding::percent_decode_str;
+use std::path::{Component, Path, PathBuf};
+
+const INDEX_FILE: &str = "index.html";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+	Brotli,
+	Gzip,
+}
+
+impl Encoding {
+	// In order of preference
+	const ALL: [Encoding; 2] = [Encoding::Brotli, Encoding::Gzip];
+
+	fn name(self) -> &'static str {
+		match self {
+			Encoding::Brotli => "br",
+			Encoding::Gzip => "gzip",
+		}
+	}
+
+	fn extension(self) -> &'static str {
+		match self {
+			Encoding::Brotli => "br",
+			Encoding::Gzip => "gz",
+		}
+	}
+}
+
+struct Asset {
+	original: PathBuf,
+	compressed: PathBuf,
+	encoding: Encoding,
+}
+
+/// Static files compressed ahead of time, stored next to the original file with a `.br` or `.gz`
+/// extension. Requests for which no suitable compressed file exists are left to other services.
+#[derive(Clone)]
+struct PrecompressedFiles {
+	directory: PathBuf,
+}
+
+impl PrecompressedFiles {
+	fn find(&self, head: &RequestHead) -> Option<Asset> {
+		if head.method != Method::GET && head.method != Method::HEAD {
+			return None;
+		}
+
+		let request_path = percent_decode_str(head.uri.path()).decode_utf8().ok()?;
+		let relative_path = Path::new(request_path.trim_start_matches('/'));
+		// Never serve files outside of the directory
+		if !relative_path
+			.components()
+			.all(|c| matches!(c, Component::Normal(_)))
+		{
+			return None;
+		}
+
+		let mut original = self.directory.join(relative_path);
+		if request_path.ends_with('/') {
+			original.push(INDEX_FILE);
+		}
+		if !original.is_file() {
+			return None;
+		}
+
+		Encoding::ALL
+			.iter()
+			.filter(|e| accepts(head, **e))
+			.find_map(|&encoding| {
+				let mut compressed = original.clone().into_os_string();
+				compressed.push(".");
+				compressed.push(encoding.extension());
+				let compressed = PathBuf::from(compressed);
+				if compressed.is_file() {
+					Some(Asset {
+						original: original.clone(),
+						compressed,
+						encoding,
+					})
+				} else {
+					None
+				}
+			})
+	}
+}
+
+impl Guard for PrecompressedFiles {
+	fn check(&self, head: &RequestHead) -> bool {
+		self.find(head).is_some()
+	}
+}
+
+fn accepts(head: &RequestHead, encoding: Encoding) -> bool {
+	head.headers()
+		.get_all(header::ACCEPT_ENCODING)
+		.filter_map(|v| v.to_str().ok())
+		.flat_map(|v| v.split(','))
+		.any(|entry| {
+			let mut parameters = entry.split(';');
+			let name = parameters.next().unwrap_or_default().trim();
+			let refused = parameters.any(|p| {
+				p.trim()
+					.strip_prefix("q=")
+					.and_then(|q| q.parse::<f32>().ok())
+					== Some(0.0)
+			});
+			name.eq_ignore_ascii_case(encoding.name()) && !refused
+		})
+}
+
+/// Serves the compressed variant of files in `directory` to clients which accept its encoding.
+/// Must be registered before the service serving the uncompressed files.
+pub fn service(directory: PathBuf) -> Resource {
+	let files = PrecompressedFiles { directory };
+	web::resource("/{path:.*}")
+		.app_data(Data::new(files.clone()))
+		.guard(files)
+		.to(serve)
+}
+
+async fn serve(
+	request: HttpRequest,
+	files: Data<PrecompressedFiles>,
+) -> Result<HttpResponse, actix_web::Error> {
+	let asset = files
+		.find(request.head())
+		.ok_or_else(|| ErrorNotFound("File not found"))?;
+	let content_type = asset
+		.original
+		.extension()
+		.and_then(|e| e.to_str())
+		.map(file_extension_to_mime)
+		.unwrap_or(mime::APPLICATION_OCTET_STREAM);
+	let mut response = NamedFile::open(&asset.compressed)?
+		.set_content_type(content_type)
+		.disable_content_disposition()
+		.into_response(&request)?;
+	let headers = response.headers_mut();
+	// Also keeps the compression middleware from encoding the response a second time
+	headers.insert(
+		header::CONTENT_ENCODING,
+		HeaderValue::from_static(asset.encoding.name()),
+	);
+	headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+	Ok(response)
+}
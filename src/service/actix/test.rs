@@ -9,9 +9,15 @@ use actix_web::{
 use http::{response::Builder, Method, Request, Response};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use std::net::SocketAddr;
 use std::ops::Deref;
+use std::time::Duration;
 
-use crate::app::App;
+use crate::app::{
+	index::DEFAULT_INSERT_BUFFER_SIZE, metrics, App, Options, DEFAULT_BIND_ADDRESS,
+	DEFAULT_MAX_JSON_BODY_SIZE,
+};
+use crate::db::DEFAULT_BUSY_TIMEOUT;
 use crate::paths::Paths;
 use crate::service::actix::*;
 use crate::service::dto;
@@ -30,7 +36,7 @@ impl ActixTestService {
 	fn process_internal<T: Serialize + Clone + 'static>(
 		&mut self,
 		request: &Request<T>,
-	) -> (Builder, Option<Bytes>) {
+	) -> (Builder, Bytes) {
 		let url = request.uri().to_string();
 		let body = request.body().clone();
 
@@ -39,6 +45,8 @@ impl ActixTestService {
 			Method::POST => self.server.post(url),
 			Method::PUT => self.server.put(url),
 			Method::DELETE => self.server.delete(url),
+			Method::HEAD => self.server.head(url),
+			Method::OPTIONS => self.server.options(url),
 			_ => unimplemented!(),
 		}
 		.timeout(std::time::Duration::from_secs(30));
@@ -51,9 +59,23 @@ impl ActixTestService {
 			actix_request = actix_request.bearer_auth(&authorization.token);
 		}
 
-		let mut actix_response = self
-			.system_runner
-			.block_on(async move { actix_request.send_json(&body).await.unwrap() });
+		// Requests declaring a content type other than JSON carry their body as-is
+		let raw_body = match request.headers().get(http::header::CONTENT_TYPE) {
+			Some(content_type) if content_type != "application/json" => {
+				match serde_json::to_value(&body).unwrap() {
+					serde_json::Value::String(s) => Some(s),
+					_ => None,
+				}
+			}
+			_ => None,
+		};
+
+		let mut actix_response = self.system_runner.block_on(async move {
+			match raw_body {
+				Some(raw_body) => actix_request.send_body(raw_body).await.unwrap(),
+				None => actix_request.send_json(&body).await.unwrap(),
+			}
+		});
 
 		let mut response_builder = Response::builder().status(actix_response.status());
 		let headers = response_builder.headers_mut().unwrap();
@@ -61,15 +83,9 @@ impl ActixTestService {
 			headers.append(name, value.clone());
 		}
 
-		let is_success = actix_response.status().is_success();
-		let body = if is_success {
-			Some(
-				self.system_runner
-					.block_on(async move { actix_response.body().await.unwrap() }),
-			)
-		} else {
-			None
-		};
+		let body = self
+			.system_runner
+			.block_on(async move { actix_response.body().await.unwrap() });
 
 		(response_builder, body)
 	}
@@ -85,12 +101,28 @@ impl TestService for ActixTestService {
 			db_file_path: output_dir.join("db.sqlite"),
 			#[cfg(unix)]
 			pid_file_path: output_dir.join("polaris.pid"),
+			#[cfg(unix)]
+			socket_file_path: None,
 			log_file_path: None,
-			swagger_dir_path: ["docs", "swagger"].iter().collect(),
+			swagger_dir_path: Some(["docs", "swagger"].iter().collect()),
+			tls_cert_file_path: None,
+			tls_key_file_path: None,
 			web_dir_path: ["test-data", "web"].iter().collect(),
 		};
 
-		let app = App::new(5050, paths).unwrap();
+		let app = App::new(
+			Options {
+				address: SocketAddr::new(DEFAULT_BIND_ADDRESS, 5050),
+				collection_cache_max_age: Duration::from_secs(60),
+				max_concurrent_transfers: Some(4),
+				index_insert_buffer_size: DEFAULT_INSERT_BUFFER_SIZE,
+				db_busy_timeout: DEFAULT_BUSY_TIMEOUT,
+				max_json_body_size: DEFAULT_MAX_JSON_BODY_SIZE,
+				metrics_allowlist: metrics::DEFAULT_ALLOWLIST.to_vec(),
+			},
+			paths,
+		)
+		.unwrap();
 
 		let system_runner = System::new("test");
 		let server = test::start(move || {
@@ -118,9 +150,7 @@ impl TestService for ActixTestService {
 		request: &Request<T>,
 	) -> Response<Vec<u8>> {
 		let (response_builder, body) = self.process_internal(request);
-		response_builder
-			.body(body.unwrap().deref().to_owned())
-			.unwrap()
+		response_builder.body(body.deref().to_owned()).unwrap()
 	}
 
 	fn fetch_json<T: Serialize + Clone + 'static, U: DeserializeOwned>(
@@ -128,7 +158,7 @@ impl TestService for ActixTestService {
 		request: &Request<T>,
 	) -> Response<U> {
 		let (response_builder, body) = self.process_internal(request);
-		let body = serde_json::from_slice(&body.unwrap()).unwrap();
+		let body = serde_json::from_slice(&body).unwrap();
 		response_builder.body(body).unwrap()
 	}
 
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 
-use crate::app::{config, ddns, settings, thumbnail, user, vfs};
+use crate::app::{
+	config, ddns, index, playlist, settings, thumbnail, transcode, user, vfs, waveform,
+};
 
 pub const API_MAJOR_VERSION: i32 = 6;
 pub const API_MINOR_VERSION: i32 = 0;
@@ -14,6 +16,13 @@ pub struct Version {
 	pub minor: i32,
 }
 
+/// Body of error responses. `error` is a stable code, `message` is meant for humans.
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
+pub struct ErrorResponse {
+	pub error: String,
+	pub message: String,
+}
+
 #[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub struct InitialSetup {
 	pub has_any_users: bool,
@@ -32,15 +41,128 @@ pub struct Authorization {
 	pub is_admin: bool,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+pub struct JwtAuthorization {
+	pub username: String,
+	pub token: String,
+	pub is_admin: bool,
+	pub expires_at: i64,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct AuthQueryParameters {
 	pub auth_token: String,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct TriggerIndexOptions {
+	pub path: Option<String>,
+	pub mode: Option<index::ScanMode>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ResolveOptions {
+	pub real: String,
+}
+
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
+pub struct ResolvedPath {
+	pub path: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FlattenOptions {
+	pub offset: Option<u32>,
+	pub count: Option<u32>,
+	pub sort: Option<index::SortField>,
+	pub order: Option<index::SortOrder>,
+}
+
+impl From<&FlattenOptions> for index::Range {
+	fn from(dto: &FlattenOptions) -> Self {
+		Self {
+			offset: dto.offset.map(i64::from),
+			count: dto.count.map(i64::from),
+		}
+	}
+}
+
+impl From<&FlattenOptions> for index::Sort {
+	fn from(dto: &FlattenOptions) -> Self {
+		Self {
+			field: dto.sort.unwrap_or_default(),
+			order: dto.order.unwrap_or_default(),
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SearchOptions {
+	pub summary: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SearchResults {
+	pub results: Vec<index::CollectionFile>,
+	pub directory_count: i64,
+	pub song_count: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AudioOptions {
+	pub format: Option<transcode::Format>,
+	pub bitrate: Option<u32>,
+}
+
+impl From<AudioOptions> for Option<transcode::Options> {
+	fn from(dto: AudioOptions) -> Self {
+		match (dto.format, dto.bitrate) {
+			(None, None) => None,
+			(format, bitrate) => Some(transcode::Options::new(format, bitrate)),
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WaveformOptions {
+	pub bins: Option<u32>,
+}
+
+impl WaveformOptions {
+	pub fn bins(&self) -> u32 {
+		self.bins.unwrap_or(waveform::DEFAULT_BINS)
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BrowseOptions {
+	pub stream: Option<bool>,
+	pub sort: Option<index::SortField>,
+	pub order: Option<index::SortOrder>,
+	pub depth: Option<u32>,
+}
+
+impl BrowseOptions {
+	pub fn depth(&self) -> u32 {
+		self.depth.unwrap_or(index::DEFAULT_BROWSE_DEPTH)
+	}
+}
+
+impl From<&BrowseOptions> for index::Sort {
+	fn from(dto: &BrowseOptions) -> Self {
+		Self {
+			field: dto.sort.unwrap_or_default(),
+			order: dto.order.unwrap_or_default(),
+		}
+	}
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ThumbnailOptions {
 	pub size: Option<ThumbnailSize>,
 	pub pad: Option<bool>,
+	pub crop: Option<bool>,
+	pub original: Option<bool>,
 }
 
 impl From<ThumbnailOptions> for thumbnail::Options {
@@ -48,6 +170,7 @@ impl From<ThumbnailOptions> for thumbnail::Options {
 		let mut options = thumbnail::Options::default();
 		options.max_dimension = dto.size.map_or(options.max_dimension, Into::into);
 		options.pad_to_square = dto.pad.unwrap_or(options.pad_to_square);
+		options.crop_to_square = dto.crop.unwrap_or(options.crop_to_square);
 		options
 	}
 }
@@ -55,7 +178,11 @@ impl From<ThumbnailOptions> for thumbnail::Options {
 #[derive(Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ThumbnailSize {
+	#[serde(alias = "100")]
+	Tiny,
+	#[serde(alias = "400")]
 	Small,
+	#[serde(alias = "1200")]
 	Large,
 	Native,
 }
@@ -63,6 +190,7 @@ pub enum ThumbnailSize {
 impl Into<Option<u32>> for ThumbnailSize {
 	fn into(self) -> Option<u32> {
 		match self {
+			Self::Tiny => Some(100),
 			Self::Small => Some(400),
 			Self::Large => Some(1200),
 			Self::Native => None,
@@ -70,9 +198,31 @@ impl Into<Option<u32>> for ThumbnailSize {
 	}
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct ListPlaylistsOptions {
+	pub sort: Option<playlist::PlaylistSort>,
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct ListPlaylistsEntry {
 	pub name: String,
+	pub created_at: i64,
+	pub updated_at: i64,
+	pub song_count: i64,
+	// In seconds
+	pub total_duration: i64,
+}
+
+impl From<playlist::PlaylistHeader> for ListPlaylistsEntry {
+	fn from(h: playlist::PlaylistHeader) -> Self {
+		Self {
+			name: h.name,
+			created_at: h.created_at,
+			updated_at: h.updated_at,
+			song_count: h.song_count,
+			total_duration: h.total_duration,
+		}
+	}
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -80,6 +230,59 @@ pub struct SavePlaylistInput {
 	pub tracks: Vec<String>,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PlaylistRename {
+	pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlaylistImportReport {
+	pub imported: usize,
+	pub unmatched: Vec<String>,
+}
+
+impl From<playlist::ImportReport> for PlaylistImportReport {
+	fn from(r: playlist::ImportReport) -> Self {
+		Self {
+			imported: r.imported,
+			unmatched: r.unmatched,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlaylistExportOptions {
+	pub format: Option<playlist::ExportFormat>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ListeningStatsOptions {
+	pub since: Option<i64>,
+	pub count: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RandomOptions {
+	pub count: Option<i64>,
+	pub seed: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecentOptions {
+	pub count: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TopSongsOptions {
+	pub count: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DailyMixOptions {
+	pub count: Option<i64>,
+	pub discovery: Option<f32>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct LastFMLink {
 	pub auth_token: String, // user::AuthToken emitted by Polaris, valid for LastFMLink scope
@@ -92,6 +295,11 @@ pub struct LastFMLinkToken {
 	pub value: String,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ListenBrainzLink {
+	pub token: String,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct User {
 	pub name: String,
@@ -130,11 +338,26 @@ pub struct UserUpdate {
 	pub new_is_admin: Option<bool>,
 }
 
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PasswordUpdate {
+	pub new_password: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PasswordChange {
+	pub old_password: String,
+	pub new_password: String,
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct DDNSConfig {
 	pub host: String,
 	pub username: String,
 	pub password: String,
+	#[serde(default)]
+	pub provider: ddns::Provider,
+	#[serde(default = "ddns::default_update_interval_seconds")]
+	pub update_interval_seconds: i32,
 }
 
 impl From<DDNSConfig> for ddns::Config {
@@ -143,6 +366,8 @@ impl From<DDNSConfig> for ddns::Config {
 			host: c.host,
 			username: c.username,
 			password: c.password,
+			provider: c.provider,
+			update_interval_seconds: c.update_interval_seconds,
 		}
 	}
 }
@@ -153,6 +378,40 @@ impl From<ddns::Config> for DDNSConfig {
 			host: c.host,
 			username: c.username,
 			password: c.password,
+			provider: c.provider,
+			update_interval_seconds: c.update_interval_seconds,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DDNSStatus {
+	pub last_update_time: Option<i32>,
+	pub last_update_error: Option<String>,
+}
+
+impl From<ddns::Status> for DDNSStatus {
+	fn from(s: ddns::Status) -> Self {
+		Self {
+			last_update_time: s.last_update_time,
+			last_update_error: s.last_update_error,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DDNSUpdateReport {
+	pub status: Option<u16>,
+	pub body: Option<String>,
+	pub error: Option<String>,
+}
+
+impl From<ddns::UpdateReport> for DDNSUpdateReport {
+	fn from(r: ddns::UpdateReport) -> Self {
+		Self {
+			status: r.response.as_ref().map(|r| r.status),
+			body: r.response.map(|r| r.body),
+			error: r.error,
 		}
 	}
 }
@@ -161,6 +420,8 @@ impl From<ddns::Config> for DDNSConfig {
 pub struct MountDir {
 	pub source: String,
 	pub name: String,
+	#[serde(default)]
+	pub scan_interval_seconds: Option<i32>,
 }
 
 impl From<MountDir> for vfs::MountDir {
@@ -168,6 +429,7 @@ impl From<MountDir> for vfs::MountDir {
 		Self {
 			name: m.name,
 			source: m.source,
+			scan_interval_seconds: m.scan_interval_seconds,
 		}
 	}
 }
@@ -177,6 +439,7 @@ impl From<vfs::MountDir> for MountDir {
 		Self {
 			name: m.name,
 			source: m.source,
+			scan_interval_seconds: m.scan_interval_seconds,
 		}
 	}
 }
@@ -206,6 +469,22 @@ impl From<Config> for config::Config {
 pub struct NewSettings {
 	pub album_art_pattern: Option<String>,
 	pub reindex_every_n_seconds: Option<i32>,
+	pub index_start_delay_seconds: Option<i32>,
+	pub unknown_artist_label: Option<String>,
+	pub normalize_path_separators: Option<bool>,
+	pub index_trigger_policy: Option<settings::IndexTriggerPolicy>,
+	pub index_max_tag_length: Option<i32>,
+	pub auth_max_failed_attempts: Option<i32>,
+	pub auth_failure_window_seconds: Option<i32>,
+	pub auth_realm: Option<String>,
+	pub cors_allowed_origins: Option<Vec<String>>,
+	pub session_lifetime_seconds: Option<i32>,
+	pub thumbnail_cache_max_size_bytes: Option<i64>,
+	pub index_audio_extensions: Option<Vec<String>>,
+	pub follow_symlinks: Option<bool>,
+	pub thumbnail_format: Option<settings::ThumbnailFormat>,
+	pub thumbnail_quality: Option<i32>,
+	pub album_art_names: Option<Vec<String>>,
 }
 
 impl From<NewSettings> for settings::NewSettings {
@@ -213,6 +492,22 @@ impl From<NewSettings> for settings::NewSettings {
 		Self {
 			album_art_pattern: s.album_art_pattern,
 			reindex_every_n_seconds: s.reindex_every_n_seconds,
+			index_start_delay_seconds: s.index_start_delay_seconds,
+			unknown_artist_label: s.unknown_artist_label,
+			normalize_path_separators: s.normalize_path_separators,
+			index_trigger_policy: s.index_trigger_policy,
+			index_max_tag_length: s.index_max_tag_length,
+			auth_max_failed_attempts: s.auth_max_failed_attempts,
+			auth_failure_window_seconds: s.auth_failure_window_seconds,
+			auth_realm: s.auth_realm,
+			cors_allowed_origins: s.cors_allowed_origins,
+			session_lifetime_seconds: s.session_lifetime_seconds,
+			thumbnail_cache_max_size_bytes: s.thumbnail_cache_max_size_bytes,
+			index_audio_extensions: s.index_audio_extensions,
+			follow_symlinks: s.follow_symlinks,
+			thumbnail_format: s.thumbnail_format,
+			thumbnail_quality: s.thumbnail_quality,
+			album_art_names: s.album_art_names,
 		}
 	}
 }
@@ -221,6 +516,23 @@ impl From<NewSettings> for settings::NewSettings {
 pub struct Settings {
 	pub album_art_pattern: String,
 	pub reindex_every_n_seconds: i32,
+	pub index_start_delay_seconds: i32,
+	pub unknown_artist_label: String,
+	pub normalize_path_separators: bool,
+	pub index_trigger_policy: settings::IndexTriggerPolicy,
+	pub index_max_tag_length: i32,
+	pub auth_max_failed_attempts: i32,
+	pub auth_failure_window_seconds: i32,
+	pub auth_realm: String,
+	pub cors_allowed_origins: Vec<String>,
+	pub session_lifetime_seconds: i32,
+	pub thumbnail_cache_max_size_bytes: i64,
+	pub index_audio_extensions: Vec<String>,
+	pub follow_symlinks: bool,
+	pub thumbnail_format: settings::ThumbnailFormat,
+	pub thumbnail_quality: i32,
+	pub album_art_names: Vec<String>,
+	pub revision: i64,
 }
 
 impl From<settings::Settings> for Settings {
@@ -228,6 +540,23 @@ impl From<settings::Settings> for Settings {
 		Self {
 			album_art_pattern: s.album_art_pattern,
 			reindex_every_n_seconds: s.reindex_every_n_seconds,
+			index_start_delay_seconds: s.index_start_delay_seconds,
+			unknown_artist_label: s.unknown_artist_label,
+			normalize_path_separators: s.normalize_path_separators,
+			index_trigger_policy: s.index_trigger_policy,
+			index_max_tag_length: s.index_max_tag_length,
+			auth_max_failed_attempts: s.auth_max_failed_attempts,
+			auth_failure_window_seconds: s.auth_failure_window_seconds,
+			auth_realm: s.auth_realm,
+			cors_allowed_origins: s.cors_allowed_origins,
+			session_lifetime_seconds: s.session_lifetime_seconds,
+			thumbnail_cache_max_size_bytes: s.thumbnail_cache_max_size_bytes,
+			index_audio_extensions: s.index_audio_extensions,
+			follow_symlinks: s.follow_symlinks,
+			thumbnail_format: s.thumbnail_format,
+			thumbnail_quality: s.thumbnail_quality,
+			album_art_names: s.album_art_names,
+			revision: s.revision,
 		}
 	}
 }
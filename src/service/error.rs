@@ -1,22 +1,48 @@
+use std::time::Duration;
 use thiserror::Error;
 
-use crate::app::index::QueryError;
-use crate::app::{config, playlist, settings, user};
+use crate::app::index::{QueryError, TriggerError};
+use crate::app::{
+	artist, config, maintenance, now_playing, operation, playlist, settings, stats, throttle,
+	transcode, user, waveform,
+};
 
 #[derive(Error, Debug)]
 pub enum APIError {
 	#[error("Incorrect Credentials")]
 	IncorrectCredentials,
-	#[error("EmptyUsername")]
+	#[error("Username must not be empty")]
 	EmptyUsername,
-	#[error("EmptyPassword")]
+	#[error("Password must not be empty")]
 	EmptyPassword,
+	#[error("Too many failed login attempts")]
+	TooManyLoginAttempts(Duration),
 	#[error("Cannot delete your own account")]
 	DeletingOwnAccount,
 	#[error("Cannot remove your own admin privilege")]
 	OwnAdminPrivilegeRemoval,
+	#[error("Cannot remove the last admin")]
+	LastAdminRemoval,
+	#[error("This operation requires admin privileges")]
+	AdminPrivilegeRequired,
+	#[error("Client is not allowed to read metrics")]
+	MetricsAccessDenied,
+	#[error("No image found for this artist")]
+	ArtistImageNotFound,
+	#[error("No artwork found")]
+	ArtworkNotFound,
+	#[error("Content is not a supported image")]
+	InvalidImage,
 	#[error("Audio file could not be opened")]
 	AudioFileIOError,
+	#[error("Client cannot play this audio format")]
+	UnplayableAudioFormat,
+	#[error("Bitrate is outside of the supported range")]
+	UnsupportedBitrate,
+	#[error("Audio could not be transcoded")]
+	TranscodingFailed,
+	#[error("Number of waveform bins is outside of the supported range")]
+	UnsupportedWaveformBins,
 	#[error("Thumbnail file could not be opened")]
 	ThumbnailFileIOError,
 	#[error("No last.fm account has been linked")]
@@ -27,20 +53,108 @@ pub enum APIError {
 	LastFMLinkContentEncodingError,
 	#[error("Path not found in virtual filesystem")]
 	VFSPathNotFound,
+	#[error("Path lies outside of the collection")]
+	VFSPathForbidden,
+	#[error("Session lifetime must be a positive number of seconds")]
+	InvalidSessionLifetime,
+	#[error("Thumbnail quality must be between 1 and 100")]
+	InvalidThumbnailQuality,
+	#[error("Settings were changed since they were read")]
+	SettingsRevisionMismatch,
+	#[error("Path contains malformed percent-encoding")]
+	InvalidPercentEncoding,
+	#[error("Path must not contain `..` segments")]
+	PathTraversal,
+	#[error("Browse depth is outside of the supported range")]
+	UnsupportedBrowseDepth,
+	#[error("Too many paths in a single song lookup")]
+	TooManySongPaths,
+	#[error("Database is unavailable")]
+	DatabaseUnavailable,
+	#[error("Operation not found")]
+	OperationNotFound,
 	#[error("User not found")]
 	UserNotFound,
 	#[error("Playlist not found")]
 	PlaylistNotFound,
+	#[error("Playlist name cannot be empty")]
+	EmptyPlaylistName,
+	#[error("A playlist with this name already exists")]
+	PlaylistAlreadyExists,
+	#[error("Session not found")]
+	SessionNotFound,
+	#[error("Index is already being updated")]
+	IndexAlreadyRunning,
+	#[error("Request body is too large")]
+	RequestBodyTooLarge,
 	#[error("Unspecified")]
 	Unspecified,
 }
 
+impl APIError {
+	/// Machine-readable identifier of the error, which does not change when messages are reworded.
+	pub fn code(&self) -> &'static str {
+		match self {
+			APIError::IncorrectCredentials => "IncorrectCredentials",
+			APIError::EmptyUsername => "EmptyUsername",
+			APIError::EmptyPassword => "EmptyPassword",
+			APIError::TooManyLoginAttempts(_) => "TooManyLoginAttempts",
+			APIError::DeletingOwnAccount => "DeletingOwnAccount",
+			APIError::OwnAdminPrivilegeRemoval => "OwnAdminPrivilegeRemoval",
+			APIError::LastAdminRemoval => "LastAdminRemoval",
+			APIError::AdminPrivilegeRequired => "AdminPrivilegeRequired",
+			APIError::MetricsAccessDenied => "MetricsAccessDenied",
+			APIError::ArtistImageNotFound => "ArtistImageNotFound",
+			APIError::ArtworkNotFound => "ArtworkNotFound",
+			APIError::InvalidImage => "InvalidImage",
+			APIError::AudioFileIOError => "AudioFileIOError",
+			APIError::UnplayableAudioFormat => "UnplayableAudioFormat",
+			APIError::UnsupportedBitrate => "UnsupportedBitrate",
+			APIError::TranscodingFailed => "TranscodingFailed",
+			APIError::UnsupportedWaveformBins => "UnsupportedWaveformBins",
+			APIError::ThumbnailFileIOError => "ThumbnailFileIOError",
+			APIError::LastFMAccountNotLinked => "LastFMAccountNotLinked",
+			APIError::LastFMLinkContentBase64DecodeError => "LastFMLinkContentBase64DecodeError",
+			APIError::LastFMLinkContentEncodingError => "LastFMLinkContentEncodingError",
+			APIError::VFSPathNotFound => "VFSPathNotFound",
+			APIError::VFSPathForbidden => "VFSPathForbidden",
+			APIError::InvalidSessionLifetime => "InvalidSessionLifetime",
+			APIError::InvalidThumbnailQuality => "InvalidThumbnailQuality",
+			APIError::SettingsRevisionMismatch => "SettingsRevisionMismatch",
+			APIError::InvalidPercentEncoding => "InvalidPercentEncoding",
+			APIError::PathTraversal => "PathTraversal",
+			APIError::UnsupportedBrowseDepth => "UnsupportedBrowseDepth",
+			APIError::TooManySongPaths => "TooManySongPaths",
+			APIError::DatabaseUnavailable => "DatabaseUnavailable",
+			APIError::OperationNotFound => "OperationNotFound",
+			APIError::UserNotFound => "UserNotFound",
+			APIError::PlaylistNotFound => "PlaylistNotFound",
+			APIError::EmptyPlaylistName => "EmptyPlaylistName",
+			APIError::PlaylistAlreadyExists => "PlaylistAlreadyExists",
+			APIError::SessionNotFound => "SessionNotFound",
+			APIError::IndexAlreadyRunning => "IndexAlreadyRunning",
+			APIError::RequestBodyTooLarge => "RequestBodyTooLarge",
+			APIError::Unspecified => "Unspecified",
+		}
+	}
+}
+
 impl From<anyhow::Error> for APIError {
 	fn from(_: anyhow::Error) -> Self {
 		APIError::Unspecified
 	}
 }
 
+impl From<artist::Error> for APIError {
+	fn from(error: artist::Error) -> APIError {
+		match error {
+			artist::Error::ArtistImageNotFound => APIError::ArtistImageNotFound,
+			artist::Error::InvalidImage => APIError::InvalidImage,
+			artist::Error::Unspecified => APIError::Unspecified,
+		}
+	}
+}
+
 impl From<config::Error> for APIError {
 	fn from(error: config::Error) -> APIError {
 		match error {
@@ -49,10 +163,58 @@ impl From<config::Error> for APIError {
 	}
 }
 
+impl From<maintenance::Error> for APIError {
+	fn from(error: maintenance::Error) -> APIError {
+		match error {
+			maintenance::Error::Unspecified => APIError::Unspecified,
+		}
+	}
+}
+
+impl From<now_playing::Error> for APIError {
+	fn from(error: now_playing::Error) -> APIError {
+		match error {
+			now_playing::Error::SongNotFound => APIError::VFSPathNotFound,
+		}
+	}
+}
+
+impl From<operation::Error> for APIError {
+	fn from(error: operation::Error) -> APIError {
+		match error {
+			operation::Error::OperationNotFound => APIError::OperationNotFound,
+		}
+	}
+}
+
+impl From<transcode::Error> for APIError {
+	fn from(error: transcode::Error) -> APIError {
+		match error {
+			transcode::Error::UnsupportedBitrate => APIError::UnsupportedBitrate,
+			transcode::Error::EncoderUnavailable(_) => APIError::TranscodingFailed,
+			transcode::Error::EncodingFailed => APIError::TranscodingFailed,
+			transcode::Error::Io(_) => APIError::TranscodingFailed,
+		}
+	}
+}
+
+impl From<waveform::Error> for APIError {
+	fn from(error: waveform::Error) -> APIError {
+		match error {
+			waveform::Error::UnsupportedBinCount => APIError::UnsupportedWaveformBins,
+			waveform::Error::Decoding(e) => e.into(),
+			waveform::Error::Io(_) => APIError::AudioFileIOError,
+			waveform::Error::Serialization(_) => APIError::Unspecified,
+		}
+	}
+}
+
 impl From<playlist::Error> for APIError {
 	fn from(error: playlist::Error) -> APIError {
 		match error {
 			playlist::Error::PlaylistNotFound => APIError::PlaylistNotFound,
+			playlist::Error::EmptyPlaylistName => APIError::EmptyPlaylistName,
+			playlist::Error::PlaylistAlreadyExists => APIError::PlaylistAlreadyExists,
 			playlist::Error::UserNotFound => APIError::UserNotFound,
 			playlist::Error::Unspecified => APIError::Unspecified,
 		}
@@ -63,24 +225,77 @@ impl From<QueryError> for APIError {
 	fn from(error: QueryError) -> APIError {
 		match error {
 			QueryError::VFSPathNotFound => APIError::VFSPathNotFound,
+			QueryError::UnsupportedDepth => APIError::UnsupportedBrowseDepth,
+			QueryError::TooManyPaths => APIError::TooManySongPaths,
 			QueryError::Unspecified => APIError::Unspecified,
 		}
 	}
 }
 
+impl From<TriggerError> for APIError {
+	fn from(error: TriggerError) -> APIError {
+		match error {
+			TriggerError::VFSPathNotFound => APIError::VFSPathNotFound,
+			TriggerError::AlreadyRunning => APIError::IndexAlreadyRunning,
+			TriggerError::ShuttingDown => APIError::Unspecified,
+			TriggerError::Unspecified => APIError::Unspecified,
+		}
+	}
+}
+
 impl From<settings::Error> for APIError {
 	fn from(error: settings::Error) -> APIError {
 		match error {
 			settings::Error::AuthSecretNotFound => APIError::Unspecified,
 			settings::Error::InvalidAuthSecret => APIError::Unspecified,
 			settings::Error::IndexSleepDurationNotFound => APIError::Unspecified,
+			settings::Error::IndexStartDelayNotFound => APIError::Unspecified,
 			settings::Error::IndexAlbumArtPatternNotFound => APIError::Unspecified,
 			settings::Error::IndexAlbumArtPatternInvalid => APIError::Unspecified,
+			settings::Error::IndexAlbumArtNamesNotFound => APIError::Unspecified,
+			settings::Error::IndexTriggerPolicyNotFound => APIError::Unspecified,
+			settings::Error::IndexTriggerPolicyInvalid => APIError::Unspecified,
+			settings::Error::IndexMaxTagLengthNotFound => APIError::Unspecified,
+			settings::Error::IndexAudioExtensionsNotFound => APIError::Unspecified,
+			settings::Error::FollowSymlinksNotFound => APIError::Unspecified,
+			settings::Error::AuthMaxFailedAttemptsNotFound => APIError::Unspecified,
+			settings::Error::AuthFailureWindowNotFound => APIError::Unspecified,
+			settings::Error::AuthRealmNotFound => APIError::Unspecified,
+			settings::Error::CorsAllowedOriginsNotFound => APIError::Unspecified,
+			settings::Error::SessionLifetimeNotFound => APIError::Unspecified,
+			settings::Error::SessionLifetimeInvalid => APIError::InvalidSessionLifetime,
+			settings::Error::ThumbnailCacheMaxSizeNotFound => APIError::Unspecified,
+			settings::Error::ThumbnailFormatNotFound => APIError::Unspecified,
+			settings::Error::ThumbnailFormatInvalid => APIError::Unspecified,
+			settings::Error::ThumbnailQualityNotFound => APIError::Unspecified,
+			settings::Error::ThumbnailQualityInvalid => APIError::InvalidThumbnailQuality,
+			settings::Error::RevisionMismatch => APIError::SettingsRevisionMismatch,
 			settings::Error::Unspecified => APIError::Unspecified,
 		}
 	}
 }
 
+impl From<stats::Error> for APIError {
+	fn from(error: stats::Error) -> APIError {
+		match error {
+			stats::Error::UserNotFound => APIError::UserNotFound,
+			stats::Error::VFSPathNotFound => APIError::VFSPathNotFound,
+			stats::Error::Unspecified => APIError::Unspecified,
+		}
+	}
+}
+
+impl From<throttle::Error> for APIError {
+	fn from(error: throttle::Error) -> APIError {
+		match error {
+			throttle::Error::TooManyAttempts(retry_after) => {
+				APIError::TooManyLoginAttempts(retry_after)
+			}
+			throttle::Error::Settings(e) => e.into(),
+		}
+	}
+}
+
 impl From<user::Error> for APIError {
 	fn from(error: user::Error) -> APIError {
 		match error {
@@ -90,6 +305,7 @@ impl From<user::Error> for APIError {
 			user::Error::IncorrectPassword => APIError::IncorrectCredentials,
 			user::Error::InvalidAuthToken => APIError::IncorrectCredentials,
 			user::Error::IncorrectAuthorizationScope => APIError::IncorrectCredentials,
+			user::Error::SessionNotFound => APIError::SessionNotFound,
 			user::Error::Unspecified => APIError::Unspecified,
 		}
 	}
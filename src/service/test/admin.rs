@@ -1,8 +1,10 @@
 use http::StatusCode;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use crate::app::index;
+use crate::app::{index, settings};
 use crate::service::dto;
-use crate::service::test::{protocol, ServiceType, TestService};
+use crate::service::test::{constants::*, protocol, ServiceType, TestService};
 use crate::test_name;
 
 #[test]
@@ -13,6 +15,14 @@ fn returns_api_version() {
 	assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[test]
+fn health_does_not_require_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::health();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+}
+
 #[test]
 fn initial_setup_golden_path() {
 	let mut service = ServiceType::new(&test_name!());
@@ -42,6 +52,32 @@ fn initial_setup_golden_path() {
 	}
 }
 
+#[test]
+fn status_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let request = protocol::status();
+	let response = service.fetch_json::<_, index::Status>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().song_count, 0);
+	assert_eq!(response.body().last_index_time, None);
+
+	service.login_admin();
+	service.index();
+
+	loop {
+		let response = service.fetch_json::<_, index::Status>(&request);
+		let status = response.body();
+		if !status.is_indexing && status.last_index_time.is_some() {
+			assert_eq!(status.song_count, 13);
+			assert_eq!(status.directory_count, 6);
+			break;
+		}
+		std::thread::sleep(std::time::Duration::from_millis(100));
+	}
+}
+
 #[test]
 fn trigger_index_golden_path() {
 	let mut service = ServiceType::new(&test_name!());
@@ -61,6 +97,142 @@ fn trigger_index_golden_path() {
 	assert_eq!(entries.len(), 3);
 }
 
+#[test]
+fn trigger_incremental_index_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+
+	let request = protocol::trigger_incremental_index();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::random();
+	let response = service.fetch_json::<_, Vec<index::Directory>>(&request);
+	assert_eq!(response.body().len(), 3);
+}
+
+#[test]
+fn events_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	let request = protocol::events();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn trigger_index_rejects_unknown_mode() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = http::Request::builder()
+		.method(http::Method::POST)
+		.uri("/api/trigger_index?mode=partial")
+		.body(())
+		.unwrap();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn trigger_index_path_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	let request = protocol::trigger_index_path(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn trigger_index_path_rejects_unknown_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let path: PathBuf = ["not_a_mount", "Khemmis"].iter().collect();
+	let request = protocol::trigger_index_path(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn trigger_index_path_rejects_path_traversal() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "..", ".."].iter().collect();
+	let request = protocol::trigger_index_path(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn trigger_index_subtree_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let khemmis_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	let request = protocol::trigger_index_subtree(&khemmis_path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let songs = loop {
+		let request = protocol::flatten(&PathBuf::new());
+		let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+		if !response.body().is_empty() {
+			break response.into_body();
+		}
+		std::thread::sleep(Duration::from_secs(1));
+	};
+	assert!(songs
+		.iter()
+		.all(|s| Path::new(&s.path).starts_with(&khemmis_path)));
+}
+
+#[test]
+fn trigger_index_subtree_rejects_unknown_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let path: PathBuf = ["not_a_mount", "Khemmis"].iter().collect();
+	let request = protocol::trigger_index_subtree(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn trigger_index_subtree_rejects_path_traversal() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "..", ".."].iter().collect();
+	let request = protocol::trigger_index_subtree(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn trigger_index_subtree_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	let request = protocol::trigger_index_subtree(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
 #[test]
 fn trigger_index_requires_auth() {
 	let mut service = ServiceType::new(&test_name!());
@@ -79,3 +251,156 @@ fn trigger_index_requires_admin() {
 	let response = service.fetch(&request);
 	assert_eq!(response.status(), StatusCode::FORBIDDEN);
 }
+
+#[test]
+fn missing_admin_privilege_is_described_in_json() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+	let request = protocol::trigger_index();
+	let response = service.fetch_json::<_, dto::ErrorResponse>(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+	assert_eq!(response.body().error, "AdminPrivilegeRequired");
+}
+
+#[test]
+fn preview_index_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::preview_index();
+	let response = service.fetch_json::<_, index::UpdatePreview>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().songs_added, 13);
+
+	service.index();
+
+	let response = service.fetch_json::<_, index::UpdatePreview>(&request);
+	assert_eq!(response.body(), &index::UpdatePreview::default());
+}
+
+#[test]
+fn preview_index_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+	let request = protocol::preview_index();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn index_progress_reports_idle_between_scans() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+
+	let request = protocol::index_progress();
+	let response = service.fetch_json::<_, index::Progress>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(
+		response.body(),
+		&index::Progress {
+			state: index::ScanState::Idle,
+			processed: 0,
+			total: 0,
+			current_path: None,
+		}
+	);
+}
+
+#[test]
+fn index_progress_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	let request = protocol::index_progress();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn resolve_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let real_path: PathBuf = [TEST_MOUNT_SOURCE, "Khemmis", "Hunted"].iter().collect();
+	let request = protocol::resolve(&real_path);
+	let response = service.fetch_json::<_, dto::ResolvedPath>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let virtual_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	assert_eq!(
+		response.body(),
+		&dto::ResolvedPath {
+			path: virtual_path.to_string_lossy().into_owned()
+		}
+	);
+}
+
+#[test]
+fn resolve_outside_mounts_returns_not_found() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let real_path: PathBuf = ["test-data", "artwork"].iter().collect();
+	let request = protocol::resolve(&real_path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+	let real_path: PathBuf = [TEST_MOUNT_SOURCE, "..", "artwork"].iter().collect();
+	let request = protocol::resolve(&real_path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn resolve_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+	let real_path: PathBuf = [TEST_MOUNT_SOURCE, "Khemmis"].iter().collect();
+	let request = protocol::resolve(&real_path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn trigger_index_queues_rapid_triggers() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	for _ in 0..5 {
+		let request = protocol::trigger_index();
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+	}
+
+	service.index();
+}
+
+#[test]
+fn trigger_index_can_reject_concurrent_triggers() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::put_settings(dto::NewSettings {
+		index_trigger_policy: Some(settings::IndexTriggerPolicy::Reject),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let statuses: Vec<StatusCode> = (0..5)
+		.map(|_| service.fetch(&protocol::trigger_index()).status())
+		.collect();
+	assert_eq!(statuses[0], StatusCode::OK);
+	assert!(statuses
+		.iter()
+		.all(|s| *s == StatusCode::OK || *s == StatusCode::CONFLICT));
+	assert!(statuses.contains(&StatusCode::CONFLICT));
+}
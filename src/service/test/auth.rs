@@ -7,9 +7,7 @@ use crate::service::dto;
 use crate::service::test::{constants::*, protocol, ServiceType, TestService};
 use crate::test_name;
 
-fn validate_added_cookies<T>(response: &Response<T>) {
-	let twenty_years = Duration::days(365 * 20);
-
+fn validate_added_cookies<T>(response: &Response<T>, lifetime: Duration) {
 	let cookies: Vec<Cookie> = response
 		.headers()
 		.get_all(http::header::SET_COOKIE)
@@ -22,21 +20,21 @@ fn validate_added_cookies<T>(response: &Response<T>) {
 		.find(|c| c.name() == dto::COOKIE_SESSION)
 		.unwrap();
 	assert_ne!(session.value(), TEST_USERNAME);
-	assert!(session.max_age().unwrap() >= twenty_years);
+	assert_eq!(session.max_age(), Some(lifetime));
 
 	let username = cookies
 		.iter()
 		.find(|c| c.name() == dto::COOKIE_USERNAME)
 		.unwrap();
 	assert_eq!(username.value(), TEST_USERNAME);
-	assert!(session.max_age().unwrap() >= twenty_years);
+	assert_eq!(username.max_age(), Some(lifetime));
 
 	let is_admin = cookies
 		.iter()
 		.find(|c| c.name() == dto::COOKIE_ADMIN)
 		.unwrap();
 	assert_eq!(is_admin.value(), false.to_string());
-	assert!(session.max_age().unwrap() >= twenty_years);
+	assert_eq!(is_admin.max_age(), Some(lifetime));
 }
 
 fn validate_no_cookies<T>(response: &Response<T>) {
@@ -71,6 +69,109 @@ fn login_rejects_bad_password() {
 	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
 
+#[test]
+fn login_failure_is_described_in_json() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let request = protocol::login(TEST_USERNAME, "garbage");
+	let response = service.fetch_json::<_, dto::ErrorResponse>(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	assert_eq!(
+		response.headers().get(http::header::CONTENT_TYPE).unwrap(),
+		"application/json"
+	);
+	assert_eq!(response.body().error, "IncorrectCredentials");
+	assert!(!response.body().message.is_empty());
+}
+
+#[test]
+fn login_is_throttled_after_repeated_failures() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	for _ in 0..5 {
+		let request = protocol::login(TEST_USERNAME, "garbage");
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	}
+
+	let request = protocol::login(TEST_USERNAME, TEST_PASSWORD);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+	let retry_after: u64 = response
+		.headers()
+		.get(http::header::RETRY_AFTER)
+		.unwrap()
+		.to_str()
+		.unwrap()
+		.parse()
+		.unwrap();
+	assert!(retry_after > 0);
+}
+
+#[test]
+fn login_throttling_is_per_client_ip() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	for i in 0..5 {
+		let request = protocol::login(&format!("garbage{}", i), TEST_PASSWORD);
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	}
+
+	let request = protocol::login_jwt(TEST_USERNAME, TEST_PASSWORD);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[test]
+fn basic_auth_is_throttled_after_repeated_failures() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	for _ in 0..5 {
+		let mut request = protocol::random();
+		let basic = headers::Authorization::basic(TEST_USERNAME, "garbage");
+		request.headers_mut().typed_insert(basic);
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	}
+
+	let mut request = protocol::random();
+	let basic = headers::Authorization::basic(TEST_USERNAME, TEST_PASSWORD);
+	request.headers_mut().typed_insert(basic);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+	let request = protocol::login(TEST_USERNAME, TEST_PASSWORD);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[test]
+fn successful_login_resets_throttling() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	for _ in 0..4 {
+		let request = protocol::login(TEST_USERNAME, "garbage");
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	}
+
+	let request = protocol::login(TEST_USERNAME, TEST_PASSWORD);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	for _ in 0..4 {
+		let request = protocol::login(TEST_USERNAME, "garbage");
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	}
+}
+
 #[test]
 fn login_golden_path() {
 	let mut service = ServiceType::new(&test_name!());
@@ -85,7 +186,80 @@ fn login_golden_path() {
 	assert_eq!(authorization.is_admin, false);
 	assert!(!authorization.token.is_empty());
 
-	validate_added_cookies(&response);
+	validate_added_cookies(&response, Duration::days(30));
+}
+
+#[test]
+fn login_cookies_follow_session_lifetime() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	let request = protocol::put_settings(dto::NewSettings {
+		session_lifetime_seconds: Some(7 * 24 * 60 * 60),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::login(TEST_USERNAME, TEST_PASSWORD);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	validate_added_cookies(&response, Duration::days(7));
+}
+
+#[test]
+fn logout_without_session_succeeds() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let request = protocol::logout();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	validate_no_cookies(&response);
+}
+
+#[test]
+fn logout_removes_cookies_and_revokes_session() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let request = protocol::login(TEST_USERNAME, TEST_PASSWORD);
+	let response = service.fetch_json::<_, dto::Authorization>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let cookie_header = response
+		.headers()
+		.get_all(http::header::SET_COOKIE)
+		.iter()
+		.map(|c| Cookie::parse(c.to_str().unwrap()).unwrap())
+		.map(|c| format!("{}={}", c.name(), c.value()))
+		.collect::<Vec<_>>()
+		.join("; ");
+	let authorization = response.into_body();
+
+	let mut request = protocol::logout();
+	request.headers_mut().insert(
+		http::header::COOKIE,
+		http::HeaderValue::from_str(&cookie_header).unwrap(),
+	);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let removed_cookies: Vec<Cookie> = response
+		.headers()
+		.get_all(http::header::SET_COOKIE)
+		.iter()
+		.map(|c| Cookie::parse(c.to_str().unwrap().to_owned()).unwrap())
+		.collect();
+	for name in &[dto::COOKIE_SESSION, dto::COOKIE_USERNAME, dto::COOKIE_ADMIN] {
+		let cookie = removed_cookies.iter().find(|c| c.name() == *name).unwrap();
+		assert_eq!(cookie.value(), "");
+		assert_eq!(cookie.max_age(), Some(Duration::zero()));
+	}
+
+	service.set_authorization(Some(authorization));
+	let request = protocol::random();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
 
 #[test]
@@ -127,6 +301,74 @@ fn authentication_via_basic_http_header_rejects_bad_password() {
 	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
 
+#[test]
+fn unauthorized_requests_offer_basic_auth_challenge() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let request = protocol::random();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	assert_eq!(
+		response
+			.headers()
+			.get(http::header::WWW_AUTHENTICATE)
+			.unwrap(),
+		"Basic realm=\"Polaris\""
+	);
+
+	let mut request = protocol::random();
+	let basic = headers::Authorization::basic(TEST_USERNAME, "garbage");
+	request.headers_mut().typed_insert(basic);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	assert!(response
+		.headers()
+		.contains_key(http::header::WWW_AUTHENTICATE));
+}
+
+#[test]
+fn basic_auth_challenge_uses_configured_realm() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::put_settings(dto::NewSettings {
+		auth_realm: Some("My Music".to_owned()),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	service.logout();
+	let request = protocol::random();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	assert_eq!(
+		response
+			.headers()
+			.get(http::header::WWW_AUTHENTICATE)
+			.unwrap(),
+		"Basic realm=\"My Music\""
+	);
+}
+
+#[test]
+fn bad_bearer_token_does_not_offer_basic_auth_challenge() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let mut request = protocol::random();
+	let bearer = headers::Authorization::bearer("garbage").unwrap();
+	request.headers_mut().typed_insert(bearer);
+
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	assert!(!response
+		.headers()
+		.contains_key(http::header::WWW_AUTHENTICATE));
+}
+
 #[test]
 fn authentication_via_basic_http_header_golden_path() {
 	let mut service = ServiceType::new(&test_name!());
@@ -139,7 +381,7 @@ fn authentication_via_basic_http_header_golden_path() {
 	let response = service.fetch(&request);
 	assert_eq!(response.status(), StatusCode::OK);
 
-	validate_added_cookies(&response);
+	validate_added_cookies(&response, Duration::days(30));
 }
 
 #[test]
@@ -178,6 +420,76 @@ fn authentication_via_bearer_http_header_golden_path() {
 	validate_no_cookies(&response);
 }
 
+#[test]
+fn login_jwt_rejects_bad_password() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let request = protocol::login_jwt(TEST_USERNAME, "garbage");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn login_jwt_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let request = protocol::login_jwt(TEST_USERNAME, TEST_PASSWORD);
+	let response = service.fetch_json::<_, dto::JwtAuthorization>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let authorization = response.body();
+	assert_eq!(authorization.username, TEST_USERNAME);
+	assert!(!authorization.is_admin);
+	assert_eq!(authorization.token.split('.').count(), 3);
+	assert!(authorization.expires_at > 0);
+
+	validate_no_cookies(&response);
+}
+
+#[test]
+fn authentication_via_bearer_jwt_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let authorization = {
+		let request = protocol::login_jwt(TEST_USERNAME, TEST_PASSWORD);
+		let response = service.fetch_json::<_, dto::JwtAuthorization>(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+		response.into_body()
+	};
+
+	let mut request = protocol::random();
+	let bearer = headers::Authorization::bearer(&authorization.token).unwrap();
+	request.headers_mut().typed_insert(bearer);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	validate_no_cookies(&response);
+}
+
+#[test]
+fn authentication_via_bearer_jwt_rejects_bad_signature() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let authorization = {
+		let request = protocol::login_jwt(TEST_USERNAME, TEST_PASSWORD);
+		let response = service.fetch_json::<_, dto::JwtAuthorization>(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+		response.into_body()
+	};
+
+	let (signing_input, _) = authorization.token.rsplit_once('.').unwrap();
+	let forged_token = format!("{}.c2lnbmF0dXJl", signing_input);
+	let mut request = protocol::random();
+	let bearer = headers::Authorization::bearer(&forged_token).unwrap();
+	request.headers_mut().typed_insert(bearer);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
 #[test]
 fn authentication_via_query_param_rejects_bad_token() {
 	let mut service = ServiceType::new(&test_name!());
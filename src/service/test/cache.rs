@@ -0,0 +1,82 @@
+use http::{header, Response, StatusCode};
+use std::path::{Path, PathBuf};
+
+use crate::service::test::{constants::*, protocol, ServiceType, TestService};
+use crate::test_name;
+
+fn cache_control<T>(response: &Response<T>) -> &str {
+	response
+		.headers()
+		.get(header::CACHE_CONTROL)
+		.unwrap()
+		.to_str()
+		.unwrap()
+}
+
+#[test]
+fn version_is_publicly_cacheable() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::version();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(cache_control(&response), "public, max-age=3600");
+}
+
+#[test]
+fn browse_is_privately_cacheable() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+	let request = protocol::browse(Path::new(""));
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(cache_control(&response), "private, max-age=60");
+}
+
+#[test]
+fn thumbnail_is_privately_cacheable() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "Folder.jpg"]
+		.iter()
+		.collect();
+	let request = protocol::thumbnail(&path, None, None);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(cache_control(&response), "private, max-age=86400");
+}
+
+#[test]
+fn login_is_never_cached() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	let request = protocol::login(TEST_USERNAME, TEST_PASSWORD);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(cache_control(&response), "no-store");
+}
+
+#[test]
+fn errors_are_never_cached() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	let request = protocol::browse(Path::new(""));
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	assert_eq!(cache_control(&response), "no-store");
+}
+
+#[test]
+fn settings_are_never_cached() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	let request = protocol::get_settings();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(cache_control(&response), "no-store");
+}
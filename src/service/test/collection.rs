@@ -1,7 +1,8 @@
-use http::StatusCode;
+use http::{Method, Request, StatusCode};
 use std::path::{Path, PathBuf};
 
-use crate::app::index;
+use crate::app::{index, user};
+use crate::service::dto;
 use crate::service::test::{add_trailing_slash, constants::*, protocol, ServiceType, TestService};
 use crate::test_name;
 
@@ -56,6 +57,224 @@ fn browse_bad_directory() {
 	assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
+#[test]
+fn browse_malformed_path_returns_bad_request() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	for endpoint in &["/api/browse/collection%ZZ", "/api/browse/collection%2"] {
+		let request = Request::builder()
+			.method(Method::GET)
+			.uri(*endpoint)
+			.body(())
+			.unwrap();
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	}
+}
+
+#[test]
+fn browse_rejects_path_traversal() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let endpoints = [
+		format!("/api/browse/{}%2F..%2F..", TEST_MOUNT_NAME),
+		format!("/api/browse/{}/..%2F..", TEST_MOUNT_NAME),
+		format!("/api/browse/{}%5C..%5C..", TEST_MOUNT_NAME),
+	];
+	for endpoint in &endpoints {
+		let request = Request::builder()
+			.method(Method::GET)
+			.uri(endpoint)
+			.body(())
+			.unwrap();
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	}
+}
+
+#[test]
+fn special_characters_round_trip_from_browse_to_audio() {
+	let test_name = test_name!();
+	let mut service = ServiceType::new(&test_name);
+
+	let collection_dir: PathBuf = ["test-output", &test_name, "collection"].iter().collect();
+	let album_dir = collection_dir.join("Sigur Rós + Friends #1");
+	std::fs::create_dir_all(&album_dir).unwrap();
+	let mut file_names = vec![
+		"ピクニック.mp3",
+		"Track #2.mp3",
+		"Rock + Roll.mp3",
+		"100% Pure.mp3",
+	];
+	if cfg!(not(windows)) {
+		file_names.push("Why?.mp3");
+	}
+	for file_name in &file_names {
+		std::fs::copy("test-data/formats/sample.mp3", album_dir.join(file_name)).unwrap();
+	}
+
+	service.complete_initial_setup();
+	service.login_admin();
+	let request = protocol::apply_config(dto::Config {
+		mount_dirs: Some(vec![dto::MountDir {
+			name: TEST_MOUNT_NAME.into(),
+			source: collection_dir.to_string_lossy().into_owned(),
+			scan_interval_seconds: None,
+		}]),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	service.index();
+	service.login();
+
+	let request = protocol::browse(Path::new(TEST_MOUNT_NAME));
+	let response = service.fetch_json::<_, Vec<index::CollectionFile>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let album_path = match &response.body()[..] {
+		[index::CollectionFile::Directory(d)] => PathBuf::from(&d.path),
+		_ => panic!("Expected a single directory"),
+	};
+
+	let request = protocol::browse(&album_path);
+	let response = service.fetch_json::<_, Vec<index::CollectionFile>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let entries = response.body();
+	assert_eq!(entries.len(), file_names.len());
+
+	for entry in entries {
+		let song_path = match entry {
+			index::CollectionFile::Song(s) => PathBuf::from(&s.path),
+			_ => panic!("Expected a song"),
+		};
+		let request = protocol::audio(&song_path);
+		let response = service.fetch_bytes(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+		let expected = std::fs::read("test-data/formats/sample.mp3").unwrap();
+		assert_eq!(response.body(), &expected);
+	}
+}
+
+#[test]
+fn browse_sorted() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::browse_sorted(Path::new(TEST_MOUNT_NAME), "name", "desc");
+	let response = service.fetch_json::<_, Vec<index::CollectionFile>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let paths: Vec<String> = response
+		.body()
+		.iter()
+		.map(|f| match f {
+			index::CollectionFile::Directory(d) => d.path.clone(),
+			_ => panic!("Expected directory"),
+		})
+		.collect();
+	let expected: Vec<String> = ["Tobokegao", "Khemmis"]
+		.iter()
+		.map(|d| {
+			let path: PathBuf = [TEST_MOUNT_NAME, d].iter().collect();
+			path.to_string_lossy().into_owned()
+		})
+		.collect();
+	assert_eq!(paths, expected);
+}
+
+#[test]
+fn browse_rejects_unknown_sort() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let request = protocol::browse_sorted(Path::new(TEST_MOUNT_NAME), "rating", "asc");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn browse_with_depth() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	let request = protocol::browse(&path);
+	let response = service.fetch_json::<_, Vec<index::BrowseEntry>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	match &response.body()[0] {
+		index::BrowseEntry::Directory(d) => assert!(d.children.is_none()),
+		_ => panic!("Expected directory"),
+	}
+
+	let request = protocol::browse_with_depth(&path, 2);
+	let response = service.fetch_json::<_, Vec<index::BrowseEntry>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	match &response.body()[0] {
+		index::BrowseEntry::Directory(d) => assert_eq!(d.children.as_ref().unwrap().len(), 5),
+		_ => panic!("Expected directory"),
+	}
+}
+
+#[test]
+fn browse_rejects_unsupported_depth() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let path = Path::new(TEST_MOUNT_NAME);
+	for depth in [0, index::MAX_BROWSE_DEPTH + 1] {
+		let request = protocol::browse_with_depth(path, depth);
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	}
+}
+
+#[test]
+fn browse_stream_directory() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let request = protocol::browse(&path);
+	let response = service.fetch_json::<_, Vec<index::CollectionFile>>(&request);
+	let expected = response.into_body();
+
+	let request = protocol::browse_stream(&path);
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let entries: Vec<index::CollectionFile> = std::str::from_utf8(response.body())
+		.unwrap()
+		.lines()
+		.map(|l| serde_json::from_str(l).unwrap())
+		.collect();
+	assert_eq!(entries, expected);
+}
+
+#[test]
+fn browse_stream_bad_directory() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let path: PathBuf = ["not_my_collection"].iter().collect();
+	let request = protocol::browse_stream(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
 #[test]
 fn flatten_requires_auth() {
 	let mut service = ServiceType::new(&test_name!());
@@ -94,6 +313,29 @@ fn flatten_directory() {
 	assert_eq!(entries.len(), 13);
 }
 
+#[test]
+fn flatten_range() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::flatten(Path::new(TEST_MOUNT_NAME));
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	let all_songs = response.body();
+
+	let request = protocol::flatten_range(Path::new(TEST_MOUNT_NAME), 10, 5);
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body()[..], all_songs[10..]);
+
+	let request = protocol::flatten_range(&PathBuf::new(), 0, 4);
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body()[..], all_songs[..4]);
+}
+
 #[test]
 fn flatten_bad_directory() {
 	let mut service = ServiceType::new(&test_name!());
@@ -106,6 +348,93 @@ fn flatten_bad_directory() {
 	assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
+#[test]
+fn songs_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::songs(&[]);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn songs_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let album: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let hunted = album.join("05 - Hunted.mp3");
+	let candlelight = album.join("02 - Candlelight.mp3");
+	let missing = album.join("06 - Missing.mp3");
+	let request = protocol::songs(&[&hunted, &missing, &candlelight, &hunted]);
+	let response = service.fetch_json::<_, Vec<Option<index::Song>>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let paths: Vec<Option<&str>> = response
+		.body()
+		.iter()
+		.map(|s| s.as_ref().map(|s| s.path.as_str()))
+		.collect();
+	assert_eq!(
+		paths,
+		vec![hunted.to_str(), None, candlelight.to_str(), hunted.to_str()]
+	);
+}
+
+#[test]
+fn songs_rejects_too_many_paths() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let path = Path::new(TEST_MOUNT_NAME);
+	let paths = vec![path; index::MAX_SONG_LOOKUP_PATHS + 1];
+	let request = protocol::songs(&paths);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn album_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let request = protocol::album(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn album_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let request = protocol::album(&path);
+	let response = service.fetch_json::<_, index::Album>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let album = response.body();
+	assert_eq!(album.directory.path, path.to_str().unwrap());
+	assert_eq!(album.discs.len(), 1);
+	assert_eq!(album.discs[0].name, "Disc 1");
+	assert_eq!(album.discs[0].songs.len(), 5);
+}
+
+#[test]
+fn album_bad_directory() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let path: PathBuf = ["not_my_collection"].iter().collect();
+	let request = protocol::album(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
 #[test]
 fn random_requires_auth() {
 	let mut service = ServiceType::new(&test_name!());
@@ -145,6 +474,49 @@ fn random_with_trailing_slash() {
 	assert_eq!(entries.len(), 3);
 }
 
+#[test]
+fn random_with_count_and_seed() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::random_albums(2, None);
+	let response = service.fetch_json::<_, Vec<index::Directory>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 2);
+
+	let request = protocol::random_albums(3, Some(1234));
+	let first = service.fetch_json::<_, Vec<index::Directory>>(&request);
+	let second = service.fetch_json::<_, Vec<index::Directory>>(&request);
+	assert_eq!(first.body().len(), 3);
+	assert_eq!(first.body(), second.body());
+}
+
+#[test]
+fn random_falls_back_to_preferred_seed() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let preferences = user::Preferences {
+		random_seed: Some(1234),
+		..Default::default()
+	};
+	let request = protocol::put_preferences(preferences);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::random_albums(3, None);
+	let preferred = service.fetch_json::<_, Vec<index::Directory>>(&request);
+	let request = protocol::random_albums(3, Some(1234));
+	let seeded = service.fetch_json::<_, Vec<index::Directory>>(&request);
+	assert_eq!(preferred.body(), seeded.body());
+}
+
 #[test]
 fn recent_requires_auth() {
 	let mut service = ServiceType::new(&test_name!());
@@ -168,6 +540,20 @@ fn recent_golden_path() {
 	assert_eq!(entries.len(), 3);
 }
 
+#[test]
+fn recent_with_count() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::recent_albums(1);
+	let response = service.fetch_json::<_, Vec<index::Directory>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 1);
+}
+
 #[test]
 fn recent_with_trailing_slash() {
 	let mut service = ServiceType::new(&test_name!());
@@ -184,6 +570,103 @@ fn recent_with_trailing_slash() {
 	assert_eq!(entries.len(), 3);
 }
 
+#[test]
+fn artists_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::artists();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn artists_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::artists();
+	let response = service.fetch_json::<_, Vec<index::Artist>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let artists: Vec<(&str, i64)> = response
+		.body()
+		.iter()
+		.map(|a| (a.name.as_str(), a.album_count))
+		.collect();
+	assert_eq!(artists, vec![("Khemmis", 1), ("Tobokegao", 2)]);
+}
+
+#[test]
+fn artist_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::artist("Tobokegao");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn artist_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::artist("Tobokegao");
+	let response = service.fetch_json::<_, Vec<index::Directory>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let albums: Vec<Option<&str>> = response.body().iter().map(|d| d.album.as_deref()).collect();
+	assert_eq!(albums, vec![Some("Picnic"), Some("Picnic (Remixes)")]);
+}
+
+#[test]
+fn genres_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::genres();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn genres_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::genres();
+	let response = service.fetch_json::<_, Vec<index::Genre>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let names: Vec<&str> = response.body().iter().map(|g| g.name.as_str()).collect();
+	assert_eq!(names, vec!["Chiptune", "Doom Metal", "Electronic", "Metal"]);
+}
+
+#[test]
+fn genre_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::genre("Metal");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn genre_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::genre("Doom Metal");
+	let response = service.fetch_json::<_, Vec<index::Directory>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let entries = response.body();
+	assert_eq!(entries.len(), 1);
+	assert_eq!(entries[0].album, Some("Hunted".to_owned()));
+}
+
 #[test]
 fn search_requires_auth() {
 	let mut service = ServiceType::new(&test_name!());
@@ -222,3 +705,70 @@ fn search_with_query() {
 		_ => panic!(),
 	}
 }
+
+#[test]
+fn search_with_field_query() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::search("artist:khemmis title:door");
+	let response = service.fetch_json::<_, Vec<index::CollectionFile>>(&request);
+	let results = response.body();
+	assert_eq!(results.len(), 1);
+	match results[0] {
+		index::CollectionFile::Song(ref s) => {
+			assert_eq!(s.title, Some("Beyond The Door".into()))
+		}
+		_ => panic!(),
+	}
+}
+
+#[test]
+fn search_with_summary() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::search_summary("artist:khemmis");
+	let response = service.fetch_json::<_, dto::SearchResults>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let summary = response.body();
+	assert_eq!(summary.directory_count, 1);
+	assert_eq!(summary.song_count, 0);
+	assert_eq!(summary.results.len(), 1);
+
+	let request = protocol::search_summary("door");
+	let response = service.fetch_json::<_, dto::SearchResults>(&request);
+	let summary = response.body();
+	assert_eq!(summary.directory_count, 0);
+	assert_eq!(summary.song_count, 1);
+	assert_eq!(summary.results.len(), 1);
+}
+
+#[test]
+fn flatten_sorted() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::flatten(Path::new(TEST_MOUNT_NAME));
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	let mut expected = response.into_body();
+	expected.reverse();
+
+	let request = protocol::flatten_sorted(Path::new(TEST_MOUNT_NAME), "name", "desc");
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body()[..], expected[..]);
+
+	let request = protocol::flatten_sorted(Path::new(TEST_MOUNT_NAME), "name", "sideways");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,84 @@
+use http::{header, Method, StatusCode};
+
+use crate::service::dto;
+use crate::service::test::{protocol, ServiceType, TestService};
+use crate::test_name;
+
+const CLIENT_ORIGIN: &str = "https://music.example.com";
+
+fn allow_client_origin(service: &mut ServiceType) {
+	service.complete_initial_setup();
+	service.login_admin();
+	let request = protocol::put_settings(dto::NewSettings {
+		cors_allowed_origins: Some(vec![CLIENT_ORIGIN.to_owned()]),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	service.logout();
+}
+
+#[test]
+fn cors_is_disabled_by_default() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	let request = protocol::version_from_origin(CLIENT_ORIGIN);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(!response
+		.headers()
+		.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+}
+
+#[test]
+fn cors_headers_are_sent_to_allowed_origins() {
+	let mut service = ServiceType::new(&test_name!());
+	allow_client_origin(&mut service);
+
+	let request = protocol::version_from_origin(CLIENT_ORIGIN);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let headers = response.headers();
+	assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], CLIENT_ORIGIN);
+	assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+
+	let request = protocol::version_from_origin("https://elsewhere.example.com");
+	let response = service.fetch(&request);
+	assert!(!response
+		.headers()
+		.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+}
+
+#[test]
+fn preflight_requests_are_answered() {
+	let mut service = ServiceType::new(&test_name!());
+	allow_client_origin(&mut service);
+
+	let request = protocol::preflight("/api/browse", CLIENT_ORIGIN, Method::GET);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NO_CONTENT);
+	let headers = response.headers();
+	assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], CLIENT_ORIGIN);
+	assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+	assert_eq!(
+		headers[header::ACCESS_CONTROL_ALLOW_HEADERS],
+		"authorization"
+	);
+	assert!(headers[header::ACCESS_CONTROL_ALLOW_METHODS]
+		.to_str()
+		.unwrap()
+		.contains("GET"));
+}
+
+#[test]
+fn preflight_requests_from_other_origins_are_refused() {
+	let mut service = ServiceType::new(&test_name!());
+	allow_client_origin(&mut service);
+
+	let request = protocol::preflight("/api/browse", "https://elsewhere.example.com", Method::GET);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+	assert!(!response
+		.headers()
+		.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+}
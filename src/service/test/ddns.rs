@@ -1,5 +1,6 @@
 use http::StatusCode;
 
+use crate::app::ddns;
 use crate::service::dto;
 use crate::service::test::{protocol, ServiceType, TestService};
 use crate::test_name;
@@ -36,6 +37,8 @@ fn put_ddns_config_requires_admin() {
 		host: "test".to_owned(),
 		username: "test".to_owned(),
 		password: "test".to_owned(),
+		provider: ddns::Provider::Ydns,
+		update_interval_seconds: ddns::DEFAULT_UPDATE_INTERVAL_SECONDS,
 	});
 	service.complete_initial_setup();
 
@@ -57,7 +60,88 @@ fn put_ddns_config_golden_path() {
 		host: "test".to_owned(),
 		username: "test".to_owned(),
 		password: "test".to_owned(),
+		provider: ddns::Provider::Ydns,
+		update_interval_seconds: ddns::DEFAULT_UPDATE_INTERVAL_SECONDS,
 	});
 	let response = service.fetch(&request);
 	assert_eq!(response.status(), StatusCode::OK);
 }
+
+#[test]
+fn put_ddns_config_saves_provider() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let config = dto::DDNSConfig {
+		host: "polaris.duckdns.org".to_owned(),
+		username: "".to_owned(),
+		password: "token".to_owned(),
+		provider: ddns::Provider::DuckDns,
+		update_interval_seconds: 600,
+	};
+	let request = protocol::put_ddns_config(config.clone());
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::get_ddns_config();
+	let response = service.fetch_json::<_, dto::DDNSConfig>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body(), &config);
+}
+
+#[test]
+fn test_ddns_config_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::test_ddns_config();
+	service.complete_initial_setup();
+
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	service.login();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn test_ddns_config_reports_missing_credentials() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::test_ddns_config();
+	let response = service.fetch_json::<_, dto::DDNSUpdateReport>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let report = response.body();
+	assert_eq!(report.status, None);
+	assert_eq!(report.body, None);
+	assert!(report.error.is_some());
+}
+
+#[test]
+fn get_ddns_status_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::get_ddns_status();
+	service.complete_initial_setup();
+
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	service.login();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn get_ddns_status_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::get_ddns_status();
+	let response = service.fetch_json::<_, dto::DDNSStatus>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().last_update_time, None);
+	assert_eq!(response.body().last_update_error, None);
+}
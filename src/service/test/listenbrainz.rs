@@ -0,0 +1,31 @@
+use http::StatusCode;
+
+use crate::service::test::{protocol, ServiceType, TestService};
+use crate::test_name;
+
+#[test]
+fn listenbrainz_link_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::listenbrainz_link("some_token");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn listenbrainz_unlink_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::listenbrainz_unlink();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn listenbrainz_unlink_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let request = protocol::listenbrainz_unlink();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+}
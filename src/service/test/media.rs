@@ -1,4 +1,4 @@
-use http::{header, HeaderValue, StatusCode};
+use http::{header, HeaderValue, Method, Request, Response, StatusCode};
 use std::path::PathBuf;
 
 use crate::service::dto::ThumbnailSize;
@@ -36,6 +36,79 @@ fn audio_golden_path() {
 	assert_eq!(response.body().len(), 24_142);
 }
 
+#[test]
+fn audio_rejects_unsupported_bitrate() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::audio_transcoded(&path, "opus", 4000);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn audio_rejects_unknown_format() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::audio_transcoded(&path, "wma", 128);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn album_stream_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let request = protocol::album_stream(&path, "opus", 128);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn album_stream_bad_directory() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "Nope"]
+		.iter()
+		.collect();
+	let request = protocol::album_stream(&path, "opus", 128);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn album_stream_rejects_unsupported_bitrate() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let request = protocol::album_stream(&path, "opus", 4000);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
 #[test]
 fn audio_partial_content() {
 	let mut service = ServiceType::new(&test_name!());
@@ -64,6 +137,291 @@ fn audio_partial_content() {
 	);
 }
 
+fn fetch_audio_range(test_name: &str, range: &str) -> Response<Vec<u8>> {
+	let mut service = ServiceType::new(test_name);
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let mut request = protocol::audio(&path);
+	let headers = request.headers_mut();
+	headers.append(header::RANGE, HeaderValue::from_str(range).unwrap());
+	service.fetch_bytes(&request)
+}
+
+#[test]
+fn audio_open_ended_range() {
+	let response = fetch_audio_range(&test_name!(), "bytes=0-");
+	assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+	assert_eq!(response.body().len(), 24_142);
+	assert_eq!(
+		response.headers().get(header::CONTENT_RANGE).unwrap(),
+		"bytes 0-24141/24142"
+	);
+}
+
+#[test]
+fn audio_suffix_range() {
+	let response = fetch_audio_range(&test_name!(), "bytes=-500");
+	assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+	assert_eq!(response.body().len(), 500);
+	assert_eq!(
+		response.headers().get(header::CONTENT_RANGE).unwrap(),
+		"bytes 23642-24141/24142"
+	);
+}
+
+#[test]
+fn audio_unsatisfiable_range() {
+	let response = fetch_audio_range(&test_name!(), "bytes=30000-40000");
+	assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+	assert_eq!(
+		response.headers().get(header::CONTENT_RANGE).unwrap(),
+		"bytes */24142"
+	);
+}
+
+#[test]
+fn audio_multiple_ranges_get_full_content() {
+	let response = fetch_audio_range(&test_name!(), "bytes=0-99,200-299");
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 24_142);
+	assert!(response.headers().get(header::CONTENT_RANGE).is_none());
+}
+
+#[test]
+fn audio_head_request() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let mut request = protocol::audio(&path);
+	*request.method_mut() = Method::HEAD;
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(response.body().is_empty());
+	assert_eq!(
+		response.headers().get(header::CONTENT_LENGTH).unwrap(),
+		"24142"
+	);
+	assert_eq!(
+		response.headers().get(header::ACCEPT_RANGES).unwrap(),
+		"bytes"
+	);
+}
+
+#[test]
+fn audio_honors_cache_validators() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::audio(&path);
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let etag = response.headers().get(header::ETAG).unwrap().clone();
+	let last_modified = response
+		.headers()
+		.get(header::LAST_MODIFIED)
+		.unwrap()
+		.clone();
+
+	let mut request = protocol::audio(&path);
+	request
+		.headers_mut()
+		.insert(header::IF_NONE_MATCH, etag.clone());
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+	assert!(response.body().is_empty());
+	assert_eq!(response.headers().get(header::ETAG), Some(&etag));
+
+	let mut request = protocol::audio(&path);
+	request
+		.headers_mut()
+		.insert(header::IF_MODIFIED_SINCE, last_modified);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+	let mut request = protocol::audio(&path);
+	request.headers_mut().insert(
+		header::IF_NONE_MATCH,
+		HeaderValue::from_static("\"outdated\""),
+	);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn audio_has_operation_id() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::audio(&path);
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let operation_id: u64 = response
+		.headers()
+		.get("x-operation-id")
+		.unwrap()
+		.to_str()
+		.unwrap()
+		.parse()
+		.unwrap();
+
+	// Operation is over once the response has been fully sent
+	let request = protocol::cancel_operation(operation_id);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn cancel_operation_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::cancel_operation(0);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn audio_path_is_decoded_once() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = Request::builder()
+		.method(Method::GET)
+		.uri(format!(
+			"/api/audio/{}%2FKhem%6Dis%2FHunted%2F02%20-%20Candlelight.mp3",
+			TEST_MOUNT_NAME
+		))
+		.body(())
+		.unwrap();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	// %25 must decode to a literal '%', not start another escape sequence
+	let request = Request::builder()
+		.method(Method::GET)
+		.uri(format!(
+			"/api/audio/{}%2FKhem%256Dis%2FHunted%2F02%20-%20Candlelight.mp3",
+			TEST_MOUNT_NAME
+		))
+		.body(())
+		.unwrap();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn audio_rejects_path_traversal() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let request = Request::builder()
+		.method(Method::GET)
+		.uri(format!(
+			"/api/audio/{}%2F..%2F..%2FCargo.toml",
+			TEST_MOUNT_NAME
+		))
+		.body(())
+		.unwrap();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn audio_ignores_absolute_path_injection() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let endpoints = [
+		"/api/audio/%2Fetc%2Fpasswd".to_owned(),
+		format!("/api/audio/{}%2F%2Fetc%2Fpasswd", TEST_MOUNT_NAME),
+	];
+	for endpoint in &endpoints {
+		let request = Request::builder()
+			.method(Method::GET)
+			.uri(endpoint)
+			.body(())
+			.unwrap();
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::NOT_FOUND);
+	}
+}
+
+#[cfg(unix)]
+#[test]
+fn audio_rejects_symlinks_leaving_collection() {
+	use crate::service::dto;
+	use std::path::Path;
+
+	let test_name = test_name!();
+	let mut service = ServiceType::new(&test_name);
+
+	let collection_dir: PathBuf = ["test-output", &test_name, "collection"].iter().collect();
+	std::fs::create_dir_all(&collection_dir).unwrap();
+	std::fs::copy(
+		"test-data/formats/sample.mp3",
+		collection_dir.join("inside.mp3"),
+	)
+	.unwrap();
+	let outside = Path::new("Cargo.toml").canonicalize().unwrap();
+	std::os::unix::fs::symlink(&outside, collection_dir.join("escape.mp3")).unwrap();
+	std::os::unix::fs::symlink("inside.mp3", collection_dir.join("alias.mp3")).unwrap();
+
+	service.complete_initial_setup();
+	service.login_admin();
+	let request = protocol::apply_config(dto::Config {
+		mount_dirs: Some(vec![dto::MountDir {
+			name: TEST_MOUNT_NAME.into(),
+			source: collection_dir.to_string_lossy().into_owned(),
+			scan_interval_seconds: None,
+		}]),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "escape.mp3"].iter().collect();
+	let response = service.fetch(&protocol::audio(&path));
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+	let response = service.fetch(&protocol::stream(&path));
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "alias.mp3"].iter().collect();
+	let response = service.fetch(&protocol::audio(&path));
+	assert_eq!(response.status(), StatusCode::OK);
+}
+
 #[test]
 fn audio_bad_path_returns_not_found() {
 	let mut service = ServiceType::new(&test_name!());
@@ -77,6 +435,131 @@ fn audio_bad_path_returns_not_found() {
 	assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
+#[test]
+fn waveform_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::waveform(&path, None);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn waveform_bad_path_returns_not_found() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let path: PathBuf = ["not_my_collection"].iter().collect();
+
+	let request = protocol::waveform(&path, None);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn waveform_rejects_unsupported_bins() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	for bins in [0, 10_001].iter() {
+		let request = protocol::waveform(&path, Some(*bins));
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	}
+}
+
+#[test]
+fn stream_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::stream(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn stream_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::stream(&path);
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 24_142);
+	assert_eq!(response.headers().get(header::VARY).unwrap(), "accept");
+}
+
+#[test]
+fn stream_serves_accepted_format() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	for accept in &[
+		"audio/mpeg",
+		"audio/*",
+		"audio/ogg, audio/mpeg;q=0.5",
+		"*/*",
+	] {
+		let mut request = protocol::stream(&path);
+		request
+			.headers_mut()
+			.append(header::ACCEPT, HeaderValue::from_static(accept));
+		let response = service.fetch_bytes(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(response.body().len(), 24_142);
+	}
+}
+
+#[test]
+fn stream_rejects_unplayable_format() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	for accept in &["audio/ogg", "audio/flac, audio/mpeg;q=0", "video/*"] {
+		let mut request = protocol::stream(&path);
+		request
+			.headers_mut()
+			.append(header::ACCEPT, HeaderValue::from_static(accept));
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+	}
+}
+
 #[test]
 fn thumbnail_requires_auth() {
 	let mut service = ServiceType::new(&test_name!());
@@ -111,6 +594,34 @@ fn thumbnail_golden_path() {
 	assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[test]
+fn thumbnail_etag_is_stable_across_cache_hits() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "Folder.jpg"]
+		.iter()
+		.collect();
+
+	let request = protocol::thumbnail(&path, None, None);
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let etag = response.headers().get(header::ETAG).unwrap().clone();
+
+	// Reading a cached thumbnail updates its modification time
+	std::thread::sleep(std::time::Duration::from_millis(10));
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.headers().get(header::ETAG), Some(&etag));
+
+	let mut request = protocol::thumbnail(&path, None, None);
+	request.headers_mut().insert(header::IF_NONE_MATCH, etag);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+}
+
 #[test]
 fn thumbnail_bad_path_returns_not_found() {
 	let mut service = ServiceType::new(&test_name!());
@@ -126,11 +637,152 @@ fn thumbnail_bad_path_returns_not_found() {
 	assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
+#[test]
+fn thumbnail_original_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic", "Folder.png"]
+		.iter()
+		.collect();
+
+	let request = protocol::thumbnail_original(&path);
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let source_path: PathBuf = [TEST_MOUNT_SOURCE, "Tobokegao", "Picnic", "Folder.png"]
+		.iter()
+		.collect();
+	let source = std::fs::read(source_path).unwrap();
+	assert_eq!(response.body(), &source);
+}
+
+#[test]
+fn thumbnail_original_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic", "Folder.png"]
+		.iter()
+		.collect();
+
+	let request = protocol::thumbnail_original(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn artwork_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let request = protocol::artwork(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn artwork_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let source_path: PathBuf = [TEST_MOUNT_SOURCE, "Khemmis", "Hunted", "Folder.jpg"]
+		.iter()
+		.collect();
+	let source = std::fs::read(source_path).unwrap();
+
+	let song_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "05 - Hunted.mp3"]
+		.iter()
+		.collect();
+	let directory_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	for path in &[song_path, directory_path] {
+		let request = protocol::artwork(path);
+		let response = service.fetch_bytes(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.headers().get(header::CONTENT_TYPE).unwrap(),
+			"image/jpeg"
+		);
+		assert_eq!(response.body(), &source);
+	}
+}
+
+#[cfg(unix)]
+#[test]
+fn artwork_rejects_symlinks_leaving_collection() {
+	use crate::service::dto;
+	use std::path::Path;
+
+	let test_name = test_name!();
+	let mut service = ServiceType::new(&test_name);
+
+	let album_dir: PathBuf = ["test-output", &test_name, "collection", "Album"]
+		.iter()
+		.collect();
+	std::fs::create_dir_all(&album_dir).unwrap();
+	std::fs::copy("test-data/formats/sample.mp3", album_dir.join("song.mp3")).unwrap();
+	let outside: PathBuf = [TEST_MOUNT_SOURCE, "Khemmis", "Hunted", "Folder.jpg"]
+		.iter()
+		.collect();
+	let outside = Path::new(&outside).canonicalize().unwrap();
+	std::os::unix::fs::symlink(&outside, album_dir.join("Folder.jpg")).unwrap();
+
+	service.complete_initial_setup();
+	service.login_admin();
+	let request = protocol::apply_config(dto::Config {
+		mount_dirs: Some(vec![dto::MountDir {
+			name: TEST_MOUNT_NAME.into(),
+			source: album_dir.parent().unwrap().to_string_lossy().into_owned(),
+			scan_interval_seconds: None,
+		}]),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Album"].iter().collect();
+	let response = service.fetch(&protocol::artwork(&path));
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn artwork_missing_returns_not_found() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	let request = protocol::artwork(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Not An Album"]
+		.iter()
+		.collect();
+	let request = protocol::artwork(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
 #[test]
 fn thumbnail_size_default() {
 	thumbnail_size(&test_name!(), None, None, 400);
 }
 
+#[test]
+fn thumbnail_size_tiny() {
+	thumbnail_size(&test_name!(), Some(ThumbnailSize::Tiny), None, 100);
+}
+
 #[test]
 fn thumbnail_size_small() {
 	thumbnail_size(&test_name!(), Some(ThumbnailSize::Small), None, 400);
@@ -148,6 +800,56 @@ fn thumbnail_size_native() {
 	thumbnail_size(&test_name!(), Some(ThumbnailSize::Native), None, 1423);
 }
 
+#[test]
+fn thumbnail_size_accepts_pixel_values() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic", "Folder.png"]
+		.iter()
+		.collect();
+
+	let mut request = protocol::thumbnail(&path, None, None);
+	*request.uri_mut() = format!("{}?size=100", request.uri()).parse().unwrap();
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let thumbnail = image::load_from_memory(response.body()).unwrap().to_rgb8();
+	assert_eq!(thumbnail.width(), 100);
+
+	let mut request = protocol::thumbnail(&path, None, None);
+	*request.uri_mut() = format!("{}?size=123", request.uri()).parse().unwrap();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn thumbnail_reports_dimensions() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic", "Folder.png"]
+		.iter()
+		.collect();
+
+	let request = protocol::thumbnail(&path, Some(ThumbnailSize::Tiny), None);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.headers().get("x-image-width").unwrap(), "100");
+	assert_eq!(response.headers().get("x-image-height").unwrap(), "100");
+
+	let request = protocol::thumbnail_original(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.headers().get("x-image-width").unwrap(), "1423");
+	assert_eq!(response.headers().get("x-image-height").unwrap(), "1411");
+}
+
 fn thumbnail_size(name: &str, size: Option<ThumbnailSize>, pad: Option<bool>, expected: u32) {
 	let mut service = ServiceType::new(name);
 	service.complete_initial_setup();
@@ -166,3 +868,46 @@ fn thumbnail_size(name: &str, size: Option<ThumbnailSize>, pad: Option<bool>, ex
 	assert_eq!(thumbnail.width(), expected);
 	assert_eq!(thumbnail.height(), expected);
 }
+
+#[test]
+fn put_artist_image_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let request = protocol::put_artist_image("Khemmis");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn artist_image_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::artist_image("Khemmis");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn artist_image_falls_back_to_album_artwork() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::artist_image("Khemmis");
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn artist_image_unknown_artist_returns_not_found() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let request = protocol::artist_image("Stratovarius");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
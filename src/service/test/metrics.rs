@@ -0,0 +1,88 @@
+use http::StatusCode;
+use std::path::PathBuf;
+
+use crate::service::test::{constants::*, protocol, ServiceType, TestService};
+use crate::test_name;
+
+#[test]
+fn metrics_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+	let request = protocol::metrics();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn metrics_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::metrics();
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let body = String::from_utf8(response.body().clone()).unwrap();
+	assert!(body.contains("polaris_requests_in_flight{class=\"api\"} 1\n"));
+	assert!(body.contains("polaris_requests_queued{class=\"api\"} 0\n"));
+	assert!(body.contains("polaris_requests_in_flight{class=\"transfer\"} 0\n"));
+	assert!(body.contains("polaris_requests_queued{class=\"transfer\"} 0\n"));
+}
+
+#[test]
+fn transfers_release_their_slot_once_served() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	for _ in 0..8 {
+		let request = protocol::audio(&path);
+		let response = service.fetch_bytes(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+	}
+
+	let request = protocol::metrics();
+	let response = service.fetch_bytes(&request);
+	let body = String::from_utf8(response.body().clone()).unwrap();
+	assert!(body.contains("polaris_requests_in_flight{class=\"transfer\"} 0\n"));
+}
+
+#[test]
+fn public_metrics_allows_local_clients() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.logout();
+
+	let request = protocol::public_metrics();
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let body = String::from_utf8(response.body().clone()).unwrap();
+	assert!(body.contains("# TYPE polaris_songs gauge\n"));
+	assert!(body.contains("polaris_songs 13\n"));
+	assert!(body.contains("polaris_directories 6\n"));
+	assert!(body.contains("polaris_transcoding_sessions 0\n"));
+}
+
+#[test]
+fn metrics_count_logins() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let request = protocol::login(TEST_USERNAME, "not the password");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	service.login_admin();
+
+	let request = protocol::public_metrics();
+	let response = service.fetch_bytes(&request);
+	let body = String::from_utf8(response.body().clone()).unwrap();
+	assert!(body.contains("polaris_logins_total{result=\"success\"} 1\n"));
+	assert!(body.contains("polaris_logins_total{result=\"failure\"} 1\n"));
+}
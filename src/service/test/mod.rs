@@ -9,12 +9,22 @@ pub mod protocol;
 
 mod admin;
 mod auth;
+mod cache;
 mod collection;
+mod cors;
 mod ddns;
 mod lastfm;
+mod listenbrainz;
+mod maintenance;
 mod media;
+mod metrics;
+mod now_playing;
 mod playlist;
+mod sessions;
 mod settings;
+#[cfg(unix)]
+mod socket;
+mod stats;
 mod swagger;
 mod user;
 mod web;
@@ -54,6 +64,7 @@ pub trait TestService {
 			mount_dirs: Some(vec![dto::MountDir {
 				name: TEST_MOUNT_NAME.into(),
 				source: TEST_MOUNT_SOURCE.into(),
+				scan_interval_seconds: None,
 			}]),
 			..Default::default()
 		};
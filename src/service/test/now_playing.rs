@@ -0,0 +1,54 @@
+use http::StatusCode;
+use std::path::PathBuf;
+
+use crate::app::now_playing;
+use crate::service::test::{constants::*, protocol, ServiceType, TestService};
+use crate::test_name;
+
+#[test]
+fn now_playing_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::now_playing();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn now_playing_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::now_playing();
+	let response = service.fetch_json::<_, Vec<now_playing::NowPlaying>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(response.body().is_empty());
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let request = protocol::lastfm_now_playing(&path);
+	service.fetch(&request);
+
+	let request = protocol::now_playing();
+	let response = service.fetch_json::<_, Vec<now_playing::NowPlaying>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let entries = response.body();
+	assert_eq!(entries.len(), 1);
+	assert_eq!(entries[0].username, TEST_USERNAME);
+	assert_eq!(entries[0].song.path, path.to_string_lossy());
+}
+
+#[test]
+fn now_playing_bad_song() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "not_my_song.mp3"].iter().collect();
+	let request = protocol::lastfm_now_playing(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -1,4 +1,5 @@
-use http::StatusCode;
+use http::{header, StatusCode};
+use std::path::PathBuf;
 
 use crate::app::index;
 use crate::service::dto;
@@ -23,6 +24,33 @@ fn list_playlists_golden_path() {
 	assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[test]
+fn list_playlists_includes_timestamps() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	{
+		let my_playlist = dto::SavePlaylistInput { tracks: Vec::new() };
+		let request = protocol::save_playlist(TEST_PLAYLIST_NAME, my_playlist);
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+	}
+
+	for sort in &["name", "updated"] {
+		let request = protocol::playlists_sorted(sort);
+		let response = service.fetch_json::<_, Vec<dto::ListPlaylistsEntry>>(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+		let entries = response.body();
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0].name, TEST_PLAYLIST_NAME);
+		assert!(entries[0].created_at > 0);
+		assert_eq!(entries[0].created_at, entries[0].updated_at);
+		assert_eq!(entries[0].song_count, 0);
+		assert_eq!(entries[0].total_duration, 0);
+	}
+}
+
 #[test]
 fn save_playlist_requires_auth() {
 	let mut service = ServiceType::new(&test_name!());
@@ -85,6 +113,41 @@ fn get_playlist_golden_path() {
 	assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[test]
+fn get_playlist_honors_if_none_match() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let save_playlist = |service: &mut ServiceType| {
+		let my_playlist = dto::SavePlaylistInput { tracks: Vec::new() };
+		let request = protocol::save_playlist(TEST_PLAYLIST_NAME, my_playlist);
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+	};
+
+	save_playlist(&mut service);
+
+	let request = protocol::read_playlist(TEST_PLAYLIST_NAME);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let etag = response.headers().get(header::ETAG).unwrap().clone();
+
+	let mut request = protocol::read_playlist(TEST_PLAYLIST_NAME);
+	request
+		.headers_mut()
+		.insert(header::IF_NONE_MATCH, etag.clone());
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+	assert_eq!(response.headers().get(header::ETAG), Some(&etag));
+
+	save_playlist(&mut service);
+
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_ne!(response.headers().get(header::ETAG), Some(&etag));
+}
+
 #[test]
 fn get_playlist_bad_name_returns_not_found() {
 	let mut service = ServiceType::new(&test_name!());
@@ -96,6 +159,120 @@ fn get_playlist_bad_name_returns_not_found() {
 	assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
+#[test]
+fn export_playlist_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::export_playlist(TEST_PLAYLIST_NAME, "m3u8");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn export_playlist_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [
+		TEST_MOUNT_NAME,
+		"Khemmis",
+		"Hunted",
+		"04 - Beyond The Door.mp3",
+	]
+	.iter()
+	.collect();
+	{
+		let my_playlist = dto::SavePlaylistInput {
+			tracks: vec![path.to_string_lossy().into_owned()],
+		};
+		let request = protocol::save_playlist(TEST_PLAYLIST_NAME, my_playlist);
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+	}
+
+	let request = protocol::export_playlist(TEST_PLAYLIST_NAME, "m3u8");
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let disposition = response
+		.headers()
+		.get(header::CONTENT_DISPOSITION)
+		.unwrap()
+		.to_str()
+		.unwrap();
+	assert!(disposition.starts_with("attachment"));
+	assert!(disposition.contains(&format!("{}.m3u8", TEST_PLAYLIST_NAME)));
+	let content = String::from_utf8(response.body().clone()).unwrap();
+	assert_eq!(
+		content,
+		format!(
+			"#EXTM3U\n#EXTINF:0,Khemmis - Beyond The Door\n{}\n",
+			path.to_string_lossy()
+		)
+	);
+}
+
+#[test]
+fn export_playlist_bad_name_returns_not_found() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let request = protocol::export_playlist(TEST_PLAYLIST_NAME, "m3u");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn import_playlist_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::import_playlist(TEST_PLAYLIST_NAME, "#EXTM3U\n");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn import_playlist_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [
+		TEST_MOUNT_NAME,
+		"Khemmis",
+		"Hunted",
+		"04 - Beyond The Door.mp3",
+	]
+	.iter()
+	.collect();
+	let content = format!(
+		"#EXTM3U\n#EXTINF:0,Khemmis - Candlelight\nCandlelight.mp3\n{}\nunknown.mp3\n",
+		path.to_string_lossy()
+	);
+	let request = protocol::import_playlist(TEST_PLAYLIST_NAME, &content);
+	let response = service.fetch_json::<_, dto::PlaylistImportReport>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(
+		response.body(),
+		&dto::PlaylistImportReport {
+			imported: 2,
+			unmatched: vec!["unknown.mp3".to_owned()],
+		}
+	);
+
+	let request = protocol::read_playlist(TEST_PLAYLIST_NAME);
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	let titles: Vec<String> = response
+		.body()
+		.iter()
+		.map(|s| s.title.clone().unwrap())
+		.collect();
+	assert_eq!(titles, vec!["Candlelight", "Beyond The Door"]);
+}
+
 #[test]
 fn delete_playlist_requires_auth() {
 	let mut service = ServiceType::new(&test_name!());
@@ -132,3 +309,67 @@ fn delete_playlist_bad_name_returns_not_found() {
 	let response = service.fetch(&request);
 	assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
+
+#[test]
+fn rename_playlist_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::rename_playlist(TEST_PLAYLIST_NAME, "Renamed");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn rename_playlist_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	for name in &[TEST_PLAYLIST_NAME, "Taken"] {
+		let my_playlist = dto::SavePlaylistInput { tracks: Vec::new() };
+		let request = protocol::save_playlist(name, my_playlist);
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+	}
+
+	let request = protocol::rename_playlist(TEST_PLAYLIST_NAME, "Taken");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::CONFLICT);
+
+	let request = protocol::rename_playlist(TEST_PLAYLIST_NAME, "");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+	let request = protocol::rename_playlist(TEST_PLAYLIST_NAME, "Renamed");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::read_playlist("Renamed");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::read_playlist(TEST_PLAYLIST_NAME);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn delete_playlists_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	{
+		let my_playlist = dto::SavePlaylistInput { tracks: Vec::new() };
+		let request = protocol::save_playlist(TEST_PLAYLIST_NAME, my_playlist);
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+	}
+
+	let request = protocol::delete_playlists();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::playlists();
+	let response = service.fetch_json::<_, Vec<dto::ListPlaylistsEntry>>(&request);
+	assert!(response.body().is_empty());
+}
@@ -29,6 +29,37 @@ pub fn version() -> Request<()> {
 		.unwrap()
 }
 
+pub fn health() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/health")
+		.body(())
+		.unwrap()
+}
+
+pub fn version_from_origin(origin: &str) -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/version")
+		.header(http::header::ORIGIN, origin)
+		.body(())
+		.unwrap()
+}
+
+pub fn preflight(path: &str, origin: &str, method: Method) -> Request<()> {
+	Request::builder()
+		.method(Method::OPTIONS)
+		.uri(path)
+		.header(http::header::ORIGIN, origin)
+		.header(http::header::ACCESS_CONTROL_REQUEST_METHOD, method.as_str())
+		.header(
+			http::header::ACCESS_CONTROL_REQUEST_HEADERS,
+			"authorization",
+		)
+		.body(())
+		.unwrap()
+}
+
 pub fn initial_setup() -> Request<()> {
 	Request::builder()
 		.method(Method::GET)
@@ -37,6 +68,14 @@ pub fn initial_setup() -> Request<()> {
 		.unwrap()
 }
 
+pub fn status() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/status")
+		.body(())
+		.unwrap()
+}
+
 pub fn login(username: &str, password: &str) -> Request<dto::Credentials> {
 	let credentials = dto::Credentials {
 		username: username.into(),
@@ -49,6 +88,26 @@ pub fn login(username: &str, password: &str) -> Request<dto::Credentials> {
 		.unwrap()
 }
 
+pub fn logout() -> Request<()> {
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/logout")
+		.body(())
+		.unwrap()
+}
+
+pub fn login_jwt(username: &str, password: &str) -> Request<dto::Credentials> {
+	let credentials = dto::Credentials {
+		username: username.into(),
+		password: password.into(),
+	};
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/auth/jwt")
+		.body(credentials)
+		.unwrap()
+}
+
 pub fn apply_config(config: dto::Config) -> Request<dto::Config> {
 	Request::builder()
 		.method(Method::PUT)
@@ -73,6 +132,15 @@ pub fn put_settings(settings: dto::NewSettings) -> Request<dto::NewSettings> {
 		.unwrap()
 }
 
+pub fn put_settings_if_match(settings: dto::NewSettings, etag: &str) -> Request<dto::NewSettings> {
+	Request::builder()
+		.method(Method::PUT)
+		.uri("/api/settings")
+		.header(http::header::IF_MATCH, etag)
+		.body(settings)
+		.unwrap()
+}
+
 pub fn get_ddns_config() -> Request<()> {
 	Request::builder()
 		.method(Method::GET)
@@ -89,6 +157,22 @@ pub fn put_ddns_config(ddns_config: dto::DDNSConfig) -> Request<dto::DDNSConfig>
 		.unwrap()
 }
 
+pub fn get_ddns_status() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/ddns/status")
+		.body(())
+		.unwrap()
+}
+
+pub fn test_ddns_config() -> Request<()> {
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/ddns/test")
+		.body(())
+		.unwrap()
+}
+
 pub fn list_users() -> Request<()> {
 	Request::builder()
 		.method(Method::GET)
@@ -113,6 +197,17 @@ pub fn update_user(username: &str, user_update: dto::UserUpdate) -> Request<dto:
 		.unwrap()
 }
 
+pub fn update_user_password(
+	username: &str,
+	password_update: dto::PasswordUpdate,
+) -> Request<dto::PasswordUpdate> {
+	Request::builder()
+		.method(Method::PUT)
+		.uri(format!("/api/user/{}/password", username))
+		.body(password_update)
+		.unwrap()
+}
+
 pub fn delete_user(username: &str) -> Request<()> {
 	Request::builder()
 		.method(Method::DELETE)
@@ -137,6 +232,16 @@ pub fn put_preferences(preferences: user::Preferences) -> Request<user::Preferen
 		.unwrap()
 }
 
+pub fn put_preferences_password(
+	password_change: dto::PasswordChange,
+) -> Request<dto::PasswordChange> {
+	Request::builder()
+		.method(Method::PUT)
+		.uri("/api/preferences/password")
+		.body(password_change)
+		.unwrap()
+}
+
 pub fn trigger_index() -> Request<()> {
 	Request::builder()
 		.method(Method::POST)
@@ -145,6 +250,100 @@ pub fn trigger_index() -> Request<()> {
 		.unwrap()
 }
 
+pub fn trigger_incremental_index() -> Request<()> {
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/trigger_index?mode=incremental")
+		.body(())
+		.unwrap()
+}
+
+pub fn trigger_index_path(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/trigger_index?path={}", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::POST)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn trigger_index_subtree(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/trigger_index/{}", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::POST)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn preview_index() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/index/preview")
+		.body(())
+		.unwrap()
+}
+
+pub fn index_progress() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/index/progress")
+		.body(())
+		.unwrap()
+}
+
+pub fn resolve(real_path: &Path) -> Request<()> {
+	let real_path = real_path.to_string_lossy();
+	let endpoint = format!("/api/resolve?real={}", url_encode(real_path.as_ref()));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn events() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/events")
+		.body(())
+		.unwrap()
+}
+
+pub fn find_orphans() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/maintenance/orphans")
+		.body(())
+		.unwrap()
+}
+
+pub fn clean_orphans() -> Request<()> {
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/maintenance/orphans/cleanup")
+		.body(())
+		.unwrap()
+}
+
+pub fn metrics() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/metrics")
+		.body(())
+		.unwrap()
+}
+
+pub fn public_metrics() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/metrics")
+		.body(())
+		.unwrap()
+}
+
 pub fn browse(path: &Path) -> Request<()> {
 	let path = path.to_string_lossy();
 	let endpoint = format!("/api/browse/{}", url_encode(path.as_ref()));
@@ -155,6 +354,56 @@ pub fn browse(path: &Path) -> Request<()> {
 		.unwrap()
 }
 
+pub fn browse_sorted(path: &Path, sort: &str, order: &str) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!(
+		"/api/browse/{}?sort={}&order={}",
+		url_encode(path.as_ref()),
+		sort,
+		order
+	);
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn browse_with_depth(path: &Path, depth: u32) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/browse/{}?depth={}", url_encode(path.as_ref()), depth);
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn browse_stream(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/browse/{}?stream=true", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn flatten_sorted(path: &Path, sort: &str, order: &str) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!(
+		"/api/flatten/{}?sort={}&order={}",
+		url_encode(path.as_ref()),
+		sort,
+		order
+	);
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn flatten(path: &Path) -> Request<()> {
 	let path = path.to_string_lossy();
 	let endpoint = format!("/api/flatten/{}", url_encode(path.as_ref()));
@@ -165,6 +414,43 @@ pub fn flatten(path: &Path) -> Request<()> {
 		.unwrap()
 }
 
+pub fn flatten_range(path: &Path, offset: u32, count: u32) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!(
+		"/api/flatten/{}?offset={}&count={}",
+		url_encode(path.as_ref()),
+		offset,
+		count
+	);
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn songs(paths: &[&Path]) -> Request<Vec<String>> {
+	let paths = paths
+		.iter()
+		.map(|p| p.to_string_lossy().into_owned())
+		.collect();
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/songs")
+		.body(paths)
+		.unwrap()
+}
+
+pub fn album(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/album/{}", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn random() -> Request<()> {
 	Request::builder()
 		.method(Method::GET)
@@ -173,6 +459,18 @@ pub fn random() -> Request<()> {
 		.unwrap()
 }
 
+pub fn random_albums(count: i64, seed: Option<u64>) -> Request<()> {
+	let endpoint = match seed {
+		Some(seed) => format!("/api/random?count={}&seed={}", count, seed),
+		None => format!("/api/random?count={}", count),
+	};
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn recent() -> Request<()> {
 	Request::builder()
 		.method(Method::GET)
@@ -181,6 +479,49 @@ pub fn recent() -> Request<()> {
 		.unwrap()
 }
 
+pub fn recent_albums(count: i64) -> Request<()> {
+	let endpoint = format!("/api/recent?count={}", count);
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn artists() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/artists")
+		.body(())
+		.unwrap()
+}
+
+pub fn artist(name: &str) -> Request<()> {
+	let endpoint = format!("/api/artist/{}", url_encode(name));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn genres() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/genres")
+		.body(())
+		.unwrap()
+}
+
+pub fn genre(name: &str) -> Request<()> {
+	let endpoint = format!("/api/genre/{}", url_encode(name));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn search(query: &str) -> Request<()> {
 	let endpoint = format!("/api/search/{}", url_encode(query));
 	Request::builder()
@@ -190,6 +531,38 @@ pub fn search(query: &str) -> Request<()> {
 		.unwrap()
 }
 
+pub fn search_summary(query: &str) -> Request<()> {
+	let endpoint = format!("/api/search/{}?summary=true", url_encode(query));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn stream(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/stream/{}", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn waveform(path: &Path, bins: Option<u32>) -> Request<()> {
+	let path = path.to_string_lossy();
+	let mut endpoint = format!("/api/waveform/{}", url_encode(path.as_ref()));
+	if let Some(bins) = bins {
+		endpoint.push_str(&format!("?bins={}", bins));
+	}
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn audio(path: &Path) -> Request<()> {
 	let path = path.to_string_lossy();
 	let endpoint = format!("/api/audio/{}", url_encode(path.as_ref()));
@@ -200,12 +573,52 @@ pub fn audio(path: &Path) -> Request<()> {
 		.unwrap()
 }
 
+pub fn audio_transcoded(path: &Path, format: &str, bitrate: u32) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!(
+		"/api/audio/{}?format={}&bitrate={}",
+		url_encode(path.as_ref()),
+		format,
+		bitrate
+	);
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn album_stream(path: &Path, format: &str, bitrate: u32) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!(
+		"/api/album_stream/{}?format={}&bitrate={}",
+		url_encode(path.as_ref()),
+		format,
+		bitrate
+	);
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn cancel_operation(id: u64) -> Request<()> {
+	let endpoint = format!("/api/operation/{}/cancel", id);
+	Request::builder()
+		.method(Method::POST)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn thumbnail(path: &Path, size: Option<ThumbnailSize>, pad: Option<bool>) -> Request<()> {
 	let path = path.to_string_lossy();
 	let mut params = String::new();
 	if let Some(s) = size {
 		params.push('?');
 		match s {
+			ThumbnailSize::Tiny => params.push_str("size=tiny"),
 			ThumbnailSize::Small => params.push_str("size=small"),
 			ThumbnailSize::Large => params.push_str("size=large"),
 			ThumbnailSize::Native => params.push_str("size=native"),
@@ -233,6 +646,44 @@ pub fn thumbnail(path: &Path, size: Option<ThumbnailSize>, pad: Option<bool>) ->
 		.unwrap()
 }
 
+pub fn thumbnail_original(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/thumbnail/{}?original=true", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn artwork(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/artwork/{}", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn put_artist_image(name: &str) -> Request<()> {
+	let endpoint = format!("/api/artist/{}/image", url_encode(name));
+	Request::builder()
+		.method(Method::PUT)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn artist_image(name: &str) -> Request<()> {
+	let endpoint = format!("/api/artist/{}/image", url_encode(name));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn playlists() -> Request<()> {
 	Request::builder()
 		.method(Method::GET)
@@ -241,6 +692,15 @@ pub fn playlists() -> Request<()> {
 		.unwrap()
 }
 
+pub fn playlists_sorted(sort: &str) -> Request<()> {
+	let endpoint = format!("/api/playlists?sort={}", sort);
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn save_playlist(
 	name: &str,
 	playlist: dto::SavePlaylistInput,
@@ -262,6 +722,29 @@ pub fn read_playlist(name: &str) -> Request<()> {
 		.unwrap()
 }
 
+pub fn export_playlist(name: &str, format: &str) -> Request<()> {
+	let endpoint = format!(
+		"/api/playlist/{}/export?format={}",
+		url_encode(name),
+		format
+	);
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn import_playlist(name: &str, content: &str) -> Request<String> {
+	let endpoint = format!("/api/playlist/{}/import", url_encode(name));
+	Request::builder()
+		.method(Method::POST)
+		.uri(&endpoint)
+		.header(http::header::CONTENT_TYPE, "audio/x-mpegurl")
+		.body(content.to_owned())
+		.unwrap()
+}
+
 pub fn delete_playlist(name: &str) -> Request<()> {
 	let endpoint = format!("/api/playlist/{}", url_encode(name));
 	Request::builder()
@@ -271,6 +754,25 @@ pub fn delete_playlist(name: &str) -> Request<()> {
 		.unwrap()
 }
 
+pub fn rename_playlist(name: &str, new_name: &str) -> Request<dto::PlaylistRename> {
+	let endpoint = format!("/api/playlist/{}/rename", url_encode(name));
+	Request::builder()
+		.method(Method::POST)
+		.uri(&endpoint)
+		.body(dto::PlaylistRename {
+			name: new_name.to_owned(),
+		})
+		.unwrap()
+}
+
+pub fn delete_playlists() -> Request<()> {
+	Request::builder()
+		.method(Method::DELETE)
+		.uri("/api/playlists")
+		.body(())
+		.unwrap()
+}
+
 pub fn lastfm_link_token() -> Request<()> {
 	Request::builder()
 		.method(Method::GET)
@@ -279,6 +781,14 @@ pub fn lastfm_link_token() -> Request<()> {
 		.unwrap()
 }
 
+pub fn now_playing() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/now_playing")
+		.body(())
+		.unwrap()
+}
+
 pub fn lastfm_now_playing(path: &Path) -> Request<()> {
 	let path = path.to_string_lossy();
 	let endpoint = format!("/api/lastfm/now_playing/{}", url_encode(path.as_ref()));
@@ -289,6 +799,66 @@ pub fn lastfm_now_playing(path: &Path) -> Request<()> {
 		.unwrap()
 }
 
+pub fn listening_stats() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/me/stats")
+		.body(())
+		.unwrap()
+}
+
+pub fn top_songs(count: i64) -> Request<()> {
+	let endpoint = format!("/api/top_songs?count={}", count);
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn scrobble_local(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/scrobble_local/{}", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::POST)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn daily_mix() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/me/daily_mix")
+		.body(())
+		.unwrap()
+}
+
+pub fn list_sessions() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/me/sessions")
+		.body(())
+		.unwrap()
+}
+
+pub fn revoke_session(id: i32) -> Request<()> {
+	let endpoint = format!("/api/me/sessions/{}", id);
+	Request::builder()
+		.method(Method::DELETE)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn list_all_sessions() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/sessions")
+		.body(())
+		.unwrap()
+}
+
 pub fn lastfm_scrobble(path: &Path) -> Request<()> {
 	let path = path.to_string_lossy();
 	let endpoint = format!("/api/lastfm/scrobble/{}", url_encode(path.as_ref()));
@@ -299,6 +869,24 @@ pub fn lastfm_scrobble(path: &Path) -> Request<()> {
 		.unwrap()
 }
 
+pub fn listenbrainz_link(token: &str) -> Request<dto::ListenBrainzLink> {
+	Request::builder()
+		.method(Method::PUT)
+		.uri("/api/listenbrainz/link")
+		.body(dto::ListenBrainzLink {
+			token: token.to_owned(),
+		})
+		.unwrap()
+}
+
+pub fn listenbrainz_unlink() -> Request<()> {
+	Request::builder()
+		.method(Method::DELETE)
+		.uri("/api/listenbrainz/link")
+		.body(())
+		.unwrap()
+}
+
 fn url_encode(input: &str) -> String {
 	percent_encode(input.as_bytes(), NON_ALPHANUMERIC).to_string()
 }
@@ -0,0 +1,81 @@
+use http::StatusCode;
+
+use crate::app::user;
+use crate::service::test::{constants::*, protocol, ServiceType, TestService};
+use crate::test_name;
+
+#[test]
+fn list_sessions_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	let request = protocol::list_sessions();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn list_sessions_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let request = protocol::list_sessions();
+	let response = service.fetch_json::<_, Vec<user::Session>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let sessions = response.body();
+	assert_eq!(sessions.len(), 1);
+	assert_eq!(sessions[0].username, TEST_USERNAME);
+}
+
+#[test]
+fn revoke_session_invalidates_it_immediately() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let request = protocol::list_sessions();
+	let response = service.fetch_json::<_, Vec<user::Session>>(&request);
+	let session = response.body()[0].id;
+
+	let request = protocol::revoke_session(session);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::list_sessions();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn revoke_session_rejects_unknown_session() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let request = protocol::revoke_session(1000);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn list_all_sessions_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+	let request = protocol::list_all_sessions();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn list_all_sessions_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+	service.login_admin();
+
+	let request = protocol::list_all_sessions();
+	let response = service.fetch_json::<_, Vec<user::Session>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 2);
+}
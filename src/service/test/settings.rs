@@ -1,4 +1,4 @@
-use http::StatusCode;
+use http::{header, StatusCode};
 
 use crate::service::dto;
 use crate::service::test::{protocol, ServiceType, TestService};
@@ -65,3 +65,46 @@ fn put_settings_golden_path() {
 	let response = service.fetch(&request);
 	assert_eq!(response.status(), StatusCode::OK);
 }
+
+#[test]
+fn put_settings_honors_if_match() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::get_settings();
+	let response = service.fetch_json::<_, dto::Settings>(&request);
+	let etag = response.headers()[header::ETAG]
+		.to_str()
+		.unwrap()
+		.to_owned();
+	let revision = response.body().revision;
+	assert_eq!(etag, format!("\"{}\"", revision));
+
+	let request = protocol::put_settings_if_match(dto::NewSettings::default(), &etag);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	// Settings changed since the ETag was obtained
+	let request = protocol::put_settings_if_match(dto::NewSettings::default(), &etag);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::CONFLICT);
+
+	let request = protocol::get_settings();
+	let response = service.fetch_json::<_, dto::Settings>(&request);
+	assert_eq!(response.body().revision, revision + 1);
+}
+
+#[test]
+fn put_settings_rejects_non_positive_session_lifetime() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::put_settings(dto::NewSettings {
+		session_lifetime_seconds: Some(0),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
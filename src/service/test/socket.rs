@@ -0,0 +1,111 @@
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::app::{
+	index::DEFAULT_INSERT_BUFFER_SIZE, metrics, App, Options, DEFAULT_BIND_ADDRESS,
+	DEFAULT_MAX_JSON_BODY_SIZE,
+};
+use crate::db::DEFAULT_BUSY_TIMEOUT;
+use crate::paths::Paths;
+use crate::service;
+use crate::test::*;
+use crate::test_name;
+
+fn make_paths(output_dir: &Path, socket_file_path: &Path) -> Paths {
+	Paths {
+		cache_dir_path: output_dir.to_owned(),
+		config_file_path: None,
+		db_file_path: output_dir.join("db.sqlite"),
+		pid_file_path: output_dir.join("polaris.pid"),
+		socket_file_path: Some(socket_file_path.to_owned()),
+		log_file_path: None,
+		swagger_dir_path: Some(["docs", "swagger"].iter().collect()),
+		tls_cert_file_path: None,
+		tls_key_file_path: None,
+		web_dir_path: ["test-data", "web"].iter().collect(),
+	}
+}
+
+fn make_app(paths: Paths) -> anyhow::Result<App> {
+	App::new(
+		Options {
+			address: SocketAddr::new(DEFAULT_BIND_ADDRESS, 5050),
+			collection_cache_max_age: Duration::from_secs(60),
+			max_concurrent_transfers: Some(4),
+			index_insert_buffer_size: DEFAULT_INSERT_BUFFER_SIZE,
+			db_busy_timeout: DEFAULT_BUSY_TIMEOUT,
+			max_json_body_size: DEFAULT_MAX_JSON_BODY_SIZE,
+			metrics_allowlist: metrics::DEFAULT_ALLOWLIST.to_vec(),
+		},
+		paths,
+	)
+}
+
+#[test]
+fn serves_over_unix_socket() {
+	let test_name = test_name!();
+	let output_dir = prepare_test_directory(&test_name);
+	let socket_file_path = output_dir.join("polaris.sock");
+
+	// Left behind by a previous run
+	drop(UnixListener::bind(&socket_file_path).unwrap());
+
+	let app = make_app(make_paths(&output_dir, &socket_file_path)).unwrap();
+
+	let (server_sender, server_receiver) = crossbeam_channel::bounded(1);
+	let server_thread = std::thread::spawn(move || service::run(app, server_sender));
+	let server = server_receiver.recv().unwrap();
+
+	let mode = std::fs::metadata(&socket_file_path)
+		.unwrap()
+		.permissions()
+		.mode();
+	assert_eq!(mode & 0o777, 0o660);
+
+	let mut stream = UnixStream::connect(&socket_file_path).unwrap();
+	stream
+		.write_all(b"GET /api/version HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+		.unwrap();
+	let mut response = String::new();
+	stream.read_to_string(&mut response).unwrap();
+	assert!(response.starts_with("HTTP/1.1 200 OK"));
+
+	drop(server.stop(true));
+	server_thread.join().unwrap().unwrap();
+	assert!(!socket_file_path.exists());
+}
+
+#[test]
+fn refuses_to_replace_other_files() {
+	let test_name = test_name!();
+	let output_dir = prepare_test_directory(&test_name);
+	let socket_file_path = output_dir.join("polaris.sock");
+	std::fs::write(&socket_file_path, "not a socket").unwrap();
+
+	let app = make_app(make_paths(&output_dir, &socket_file_path)).unwrap();
+
+	let (server_sender, _server_receiver) = crossbeam_channel::bounded(1);
+	assert!(service::run(app, server_sender).is_err());
+	assert_eq!(
+		std::fs::read_to_string(&socket_file_path).unwrap(),
+		"not a socket"
+	);
+}
+
+#[test]
+fn rejects_tls_settings() {
+	let test_name = test_name!();
+	let output_dir = prepare_test_directory(&test_name);
+	let socket_file_path = output_dir.join("polaris.sock");
+
+	let paths = Paths {
+		tls_cert_file_path: Some(["test-data", "tls", "cert.pem"].iter().collect()),
+		tls_key_file_path: Some(["test-data", "tls", "key.pem"].iter().collect()),
+		..make_paths(&output_dir, &socket_file_path)
+	};
+	assert!(make_app(paths).is_err());
+}
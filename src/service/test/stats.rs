@@ -0,0 +1,150 @@
+use http::StatusCode;
+use std::path::PathBuf;
+
+use crate::app::{index, stats};
+use crate::service::test::{constants::*, protocol, ServiceType, TestService};
+use crate::test_name;
+
+#[test]
+fn listening_stats_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::listening_stats();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn listening_stats_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::listening_stats();
+	let response = service.fetch_json::<_, stats::ListeningStats>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body(), &stats::ListeningStats::default());
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let request = protocol::scrobble_local(&path);
+	service.fetch(&request);
+
+	let request = protocol::listening_stats();
+	let response = service.fetch_json::<_, stats::ListeningStats>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let stats = response.body();
+	assert_eq!(stats.total_plays, 1);
+	assert_eq!(stats.top_songs.len(), 1);
+}
+
+#[test]
+fn lastfm_scrobble_does_not_record_play() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let request = protocol::lastfm_scrobble(&path);
+	service.fetch(&request);
+
+	let request = protocol::listening_stats();
+	let response = service.fetch_json::<_, stats::ListeningStats>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().total_plays, 0);
+}
+
+#[test]
+fn scrobble_local_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let request = protocol::scrobble_local(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn top_songs_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::top_songs(10);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn top_songs_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let candlelight: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let hunted: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "05 - Hunted.mp3"]
+		.iter()
+		.collect();
+	for path in &[&hunted, &candlelight, &candlelight] {
+		let request = protocol::scrobble_local(path);
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+	}
+
+	let request = protocol::top_songs(10);
+	let response = service.fetch_json::<_, Vec<stats::SongStats>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let songs = response.body();
+	assert_eq!(songs.len(), 2);
+	assert_eq!(songs[0].song.path, candlelight.to_string_lossy().as_ref());
+	assert_eq!(songs[0].play_count, 2);
+	assert_eq!(songs[1].song.path, hunted.to_string_lossy().as_ref());
+	assert_eq!(songs[1].play_count, 1);
+	assert!(songs[0].last_played >= songs[1].last_played);
+
+	let request = protocol::top_songs(1);
+	let response = service.fetch_json::<_, Vec<stats::SongStats>>(&request);
+	assert_eq!(response.body().len(), 1);
+}
+
+#[test]
+fn scrobble_local_bad_path_returns_not_found() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let path: PathBuf = ["not_my_mount", "song.mp3"].iter().collect();
+	let request = protocol::scrobble_local(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn daily_mix_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::daily_mix();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn daily_mix_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::daily_mix();
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 13);
+}
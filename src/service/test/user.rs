@@ -106,6 +106,91 @@ fn update_user_cannot_unadmin_self() {
 	assert_eq!(response.status(), StatusCode::CONFLICT);
 }
 
+#[test]
+fn update_user_password_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	let request = protocol::update_user_password(
+		TEST_USERNAME,
+		dto::PasswordUpdate {
+			new_password: "new_password".into(),
+		},
+	);
+
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	service.login();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn update_user_password_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	let request = protocol::update_user_password(
+		TEST_USERNAME,
+		dto::PasswordUpdate {
+			new_password: "new_password".into(),
+		},
+	);
+
+	service.login_admin();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::login(TEST_USERNAME, TEST_PASSWORD);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	let request = protocol::login(TEST_USERNAME, "new_password");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn update_user_password_revokes_sessions() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let request = protocol::login(TEST_USERNAME, TEST_PASSWORD);
+	let response = service.fetch_json::<_, dto::Authorization>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let authorization = response.into_body();
+
+	service.login_admin();
+	let request = protocol::update_user_password(
+		TEST_USERNAME,
+		dto::PasswordUpdate {
+			new_password: "new_password".into(),
+		},
+	);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	service.set_authorization(Some(authorization));
+	let request = protocol::get_preferences();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn update_user_password_rejects_unknown_user() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	let request = protocol::update_user_password(
+		"Walter",
+		dto::PasswordUpdate {
+			new_password: "new_password".into(),
+		},
+	);
+
+	service.login_admin();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
 #[test]
 fn delete_user_requires_admin() {
 	let mut service = ServiceType::new(&test_name!());
@@ -168,6 +253,58 @@ fn put_preferences_requires_auth() {
 	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
 
+#[test]
+fn put_preferences_password_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::put_preferences_password(dto::PasswordChange {
+		old_password: TEST_PASSWORD.into(),
+		new_password: "new_password".into(),
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn put_preferences_password_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let request = protocol::put_preferences_password(dto::PasswordChange {
+		old_password: TEST_PASSWORD.into(),
+		new_password: "new_password".into(),
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	// The session which changed the password remains valid
+	let request = protocol::get_preferences();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::login(TEST_USERNAME, "new_password");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn put_preferences_password_rejects_wrong_password() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let request = protocol::put_preferences_password(dto::PasswordChange {
+		old_password: "not_the_password".into(),
+		new_password: "new_password".into(),
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	let request = protocol::login(TEST_USERNAME, TEST_PASSWORD);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+}
+
 #[test]
 fn put_preferences_golden_path() {
 	let mut service = ServiceType::new(&test_name!());
@@ -1,4 +1,4 @@
-use http::StatusCode;
+use http::{header, StatusCode};
 
 use crate::service::test::{protocol, ServiceType, TestService};
 use crate::test_name;
@@ -10,3 +10,32 @@ fn serves_web_client() {
 	let response = service.fetch_bytes(&request);
 	assert_eq!(response.status(), StatusCode::OK);
 }
+
+#[test]
+fn serves_precompressed_web_client() {
+	let mut service = ServiceType::new(&test_name!());
+	let mut request = protocol::web_index();
+	request
+		.headers_mut()
+		.insert(header::ACCEPT_ENCODING, "gzip".parse().unwrap());
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+	assert_eq!(response.headers()[header::CONTENT_TYPE], "text/html");
+	let body = String::from_utf8_lossy(response.body());
+	assert!(body.contains("Precompressed"));
+}
+
+#[test]
+fn serves_uncompressed_web_client_without_accepted_encoding() {
+	let mut service = ServiceType::new(&test_name!());
+	let mut request = protocol::web_index();
+	request
+		.headers_mut()
+		.insert(header::ACCEPT_ENCODING, "identity".parse().unwrap());
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+	let body = String::from_utf8_lossy(response.body());
+	assert!(!body.contains("Precompressed"));
+}
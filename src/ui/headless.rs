@@ -1,10 +1,7 @@
+use crossbeam_channel::Receiver;
 use log::info;
-use std::thread;
-use std::time;
 
-pub fn run() {
+pub fn run(shutdown_receiver: Receiver<()>) {
 	info!("Starting up UI (headless)");
-	loop {
-		thread::sleep(time::Duration::from_secs(10));
-	}
+	let _ = shutdown_receiver.recv();
 }
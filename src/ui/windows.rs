@@ -1,3 +1,4 @@
+use crossbeam_channel::Receiver;
 use log::info;
 use native_windows_derive::NwgUi;
 use native_windows_gui::{self as nwg, NativeUi};
@@ -23,6 +24,10 @@ pub struct SystemTray {
 	#[nwg_control(parent: tray_menu, text: "Quit Polaris")]
 	#[nwg_events(OnMenuItemSelected: [SystemTray::exit])]
 	exit_menu_item: nwg::MenuItem,
+
+	#[nwg_control(parent: window)]
+	#[nwg_events(OnNotice: [SystemTray::exit])]
+	shutdown_notice: nwg::Notice,
 }
 
 impl SystemTray {
@@ -36,9 +41,15 @@ impl SystemTray {
 	}
 }
 
-pub fn run() {
+pub fn run(shutdown_receiver: Receiver<()>) {
 	info!("Starting up UI (Windows system tray)");
 	nwg::init().expect("Failed to init Native Windows GUI");
-	let _ui = SystemTray::build_ui(Default::default()).expect("Failed to build tray UI");
+	let ui = SystemTray::build_ui(Default::default()).expect("Failed to build tray UI");
+	let shutdown_notice = ui.shutdown_notice.sender();
+	std::thread::spawn(move || {
+		if shutdown_receiver.recv().is_ok() {
+			shutdown_notice.notice();
+		}
+	});
 	nwg::dispatch_thread_events();
 }
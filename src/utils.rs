@@ -49,6 +49,29 @@ pub fn get_audio_format(path: &Path) -> Option<AudioFormat> {
 	}
 }
 
+/// Decodes a percent-encoded string. Unlike lossy decoding, this fails on malformed escape
+/// sequences and on content that is not valid UTF-8.
+pub fn percent_decode_strict(input: &str) -> Option<String> {
+	let bytes = input.as_bytes();
+	let mut output = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		if bytes[i] == b'%' {
+			let hex = bytes.get(i + 1..i + 3)?;
+			if !hex.iter().all(u8::is_ascii_hexdigit) {
+				return None;
+			}
+			let hex = std::str::from_utf8(hex).ok()?;
+			output.push(u8::from_str_radix(hex, 16).ok()?);
+			i += 3;
+		} else {
+			output.push(bytes[i]);
+			i += 1;
+		}
+	}
+	String::from_utf8(output).ok()
+}
+
 #[test]
 fn can_guess_audio_format() {
 	assert_eq!(get_audio_format(Path::new("animals/🐷/my🐖file.jpg")), None);
@@ -69,3 +92,46 @@ fn can_guess_audio_format() {
 		Some(AudioFormat::WAVE)
 	);
 }
+
+#[test]
+fn can_percent_decode() {
+	assert_eq!(
+		percent_decode_strict("Khemmis/Hunted"),
+		Some("Khemmis/Hunted".to_owned())
+	);
+	assert_eq!(
+		percent_decode_strict("animals%2F%F0%9F%90%B7"),
+		Some("animals/🐷".to_owned())
+	);
+	assert_eq!(
+		percent_decode_strict("a+b%2Bc"),
+		Some("a+b+c".to_owned())
+	);
+	assert_eq!(
+		percent_decode_strict("100%25%20pure"),
+		Some("100% pure".to_owned())
+	);
+	assert_eq!(
+		percent_decode_strict("double%2541encoded"),
+		Some("double%41encoded".to_owned())
+	);
+	assert_eq!(
+		percent_decode_strict("Track%20%232%2FWhy%3F.mp3"),
+		Some("Track #2/Why?.mp3".to_owned())
+	);
+	assert_eq!(
+		percent_decode_strict("%E3%83%94%E3%82%AF%E3%83%8B%E3%83%83%E3%82%AF"),
+		Some("ピクニック".to_owned())
+	);
+}
+
+#[test]
+fn percent_decode_rejects_malformed_input() {
+	assert_eq!(percent_decode_strict("100%"), None);
+	assert_eq!(percent_decode_strict("100%2"), None);
+	assert_eq!(percent_decode_strict("100% pure"), None);
+	assert_eq!(percent_decode_strict("%ZZ"), None);
+	assert_eq!(percent_decode_strict("%+1"), None);
+	assert_eq!(percent_decode_strict("%FF%FE"), None);
+	assert_eq!(percent_decode_strict("%F0%9F%90"), None);
+}